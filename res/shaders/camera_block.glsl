@@ -0,0 +1,4 @@
+layout(set = 0, binding = 0) uniform descriptor_camera_block {
+	mat4 view;
+	mat4 projection;
+} descriptor_camera;
\ No newline at end of file
@@ -0,0 +1,9 @@
+#ifndef MESH_JOINTS_SET
+#define MESH_JOINTS_SET 3
+#endif
+
+// Global joint transform times inverse bind matrix, indexed by the
+// vertex's joints.
+layout(std430, set = MESH_JOINTS_SET, binding = 0) readonly buffer descriptor_joints_block {
+	mat4 matrices[];
+} descriptor_joints;
\ No newline at end of file
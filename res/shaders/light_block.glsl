@@ -0,0 +1,43 @@
+#define MAX_DIRECTIONAL_LIGHTS 4
+#define MAX_POINT_LIGHTS 16
+#define MAX_SPOT_LIGHTS 8
+#define MAX_SHADOW_MAPS 8
+
+struct DirectionalLight {
+	vec4 direction;
+	vec4 color;
+};
+
+struct PointLight {
+	vec4 position;
+	vec4 color;
+};
+
+struct SpotLight {
+	vec4 position;
+	vec4 direction;
+	vec4 color;
+	vec4 cone;
+};
+
+layout(set = 1, binding = 0) uniform descriptor_light_block {
+	vec4 ambient;
+	uvec4 counts;
+	uvec4 shadow_casters;
+	vec4 shadow_params;
+	DirectionalLight directional[MAX_DIRECTIONAL_LIGHTS];
+	PointLight point[MAX_POINT_LIGHTS];
+	SpotLight spot[MAX_SPOT_LIGHTS];
+	mat4 shadow_matrices[MAX_SHADOW_MAPS];
+} descriptor_lights;
+
+// Inverse square falloff windowed to reach zero at the range.
+float range_attenuation(float distance, float range) {
+	float window = clamp(1.0 - pow(distance / range, 4.0), 0.0, 1.0);
+	return window * window / (distance * distance + 1.0);
+}
+
+float spot_attenuation(SpotLight light, vec3 to_light) {
+	float cos_angle = dot(-to_light, normalize(light.direction.xyz));
+	return smoothstep(light.cone.y, light.cone.x, cos_angle);
+}
\ No newline at end of file
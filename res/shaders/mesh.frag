@@ -0,0 +1,76 @@
+#version 450
+#extension GL_GOOGLE_include_directive : require
+
+layout(location = 0) in vec3 v_position;
+layout(location = 1) in vec3 v_normal;
+layout(location = 2) in vec4 v_tangent;
+layout(location = 3) in vec2 v_uv;
+layout(location = 4) in vec4 v_color;
+layout(location = 5) in vec3 v_camera_position;
+
+layout(location = 0) out vec4 f_color;
+
+#include "light_block.glsl"
+#ifdef SHADOWS
+layout(set = 2, binding = 0) uniform sampler2D shadow_atlas;
+#include "shadow.glsl"
+#define MESH_MODEL_SET 3
+#endif
+#include "mesh_model_block.glsl"
+
+vec3 blinn_phong(vec3 normal, vec3 view, vec3 to_light, vec3 radiance) {
+	float diffuse = max(dot(normal, to_light), 0.0);
+	vec3 halfway = normalize(to_light + view);
+	float specular = diffuse > 0.0 ?
+		pow(max(dot(normal, halfway), 0.0), descriptor_model.specular.a) :
+		0.0;
+	return radiance * (v_color.rgb * diffuse + descriptor_model.specular.rgb * specular);
+}
+
+void main() {
+	vec3 normal = normalize(v_normal);
+	vec3 view = normalize(v_camera_position - v_position);
+	vec3 color = descriptor_lights.ambient.rgb * v_color.rgb;
+	for (uint i = 0; i < min(descriptor_lights.counts.x, MAX_DIRECTIONAL_LIGHTS); i++) {
+		DirectionalLight light = descriptor_lights.directional[i];
+		float shadow = 1.0;
+#ifdef SHADOWS
+		shadow = directional_shadow(shadow_atlas, light, v_position, normal);
+#endif
+		color += blinn_phong(
+			normal,
+			view,
+			-normalize(light.direction.xyz),
+			light.color.rgb * light.color.a * shadow
+		);
+	}
+	for (uint i = 0; i < min(descriptor_lights.counts.y, MAX_POINT_LIGHTS); i++) {
+		PointLight light = descriptor_lights.point[i];
+		vec3 to_light = light.position.xyz - v_position;
+		float distance = length(to_light);
+		float attenuation = range_attenuation(distance, light.position.w);
+		color += blinn_phong(
+			normal,
+			view,
+			to_light / max(distance, 0.0001),
+			light.color.rgb * light.color.a * attenuation
+		);
+	}
+	for (uint i = 0; i < min(descriptor_lights.counts.z, MAX_SPOT_LIGHTS); i++) {
+		SpotLight light = descriptor_lights.spot[i];
+		vec3 to_light = light.position.xyz - v_position;
+		float distance = length(to_light);
+		to_light /= max(distance, 0.0001);
+		float attenuation = range_attenuation(distance, light.position.w) * spot_attenuation(light, to_light);
+#ifdef SHADOWS
+		attenuation *= spot_shadow(shadow_atlas, light, v_position, normal, to_light);
+#endif
+		color += blinn_phong(
+			normal,
+			view,
+			to_light,
+			light.color.rgb * light.color.a * attenuation
+		);
+	}
+	f_color = vec4(color, v_color.a);
+}
\ No newline at end of file
@@ -0,0 +1,9 @@
+#ifndef MESH_MODEL_SET
+#define MESH_MODEL_SET 2
+#endif
+
+layout(set = MESH_MODEL_SET, binding = 0) uniform descriptor_mesh_model_block {
+	mat4 model;
+	vec4 color;
+	vec4 specular;
+} descriptor_model;
\ No newline at end of file
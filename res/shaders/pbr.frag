@@ -0,0 +1,126 @@
+#version 450
+#extension GL_GOOGLE_include_directive : require
+
+layout(location = 0) in vec3 v_position;
+layout(location = 1) in vec3 v_normal;
+layout(location = 2) in vec4 v_tangent;
+layout(location = 3) in vec2 v_uv;
+layout(location = 4) in vec4 v_color;
+layout(location = 5) in vec3 v_camera_position;
+
+layout(location = 0) out vec4 f_color;
+
+#include "light_block.glsl"
+#include "pbr_blocks.glsl"
+#include "pbr_common.glsl"
+#ifdef SHADOWS
+#include "shadow.glsl"
+#endif
+
+vec3 fresnel_schlick(float cos_theta, vec3 f0) {
+	return f0 + (1.0 - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+vec3 fresnel_schlick_roughness(float cos_theta, vec3 f0, float roughness) {
+	return f0 + (max(vec3(1.0 - roughness), f0) - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+// Cook-Torrance specular plus Lambert diffuse for one light.
+vec3 radiance_from(vec3 normal, vec3 view, vec3 to_light, vec3 radiance, vec3 albedo, float metallic, float roughness, vec3 f0) {
+	vec3 halfway = normalize(view + to_light);
+	float n_dot_l = max(dot(normal, to_light), 0.0);
+	float n_dot_v = max(dot(normal, view), 0.0001);
+	float k = (roughness + 1.0) * (roughness + 1.0) / 8.0;
+	float d = distribution_ggx(max(dot(normal, halfway), 0.0), roughness);
+	float g = geometry_smith(n_dot_v, n_dot_l, k);
+	vec3 f = fresnel_schlick(max(dot(halfway, view), 0.0), f0);
+	vec3 specular = d * g * f / (4.0 * n_dot_v * n_dot_l + 0.0001);
+	vec3 diffuse = (1.0 - f) * (1.0 - metallic) * albedo / PI;
+	return (diffuse + specular) * radiance * n_dot_l;
+}
+
+// Narkowicz's fit of the ACES filmic curve.
+vec3 tonemap_aces(vec3 x) {
+	return clamp((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14), 0.0, 1.0);
+}
+
+void main() {
+	vec4 base_color = texture(material_textures[0], v_uv) * descriptor_material.base_color * v_color;
+	vec4 metallic_roughness = texture(material_textures[1], v_uv);
+	float metallic = clamp(metallic_roughness.b * descriptor_material.params.x, 0.0, 1.0);
+	float roughness = clamp(metallic_roughness.g * descriptor_material.params.y, 0.04, 1.0);
+	float occlusion = mix(1.0, texture(material_textures[3], v_uv).r, descriptor_material.params.w);
+	vec3 emissive = texture(material_textures[4], v_uv).rgb * descriptor_material.emissive.rgb;
+
+	vec3 normal = normalize(v_normal);
+	vec3 tangent = normalize(v_tangent.xyz - normal * dot(normal, v_tangent.xyz));
+	vec3 bitangent = cross(normal, tangent) * v_tangent.w;
+	vec3 tangent_normal = texture(material_textures[2], v_uv).xyz * 2.0 - 1.0;
+	tangent_normal.xy *= descriptor_material.params.z;
+	normal = normalize(mat3(tangent, bitangent, normal) * tangent_normal);
+
+	vec3 view = normalize(v_camera_position - v_position);
+	vec3 albedo = base_color.rgb;
+	vec3 f0 = mix(vec3(0.04), albedo, metallic);
+	vec3 color = vec3(0.0);
+	for (uint i = 0; i < min(descriptor_lights.counts.x, MAX_DIRECTIONAL_LIGHTS); i++) {
+		DirectionalLight light = descriptor_lights.directional[i];
+		float shadow = 1.0;
+#ifdef SHADOWS
+		shadow = directional_shadow(lighting_maps[1], light, v_position, normalize(v_normal));
+#endif
+		color += radiance_from(
+			normal,
+			view,
+			-normalize(light.direction.xyz),
+			light.color.rgb * light.color.a * shadow,
+			albedo, metallic, roughness, f0
+		);
+	}
+	for (uint i = 0; i < min(descriptor_lights.counts.y, MAX_POINT_LIGHTS); i++) {
+		PointLight light = descriptor_lights.point[i];
+		vec3 to_light = light.position.xyz - v_position;
+		float distance = length(to_light);
+		float attenuation = range_attenuation(distance, light.position.w);
+		color += radiance_from(
+			normal,
+			view,
+			to_light / max(distance, 0.0001),
+			light.color.rgb * light.color.a * attenuation,
+			albedo, metallic, roughness, f0
+		);
+	}
+	for (uint i = 0; i < min(descriptor_lights.counts.z, MAX_SPOT_LIGHTS); i++) {
+		SpotLight light = descriptor_lights.spot[i];
+		vec3 to_light = light.position.xyz - v_position;
+		float distance = length(to_light);
+		to_light /= max(distance, 0.0001);
+		float attenuation = range_attenuation(distance, light.position.w) * spot_attenuation(light, to_light);
+#ifdef SHADOWS
+		attenuation *= spot_shadow(lighting_maps[1], light, v_position, normalize(v_normal), to_light);
+#endif
+		color += radiance_from(
+			normal,
+			view,
+			to_light,
+			light.color.rgb * light.color.a * attenuation,
+			albedo, metallic, roughness, f0
+		);
+	}
+
+	float n_dot_v = max(dot(normal, view), 0.0);
+	vec3 f = fresnel_schlick_roughness(n_dot_v, f0, roughness);
+	vec3 irradiance = texture(environment_maps[0], normal).rgb;
+	vec3 diffuse = (1.0 - f) * (1.0 - metallic) * irradiance * albedo;
+	vec3 reflection = reflect(-view, normal);
+	float max_lod = descriptor_environment.params.z - 1.0;
+	vec3 prefiltered = textureLod(environment_maps[1], reflection, roughness * max_lod).rgb;
+	vec2 brdf = texture(lighting_maps[0], vec2(n_dot_v, roughness)).rg;
+	vec3 specular = prefiltered * (f * brdf.x + brdf.y);
+	color += (diffuse + specular) * occlusion * descriptor_environment.params.y;
+	color += descriptor_lights.ambient.rgb * albedo * occlusion;
+	color += emissive;
+
+	// The swapchain is sRGB, so the tonemapped color stays linear.
+	f_color = vec4(tonemap_aces(color * descriptor_environment.params.x), base_color.a);
+}
\ No newline at end of file
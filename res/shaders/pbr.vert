@@ -0,0 +1,33 @@
+#version 450
+#extension GL_GOOGLE_include_directive : require
+
+layout(location = 0) in vec3 position;
+layout(location = 1) in vec3 normal;
+layout(location = 2) in vec4 tangent;
+layout(location = 3) in vec2 uv;
+layout(location = 4) in vec4 color;
+
+layout(location = 0) out vec3 v_position;
+layout(location = 1) out vec3 v_normal;
+layout(location = 2) out vec4 v_tangent;
+layout(location = 3) out vec2 v_uv;
+layout(location = 4) out vec4 v_color;
+layout(location = 5) out vec3 v_camera_position;
+
+#include "camera_block.glsl"
+#include "pbr_blocks.glsl"
+
+void main() {
+	vec4 world_position = descriptor_model.model * vec4(position, 1.0);
+	mat3 normal_matrix = transpose(inverse(mat3(descriptor_model.model)));
+	v_position = world_position.xyz;
+	v_normal = normalize(normal_matrix * normal);
+	v_tangent = vec4(normalize(mat3(descriptor_model.model) * tangent.xyz), tangent.w);
+	v_uv = uv;
+	v_color = color * descriptor_model.color;
+	v_camera_position = inverse(descriptor_camera.view)[3].xyz;
+	gl_Position =
+		descriptor_camera.projection *
+		descriptor_camera.view *
+		world_position;
+}
\ No newline at end of file
@@ -0,0 +1,20 @@
+layout(set = 2, binding = 0) uniform descriptor_environment_block {
+	vec4 params;
+} descriptor_environment;
+
+// Irradiance, then prefiltered radiance.
+layout(set = 3, binding = 0) uniform samplerCube environment_maps[2];
+// BRDF LUT, then the shadow atlas (the LUT again without shadows).
+layout(set = 4, binding = 0) uniform sampler2D lighting_maps[2];
+
+layout(set = 5, binding = 0) uniform descriptor_pbr_material_block {
+	vec4 base_color;
+	vec4 emissive;
+	vec4 params;
+} descriptor_material;
+
+// Base color, metallic roughness, normal, occlusion, emissive.
+layout(set = 6, binding = 0) uniform sampler2D material_textures[5];
+
+#define MESH_MODEL_SET 7
+#include "mesh_model_block.glsl"
\ No newline at end of file
@@ -0,0 +1,52 @@
+#define PI 3.14159265359
+
+// Direction through the center of texel `texel.xy` of face `texel.z`,
+// faces ordered +x, -x, +y, -y, +z, -z as in a Vulkan cube image.
+vec3 cube_direction(uvec3 texel, uint size) {
+	vec2 uv = (vec2(texel.xy) + 0.5) / float(size) * 2.0 - 1.0;
+	vec3 direction;
+	switch (texel.z) {
+		case 0: direction = vec3(1.0, -uv.y, -uv.x); break;
+		case 1: direction = vec3(-1.0, -uv.y, uv.x); break;
+		case 2: direction = vec3(uv.x, 1.0, uv.y); break;
+		case 3: direction = vec3(uv.x, -1.0, -uv.y); break;
+		case 4: direction = vec3(uv.x, -uv.y, 1.0); break;
+		default: direction = vec3(-uv.x, -uv.y, -1.0); break;
+	}
+	return normalize(direction);
+}
+
+vec2 hammersley(uint i, uint count) {
+	uint bits = bitfieldReverse(i);
+	return vec2(float(i) / float(count), float(bits) * 2.3283064365386963e-10);
+}
+
+// Halfway vector around `normal` distributed like the GGX lobe of `roughness`.
+vec3 importance_sample_ggx(vec2 xi, vec3 normal, float roughness) {
+	float a = roughness * roughness;
+	float phi = 2.0 * PI * xi.x;
+	float cos_theta = sqrt((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y));
+	float sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+	vec3 h = vec3(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta);
+	vec3 up = abs(normal.z) < 0.999 ? vec3(0.0, 0.0, 1.0) : vec3(1.0, 0.0, 0.0);
+	vec3 tangent = normalize(cross(up, normal));
+	vec3 bitangent = cross(normal, tangent);
+	return normalize(tangent * h.x + bitangent * h.y + normal * h.z);
+}
+
+float distribution_ggx(float n_dot_h, float roughness) {
+	float a = roughness * roughness;
+	float a2 = a * a;
+	float d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+	return a2 / (PI * d * d);
+}
+
+float geometry_schlick_ggx(float n_dot_v, float k) {
+	return n_dot_v / (n_dot_v * (1.0 - k) + k);
+}
+
+// Smith shadowing and masking, `k` is (r + 1)² / 8 for analytic lights
+// and r² / 2 for image based lighting.
+float geometry_smith(float n_dot_v, float n_dot_l, float k) {
+	return geometry_schlick_ggx(n_dot_v, k) * geometry_schlick_ggx(n_dot_l, k);
+}
\ No newline at end of file
@@ -0,0 +1,65 @@
+// Needs light_block.glsl. Every shadow map is one tile of the atlas.
+#define SHADOW_ATLAS_COLUMNS 4
+#define SHADOW_ATLAS_ROWS 2
+
+// Fraction of light reaching `position` in shadow map `map`, filtered over
+// (2r + 1)² texels. Positions outside the map are lit.
+float sample_shadow_map(sampler2D atlas, int map, vec3 position, vec3 normal, vec3 to_light) {
+	vec2 atlas_size = vec2(textureSize(atlas, 0));
+	vec2 tile_size = atlas_size / vec2(SHADOW_ATLAS_COLUMNS, SHADOW_ATLAS_ROWS);
+	vec4 params = descriptor_lights.shadow_params;
+	// Push the lookup out along the normal, more so at grazing angles.
+	mat4 shadow_matrix = descriptor_lights.shadow_matrices[map];
+	vec3 row_x = vec3(shadow_matrix[0].x, shadow_matrix[1].x, shadow_matrix[2].x);
+	float w = (shadow_matrix * vec4(position, 1.0)).w;
+	float world_texel = 2.0 * w / (length(row_x) * tile_size.x);
+	float slope = 1.0 - clamp(dot(normal, to_light), 0.0, 1.0);
+	vec4 clip = shadow_matrix * vec4(position + normal * params.x * world_texel * (0.5 + slope), 1.0);
+	vec3 ndc = clip.xyz / clip.w;
+	vec2 uv = ndc.xy * 0.5 + 0.5;
+	if (any(lessThan(uv, vec2(0.0))) || any(greaterThan(uv, vec2(1.0))) || ndc.z > 1.0) {
+		return 1.0;
+	}
+	vec2 tile = vec2(map % SHADOW_ATLAS_COLUMNS, map / SHADOW_ATLAS_COLUMNS);
+	vec2 texel = 1.0 / atlas_size;
+	// Clamping keeps the filter from reading the neighbouring tiles.
+	vec2 tile_min = tile / vec2(SHADOW_ATLAS_COLUMNS, SHADOW_ATLAS_ROWS) + texel * 0.5;
+	vec2 tile_max = (tile + 1.0) / vec2(SHADOW_ATLAS_COLUMNS, SHADOW_ATLAS_ROWS) - texel * 0.5;
+	vec2 atlas_uv = (tile + uv) / vec2(SHADOW_ATLAS_COLUMNS, SHADOW_ATLAS_ROWS);
+	int radius = int(params.z);
+	float lit = 0.0;
+	for (int x = -radius; x <= radius; x++) {
+		for (int y = -radius; y <= radius; y++) {
+			vec2 sample_uv = clamp(atlas_uv + vec2(x, y) * texel, tile_min, tile_max);
+			float depth = texture(atlas, sample_uv).r;
+			lit += ndc.z - params.y <= depth ? 1.0 : 0.0;
+		}
+	}
+	return lit / float((2 * radius + 1) * (2 * radius + 1));
+}
+
+// Picks the first cascade of a directional light that covers `position`.
+float directional_shadow(sampler2D atlas, DirectionalLight light, vec3 position, vec3 normal) {
+	int first = int(light.direction.w);
+	if (first < 0) {
+		return 1.0;
+	}
+	vec3 to_light = -normalize(light.direction.xyz);
+	int cascades = int(descriptor_lights.shadow_params.w);
+	for (int i = 0; i < cascades; i++) {
+		vec4 clip = descriptor_lights.shadow_matrices[first + i] * vec4(position, 1.0);
+		// Inset so the filter of the last texels stays inside the cascade.
+		if (all(lessThan(abs(clip.xy), vec2(0.98))) && clip.z >= 0.0 && clip.z <= 1.0) {
+			return sample_shadow_map(atlas, first + i, position, normal, to_light);
+		}
+	}
+	return 1.0;
+}
+
+float spot_shadow(sampler2D atlas, SpotLight light, vec3 position, vec3 normal, vec3 to_light) {
+	int map = int(light.direction.w);
+	if (map < 0) {
+		return 1.0;
+	}
+	return sample_shadow_map(atlas, map, position, normal, to_light);
+}
\ No newline at end of file
@@ -0,0 +1,36 @@
+#version 450
+#extension GL_GOOGLE_include_directive : require
+
+layout(location = 0) in vec3 position;
+#ifdef SKINNED
+layout(location = 5) in uvec4 joints;
+layout(location = 6) in vec4 weights;
+#endif
+
+layout(push_constant) uniform push_constants_block {
+	mat4 light_view_projection;
+} push_constants;
+
+#define MESH_MODEL_SET 0
+#define MESH_JOINTS_SET 1
+#include "mesh_model_block.glsl"
+#ifdef SKINNED
+#include "joint_block.glsl"
+#endif
+
+void main() {
+#ifdef SKINNED
+	mat4 model = descriptor_model.model * (
+		weights.x * descriptor_joints.matrices[joints.x] +
+		weights.y * descriptor_joints.matrices[joints.y] +
+		weights.z * descriptor_joints.matrices[joints.z] +
+		weights.w * descriptor_joints.matrices[joints.w]
+	);
+#else
+	mat4 model = descriptor_model.model;
+#endif
+	gl_Position =
+		push_constants.light_view_projection *
+		model *
+		vec4(position, 1.0);
+}
\ No newline at end of file
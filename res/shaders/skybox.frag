@@ -0,0 +1,29 @@
+#version 450
+#extension GL_GOOGLE_include_directive : require
+
+layout(location = 0) in vec3 v_direction;
+
+layout(location = 0) out vec4 f_color;
+
+layout(set = 1, binding = 0) uniform descriptor_sky_block {
+	vec4 zenith;
+	vec4 horizon;
+	vec4 ground;
+	vec4 params;
+} descriptor_sky;
+layout(set = 2, binding = 0) uniform samplerCube sky_cubemap;
+
+vec3 gradient(vec3 direction) {
+	float height = clamp(direction.y, -1.0, 1.0);
+	float blend = pow(1.0 - abs(height), descriptor_sky.params.z);
+	vec3 pole = height >= 0.0 ? descriptor_sky.zenith.rgb : descriptor_sky.ground.rgb;
+	return mix(pole, descriptor_sky.horizon.rgb, blend);
+}
+
+void main() {
+	vec3 direction = normalize(v_direction);
+	vec3 color = descriptor_sky.params.x > 0.5 ?
+		textureLod(sky_cubemap, direction, 0.0).rgb :
+		gradient(direction);
+	f_color = vec4(color * descriptor_sky.params.y, 1.0);
+}
\ No newline at end of file
@@ -0,0 +1,16 @@
+#version 450
+#extension GL_GOOGLE_include_directive : require
+
+layout(location = 0) in vec3 position;
+
+layout(location = 0) out vec3 v_direction;
+
+#include "camera_block.glsl"
+
+void main() {
+	v_direction = position;
+	// Only the rotation of the view, the sky never gets closer.
+	vec4 clip = descriptor_camera.projection * vec4(mat3(descriptor_camera.view) * position, 1.0);
+	// Depth w / w lands every fragment on the far plane.
+	gl_Position = clip.xyww;
+}
\ No newline at end of file
@@ -0,0 +1,37 @@
+#version 450
+#extension GL_GOOGLE_include_directive : require
+
+layout(location = 0) in vec2 v_uv;
+layout(location = 1) in vec4 v_color;
+
+layout(location = 0) out vec4 f_color;
+
+#include "text_block.glsl"
+
+layout(set = 2, binding = 0) uniform sampler2D descriptor_font;
+
+void main() {
+	float range = descriptor_text.params.y;
+	// Screen pixels per distance field unit, from how fast the uvs change
+	// across the pixel. Keeps the edge a pixel wide at every zoom level.
+	vec2 unit_range = vec2(2.0 * range) / vec2(textureSize(descriptor_font, 0));
+	vec2 screen_size = vec2(1.0) / fwidth(v_uv);
+	float screen_range = max(0.5 * dot(unit_range, screen_size), 1.0);
+
+	float distance = texture(descriptor_font, v_uv).a - 0.5;
+	float fill = clamp(screen_range * distance + 0.5, 0.0, 1.0);
+	float outline_offset = descriptor_text.params.z / (2.0 * range);
+	float outline = clamp(screen_range * (distance + outline_offset) + 0.5, 0.0, 1.0);
+
+	// Fill composited over the outline, straight alpha out.
+	vec4 color = descriptor_text.color * v_color;
+	float fill_alpha = color.a * fill;
+	float outline_alpha = descriptor_text.params.z > 0.0 ?
+		descriptor_text.outline_color.a * outline * (1.0 - fill_alpha) :
+		0.0;
+	float alpha = fill_alpha + outline_alpha;
+	vec3 rgb =
+		color.rgb * fill_alpha +
+		descriptor_text.outline_color.rgb * outline_alpha;
+	f_color = vec4(rgb / max(alpha, 0.0001), alpha);
+}
\ No newline at end of file
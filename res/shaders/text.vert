@@ -0,0 +1,27 @@
+#version 450
+#extension GL_GOOGLE_include_directive : require
+
+layout(location = 0) in vec2 position;
+layout(location = 1) in vec2 uv;
+layout(location = 2) in vec4 color;
+
+layout(location = 0) out vec2 v_uv;
+layout(location = 1) out vec4 v_color;
+
+#include "camera_block.glsl"
+#include "text_block.glsl"
+
+void main() {
+	// Screen space text skips the view so it ignores pan and zoom.
+	mat4 view = descriptor_text.params.x > 0.5 ?
+		mat4(1.0) :
+		descriptor_camera.view;
+	vec4 screen_view =
+		descriptor_camera.projection *
+		view *
+		descriptor_text.model *
+		vec4(position, 0.0, 1.0);
+	v_uv = uv;
+	v_color = color;
+	gl_Position = screen_view;
+}
\ No newline at end of file
@@ -0,0 +1,7 @@
+layout(set = 1, binding = 1) uniform descriptor_text_block {
+	mat4 model;
+	vec4 color;
+	vec4 outline_color;
+	// x: screen space, y: distance range, z: outline width
+	vec4 params;
+} descriptor_text;
\ No newline at end of file
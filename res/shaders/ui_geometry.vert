@@ -1,14 +1,12 @@
 #version 450
+#extension GL_GOOGLE_include_directive : require
 
 layout(location = 0) in vec2 position;
 layout(location = 1) in vec4 color;
 
 layout(location = 0) out vec4 v_color;
 
-layout(set = 0, binding = 0) uniform descriptor_camera_block {
-	mat4 view;
-	mat4 projection;
-} descriptor_camera;
+#include "camera_block.glsl"
 
 layout(set = 1, binding = 1) uniform descriptor_model_block {
 	mat4 model;
@@ -23,6 +21,10 @@ void main() {
 		descriptor_camera.projection *
 		world_view *
 		vec4(position, 0.0, 1.0);
+#ifdef IGNORE_VERTEX_COLOR
+	v_color = descriptor_model.color;
+#else
 	v_color = color * descriptor_model.color;
+#endif
 	gl_Position = screen_view;
 }
\ No newline at end of file
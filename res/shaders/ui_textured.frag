@@ -0,0 +1,12 @@
+#version 450
+
+layout(location = 0) in vec2 v_uv;
+layout(location = 1) in vec4 v_color;
+
+layout(location = 0) out vec4 f_color;
+
+layout(set = 2, binding = 0) uniform sampler2D descriptor_texture;
+
+void main() {
+	f_color = texture(descriptor_texture, v_uv) * v_color;
+}
\ No newline at end of file
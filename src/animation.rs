@@ -0,0 +1,6 @@
+mod skeleton;
+pub use skeleton::*;
+mod clip;
+pub use clip::*;
+mod animator;
+pub use animator::*;
\ No newline at end of file
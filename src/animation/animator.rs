@@ -0,0 +1,186 @@
+use std::sync::Arc;
+
+use crate::{AnimationClip, JointPose, Skeleton};
+
+/// One clip playing in an `Animator`.
+#[derive(Clone, Debug)]
+pub struct AnimationLayer {
+	pub clip: Arc<AnimationClip>,
+	/// Seconds into the clip.
+	pub time: f32,
+	/// Playback rate, negative plays backwards.
+	pub speed: f32,
+	/// Wraps around at the end of the clip instead of holding the last key.
+	pub looping: bool,
+	/// Share of the blended pose relative to the other layers.
+	pub weight: f32,
+	/// Weight the layer fades towards, the layer is dropped once both
+	/// reach 0.
+	pub target_weight: f32,
+	/// Weight change per second while fading.
+	pub fade_rate: f32,
+}
+
+impl AnimationLayer {
+	pub fn new(
+		clip: Arc<AnimationClip>,
+	) -> Self {
+		Self {
+			clip,
+			time: 0.0,
+			speed: 1.0,
+			looping: true,
+			weight: 1.0,
+			target_weight: 1.0,
+			fade_rate: 0.0,
+		}
+	}
+
+	pub fn with_looping(
+		mut self,
+		looping: bool,
+	) -> Self {
+		self.looping = looping;
+		self
+	}
+
+	pub fn with_speed(
+		mut self,
+		speed: f32,
+	) -> Self {
+		self.speed = speed;
+		self
+	}
+
+	pub fn with_weight(
+		mut self,
+		weight: f32,
+	) -> Self {
+		self.weight = weight;
+		self.target_weight = weight;
+		self
+	}
+
+	/// Moves the weight to `target` over `duration` seconds.
+	pub fn fade_to(
+		&mut self,
+		target: f32,
+		duration: f32,
+	) {
+		self.target_weight = target;
+		self.fade_rate = match duration > 0.0 {
+			true => (target - self.weight).abs() / duration,
+			false => f32::INFINITY,
+		};
+	}
+
+	/// Clip ended and isn't looping, only meaningful for forward playback.
+	pub fn finished(
+		&self,
+	) -> bool {
+		!self.looping && self.time >= self.clip.duration
+	}
+
+	fn advance(
+		&mut self,
+		delta_time: f32,
+	) {
+		let duration = self.clip.duration;
+		self.time += delta_time * self.speed;
+		self.time = match (self.looping, duration > 0.0) {
+			(true, true) => self.time.rem_euclid(duration),
+			_ => self.time.clamp(0.0, duration),
+		};
+		let step = self.fade_rate * delta_time;
+		self.weight = match self.weight < self.target_weight {
+			true => (self.weight + step).min(self.target_weight),
+			false => (self.weight - step).max(self.target_weight),
+		};
+	}
+}
+
+/// Plays and blends clips of one skeleton. Layers are blended by their
+/// weights, so a clip at weight 1 and another at weight 3 give a pose a
+/// quarter of the way from the first to the second.
+#[derive(Clone, Debug, Default)]
+pub struct Animator {
+	pub layers: Vec<AnimationLayer>,
+}
+
+impl Animator {
+	pub fn new(
+	) -> Self {
+		Self::default()
+	}
+
+	/// Replaces every layer with `clip`, looping from the start.
+	pub fn play(
+		&mut self,
+		clip: Arc<AnimationClip>,
+	) {
+		self.layers.clear();
+		self.layers.push(AnimationLayer::new(clip));
+	}
+
+	/// Fades `clip` in from the start while every other layer fades out,
+	/// both over `duration` seconds.
+	pub fn crossfade(
+		&mut self,
+		clip: Arc<AnimationClip>,
+		duration: f32,
+	) {
+		for layer in self.layers.iter_mut() {
+			layer.fade_to(0.0, duration);
+		}
+		let mut layer = AnimationLayer::new(clip).with_weight(0.0);
+		layer.fade_to(1.0, duration);
+		self.layers.push(layer);
+	}
+
+	/// Adds a layer blended with the others, returns its index.
+	pub fn add_layer(
+		&mut self,
+		layer: AnimationLayer,
+	) -> usize {
+		self.layers.push(layer);
+		self.layers.len() - 1
+	}
+
+	/// Moves every layer forward and drops the ones that faded out.
+	pub fn advance(
+		&mut self,
+		delta_time: f32,
+	) {
+		for layer in self.layers.iter_mut() {
+			layer.advance(delta_time);
+		}
+		self.layers.retain(|x| x.weight > 0.0 || x.target_weight > 0.0);
+	}
+
+	/// Blended pose of every joint, the rest pose where no layer has weight.
+	pub fn sample(
+		&self,
+		skeleton: &Skeleton,
+		poses: &mut Vec<JointPose>,
+	) {
+		let rest = skeleton.rest_pose();
+		poses.clear();
+		poses.extend_from_slice(&rest);
+		let mut layer_poses = rest.clone();
+		let mut total_weight = 0.0;
+		for layer in self.layers.iter().filter(|x| x.weight > 0.0) {
+			layer_poses.copy_from_slice(&rest);
+			layer.clip.sample(
+				layer.time,
+				&mut layer_poses,
+			);
+			// Blending each layer in by its share of the weight so far
+			// averages the layers by weight.
+			total_weight += layer.weight;
+			let t = layer.weight / total_weight;
+			for (pose, layer_pose) in poses.iter_mut().zip(layer_poses.iter()) {
+				*pose = pose.blend(layer_pose, t);
+			}
+		}
+	}
+}
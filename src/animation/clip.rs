@@ -0,0 +1,148 @@
+use nalgebra::{Quaternion, UnitQuaternion, Vector4};
+
+use crate::{JointPose, animation::skeleton::nlerp};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interpolation {
+	Linear,
+	/// Holds each key until the next one.
+	Step,
+	/// Hermite spline through the keys with an in and out tangent per key.
+	CubicSpline,
+}
+
+/// Property of a joint a channel animates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChannelTarget {
+	Translation,
+	/// xyzw quaternion.
+	Rotation,
+	Scale,
+}
+
+/// Keyframes of one property of one joint.
+#[derive(Clone, Debug)]
+pub struct Channel {
+	pub joint: usize,
+	pub target: ChannelTarget,
+	pub interpolation: Interpolation,
+	/// Key times in seconds, ascending.
+	pub times: Vec<f32>,
+	/// One value per key with w unused for translation and scale. Cubic
+	/// splines have three per key: in tangent, value and out tangent.
+	pub values: Vec<[f32; 4]>,
+}
+
+impl Channel {
+	/// Value at `time`, clamped to the first and last key.
+	pub fn sample(
+		&self,
+		time: f32,
+	) -> Vector4<f32> {
+		let cubic = self.interpolation == Interpolation::CubicSpline;
+		let value = |key: usize| -> Vector4<f32> {
+			Vector4::from(match cubic {
+				true => self.values[key * 3 + 1],
+				false => self.values[key],
+			})
+		};
+		let last = match self.times.len() {
+			0 => return Vector4::zeros(),
+			x => x - 1,
+		};
+		if time <= self.times[0] {
+			return value(0);
+		}
+		if time >= self.times[last] {
+			return value(last);
+		}
+		let next = self.times.partition_point(|x| *x <= time);
+		let key = next - 1;
+		let duration = self.times[next] - self.times[key];
+		let t = match duration > 0.0 {
+			true => (time - self.times[key]) / duration,
+			false => 0.0,
+		};
+		let rotation = self.target == ChannelTarget::Rotation;
+		match self.interpolation {
+			Interpolation::Step => value(key),
+			Interpolation::Linear if rotation => nlerp(
+				&quaternion(value(key)),
+				&quaternion(value(next)),
+				t,
+			).coords,
+			Interpolation::Linear => value(key).lerp(&value(next), t),
+			Interpolation::CubicSpline => {
+				let out_tangent = Vector4::from(self.values[key * 3 + 2]) * duration;
+				let in_tangent = Vector4::from(self.values[next * 3]) * duration;
+				let (t2, t3) = (t * t, t * t * t);
+				let spline =
+					value(key) * (2.0 * t3 - 3.0 * t2 + 1.0) +
+					out_tangent * (t3 - 2.0 * t2 + t) +
+					value(next) * (-2.0 * t3 + 3.0 * t2) +
+					in_tangent * (t3 - t2);
+				match rotation {
+					true => quaternion(spline).coords,
+					false => spline,
+				}
+			},
+		}
+	}
+
+	/// Writes the value at `time` into the targeted property of `pose`.
+	pub fn apply(
+		&self,
+		time: f32,
+		pose: &mut JointPose,
+	) {
+		let value = self.sample(time);
+		match self.target {
+			ChannelTarget::Translation => pose.translation = value.xyz(),
+			ChannelTarget::Rotation => pose.rotation = quaternion(value),
+			ChannelTarget::Scale => pose.scale = value.xyz(),
+		}
+	}
+}
+
+fn quaternion(
+	value: Vector4<f32>,
+) -> UnitQuaternion<f32> {
+	UnitQuaternion::from_quaternion(Quaternion::from(value))
+}
+
+/// Channels animating the joints of one `Skeleton`.
+#[derive(Clone, Debug)]
+pub struct AnimationClip {
+	pub name: Option<String>,
+	/// Time of the last key of any channel.
+	pub duration: f32,
+	pub channels: Vec<Channel>,
+}
+
+impl AnimationClip {
+	pub fn new(
+		name: Option<String>,
+		channels: Vec<Channel>,
+	) -> Self {
+		let duration = channels.iter().filter_map(|x| x.times.last()).copied().fold(0.0, f32::max);
+		Self {
+			name,
+			duration,
+			channels,
+		}
+	}
+
+	/// Overwrites the animated properties of `poses` with their values at
+	/// `time`, joints the clip doesn't animate keep their pose.
+	pub fn sample(
+		&self,
+		time: f32,
+		poses: &mut [JointPose],
+	) {
+		for channel in self.channels.iter() {
+			if let Some(pose) = poses.get_mut(channel.joint) {
+				channel.apply(time, pose);
+			}
+		}
+	}
+}
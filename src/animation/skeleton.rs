@@ -0,0 +1,187 @@
+use nalgebra::{Matrix4, Quaternion, UnitQuaternion, Vector3};
+
+/// Local transform of a joint relative to its parent.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct JointPose {
+	pub translation: Vector3<f32>,
+	pub rotation: UnitQuaternion<f32>,
+	pub scale: Vector3<f32>,
+}
+
+impl Default for JointPose {
+	fn default() -> Self {
+		Self {
+			translation: Vector3::zeros(),
+			rotation: UnitQuaternion::identity(),
+			scale: Vector3::repeat(1.0),
+		}
+	}
+}
+
+impl JointPose {
+	pub fn matrix(
+		&self,
+	) -> Matrix4<f32> {
+		Matrix4::new_translation(&self.translation) *
+		self.rotation.to_homogeneous() *
+		Matrix4::new_nonuniform_scaling(&self.scale)
+	}
+
+	/// Splits a translation, rotation and scale matrix back into its parts,
+	/// shear is lost.
+	pub fn from_matrix(
+		matrix: &Matrix4<f32>,
+	) -> JointPose {
+		let linear = matrix.fixed_slice::<3, 3>(0, 0).into_owned();
+		let scale = Vector3::new(
+			linear.column(0).norm(),
+			linear.column(1).norm(),
+			linear.column(2).norm(),
+		);
+		let mut rotation = linear;
+		for (i, mut column) in rotation.column_iter_mut().enumerate() {
+			if scale[i] > 0.0 {
+				column /= scale[i];
+			}
+		}
+		JointPose {
+			translation: matrix.fixed_slice::<3, 1>(0, 3).into_owned(),
+			rotation: UnitQuaternion::from_matrix(&rotation),
+			scale,
+		}
+	}
+
+	/// `t` of the way from `self` to `other`, rotations take the shortest
+	/// path.
+	pub fn blend(
+		&self,
+		other: &JointPose,
+		t: f32,
+	) -> JointPose {
+		JointPose {
+			translation: self.translation.lerp(&other.translation, t),
+			rotation: nlerp(&self.rotation, &other.rotation, t),
+			scale: self.scale.lerp(&other.scale, t),
+		}
+	}
+}
+
+/// Normalized lerp along the shorter arc, close enough to slerp for
+/// neighbouring keyframes and blend weights.
+pub(crate) fn nlerp(
+	a: &UnitQuaternion<f32>,
+	b: &UnitQuaternion<f32>,
+	t: f32,
+) -> UnitQuaternion<f32> {
+	let b = match a.coords.dot(&b.coords) < 0.0 {
+		true => -b.coords,
+		false => b.coords,
+	};
+	UnitQuaternion::from_quaternion(Quaternion::from(a.coords.lerp(&b, t)))
+}
+
+#[derive(Clone, Debug)]
+pub struct Joint {
+	pub name: Option<String>,
+	/// Index of the parent joint, `None` for roots.
+	pub parent: Option<usize>,
+	/// Pose of the joint when no clip animates it.
+	pub rest: JointPose,
+	/// Takes mesh space to the joint's space at bind time.
+	pub inverse_bind: Matrix4<f32>,
+	/// Transform of a root joint's ancestors that aren't joints themselves,
+	/// ignored for other joints.
+	pub parent_transform: Matrix4<f32>,
+}
+
+/// Joint hierarchy a skinned mesh is bound to. Joint indices are the ones
+/// used by `VertexSkinned::joints` and `Channel::joint`.
+#[derive(Clone, Debug)]
+pub struct Skeleton {
+	joints: Vec<Joint>,
+	/// Joint indices with every parent before its children.
+	order: Vec<usize>,
+}
+
+impl Skeleton {
+	/// Panics when a parent index is out of range or the parents form a cycle.
+	pub fn new(
+		joints: Vec<Joint>,
+	) -> Self {
+		let mut order = Vec::with_capacity(joints.len());
+		let mut placed = vec![false; joints.len()];
+		while order.len() < joints.len() {
+			let before = order.len();
+			for (i, joint) in joints.iter().enumerate() {
+				if placed[i] {
+					continue;
+				}
+				let ready = match joint.parent {
+					Some(parent) => {
+						assert!(parent < joints.len(), "joint {} has parent {} out of range", i, parent);
+						placed[parent]
+					},
+					None => true,
+				};
+				if ready {
+					placed[i] = true;
+					order.push(i);
+				}
+			}
+			assert!(order.len() > before, "skeleton joints form a cycle");
+		}
+		Self {
+			joints,
+			order,
+		}
+	}
+
+	pub fn joints(
+		&self,
+	) -> &[Joint] {
+		&self.joints
+	}
+
+	pub fn joint_index(
+		&self,
+		name: &str,
+	) -> Option<usize> {
+		self.joints.iter().position(|x| x.name.as_deref() == Some(name))
+	}
+
+	pub fn rest_pose(
+		&self,
+	) -> Vec<JointPose> {
+		self.joints.iter().map(|x| x.rest).collect()
+	}
+
+	/// Joint to model space transform of every joint for `poses`.
+	pub fn global_transforms(
+		&self,
+		poses: &[JointPose],
+	) -> Vec<Matrix4<f32>> {
+		assert_eq!(poses.len(), self.joints.len(), "pose doesn't match the skeleton");
+		let mut globals = vec![Matrix4::identity(); self.joints.len()];
+		for i in self.order.iter().copied() {
+			let joint = &self.joints[i];
+			let parent = match joint.parent {
+				Some(parent) => globals[parent],
+				None => joint.parent_transform,
+			};
+			globals[i] = parent * poses[i].matrix();
+		}
+		globals
+	}
+
+	/// Skinning matrices for `poses`, global transforms times inverse bind
+	/// matrices.
+	pub fn joint_matrices(
+		&self,
+		poses: &[JointPose],
+	) -> Vec<Matrix4<f32>> {
+		self.global_transforms(poses).iter().zip(self.joints.iter()).map(
+			|(global, joint)|
+			global * joint.inverse_bind
+		).collect()
+	}
+}
@@ -6,10 +6,22 @@ mod camera2d;
 pub use camera2d::*;
 mod model_example;
 pub use model_example::*;
+mod text;
+pub use text::*;
+mod lights;
+pub use lights::*;
+mod mesh_model;
+pub use mesh_model::*;
+mod pbr;
+pub use pbr::*;
+mod sky;
+pub use sky::*;
+mod skin;
+pub use skin::*;
 use nalgebra::Matrix4;
 use vpb::ProgramData;
 
-use crate::{InputState, RenderState};
+use crate::{InputState, RenderState, CameraDescription};
 
 pub trait Camera {
 	fn build_perspective(
@@ -34,4 +46,18 @@ pub trait Camera {
 	fn get_view(
 		&self,
 	) -> (Matrix4<f32>, f32);
+
+	/// Projection of a perspective camera, lets shadow cascades follow
+	/// the view frustum.
+	fn get_projection(
+		&self,
+	) -> Option<Matrix4<f32>> {
+		None
+	}
+
+	fn describe(
+		&self,
+	) -> Option<CameraDescription> {
+		None
+	}
 }
\ No newline at end of file
@@ -1,13 +1,13 @@
-use std::{mem::size_of, sync::Arc, f32::consts::{PI, FRAC_PI_2}, collections::{HashSet, BTreeMap}};
+use std::{sync::Arc, f32::consts::{PI, FRAC_PI_2}, collections::{HashSet, BTreeMap}};
 
-use ash::vk;
 use bytemuck::{Zeroable, Pod};
 use nalgebra::{Matrix4, vector, Vector3, Vector2, Perspective3, Orthographic3, point, Point3};
-use vpb::{BindingId, DDType, DescriptorDescription, DDTypeUniform, ProgramData};
-use crate::{InputState, RenderState, Camera};
+use vpb::ProgramData;
+use crate::{InputState, RenderState, Camera, CameraDescription, Block, BlockStateExt};
 
 #[repr(C)]
-#[derive(Debug, Copy, Clone, Pod, Zeroable)]
+#[derive(Debug, Copy, Clone, Pod, Zeroable, Block)]
+#[block(descriptor = uniform, stages(vertex))]
 pub struct BlockCamera2d {
 	pub view: [f32; 16],
 	pub projection: [f32; 16],
@@ -22,49 +22,6 @@ impl Default for BlockCamera2d {
 	}
 }
 
-impl vpb::Block for BlockCamera2d {
-	fn create_block_state(
-		program_data: &ProgramData,
-		descriptor_set_layout: &vk::DescriptorSetLayout,
-		frame_count: usize,
-		binding: BindingId,
-		set: vpb::SetId,
-	) -> Arc<vpb::BlockState> {
-		Arc::new(vpb::BlockState::new(
-			program_data,
-			descriptor_set_layout,
-			frame_count,
-			set,
-			DescriptorDescription::new(vec![
-				DDType::Uniform(DDTypeUniform {
-					binding,
-					size: size_of::<BlockCamera2d>(),
-				}),
-			]),
-		))
-	}
-
-	fn create_descriptor_set_layout(
-		device: &Arc<vpb::Device>,
-		binding: BindingId,
-	) -> vk::DescriptorSetLayout { unsafe {
-		let descriptor_set_layout_binding = vk::DescriptorSetLayoutBinding::builder()
-			.binding(binding.0)
-			.descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
-			.stage_flags(vk::ShaderStageFlags::VERTEX)
-			.descriptor_count(1)
-			.build();
-		let descriptor_set_layout_info = vk::DescriptorSetLayoutCreateInfo::builder()
-			.bindings(&[
-				descriptor_set_layout_binding,
-			]).build();
-		device.device.create_descriptor_set_layout(
-			&descriptor_set_layout_info,
-			None,
-		).unwrap()
-	}}
-}
-
 #[derive(Default, Debug)]
 pub struct CameraState2d {
 	pub block: BlockCamera2d,
@@ -123,6 +80,20 @@ impl CameraState2d {
 		}
 	}
 
+	/// Jumps straight to a zoom level without easing into it.
+	pub fn set_zoom_index(
+		&mut self,
+		predistance_index: i16,
+	) {
+		self.predistance_index = predistance_index.max(0);
+		self.predistance = CameraState2d::calc_predistance(
+			self.predistance_index,
+			self.step_factor_exp,
+			self.step_factor_mul,
+		);
+		self.postdistance = self.predistance;
+	}
+
 	fn calc_predistance(
 		index: i16,
 		step_factor_exp: f32,
@@ -218,7 +189,7 @@ impl Camera for CameraState2d {
 		frame: Option<usize>,
 		block_state: &Arc<vpb::BlockState>,
 	) {
-		block_state.update(
+		block_state.update_checked(
 			device,
 			&self.block,
 			frame,
@@ -231,4 +202,16 @@ impl Camera for CameraState2d {
 		let translate_scale_dynamic = self.max_zoom / (self.postdistance as f32);
 		(Matrix4::<f32>::from_column_slice(&self.block.view), translate_scale_dynamic)
 	}
+
+	fn describe(
+		&self,
+	) -> Option<CameraDescription> {
+		Some(CameraDescription::Camera2d {
+			position: [
+				self.camera_preposition.x,
+				self.camera_preposition.y,
+			],
+			zoom_index: self.predistance_index,
+		})
+	}
 }
\ No newline at end of file
@@ -1,15 +1,15 @@
-use std::{mem::size_of, sync::Arc, f32::consts::{PI, FRAC_PI_2}};
+use std::{sync::Arc, f32::consts::{PI, FRAC_PI_2}};
 
-use ash::vk;
 use bytemuck::{Zeroable, Pod};
 use glfw::ffi;
 use nalgebra::{Matrix4, vector, Vector3, Vector2, Perspective3};
-use vpb::{DDType, DDTypeUniform, DescriptorDescription, BindingId, ProgramData};
+use vpb::ProgramData;
 
-use crate::{InputState, RenderState, Camera};
+use crate::{InputState, RenderState, Camera, CameraDescription, Block, BlockStateExt};
 
 #[repr(C)]
-#[derive(Debug, Copy, Clone, Pod, Zeroable)]
+#[derive(Debug, Copy, Clone, Pod, Zeroable, Block)]
+#[block(descriptor = uniform, stages(vertex))]
 pub struct BlockCamera3d {
 	pub view: [f32; 16],
 	pub projection: [f32; 16],
@@ -24,49 +24,6 @@ impl Default for BlockCamera3d {
 	}
 }
 
-impl vpb::Block for BlockCamera3d {
-	fn create_block_state(
-		program_data: &ProgramData,
-		descriptor_set_layout: &vk::DescriptorSetLayout,
-		frame_count: usize,
-		binding: BindingId,
-		set: vpb::SetId,
-	) -> Arc<vpb::BlockState> {
-		Arc::new(vpb::BlockState::new(
-			program_data,
-			descriptor_set_layout,
-			frame_count,
-			set,
-			DescriptorDescription::new(vec![
-				DDType::Uniform(DDTypeUniform {
-					binding,
-					size: size_of::<BlockCamera3d>(),
-				}),
-			]),
-		))
-	}
-
-	fn create_descriptor_set_layout(
-		device: &Arc<vpb::Device>,
-		binding: BindingId,
-	) -> vk::DescriptorSetLayout { unsafe {
-		let descriptor_set_layout_binding = vk::DescriptorSetLayoutBinding::builder()
-			.binding(binding.0)
-			.descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
-			.stage_flags(vk::ShaderStageFlags::VERTEX)
-			.descriptor_count(1)
-			.build();
-		let descriptor_set_layout_info = vk::DescriptorSetLayoutCreateInfo::builder()
-			.bindings(&[
-				descriptor_set_layout_binding,
-			]).build();
-		device.device.create_descriptor_set_layout(
-			&descriptor_set_layout_info,
-			None,
-		).unwrap()
-	}}
-}
-
 #[derive(Default, Debug)]
 pub struct CameraState3d {
 	pub block: BlockCamera3d,
@@ -211,7 +168,7 @@ impl Camera for CameraState3d {
 		frame: Option<usize>,
 		block_state: &Arc<vpb::BlockState>,
 	) {
-		block_state.update(
+		block_state.update_checked(
 			device,
 			&self.block,
 			frame,
@@ -223,4 +180,26 @@ impl Camera for CameraState3d {
 	) -> (Matrix4<f32>, f32) {
 		(Matrix4::from_column_slice(&self.block.view), 1.0)
 	}
+
+	fn get_projection(
+		&self,
+	) -> Option<Matrix4<f32>> {
+		Some(Matrix4::from_column_slice(&self.block.projection))
+	}
+
+	fn describe(
+		&self,
+	) -> Option<CameraDescription> {
+		Some(CameraDescription::Camera3d {
+			position: [
+				self.camera_preposition.x,
+				self.camera_preposition.y,
+				self.camera_preposition.z,
+			],
+			rotation: [
+				self.camera_rotation.x,
+				self.camera_rotation.y,
+			],
+		})
+	}
 }
\ No newline at end of file
@@ -0,0 +1,199 @@
+use bytemuck::{Zeroable, Pod};
+use nalgebra::Vector3;
+
+use crate::{Block, Std140, Std430, Std140Vec4, Std140Mat4, Std140Array};
+
+pub const MAX_DIRECTIONAL_LIGHTS: usize = 4;
+pub const MAX_POINT_LIGHTS: usize = 16;
+pub const MAX_SPOT_LIGHTS: usize = 8;
+/// Tiles of the shadow atlas, each directional cascade and each spot
+/// light takes one.
+pub const MAX_SHADOW_MAPS: usize = 8;
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, Pod, Zeroable)]
+pub struct DirectionalLight {
+	/// xyz the light travels along, w shadow map of the first cascade or -1.
+	pub direction: Std140Vec4,
+	/// rgb color, a intensity.
+	pub color: Std140Vec4,
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, Pod, Zeroable)]
+pub struct PointLight {
+	/// xyz world position, w range past which the light has no effect.
+	pub position: Std140Vec4,
+	/// rgb color, a intensity.
+	pub color: Std140Vec4,
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, Pod, Zeroable)]
+pub struct SpotLight {
+	/// xyz world position, w range past which the light has no effect.
+	pub position: Std140Vec4,
+	/// xyz the light points along, w shadow map or -1.
+	pub direction: Std140Vec4,
+	/// rgb color, a intensity.
+	pub color: Std140Vec4,
+	/// x cosine of the inner cone angle, y of the outer one, zw unused.
+	pub cone: Std140Vec4,
+}
+
+unsafe impl Std140 for DirectionalLight {
+	const ALIGN: usize = 16;
+	const SIZE: usize = 32;
+}
+
+unsafe impl Std430 for DirectionalLight {
+	const ALIGN: usize = 16;
+	const SIZE: usize = 32;
+}
+
+unsafe impl Std140 for PointLight {
+	const ALIGN: usize = 16;
+	const SIZE: usize = 32;
+}
+
+unsafe impl Std430 for PointLight {
+	const ALIGN: usize = 16;
+	const SIZE: usize = 32;
+}
+
+unsafe impl Std140 for SpotLight {
+	const ALIGN: usize = 16;
+	const SIZE: usize = 64;
+}
+
+unsafe impl Std430 for SpotLight {
+	const ALIGN: usize = 16;
+	const SIZE: usize = 64;
+}
+
+/// Every light of a scene, matches `res/shaders/light_block.glsl`.
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, Pod, Zeroable, Block)]
+#[block(descriptor = uniform, stages(fragment))]
+pub struct BlockLights {
+	/// rgb ambient light added to every surface, a unused.
+	pub ambient: Std140Vec4,
+	/// x directional lights, y point lights, z spot lights in use.
+	pub counts: [u32; 4],
+	/// Lights that should cast shadows, one bit per light: x directional,
+	/// y spot. Read by `ShadowMaps::assign`, not by shaders.
+	pub shadow_casters: [u32; 4],
+	/// x normal offset in shadow map texels, y depth bias, z PCF radius in
+	/// texels, w cascades per directional light. Written by `ShadowMaps`.
+	pub shadow_params: Std140Vec4,
+	pub directional: Std140Array<DirectionalLight, MAX_DIRECTIONAL_LIGHTS>,
+	pub point: Std140Array<PointLight, MAX_POINT_LIGHTS>,
+	pub spot: Std140Array<SpotLight, MAX_SPOT_LIGHTS>,
+	/// World to shadow map clip space, indexed by the lights' shadow maps.
+	pub shadow_matrices: Std140Array<Std140Mat4, MAX_SHADOW_MAPS>,
+}
+
+impl BlockLights {
+	pub fn clear(
+		&mut self,
+	) {
+		self.counts = [0; 4];
+		self.shadow_casters = [0; 4];
+	}
+
+	/// Returns false when every directional slot is taken.
+	pub fn push_directional(
+		&mut self,
+		direction: Vector3<f32>,
+		color: [f32; 3],
+		intensity: f32,
+	) -> bool {
+		let count = self.counts[0] as usize;
+		if count == MAX_DIRECTIONAL_LIGHTS {
+			return false;
+		}
+		let direction = direction.normalize();
+		self.directional.elements[count] = DirectionalLight {
+			direction: Std140Vec4 { value: [direction.x, direction.y, direction.z, -1.0] },
+			color: Std140Vec4 { value: [color[0], color[1], color[2], intensity] },
+		};
+		self.counts[0] += 1;
+		true
+	}
+
+	/// Returns false when every point slot is taken.
+	pub fn push_point(
+		&mut self,
+		position: Vector3<f32>,
+		color: [f32; 3],
+		intensity: f32,
+		range: f32,
+	) -> bool {
+		let count = self.counts[1] as usize;
+		if count == MAX_POINT_LIGHTS {
+			return false;
+		}
+		self.point.elements[count] = PointLight {
+			position: Std140Vec4 { value: [position.x, position.y, position.z, range] },
+			color: Std140Vec4 { value: [color[0], color[1], color[2], intensity] },
+		};
+		self.counts[1] += 1;
+		true
+	}
+
+	/// Returns false when every spot slot is taken. Angles are the half
+	/// angles of the cone in radians, the light fades out between them.
+	pub fn push_spot(
+		&mut self,
+		position: Vector3<f32>,
+		direction: Vector3<f32>,
+		color: [f32; 3],
+		intensity: f32,
+		range: f32,
+		inner_angle: f32,
+		outer_angle: f32,
+	) -> bool {
+		let count = self.counts[2] as usize;
+		if count == MAX_SPOT_LIGHTS {
+			return false;
+		}
+		let direction = direction.normalize();
+		self.spot.elements[count] = SpotLight {
+			position: Std140Vec4 { value: [position.x, position.y, position.z, range] },
+			direction: Std140Vec4 { value: [direction.x, direction.y, direction.z, -1.0] },
+			color: Std140Vec4 { value: [color[0], color[1], color[2], intensity] },
+			cone: Std140Vec4 { value: [inner_angle.cos(), outer_angle.max(inner_angle).cos(), 0.0, 0.0] },
+		};
+		self.counts[2] += 1;
+		true
+	}
+
+	/// Makes directional light `index` cast shadows once the pipeline is
+	/// given `ShadowMaps`.
+	pub fn set_directional_shadow(
+		&mut self,
+		index: usize,
+		enabled: bool,
+	) {
+		set_bit(&mut self.shadow_casters[0], index, enabled);
+	}
+
+	pub fn set_spot_shadow(
+		&mut self,
+		index: usize,
+		enabled: bool,
+	) {
+		set_bit(&mut self.shadow_casters[1], index, enabled);
+	}
+}
+
+fn set_bit(
+	bits: &mut u32,
+	index: usize,
+	enabled: bool,
+) {
+	match enabled {
+		true => *bits |= 1 << index,
+		false => *bits &= !(1 << index),
+	}
+}
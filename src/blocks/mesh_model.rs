@@ -0,0 +1,25 @@
+use bytemuck::{Zeroable, Pod};
+use nalgebra::Matrix4;
+
+use crate::{Block, Std140Mat4, Std140Vec4};
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Pod, Zeroable, Block)]
+#[block(descriptor = uniform, stages(vertex, fragment))]
+pub struct BlockMeshModel {
+	pub model: Std140Mat4,
+	/// Multiplies the vertex color.
+	pub color: Std140Vec4,
+	/// rgb specular color, a Blinn-Phong shininess exponent.
+	pub specular: Std140Vec4,
+}
+
+impl Default for BlockMeshModel {
+	fn default() -> Self {
+		Self {
+			model: Matrix4::identity().into(),
+			color: Std140Vec4 { value: [1.0; 4] },
+			specular: Std140Vec4 { value: [0.5, 0.5, 0.5, 32.0] },
+		}
+	}
+}
@@ -0,0 +1,43 @@
+use bytemuck::{Zeroable, Pod};
+
+use crate::{Block, Std140Vec4};
+
+/// Image based lighting settings, matches `res/shaders/pbr_blocks.glsl`.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Pod, Zeroable, Block)]
+#[block(descriptor = uniform, stages(fragment))]
+pub struct BlockEnvironment {
+	/// x exposure applied before tonemapping, y environment intensity, z
+	/// mip levels of the prefiltered map, w unused.
+	pub params: Std140Vec4,
+}
+
+impl Default for BlockEnvironment {
+	fn default() -> Self {
+		Self {
+			params: Std140Vec4 { value: [1.0, 1.0, 1.0, 0.0] },
+		}
+	}
+}
+
+/// Metallic-roughness material factors, each multiplies its texture.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Pod, Zeroable, Block)]
+#[block(descriptor = uniform, stages(fragment))]
+pub struct BlockPbrMaterial {
+	pub base_color: Std140Vec4,
+	/// rgb emissive color, a unused.
+	pub emissive: Std140Vec4,
+	/// x metallic, y roughness, z normal map scale, w occlusion strength.
+	pub params: Std140Vec4,
+}
+
+impl Default for BlockPbrMaterial {
+	fn default() -> Self {
+		Self {
+			base_color: Std140Vec4 { value: [1.0; 4] },
+			emissive: Std140Vec4 { value: [0.0; 4] },
+			params: Std140Vec4 { value: [1.0, 1.0, 1.0, 1.0] },
+		}
+	}
+}
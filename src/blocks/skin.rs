@@ -0,0 +1,15 @@
+use bytemuck::{Zeroable, Pod};
+
+use crate::{Block, Std140Mat4};
+
+/// Joints a `SkinnedMeshObject` can upload, sizes its storage buffer.
+pub const MAX_JOINTS: usize = 128;
+
+/// Element of the joint storage buffer, matches `res/shaders/joint_block.glsl`.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Pod, Zeroable, Block)]
+#[block(descriptor = storage, stages(vertex))]
+pub struct BlockJoint {
+	/// Global joint transform times the inverse bind matrix.
+	pub matrix: Std140Mat4,
+}
@@ -0,0 +1,32 @@
+use bytemuck::{Zeroable, Pod};
+
+use crate::{Block, Std140Vec4};
+
+/// Sky settings of `PipelineSkybox`, matches `res/shaders/skybox.frag`.
+/// The gradient is drawn whenever no cubemap is bound.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Pod, Zeroable, Block)]
+#[block(descriptor = uniform, stages(fragment))]
+pub struct BlockSky {
+	/// Color straight up, a unused.
+	pub zenith: Std140Vec4,
+	/// Color at the horizon, a unused.
+	pub horizon: Std140Vec4,
+	/// Color straight down, a unused.
+	pub ground: Std140Vec4,
+	/// x 1 when sampling the cubemap and 0 for the gradient, y intensity,
+	/// z gradient falloff exponent, higher keeps the horizon color closer
+	/// to the horizon, w unused.
+	pub params: Std140Vec4,
+}
+
+impl Default for BlockSky {
+	fn default() -> Self {
+		Self {
+			zenith: Std140Vec4 { value: [0.18, 0.36, 0.72, 1.0] },
+			horizon: Std140Vec4 { value: [0.72, 0.82, 0.92, 1.0] },
+			ground: Std140Vec4 { value: [0.24, 0.22, 0.2, 1.0] },
+			params: Std140Vec4 { value: [0.0, 1.0, 3.0, 0.0] },
+		}
+	}
+}
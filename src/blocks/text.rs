@@ -0,0 +1,27 @@
+use bytemuck::{Zeroable, Pod};
+use nalgebra::Matrix4;
+
+use crate::{Block, Std140Mat4, Std140Vec4};
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Pod, Zeroable, Block)]
+#[block(descriptor = uniform, stages(vertex, fragment))]
+pub struct BlockText {
+	pub model: Std140Mat4,
+	pub color: Std140Vec4,
+	pub outline_color: Std140Vec4,
+	/// x: 1 to skip the camera view (screen space), y: distance field
+	/// range of the font, z: outline width, both in atlas pixels.
+	pub params: Std140Vec4,
+}
+
+impl Default for BlockText {
+	fn default() -> Self {
+		Self {
+			model: Matrix4::identity().into(),
+			color: Std140Vec4 { value: [1.0; 4] },
+			outline_color: Std140Vec4 { value: [0.0, 0.0, 0.0, 1.0] },
+			params: Std140Vec4 { value: [0.0; 4] },
+		}
+	}
+}
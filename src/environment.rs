@@ -0,0 +1,646 @@
+use std::{ffi::CStr, path::Path};
+
+use ash::vk;
+use shaderc::ShaderKind;
+use vpb::ProgramData;
+
+use crate::{Texture, TextureError, TextureOptions, Cubemap, Sampler, SamplerDescription, ShaderDefines, compile_shader, create_shader_module, create_device_image, transition_image_layers, generate_mipmaps, mip_level_count, submit_setup_commands};
+
+#[derive(Clone, Copy, Debug)]
+pub struct EnvironmentOptions {
+	/// Faces of the cubemap the equirectangular image is resampled into.
+	pub cube_size: u32,
+	pub irradiance_size: u32,
+	pub prefiltered_size: u32,
+	/// Roughness levels of the prefiltered map, 0 at the top mip and 1 at
+	/// the last. Clamped to the map's mip count.
+	pub prefiltered_levels: u32,
+	/// GGX samples per prefiltered texel and per BRDF LUT texel.
+	pub sample_count: u32,
+	pub brdf_lut_size: u32,
+}
+
+impl Default for EnvironmentOptions {
+	fn default() -> Self {
+		Self {
+			cube_size: 512,
+			irradiance_size: 32,
+			prefiltered_size: 256,
+			prefiltered_levels: 6,
+			sample_count: 1024,
+			brdf_lut_size: 256,
+		}
+	}
+}
+
+/// Image based lighting generated on the GPU from an equirectangular HDR.
+/// Every map is RGBA16F and linear.
+pub struct Environment {
+	/// The source resampled into a cubemap with a full mip chain.
+	pub cubemap: Cubemap,
+	/// Cosine convolved radiance, looked up by normal.
+	pub irradiance: Cubemap,
+	/// GGX prefiltered radiance, looked up by reflection vector at mip
+	/// `roughness * (mip_levels - 1)`.
+	pub prefiltered: Cubemap,
+	/// Split sum scale in r and bias in g of the specular BRDF, by n·v
+	/// along u and roughness along v.
+	pub brdf_lut: Texture,
+}
+
+impl Environment {
+	pub fn from_hdr<P: AsRef<Path>>(
+		program_data: &ProgramData,
+		pipeline_cache: vk::PipelineCache,
+		path: P,
+		options: &EnvironmentOptions,
+	) -> Result<Self, TextureError> {
+		let mut source = load_equirectangular(
+			program_data,
+			path,
+		)?;
+		let environment = Environment::from_equirectangular(
+			program_data,
+			pipeline_cache,
+			&source,
+			options,
+		);
+		source.destroy(program_data);
+		Ok(environment)
+	}
+
+	/// `source` must be a linear equirectangular panorama, +y up.
+	pub fn from_equirectangular(
+		program_data: &ProgramData,
+		pipeline_cache: vk::PipelineCache,
+		source: &Texture,
+		options: &EnvironmentOptions,
+	) -> Self { unsafe {
+		let device = &program_data.device.device;
+		let format = vk::Format::R16G16B16A16_SFLOAT;
+		let cube_mips = mip_level_count(options.cube_size, options.cube_size);
+		let prefiltered_levels = options.prefiltered_levels.clamp(
+			1,
+			mip_level_count(options.prefiltered_size, options.prefiltered_size),
+		);
+		let cubemap = Cubemap::new(
+			program_data,
+			options.cube_size,
+			cube_mips,
+			format,
+			vk::ImageUsageFlags::STORAGE | vk::ImageUsageFlags::TRANSFER_SRC | vk::ImageUsageFlags::TRANSFER_DST,
+			SamplerDescription::default(),
+		);
+		let irradiance = Cubemap::new(
+			program_data,
+			options.irradiance_size,
+			1,
+			format,
+			vk::ImageUsageFlags::STORAGE,
+			SamplerDescription::default(),
+		);
+		let prefiltered = Cubemap::new(
+			program_data,
+			options.prefiltered_size,
+			prefiltered_levels,
+			format,
+			vk::ImageUsageFlags::STORAGE,
+			SamplerDescription::default(),
+		);
+		let brdf_lut = storage_texture(
+			program_data,
+			options.brdf_lut_size,
+			format,
+		);
+
+		let mut pipelines = GenerationPipelines::new(
+			program_data,
+			pipeline_cache,
+			&["environment_cube", "environment_irradiance", "environment_prefilter", "brdf_lut"],
+			3 + prefiltered_levels,
+		);
+		let cube_view = cubemap.level_view(program_data, 0);
+		let irradiance_view = irradiance.level_view(program_data, 0);
+		let prefiltered_views: Vec<vk::ImageView> = (0..prefiltered_levels).map(
+			|x|
+			prefiltered.level_view(program_data, x)
+		).collect();
+		submit_setup_commands(program_data, |command_buffer| {
+			let transition = |image, old_layout, new_layout, mip_levels, layers| {
+				transition_image_layers(
+					&program_data.device,
+					command_buffer,
+					image,
+					old_layout,
+					new_layout,
+					mip_levels,
+					layers,
+				);
+			};
+			record_equirectangular_cube(
+				&program_data.device,
+				command_buffer,
+				&mut pipelines,
+				source,
+				&cubemap,
+				cube_view,
+			);
+			// The blits only wait for fragment shaders, the convolutions are compute.
+			transition(cubemap.image, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL, cube_mips, 6);
+
+			transition(irradiance.image, vk::ImageLayout::UNDEFINED, vk::ImageLayout::GENERAL, 1, 6);
+			pipelines.dispatch(
+				&program_data.device,
+				command_buffer,
+				GenerationPipelines::IRRADIANCE,
+				Some((cubemap.image_view, cubemap.sampler.sampler)),
+				irradiance_view,
+				[0; 2],
+				[options.irradiance_size, options.irradiance_size, 6],
+			);
+			transition(irradiance.image, vk::ImageLayout::GENERAL, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL, 1, 6);
+
+			transition(prefiltered.image, vk::ImageLayout::UNDEFINED, vk::ImageLayout::GENERAL, prefiltered_levels, 6);
+			for (level, view) in prefiltered_views.iter().enumerate() {
+				let roughness = match prefiltered_levels {
+					1 => 0.0,
+					levels => level as f32 / (levels - 1) as f32,
+				};
+				let size = prefiltered.level_size(level as u32);
+				pipelines.dispatch(
+					&program_data.device,
+					command_buffer,
+					GenerationPipelines::PREFILTER,
+					Some((cubemap.image_view, cubemap.sampler.sampler)),
+					*view,
+					[roughness.to_bits(), options.sample_count],
+					[size, size, 6],
+				);
+			}
+			transition(prefiltered.image, vk::ImageLayout::GENERAL, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL, prefiltered_levels, 6);
+
+			transition(brdf_lut.image, vk::ImageLayout::UNDEFINED, vk::ImageLayout::GENERAL, 1, 1);
+			pipelines.dispatch(
+				&program_data.device,
+				command_buffer,
+				GenerationPipelines::BRDF_LUT,
+				None,
+				brdf_lut.image_view,
+				[0, options.sample_count],
+				[options.brdf_lut_size, options.brdf_lut_size, 1],
+			);
+			transition(brdf_lut.image, vk::ImageLayout::GENERAL, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL, 1, 1);
+		});
+		pipelines.destroy(program_data);
+		for view in std::iter::once(cube_view).chain(std::iter::once(irradiance_view)).chain(prefiltered_views) {
+			device.destroy_image_view(
+				view,
+				None,
+			);
+		}
+		Self {
+			cubemap,
+			irradiance,
+			prefiltered,
+			brdf_lut,
+		}
+	}}
+
+	pub fn destroy(
+		&mut self,
+		program_data: &ProgramData,
+	) {
+		self.cubemap.destroy(program_data);
+		self.irradiance.destroy(program_data);
+		self.prefiltered.destroy(program_data);
+		self.brdf_lut.destroy(program_data);
+	}
+}
+
+impl Cubemap {
+	/// Resamples a Radiance `.hdr` panorama, see `from_equirectangular`.
+	pub fn from_hdr<P: AsRef<Path>>(
+		program_data: &ProgramData,
+		pipeline_cache: vk::PipelineCache,
+		path: P,
+		size: u32,
+	) -> Result<Self, TextureError> {
+		let mut source = load_equirectangular(
+			program_data,
+			path,
+		)?;
+		let cubemap = Cubemap::from_equirectangular(
+			program_data,
+			pipeline_cache,
+			&source,
+			size,
+		);
+		source.destroy(program_data);
+		Ok(cubemap)
+	}
+
+	/// Resamples a linear equirectangular panorama, +y up, into an RGBA16F
+	/// cubemap with a full mip chain.
+	pub fn from_equirectangular(
+		program_data: &ProgramData,
+		pipeline_cache: vk::PipelineCache,
+		source: &Texture,
+		size: u32,
+	) -> Self { unsafe {
+		let cubemap = Cubemap::new(
+			program_data,
+			size,
+			mip_level_count(size, size),
+			vk::Format::R16G16B16A16_SFLOAT,
+			vk::ImageUsageFlags::STORAGE | vk::ImageUsageFlags::TRANSFER_SRC | vk::ImageUsageFlags::TRANSFER_DST,
+			SamplerDescription::default(),
+		);
+		let mut pipelines = GenerationPipelines::new(
+			program_data,
+			pipeline_cache,
+			&["environment_cube"],
+			1,
+		);
+		let cube_view = cubemap.level_view(program_data, 0);
+		submit_setup_commands(program_data, |command_buffer| {
+			record_equirectangular_cube(
+				&program_data.device,
+				command_buffer,
+				&mut pipelines,
+				source,
+				&cubemap,
+				cube_view,
+			);
+		});
+		pipelines.destroy(program_data);
+		program_data.device.device.destroy_image_view(
+			cube_view,
+			None,
+		);
+		cubemap
+	}}
+}
+
+/// Panoramas wrap around horizontally, so the source repeats.
+fn load_equirectangular<P: AsRef<Path>>(
+	program_data: &ProgramData,
+	path: P,
+) -> Result<Texture, TextureError> {
+	Texture::from_hdr(
+		program_data,
+		path,
+		&TextureOptions {
+			mipmaps: true,
+			sampler: SamplerDescription {
+				address_mode: vk::SamplerAddressMode::REPEAT,
+				..Default::default()
+			},
+			srgb: false,
+		},
+	)
+}
+
+/// Records the resampling of `source` into the top level of `cubemap`
+/// through `cube_view` and the blits of its mip chain. `pipelines` must
+/// hold the cube shader at `GenerationPipelines::CUBE`, every level ends
+/// up `SHADER_READ_ONLY_OPTIMAL`.
+fn record_equirectangular_cube(
+	device: &vpb::Device,
+	command_buffer: vk::CommandBuffer,
+	pipelines: &mut GenerationPipelines,
+	source: &Texture,
+	cubemap: &Cubemap,
+	cube_view: vk::ImageView,
+) {
+	transition_image_layers(
+		device,
+		command_buffer,
+		cubemap.image,
+		vk::ImageLayout::UNDEFINED,
+		vk::ImageLayout::GENERAL,
+		cubemap.mip_levels,
+		6,
+	);
+	pipelines.dispatch(
+		device,
+		command_buffer,
+		GenerationPipelines::CUBE,
+		Some((source.image_view, source.sampler.sampler)),
+		cube_view,
+		[0; 2],
+		[cubemap.size, cubemap.size, 6],
+	);
+	transition_image_layers(
+		device,
+		command_buffer,
+		cubemap.image,
+		vk::ImageLayout::GENERAL,
+		vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+		cubemap.mip_levels,
+		6,
+	);
+	generate_mipmaps(
+		device,
+		command_buffer,
+		cubemap.image,
+		cubemap.size,
+		cubemap.size,
+		cubemap.mip_levels,
+		6,
+	);
+}
+
+/// Square 2d image written by a compute shader, sampled clamped to edge.
+fn storage_texture(
+	program_data: &ProgramData,
+	size: u32,
+	format: vk::Format,
+) -> Texture { unsafe {
+	let image_info = vk::ImageCreateInfo::builder()
+		.image_type(vk::ImageType::TYPE_2D)
+		.format(format)
+		.extent(vk::Extent3D { width: size, height: size, depth: 1 })
+		.mip_levels(1)
+		.array_layers(1)
+		.samples(vk::SampleCountFlags::TYPE_1)
+		.tiling(vk::ImageTiling::OPTIMAL)
+		.usage(vk::ImageUsageFlags::STORAGE | vk::ImageUsageFlags::SAMPLED)
+		.sharing_mode(vk::SharingMode::EXCLUSIVE)
+		.initial_layout(vk::ImageLayout::UNDEFINED)
+		.build();
+	let (image, allocation) = create_device_image(
+		program_data,
+		&image_info,
+	);
+	let image_view_info = vk::ImageViewCreateInfo::builder()
+		.image(image)
+		.view_type(vk::ImageViewType::TYPE_2D)
+		.format(format)
+		.subresource_range(
+			vk::ImageSubresourceRange::builder()
+				.aspect_mask(vk::ImageAspectFlags::COLOR)
+				.level_count(1)
+				.layer_count(1)
+				.build()
+		).build();
+	let image_view = program_data.device.device.create_image_view(
+		&image_view_info,
+		None,
+	).unwrap();
+	Texture {
+		image,
+		allocation,
+		image_view,
+		sampler: Sampler::new(
+			program_data,
+			SamplerDescription::default(),
+		),
+		width: size,
+		height: size,
+		mip_levels: 1,
+		format,
+	}
+}}
+
+/// One off compute pipelines sharing a layout: a sampled input at binding
+/// 0, a storage image output at binding 1 and two 32 bit push constants.
+/// Shaders run 8x8 invocations per group with the layer along z.
+struct GenerationPipelines {
+	descriptor_set_layout: vk::DescriptorSetLayout,
+	descriptor_pool: vk::DescriptorPool,
+	pipeline_layout: vk::PipelineLayout,
+	pipelines: Vec<vk::Pipeline>,
+}
+
+impl GenerationPipelines {
+	const CUBE: usize = 0;
+	const IRRADIANCE: usize = 1;
+	const PREFILTER: usize = 2;
+	const BRDF_LUT: usize = 3;
+
+	/// `max_dispatches` sizes the descriptor pool, every dispatch takes a set.
+	fn new(
+		program_data: &ProgramData,
+		pipeline_cache: vk::PipelineCache,
+		shader_names: &[&str],
+		max_dispatches: u32,
+	) -> Self { unsafe {
+		let device = &program_data.device.device;
+		let bindings = [
+			vk::DescriptorSetLayoutBinding::builder()
+				.binding(0)
+				.descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+				.descriptor_count(1)
+				.stage_flags(vk::ShaderStageFlags::COMPUTE)
+				.build(),
+			vk::DescriptorSetLayoutBinding::builder()
+				.binding(1)
+				.descriptor_type(vk::DescriptorType::STORAGE_IMAGE)
+				.descriptor_count(1)
+				.stage_flags(vk::ShaderStageFlags::COMPUTE)
+				.build(),
+		];
+		let descriptor_set_layout_info = vk::DescriptorSetLayoutCreateInfo::builder()
+			.bindings(&bindings)
+			.build();
+		let descriptor_set_layout = device.create_descriptor_set_layout(
+			&descriptor_set_layout_info,
+			None,
+		).unwrap();
+		let pool_sizes = [
+			vk::DescriptorPoolSize::builder()
+				.ty(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+				.descriptor_count(max_dispatches)
+				.build(),
+			vk::DescriptorPoolSize::builder()
+				.ty(vk::DescriptorType::STORAGE_IMAGE)
+				.descriptor_count(max_dispatches)
+				.build(),
+		];
+		let descriptor_pool_info = vk::DescriptorPoolCreateInfo::builder()
+			.pool_sizes(&pool_sizes)
+			.max_sets(max_dispatches)
+			.build();
+		let descriptor_pool = device.create_descriptor_pool(
+			&descriptor_pool_info,
+			None,
+		).unwrap();
+		let push_constant_ranges = [
+			vk::PushConstantRange::builder()
+				.stage_flags(vk::ShaderStageFlags::COMPUTE)
+				.offset(0)
+				.size(8)
+				.build(),
+		];
+		let set_layouts = [descriptor_set_layout];
+		let pipeline_layout_info = vk::PipelineLayoutCreateInfo::builder()
+			.set_layouts(&set_layouts)
+			.push_constant_ranges(&push_constant_ranges)
+			.build();
+		let pipeline_layout = device.create_pipeline_layout(
+			&pipeline_layout_info,
+			None,
+		).unwrap();
+		let entry_point = CStr::from_bytes_with_nul_unchecked(b"main\0");
+		let pipelines = shader_names.iter().map(
+			|shader_name| {
+				let code = compile_shader(
+					ShaderKind::Compute,
+					shader_name,
+					&ShaderDefines::new(),
+				).expect("failed to compile compute shader");
+				let shader_module = create_shader_module(
+					program_data,
+					&code,
+				);
+				let pipeline_info = vk::ComputePipelineCreateInfo::builder()
+					.stage(
+						vk::PipelineShaderStageCreateInfo::builder()
+							.stage(vk::ShaderStageFlags::COMPUTE)
+							.module(shader_module)
+							.name(entry_point)
+							.build()
+					)
+					.layout(pipeline_layout)
+					.build();
+				let pipeline = device.create_compute_pipelines(
+					pipeline_cache,
+					&[pipeline_info],
+					None,
+				).unwrap()[0];
+				device.destroy_shader_module(shader_module, None);
+				pipeline
+			}
+		).collect();
+		Self {
+			descriptor_set_layout,
+			descriptor_pool,
+			pipeline_layout,
+			pipelines,
+		}
+	}}
+
+	/// Records one dispatch covering `size` invocations.
+	fn dispatch(
+		&mut self,
+		device: &vpb::Device,
+		command_buffer: vk::CommandBuffer,
+		pipeline: usize,
+		input: Option<(vk::ImageView, vk::Sampler)>,
+		output: vk::ImageView,
+		push_constants: [u32; 2],
+		size: [u32; 3],
+	) { unsafe {
+		let set_layouts = [self.descriptor_set_layout];
+		let allocate_info = vk::DescriptorSetAllocateInfo::builder()
+			.descriptor_pool(self.descriptor_pool)
+			.set_layouts(&set_layouts)
+			.build();
+		let descriptor_set = device.device.allocate_descriptor_sets(
+			&allocate_info,
+		).unwrap()[0];
+		let output_info = [
+			vk::DescriptorImageInfo::builder()
+				.image_view(output)
+				.image_layout(vk::ImageLayout::GENERAL)
+				.build(),
+		];
+		let mut writes = vec![
+			vk::WriteDescriptorSet::builder()
+				.dst_set(descriptor_set)
+				.dst_binding(1)
+				.descriptor_type(vk::DescriptorType::STORAGE_IMAGE)
+				.image_info(&output_info)
+				.build(),
+		];
+		let input_info = input.map(
+			|(image_view, sampler)|
+			[
+				vk::DescriptorImageInfo::builder()
+					.image_view(image_view)
+					.sampler(sampler)
+					.image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+					.build(),
+			]
+		);
+		if let Some(input_info) = input_info.as_ref() {
+			writes.push(
+				vk::WriteDescriptorSet::builder()
+					.dst_set(descriptor_set)
+					.dst_binding(0)
+					.descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+					.image_info(input_info)
+					.build()
+			);
+		}
+		device.device.update_descriptor_sets(
+			&writes,
+			&[],
+		);
+		device.device.cmd_bind_pipeline(
+			command_buffer,
+			vk::PipelineBindPoint::COMPUTE,
+			self.pipelines[pipeline],
+		);
+		device.device.cmd_bind_descriptor_sets(
+			command_buffer,
+			vk::PipelineBindPoint::COMPUTE,
+			self.pipeline_layout,
+			0,
+			&[descriptor_set],
+			&[],
+		);
+		device.device.cmd_push_constants(
+			command_buffer,
+			self.pipeline_layout,
+			vk::ShaderStageFlags::COMPUTE,
+			0,
+			bytemuck::cast_slice(&push_constants),
+		);
+		device.device.cmd_dispatch(
+			command_buffer,
+			(size[0] + 7) / 8,
+			(size[1] + 7) / 8,
+			size[2],
+		);
+		// Every dispatch either feeds the next one or is followed by a layout transition.
+		let barrier = vk::MemoryBarrier::builder()
+			.src_access_mask(vk::AccessFlags::SHADER_WRITE)
+			.dst_access_mask(vk::AccessFlags::SHADER_READ | vk::AccessFlags::TRANSFER_READ)
+			.build();
+		device.device.cmd_pipeline_barrier(
+			command_buffer,
+			vk::PipelineStageFlags::COMPUTE_SHADER,
+			vk::PipelineStageFlags::COMPUTE_SHADER | vk::PipelineStageFlags::TRANSFER,
+			vk::DependencyFlags::empty(),
+			&[barrier],
+			&[],
+			&[],
+		);
+	}}
+
+	fn destroy(
+		&mut self,
+		program_data: &ProgramData,
+	) { unsafe {
+		let device = &program_data.device.device;
+		for pipeline in self.pipelines.iter() {
+			device.destroy_pipeline(
+				*pipeline,
+				None,
+			);
+		}
+		device.destroy_pipeline_layout(
+			self.pipeline_layout,
+			None,
+		);
+		device.destroy_descriptor_pool(
+			self.descriptor_pool,
+			None,
+		);
+		device.destroy_descriptor_set_layout(
+			self.descriptor_set_layout,
+			None,
+		);
+	}}
+}
@@ -0,0 +1,299 @@
+use std::{fmt, sync::Arc};
+
+use nalgebra::Matrix4;
+use vpb::ProgramData;
+
+use crate::{DeviceMesh, BufferSource, MeshObject, SkinnedMeshObject, Skeleton, AnimationClip, Texture, TextureError, BlockMeshModel, BlockPbrMaterial, Material, MaterialTemplate, Std140Vec4, pipelines::pbr::PipelinePbr};
+
+mod gltf_import;
+mod obj_import;
+pub use obj_import::*;
+
+#[derive(Debug)]
+pub enum ImportError {
+	Io(std::io::Error),
+	Gltf(gltf::Error),
+	Texture(TextureError),
+	/// A primitive without the named vertex attribute.
+	MissingAttribute(&'static str),
+	/// Malformed line of a text format.
+	Parse { line: usize, message: String },
+	Unsupported(String),
+}
+
+impl fmt::Display for ImportError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			ImportError::Io(e) => write!(f, "import io error: {}", e),
+			ImportError::Gltf(e) => write!(f, "gltf error: {}", e),
+			ImportError::Texture(e) => write!(f, "import texture error: {}", e),
+			ImportError::MissingAttribute(attribute) => write!(f, "mesh has no {} attribute", attribute),
+			ImportError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+			ImportError::Unsupported(feature) => write!(f, "unsupported: {}", feature),
+		}
+	}
+}
+
+impl std::error::Error for ImportError {}
+
+impl From<std::io::Error> for ImportError {
+	fn from(e: std::io::Error) -> Self {
+		ImportError::Io(e)
+	}
+}
+
+impl From<gltf::Error> for ImportError {
+	fn from(e: gltf::Error) -> Self {
+		ImportError::Gltf(e)
+	}
+}
+
+impl From<TextureError> for ImportError {
+	fn from(e: TextureError) -> Self {
+		ImportError::Texture(e)
+	}
+}
+
+/// Metallic-roughness material, textures index `ImportedModel::textures`.
+#[derive(Clone, Debug)]
+pub struct ImportedMaterial {
+	pub name: Option<String>,
+	pub base_color: [f32; 4],
+	pub base_color_texture: Option<usize>,
+	pub metallic: f32,
+	pub roughness: f32,
+	/// Roughness in green, metallic in blue.
+	pub metallic_roughness_texture: Option<usize>,
+	pub normal_texture: Option<usize>,
+	pub normal_scale: f32,
+	pub occlusion_texture: Option<usize>,
+	pub emissive: [f32; 3],
+	pub emissive_texture: Option<usize>,
+	pub double_sided: bool,
+}
+
+impl Default for ImportedMaterial {
+	fn default() -> Self {
+		Self {
+			name: None,
+			base_color: [1.0; 4],
+			base_color_texture: None,
+			metallic: 1.0,
+			roughness: 1.0,
+			metallic_roughness_texture: None,
+			normal_texture: None,
+			normal_scale: 1.0,
+			occlusion_texture: None,
+			emissive: [0.0; 3],
+			emissive_texture: None,
+			double_sided: false,
+		}
+	}
+}
+
+impl ImportedMaterial {
+	/// Closest Blinn-Phong look for `PipelineMeshExample`.
+	pub fn mesh_model_block(
+		&self,
+		transform: Matrix4<f32>,
+	) -> BlockMeshModel {
+		let [r, g, b, _] = self.base_color;
+		let specular = [r, g, b].map(|x| 0.04 + (x - 0.04) * self.metallic);
+		let alpha = (self.roughness * self.roughness).max(0.001);
+		let shininess = (2.0 / (alpha * alpha) - 2.0).clamp(1.0, 256.0);
+		BlockMeshModel {
+			model: transform.into(),
+			color: Std140Vec4 { value: self.base_color },
+			specular: Std140Vec4 { value: [specular[0], specular[1], specular[2], shininess] },
+		}
+	}
+
+	/// Material factors for `PipelinePbr`.
+	pub fn pbr_block(
+		&self,
+	) -> BlockPbrMaterial {
+		let [r, g, b] = self.emissive;
+		BlockPbrMaterial {
+			base_color: Std140Vec4 { value: self.base_color },
+			emissive: Std140Vec4 { value: [r, g, b, 0.0] },
+			params: Std140Vec4 { value: [self.metallic, self.roughness, self.normal_scale, 1.0] },
+		}
+	}
+}
+
+/// One primitive of a source mesh, uploaded with `Vertex3d` vertices.
+pub struct ImportedMesh {
+	pub name: Option<String>,
+	pub mesh: Arc<DeviceMesh>,
+	pub material: Option<usize>,
+	/// Uploaded with `VertexSkinned` vertices instead, for primitives with
+	/// joints and weights.
+	pub skinned: bool,
+	/// Vertices and indices the mesh was uploaded from, so its objects can
+	/// be saved into scene files. `None` for skinned meshes.
+	pub source: Option<Arc<BufferSource>>,
+}
+
+/// A mesh placed in the scene with its flattened node transform.
+#[derive(Clone, Debug)]
+pub struct ImportedInstance {
+	pub name: Option<String>,
+	pub mesh: usize,
+	pub transform: Matrix4<f32>,
+	/// Index into `ImportedModel::skins` for skinned meshes, which are
+	/// placed by their skeleton and get an identity `transform`.
+	pub skin: Option<usize>,
+}
+
+/// Skeleton a skinned mesh is bound to and the clips animating it.
+#[derive(Clone, Debug)]
+pub struct ImportedSkin {
+	pub name: Option<String>,
+	pub skeleton: Arc<Skeleton>,
+	pub clips: Vec<Arc<AnimationClip>>,
+}
+
+/// Everything a model file describes, uploaded and ready to be turned
+/// into objects.
+#[derive(Default)]
+pub struct ImportedModel {
+	pub meshes: Vec<ImportedMesh>,
+	pub materials: Vec<ImportedMaterial>,
+	pub textures: Vec<Texture>,
+	pub instances: Vec<ImportedInstance>,
+	pub skins: Vec<ImportedSkin>,
+	/// Parts of the file that were skipped, such as unsupported textures.
+	pub warnings: Vec<String>,
+}
+
+impl ImportedModel {
+	/// One `MeshObject` per instance for a `PipelineMeshExample` bucket,
+	/// named `{prefix}{index}` or `{prefix}{node name}`. The objects share
+	/// the model's meshes. Skinned instances are left out, see
+	/// `skinned_objects`.
+	pub fn objects(
+		&self,
+		program_data: &ProgramData,
+		prefix: &str,
+	) -> Vec<MeshObject> {
+		self.instances.iter().enumerate().filter(
+			|(_, instance)|
+			!self.meshes[instance.mesh].skinned
+		).map(
+			|(i, instance)| {
+				let mesh = &self.meshes[instance.mesh];
+				let name = instance_name(prefix, i, instance);
+				let mut object = MeshObject::from_mesh(
+					program_data,
+					&name,
+					mesh.mesh.clone(),
+				);
+				if let Some(source) = &mesh.source {
+					object = object.with_source(source.clone());
+				}
+				let material = mesh.material.and_then(|x| self.materials.get(x)).cloned().unwrap_or_default();
+				object.set_block(material.mesh_model_block(instance.transform));
+				object
+			}
+		).collect()
+	}
+
+	/// One `SkinnedMeshObject` per skinned instance for a
+	/// `PipelineMeshExample::skinned` bucket, named like `objects`. The
+	/// animators start empty, the skin's clips are ready to `play`.
+	pub fn skinned_objects(
+		&self,
+		program_data: &ProgramData,
+		prefix: &str,
+	) -> Vec<SkinnedMeshObject> {
+		self.instances.iter().enumerate().filter_map(
+			|(i, instance)| {
+				let mesh = &self.meshes[instance.mesh];
+				let skin = self.skins.get(instance.skin?)?;
+				if !mesh.skinned {
+					return None;
+				}
+				let mut object = SkinnedMeshObject::from_mesh(
+					program_data,
+					&instance_name(prefix, i, instance),
+					mesh.mesh.clone(),
+					skin.skeleton.clone(),
+				);
+				let material = mesh.material.and_then(|x| self.materials.get(x)).cloned().unwrap_or_default();
+				object.set_block(material.mesh_model_block(Matrix4::identity()));
+				Some(object)
+			}
+		).collect()
+	}
+
+	/// One material per imported material from a `PipelinePbr` template,
+	/// named `{prefix}{index}` or `{prefix}{material name}`. Missing
+	/// textures keep the template's defaults.
+	pub fn pbr_materials(
+		&self,
+		program_data: &ProgramData,
+		template: &Arc<MaterialTemplate>,
+		prefix: &str,
+	) -> Vec<Material> {
+		self.materials.iter().enumerate().map(
+			|(i, imported)| {
+				let name = match &imported.name {
+					Some(name) => format!("{}{}", prefix, name),
+					None => format!("{}{}", prefix, i),
+				};
+				let mut material = Material::new(
+					program_data,
+					template,
+					&name,
+				);
+				material.set_parameters(
+					PipelinePbr::MATERIAL_BLOCK,
+					imported.pbr_block(),
+				);
+				for (element, texture) in [
+					(PipelinePbr::BASE_COLOR_TEXTURE, imported.base_color_texture),
+					(PipelinePbr::METALLIC_ROUGHNESS_TEXTURE, imported.metallic_roughness_texture),
+					(PipelinePbr::NORMAL_TEXTURE, imported.normal_texture),
+					(PipelinePbr::OCCLUSION_TEXTURE, imported.occlusion_texture),
+					(PipelinePbr::EMISSIVE_TEXTURE, imported.emissive_texture),
+				] {
+					if let Some(texture) = texture.and_then(|x| self.textures.get(x)) {
+						material.bind_texture_element(
+							&program_data.device,
+							PipelinePbr::MATERIAL_TEXTURES_BLOCK,
+							element,
+							texture,
+							None,
+						);
+					}
+				}
+				material
+			}
+		).collect()
+	}
+
+	/// Frees the meshes and textures, every object made from the model must
+	/// be removed first.
+	pub fn destroy(
+		&mut self,
+		program_data: &ProgramData,
+	) {
+		for mesh in self.meshes.iter_mut() {
+			vpb::gmuc!(mesh.mesh).destroy(program_data);
+		}
+		for texture in self.textures.iter_mut() {
+			texture.destroy(program_data);
+		}
+	}
+}
+
+fn instance_name(
+	prefix: &str,
+	index: usize,
+	instance: &ImportedInstance,
+) -> String {
+	match &instance.name {
+		Some(name) => format!("{}{}", prefix, name),
+		None => format!("{}{}", prefix, index),
+	}
+}
@@ -0,0 +1,300 @@
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+use ash::vk;
+use gltf::{animation::util::ReadOutputs, image::Format, mesh::Mode, texture::{MagFilter, MinFilter, WrappingMode}};
+use nalgebra::{Matrix4, Quaternion, UnitQuaternion, Vector3, Vector4};
+use vpb::ProgramData;
+
+use crate::{DeviceMesh, BufferSource, Vertex3d, VertexSkinned, Texture, TextureOptions, SamplerDescription, ImportError, ImportedMaterial, ImportedMesh, ImportedInstance, ImportedModel, ImportedSkin, Skeleton, Joint, JointPose, AnimationClip, Channel, ChannelTarget, Interpolation, primitives::{compute_normals, compute_tangents}};
+
+impl ImportedModel {
+	/// Loads a `.gltf` (with its external or embedded buffers and images)
+	/// or a `.glb`. Instances come from the default scene, or the first
+	/// one when the file doesn't name a default. Skinned primitives get
+	/// their skin's skeleton and every animation that moves its joints.
+	/// Primitives other than triangle lists are listed in `warnings`.
+	pub fn from_gltf<P: AsRef<Path>>(
+		program_data: &ProgramData,
+		path: P,
+	) -> Result<Self, ImportError> {
+		let (document, buffers, images) = gltf::import(path)?;
+		let mut model = ImportedModel::default();
+
+		// Color textures are sampled as sRGB, everything else is data.
+		let mut srgb = vec![false; document.textures().len()];
+		for material in document.materials() {
+			let color_textures = [
+				material.pbr_metallic_roughness().base_color_texture().map(|x| x.texture().index()),
+				material.emissive_texture().map(|x| x.texture().index()),
+			];
+			for texture in color_textures.iter().flatten() {
+				srgb[*texture] = true;
+			}
+		}
+		for texture in document.textures() {
+			let image = &images[texture.source().index()];
+			let sampler = texture.sampler();
+			let pixels = rgba8(image)?;
+			let options = TextureOptions {
+				mipmaps: true,
+				sampler: SamplerDescription {
+					mag_filter: match sampler.mag_filter() {
+						Some(MagFilter::Nearest) => vk::Filter::NEAREST,
+						_ => vk::Filter::LINEAR,
+					},
+					min_filter: match sampler.min_filter() {
+						Some(MinFilter::Nearest | MinFilter::NearestMipmapNearest | MinFilter::NearestMipmapLinear) => vk::Filter::NEAREST,
+						_ => vk::Filter::LINEAR,
+					},
+					mipmap_mode: match sampler.min_filter() {
+						Some(MinFilter::NearestMipmapNearest | MinFilter::LinearMipmapNearest) => vk::SamplerMipmapMode::NEAREST,
+						_ => vk::SamplerMipmapMode::LINEAR,
+					},
+					address_mode: match sampler.wrap_s() {
+						WrappingMode::ClampToEdge => vk::SamplerAddressMode::CLAMP_TO_EDGE,
+						WrappingMode::MirroredRepeat => vk::SamplerAddressMode::MIRRORED_REPEAT,
+						WrappingMode::Repeat => vk::SamplerAddressMode::REPEAT,
+					},
+					..Default::default()
+				},
+				srgb: srgb[texture.index()],
+			};
+			model.textures.push(Texture::from_rgba8(
+				program_data,
+				image.width,
+				image.height,
+				&pixels,
+				&options,
+			));
+		}
+
+		for material in document.materials() {
+			let pbr = material.pbr_metallic_roughness();
+			model.materials.push(ImportedMaterial {
+				name: material.name().map(|x| x.to_string()),
+				base_color: pbr.base_color_factor(),
+				base_color_texture: pbr.base_color_texture().map(|x| x.texture().index()),
+				metallic: pbr.metallic_factor(),
+				roughness: pbr.roughness_factor(),
+				metallic_roughness_texture: pbr.metallic_roughness_texture().map(|x| x.texture().index()),
+				normal_texture: material.normal_texture().map(|x| x.texture().index()),
+				normal_scale: material.normal_texture().map_or(1.0, |x| x.scale()),
+				occlusion_texture: material.occlusion_texture().map(|x| x.texture().index()),
+				emissive: material.emissive_factor(),
+				emissive_texture: material.emissive_texture().map(|x| x.texture().index()),
+				double_sided: material.double_sided(),
+			});
+		}
+
+		// Indices into `model.meshes` of every primitive of each gltf mesh.
+		let mut mesh_primitives = Vec::with_capacity(document.meshes().len());
+		for mesh in document.meshes() {
+			let mut primitives = Vec::with_capacity(mesh.primitives().len());
+			for primitive in mesh.primitives() {
+				if primitive.mode() != Mode::Triangles {
+					model.warnings.push(format!("skipped {:?} primitive of mesh {:?}, only triangles are imported", primitive.mode(), mesh.name()));
+					continue;
+				}
+				let reader = primitive.reader(|x| Some(&buffers[x.index()]));
+				let mut vertices: Vec<Vertex3d> = reader.read_positions().ok_or(
+					ImportError::MissingAttribute("POSITION")
+				)?.map(
+					|position|
+					Vertex3d {
+						position,
+						color: [1.0; 4],
+						..Default::default()
+					}
+				).collect();
+				let indices: Vec<u32> = match reader.read_indices() {
+					Some(indices) => indices.into_u32().collect(),
+					None => (0..vertices.len() as u32).collect(),
+				};
+				match reader.read_normals() {
+					Some(normals) => vertices.iter_mut().zip(normals).for_each(|(v, x)| v.normal = x),
+					None => compute_normals(&mut vertices, &indices),
+				}
+				if let Some(uvs) = reader.read_tex_coords(0) {
+					vertices.iter_mut().zip(uvs.into_f32()).for_each(|(v, x)| v.uv = x);
+				}
+				if let Some(colors) = reader.read_colors(0) {
+					vertices.iter_mut().zip(colors.into_rgba_f32()).for_each(|(v, x)| v.color = x);
+				}
+				match reader.read_tangents() {
+					Some(tangents) => vertices.iter_mut().zip(tangents).for_each(|(v, x)| v.tangent = x),
+					None => compute_tangents(&mut vertices, &indices),
+				}
+				let (device_mesh, source, skinned) = match (reader.read_joints(0), reader.read_weights(0)) {
+					(Some(joints), Some(weights)) => {
+						let vertices: Vec<VertexSkinned> = vertices.into_iter().zip(joints.into_u16().zip(weights.into_f32())).map(
+							|(v, (joints, weights))|
+							VertexSkinned::from_vertex(v, joints.map(|x| x as u32), weights)
+						).collect();
+						(DeviceMesh::new(program_data, &vertices, &indices), None, true)
+					},
+					_ => (
+						DeviceMesh::new(program_data, &vertices, &indices),
+						Some(Arc::new(BufferSource::new("Vertex3d", &vertices, &indices))),
+						false,
+					),
+				};
+				primitives.push(model.meshes.len());
+				model.meshes.push(ImportedMesh {
+					name: mesh.name().map(|x| x.to_string()),
+					mesh: Arc::new(device_mesh),
+					material: primitive.material().index(),
+					skinned,
+					source,
+				});
+			}
+			mesh_primitives.push(primitives);
+		}
+
+		let mut parents = vec![None; document.nodes().len()];
+		for node in document.nodes() {
+			for child in node.children() {
+				parents[child.index()] = Some(node.index());
+			}
+		}
+		let locals: Vec<Matrix4<f32>> = document.nodes().map(|x| Matrix4::from(x.transform().matrix())).collect();
+		for skin in document.skins() {
+			// Joint index of each node of the skin.
+			let joint_indices: HashMap<usize, usize> = skin.joints().enumerate().map(|(i, x)| (x.index(), i)).collect();
+			let inverse_binds: Vec<Matrix4<f32>> = skin.reader(|x| Some(&buffers[x.index()])).read_inverse_bind_matrices().map_or(
+				Vec::new(),
+				|x| x.map(Matrix4::from).collect(),
+			);
+			let joints = skin.joints().enumerate().map(
+				|(i, node)| {
+					// Nearest ancestor that is a joint, with the transforms of
+					// any other ancestors folded into `parent_transform`.
+					let mut parent = None;
+					let mut parent_transform = Matrix4::identity();
+					let mut ancestor = parents[node.index()];
+					while let Some(index) = ancestor {
+						if let Some(joint) = joint_indices.get(&index) {
+							parent = Some(*joint);
+							break;
+						}
+						parent_transform = locals[index] * parent_transform;
+						ancestor = parents[index];
+					}
+					let (translation, rotation, scale) = node.transform().decomposed();
+					Joint {
+						name: node.name().map(|x| x.to_string()),
+						parent,
+						rest: JointPose {
+							translation: Vector3::from(translation),
+							rotation: UnitQuaternion::from_quaternion(Quaternion::from(Vector4::from(rotation))),
+							scale: Vector3::from(scale),
+						},
+						inverse_bind: inverse_binds.get(i).copied().unwrap_or_else(Matrix4::identity),
+						parent_transform,
+					}
+				}
+			).collect();
+			let mut clips = Vec::new();
+			for animation in document.animations() {
+				let mut channels = Vec::new();
+				for channel in animation.channels() {
+					let joint = match joint_indices.get(&channel.target().node().index()) {
+						Some(joint) => *joint,
+						None => continue,
+					};
+					let reader = channel.reader(|x| Some(&buffers[x.index()]));
+					let (times, outputs): (Vec<f32>, _) = match (reader.read_inputs(), reader.read_outputs()) {
+						(Some(times), Some(outputs)) => (times.collect(), outputs),
+						_ => continue,
+					};
+					let (target, values): (_, Vec<[f32; 4]>) = match outputs {
+						ReadOutputs::Translations(x) => (ChannelTarget::Translation, x.map(|[x, y, z]| [x, y, z, 0.0]).collect()),
+						ReadOutputs::Rotations(x) => (ChannelTarget::Rotation, x.into_f32().collect()),
+						ReadOutputs::Scales(x) => (ChannelTarget::Scale, x.map(|[x, y, z]| [x, y, z, 0.0]).collect()),
+						ReadOutputs::MorphTargetWeights(_) => continue,
+					};
+					channels.push(Channel {
+						joint,
+						target,
+						interpolation: match channel.sampler().interpolation() {
+							gltf::animation::Interpolation::Linear => Interpolation::Linear,
+							gltf::animation::Interpolation::Step => Interpolation::Step,
+							gltf::animation::Interpolation::CubicSpline => Interpolation::CubicSpline,
+						},
+						times,
+						values,
+					});
+				}
+				if !channels.is_empty() {
+					clips.push(Arc::new(AnimationClip::new(
+						animation.name().map(|x| x.to_string()),
+						channels,
+					)));
+				}
+			}
+			model.skins.push(ImportedSkin {
+				name: skin.name().map(|x| x.to_string()),
+				skeleton: Arc::new(Skeleton::new(joints)),
+				clips,
+			});
+		}
+
+		let scene = document.default_scene().or_else(|| document.scenes().next());
+		let mut stack: Vec<(gltf::Node, Matrix4<f32>)> = scene.iter().flat_map(
+			|x|
+			x.nodes()
+		).map(
+			|x|
+			(x, Matrix4::identity())
+		).collect();
+		while let Some((node, parent)) = stack.pop() {
+			let transform = parent * Matrix4::from(node.transform().matrix());
+			if let Some(mesh) = node.mesh() {
+				// Skinned meshes are placed by their joints alone, the gltf spec
+				// has their node transform ignored.
+				let mesh_transform = match node.skin() {
+					Some(_) => Matrix4::identity(),
+					None => transform,
+				};
+				for primitive in mesh_primitives[mesh.index()].iter() {
+					model.instances.push(ImportedInstance {
+						name: node.name().map(|x| x.to_string()),
+						mesh: *primitive,
+						transform: mesh_transform,
+						skin: node.skin().map(|x| x.index()),
+					});
+				}
+			}
+			stack.extend(node.children().map(|x| (x, transform)));
+		}
+		Ok(model)
+	}
+}
+
+/// Expands any 8 or 16 bit gltf image into RGBA8.
+fn rgba8(
+	image: &gltf::image::Data,
+) -> Result<Vec<u8>, ImportError> {
+	let high_bytes = |x: &[u8]| -> Vec<u8> {
+		x.chunks_exact(2).map(|x| (u16::from_ne_bytes([x[0], x[1]]) >> 8) as u8).collect()
+	};
+	let (channels, bytes) = match image.format {
+		Format::R8 => (1, image.pixels.clone()),
+		Format::R8G8 => (2, image.pixels.clone()),
+		Format::R8G8B8 => (3, image.pixels.clone()),
+		Format::R8G8B8A8 => return Ok(image.pixels.clone()),
+		Format::R16 => (1, high_bytes(&image.pixels)),
+		Format::R16G16 => (2, high_bytes(&image.pixels)),
+		Format::R16G16B16 => (3, high_bytes(&image.pixels)),
+		Format::R16G16B16A16 => (4, high_bytes(&image.pixels)),
+		format => return Err(ImportError::Unsupported(format!("{:?} images", format))),
+	};
+	Ok(bytes.chunks_exact(channels).flat_map(
+		|x|
+		match channels {
+			1 => [x[0], x[0], x[0], 255],
+			2 => [x[0], x[1], 0, 255],
+			3 => [x[0], x[1], x[2], 255],
+			_ => [x[0], x[1], x[2], x[3]],
+		}
+	).collect())
+}
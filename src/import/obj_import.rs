@@ -0,0 +1,454 @@
+use std::{collections::HashMap, path::{Path, PathBuf}, sync::Arc};
+
+use ash::vk;
+use nalgebra::Matrix4;
+use vpb::ProgramData;
+
+use crate::{DeviceMesh, BufferSource, Vertex3d, Texture, TextureOptions, SamplerDescription, ImportError, ImportedMaterial, ImportedMesh, ImportedInstance, ImportedModel, primitives::{compute_normals, compute_tangents}};
+
+/// Triangles of one object or material group, vertices deduplicated by
+/// their position, uv and normal indices.
+#[derive(Clone, Debug, Default)]
+pub struct ObjGroup {
+	pub name: Option<String>,
+	pub material: Option<String>,
+	pub vertices: Vec<Vertex3d>,
+	pub indices: Vec<u32>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ObjData {
+	pub groups: Vec<ObjGroup>,
+	/// `mtllib` paths, relative to the obj file.
+	pub material_libraries: Vec<String>,
+}
+
+/// Group being filled while parsing.
+#[derive(Default)]
+struct GroupBuilder {
+	group: ObjGroup,
+	/// (position, uv, normal) to vertex index.
+	vertices: HashMap<(usize, Option<usize>, Option<usize>), u32>,
+	missing_normals: bool,
+}
+
+impl GroupBuilder {
+	fn finish(
+		&mut self,
+		groups: &mut Vec<ObjGroup>,
+	) {
+		let builder = std::mem::take(self);
+		let mut group = builder.group;
+		if group.indices.is_empty() {
+			self.group.name = group.name;
+			self.group.material = group.material;
+			return;
+		}
+		if builder.missing_normals {
+			compute_normals(&mut group.vertices, &group.indices);
+		}
+		compute_tangents(&mut group.vertices, &group.indices);
+		self.group.name = group.name.clone();
+		self.group.material = group.material.clone();
+		groups.push(group);
+	}
+}
+
+fn parse_error(
+	line: usize,
+	message: String,
+) -> ImportError {
+	ImportError::Parse {
+		line,
+		message,
+	}
+}
+
+fn parse_floats<const N: usize>(
+	line: usize,
+	tokens: &[&str],
+	defaults: [f32; N],
+) -> Result<[f32; N], ImportError> {
+	let mut values = defaults;
+	for (value, token) in values.iter_mut().zip(tokens.iter()) {
+		*value = token.parse().map_err(|_| parse_error(line, format!("invalid number {:?}", token)))?;
+	}
+	Ok(values)
+}
+
+/// 1 based, negative counts back from the last element so far.
+fn resolve_index(
+	line: usize,
+	token: &str,
+	count: usize,
+) -> Result<usize, ImportError> {
+	let index: i64 = token.parse().map_err(|_| parse_error(line, format!("invalid index {:?}", token)))?;
+	let resolved = match index {
+		i if i > 0 => i - 1,
+		i if i < 0 => count as i64 + i,
+		_ => -1,
+	};
+	match resolved >= 0 && (resolved as usize) < count {
+		true => Ok(resolved as usize),
+		false => Err(parse_error(line, format!("index {} out of range", index))),
+	}
+}
+
+impl ObjData {
+	/// Polygons are triangulated as fans, so they must be convex. uvs are
+	/// flipped to put the origin at the top left like the rest of vpe.
+	pub fn parse(
+		source: &str,
+	) -> Result<Self, ImportError> {
+		let mut data = ObjData::default();
+		let mut positions: Vec<[f32; 3]> = Vec::new();
+		let mut colors: Vec<[f32; 4]> = Vec::new();
+		let mut uvs: Vec<[f32; 2]> = Vec::new();
+		let mut normals: Vec<[f32; 3]> = Vec::new();
+		let mut builder = GroupBuilder::default();
+		for (i, text) in source.lines().enumerate() {
+			let line = i + 1;
+			let text = text.split('#').next().unwrap_or("").trim();
+			let tokens: Vec<&str> = text.split_whitespace().collect();
+			let (keyword, arguments) = match tokens.split_first() {
+				Some((keyword, arguments)) => (*keyword, arguments),
+				None => continue,
+			};
+			match keyword {
+				"v" => {
+					let [x, y, z, r, g, b] = parse_floats(line, arguments, [0.0, 0.0, 0.0, 1.0, 1.0, 1.0])?;
+					positions.push([x, y, z]);
+					// `v x y z r g b` is a common extension, `v x y z w` is not a color.
+					colors.push(match arguments.len() >= 6 {
+						true => [r, g, b, 1.0],
+						false => [1.0; 4],
+					});
+				},
+				"vt" => {
+					let [u, v] = parse_floats(line, arguments, [0.0, 0.0])?;
+					uvs.push([u, 1.0 - v]);
+				},
+				"vn" => {
+					normals.push(parse_floats(line, arguments, [0.0, 0.0, 1.0])?);
+				},
+				"f" => {
+					if arguments.len() < 3 {
+						return Err(parse_error(line, "face with less than 3 vertices".to_string()));
+					}
+					let mut corners = Vec::with_capacity(arguments.len());
+					for argument in arguments.iter() {
+						let mut parts = argument.split('/');
+						let position = resolve_index(line, parts.next().unwrap_or(""), positions.len())?;
+						let uv = match parts.next() {
+							Some(x) if !x.is_empty() => Some(resolve_index(line, x, uvs.len())?),
+							_ => None,
+						};
+						let normal = match parts.next() {
+							Some(x) if !x.is_empty() => Some(resolve_index(line, x, normals.len())?),
+							_ => None,
+						};
+						builder.missing_normals |= normal.is_none();
+						let key = (position, uv, normal);
+						let index = match builder.vertices.get(&key) {
+							Some(index) => *index,
+							None => {
+								let index = builder.group.vertices.len() as u32;
+								builder.group.vertices.push(Vertex3d {
+									position: positions[position],
+									normal: normal.map_or([0.0; 3], |x| normals[x]),
+									uv: uv.map_or([0.0; 2], |x| uvs[x]),
+									color: colors[position],
+									..Default::default()
+								});
+								builder.vertices.insert(key, index);
+								index
+							},
+						};
+						corners.push(index);
+					}
+					for i in 1..corners.len() - 1 {
+						builder.group.indices.extend_from_slice(&[corners[0], corners[i], corners[i + 1]]);
+					}
+				},
+				"o" | "g" => {
+					builder.finish(&mut data.groups);
+					builder.group.name = match arguments.is_empty() {
+						true => None,
+						false => Some(arguments.join(" ")),
+					};
+				},
+				"usemtl" => {
+					builder.finish(&mut data.groups);
+					builder.group.material = Some(arguments.join(" "));
+				},
+				"mtllib" => {
+					data.material_libraries.extend(arguments.iter().map(|x| x.to_string()));
+				},
+				// Smoothing groups, lines, points and curves.
+				_ => {},
+			}
+		}
+		builder.finish(&mut data.groups);
+		Ok(data)
+	}
+}
+
+/// Material of an mtl library, in the library's own terms.
+#[derive(Clone, Debug)]
+pub struct MtlMaterial {
+	pub name: String,
+	/// `Kd`
+	pub diffuse: [f32; 3],
+	/// `Ks`
+	pub specular: [f32; 3],
+	/// `Ke`
+	pub emissive: [f32; 3],
+	/// `Ns`
+	pub shininess: f32,
+	/// `d`, or one minus `Tr`.
+	pub dissolve: f32,
+	/// `map_Kd`
+	pub diffuse_map: Option<String>,
+	/// `norm`, `bump` or `map_Bump`.
+	pub normal_map: Option<String>,
+}
+
+impl MtlMaterial {
+	pub fn new(
+		name: &str,
+	) -> Self {
+		Self {
+			name: name.to_string(),
+			diffuse: [1.0; 3],
+			specular: [0.0; 3],
+			emissive: [0.0; 3],
+			shininess: 1.0,
+			dissolve: 1.0,
+			diffuse_map: None,
+			normal_map: None,
+		}
+	}
+
+	pub fn parse_library(
+		source: &str,
+	) -> Result<Vec<Self>, ImportError> {
+		let mut materials: Vec<MtlMaterial> = Vec::new();
+		for (i, text) in source.lines().enumerate() {
+			let line = i + 1;
+			let text = text.split('#').next().unwrap_or("").trim();
+			let tokens: Vec<&str> = text.split_whitespace().collect();
+			let (keyword, arguments) = match tokens.split_first() {
+				Some((keyword, arguments)) => (*keyword, arguments),
+				None => continue,
+			};
+			if keyword == "newmtl" {
+				materials.push(MtlMaterial::new(&arguments.join(" ")));
+				continue;
+			}
+			let material = match materials.last_mut() {
+				Some(material) => material,
+				None => return Err(parse_error(line, format!("{} before newmtl", keyword))),
+			};
+			match keyword {
+				"Kd" => material.diffuse = parse_floats(line, arguments, [0.0; 3])?,
+				"Ks" => material.specular = parse_floats(line, arguments, [0.0; 3])?,
+				"Ke" => material.emissive = parse_floats(line, arguments, [0.0; 3])?,
+				"Ns" => material.shininess = parse_floats(line, arguments, [1.0])?[0],
+				"d" => material.dissolve = parse_floats(line, arguments, [1.0])?[0],
+				"Tr" => material.dissolve = 1.0 - parse_floats(line, arguments, [0.0])?[0],
+				// Options like `-bm 1.0` come before the file name.
+				"map_Kd" => material.diffuse_map = arguments.last().map(|x| x.to_string()),
+				"norm" | "bump" | "map_Bump" | "map_bump" => material.normal_map = arguments.last().map(|x| x.to_string()),
+				_ => {},
+			}
+		}
+		Ok(materials)
+	}
+}
+
+impl ImportedModel {
+	/// Loads an obj with the mtl libraries it names, one mesh per object
+	/// or material group, all at the origin. Only png texture maps load,
+	/// missing libraries and other maps are listed in `warnings`.
+	pub fn from_obj<P: AsRef<Path>>(
+		program_data: &ProgramData,
+		path: P,
+	) -> Result<Self, ImportError> {
+		let path = path.as_ref();
+		let directory = path.parent().unwrap_or(Path::new(""));
+		let data = ObjData::parse(&std::fs::read_to_string(path)?)?;
+		let mut model = ImportedModel::default();
+
+		let mut material_indices = HashMap::new();
+		let mut texture_indices: HashMap<(PathBuf, bool), usize> = HashMap::new();
+		for library in data.material_libraries.iter() {
+			let source = match std::fs::read_to_string(directory.join(library)) {
+				Ok(source) => source,
+				Err(e) => {
+					model.warnings.push(format!("skipped material library {}: {}", library, e));
+					continue;
+				},
+			};
+			for material in MtlMaterial::parse_library(&source)? {
+				let mut texture = |map: &Option<String>, srgb: bool| -> Result<Option<usize>, ImportError> {
+					let map = match map {
+						Some(map) => directory.join(map),
+						None => return Ok(None),
+					};
+					if map.extension().and_then(|x| x.to_str()).map(|x| x.to_lowercase()) != Some("png".to_string()) {
+						model.warnings.push(format!("skipped texture {}, only png is supported", map.display()));
+						return Ok(None);
+					}
+					if let Some(index) = texture_indices.get(&(map.clone(), srgb)) {
+						return Ok(Some(*index));
+					}
+					model.textures.push(Texture::from_png(
+						program_data,
+						&map,
+						&TextureOptions {
+							sampler: SamplerDescription {
+								address_mode: vk::SamplerAddressMode::REPEAT,
+								..Default::default()
+							},
+							srgb,
+							..Default::default()
+						},
+					)?);
+					texture_indices.insert((map, srgb), model.textures.len() - 1);
+					Ok(Some(model.textures.len() - 1))
+				};
+				let base_color_texture = texture(&material.diffuse_map, true)?;
+				let normal_texture = texture(&material.normal_map, false)?;
+				let [r, g, b] = material.diffuse;
+				material_indices.insert(material.name.clone(), model.materials.len());
+				model.materials.push(ImportedMaterial {
+					name: Some(material.name.clone()),
+					base_color: [r, g, b, material.dissolve],
+					base_color_texture,
+					metallic: 0.0,
+					// Inverse of the Blinn-Phong exponent to roughness mapping.
+					roughness: (2.0 / (material.shininess.max(0.0) + 2.0)).sqrt().sqrt(),
+					normal_texture,
+					emissive: material.emissive,
+					..Default::default()
+				});
+			}
+		}
+
+		for group in data.groups.iter() {
+			model.instances.push(ImportedInstance {
+				name: group.name.clone(),
+				mesh: model.meshes.len(),
+				transform: Matrix4::identity(),
+				skin: None,
+			});
+			model.meshes.push(ImportedMesh {
+				name: group.name.clone(),
+				mesh: Arc::new(DeviceMesh::new(
+					program_data,
+					&group.vertices,
+					&group.indices,
+				)),
+				material: group.material.as_ref().and_then(|x| material_indices.get(x).copied()),
+				skinned: false,
+				source: Some(Arc::new(BufferSource::new(
+					"Vertex3d",
+					&group.vertices,
+					&group.indices,
+				))),
+			});
+		}
+		Ok(model)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const QUAD: &str = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n";
+
+	#[test]
+	fn quad_is_a_triangle_fan() {
+		let data = ObjData::parse(&format!("{}f 1 2 3 4", QUAD)).unwrap();
+		assert_eq!(data.groups.len(), 1);
+		assert_eq!(data.groups[0].vertices.len(), 4);
+		assert_eq!(data.groups[0].indices, vec![0, 1, 2, 0, 2, 3]);
+	}
+
+	#[test]
+	fn normal_and_uv_forms() {
+		let data = ObjData::parse(&format!("{}vn 0 0 -1\nf 1//1 2//1 3//1", QUAD)).unwrap();
+		for vertex in data.groups[0].vertices.iter() {
+			assert_eq!(vertex.normal, [0.0, 0.0, -1.0]);
+			assert_eq!(vertex.uv, [0.0, 0.0]);
+		}
+		let data = ObjData::parse(&format!("{}vt 0.5 0.25\nf 1/1 2/1 3/1", QUAD)).unwrap();
+		for vertex in data.groups[0].vertices.iter() {
+			// Flipped to a top left origin.
+			assert_eq!(vertex.uv, [0.5, 0.75]);
+		}
+	}
+
+	#[test]
+	fn negative_indices_count_back() {
+		let absolute = ObjData::parse(&format!("{}f 2 3 4", QUAD)).unwrap();
+		let relative = ObjData::parse(&format!("{}f -3 -2 -1", QUAD)).unwrap();
+		let positions = |data: &ObjData| -> Vec<[f32; 3]> {
+			data.groups[0].vertices.iter().map(|x| x.position).collect()
+		};
+		assert_eq!(positions(&absolute), positions(&relative));
+		assert_eq!(absolute.groups[0].indices, relative.groups[0].indices);
+	}
+
+	#[test]
+	fn vertices_are_deduplicated() {
+		let data = ObjData::parse(&format!("{}f 1 2 3\nf 1 3 4", QUAD)).unwrap();
+		assert_eq!(data.groups[0].vertices.len(), 4);
+		assert_eq!(data.groups[0].indices, vec![0, 1, 2, 0, 2, 3]);
+		// Same position with another uv is another vertex.
+		let data = ObjData::parse(&format!("{}vt 0 0\nvt 1 1\nf 1/1 2/1 3/1\nf 1/2 3/1 4/1", QUAD)).unwrap();
+		assert_eq!(data.groups[0].vertices.len(), 5);
+	}
+
+	#[test]
+	fn out_of_range_index_is_an_error() {
+		match ObjData::parse(&format!("{}f 1 2 5", QUAD)) {
+			Err(ImportError::Parse { line, .. }) => assert_eq!(line, 5),
+			_ => panic!("expected a parse error"),
+		}
+		match ObjData::parse(&format!("{}f 1 2 0", QUAD)) {
+			Err(ImportError::Parse { line, .. }) => assert_eq!(line, 5),
+			_ => panic!("expected a parse error"),
+		}
+	}
+
+	#[test]
+	fn groups_split_on_material() {
+		let data = ObjData::parse(&format!("{}mtllib a.mtl\no quad\nusemtl red\nf 1 2 3\nusemtl blue\nf 1 3 4", QUAD)).unwrap();
+		assert_eq!(data.material_libraries, vec!["a.mtl".to_string()]);
+		assert_eq!(data.groups.len(), 2);
+		assert_eq!(data.groups[0].name.as_deref(), Some("quad"));
+		assert_eq!(data.groups[0].material.as_deref(), Some("red"));
+		assert_eq!(data.groups[1].material.as_deref(), Some("blue"));
+		assert_eq!(data.groups[1].vertices.len(), 3);
+	}
+
+	#[test]
+	fn material_library() {
+		let materials = MtlMaterial::parse_library(
+			"newmtl red\nKd 1 0 0\nTr 0.25\nmap_Kd -bm 1.0 red.png\nnewmtl plain\nNs 10",
+		).unwrap();
+		assert_eq!(materials.len(), 2);
+		assert_eq!(materials[0].name, "red");
+		assert_eq!(materials[0].diffuse, [1.0, 0.0, 0.0]);
+		assert_eq!(materials[0].dissolve, 0.75);
+		assert_eq!(materials[0].diffuse_map.as_deref(), Some("red.png"));
+		assert_eq!(materials[1].shininess, 10.0);
+		assert!(materials[1].diffuse_map.is_none());
+		assert!(matches!(
+			MtlMaterial::parse_library("Kd 1 1 1"),
+			Err(ImportError::Parse { line: 1, .. })
+		));
+	}
+}
@@ -0,0 +1,260 @@
+use std::sync::Arc;
+
+use bytemuck::{Pod, Zeroable};
+use nalgebra::{Matrix3, Matrix4, Vector2, Vector3, Vector4};
+
+/// Types whose rust layout matches a glsl type under std140, so that a
+/// `repr(C)` struct made of them can be copied straight into a uniform
+/// buffer. `#[derive(Block)]` lays the fields out with these rules and
+/// fails to compile when rust places a field anywhere else.
+///
+/// # Safety
+/// `ALIGN` and `SIZE` must be the std140 base alignment and size of the
+/// glsl type, and `size_of::<Self>()` must be at least `SIZE`.
+pub unsafe trait Std140 {
+	const ALIGN: usize;
+	const SIZE: usize;
+}
+
+/// Same as `Std140` for std430 (storage buffers and push constants).
+///
+/// # Safety
+/// See `Std140`.
+pub unsafe trait Std430 {
+	const ALIGN: usize;
+	const SIZE: usize;
+}
+
+/// Implemented by `#[derive(Block)]` once every field offset has been
+/// checked, see `BlockStateExt`.
+///
+/// # Safety
+/// The type's layout must match the glsl block it is bound to.
+pub unsafe trait LayoutChecked: Copy {}
+
+pub const fn round_up(
+	offset: usize,
+	align: usize,
+) -> usize {
+	(offset + align - 1) / align * align
+}
+
+macro_rules! layout {
+	($ty:ty, $align:expr, $size:expr) => {
+		unsafe impl Std140 for $ty {
+			const ALIGN: usize = $align;
+			const SIZE: usize = $size;
+		}
+		unsafe impl Std430 for $ty {
+			const ALIGN: usize = $align;
+			const SIZE: usize = $size;
+		}
+	};
+}
+
+layout!(f32, 4, 4);
+layout!(i32, 4, 4);
+layout!(u32, 4, 4);
+layout!([f32; 2], 8, 8);
+layout!([i32; 2], 8, 8);
+layout!([u32; 2], 8, 8);
+layout!([f32; 4], 16, 16);
+layout!([i32; 4], 16, 16);
+layout!([u32; 4], 16, 16);
+// Column major `mat4`, not `float[16]` (which has a 16 byte std140 stride).
+layout!([f32; 16], 16, 64);
+layout!(Vector2<f32>, 8, 8);
+layout!(Vector4<f32>, 16, 16);
+layout!(Matrix4<f32>, 16, 64);
+
+/// `vec2`
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Pod, Zeroable)]
+pub struct Std140Vec2 {
+	pub value: [f32; 2],
+}
+
+/// `vec3`, padded to 16 bytes. The glsl size is still 12 so a scalar
+/// placed right after it in glsl is caught by `#[derive(Block)]`.
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Pod, Zeroable)]
+pub struct Std140Vec3 {
+	pub value: [f32; 3],
+	_padding: f32,
+}
+
+/// `vec4`
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Pod, Zeroable)]
+pub struct Std140Vec4 {
+	pub value: [f32; 4],
+}
+
+/// `mat3`, every column padded to a `vec4`.
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Pod, Zeroable)]
+pub struct Std140Mat3 {
+	pub columns: [[f32; 4]; 3],
+}
+
+/// `mat4`
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Pod, Zeroable)]
+pub struct Std140Mat4 {
+	pub columns: [[f32; 4]; 4],
+}
+
+/// `T[N]` where every element already has a 16 byte multiple stride,
+/// for example `Std140Vec4` or `Std140Mat4`.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Std140Array<T: Std140 + Pod, const N: usize> {
+	pub elements: [T; N],
+}
+
+unsafe impl<T: Std140 + Pod, const N: usize> Zeroable for Std140Array<T, N> {}
+unsafe impl<T: Std140 + Pod, const N: usize> Pod for Std140Array<T, N> {}
+
+layout!(Std140Vec2, 8, 8);
+layout!(Std140Vec3, 16, 12);
+layout!(Std140Vec4, 16, 16);
+layout!(Std140Mat3, 16, 48);
+layout!(Std140Mat4, 16, 64);
+
+unsafe impl<T: Std140 + Pod, const N: usize> Std140 for Std140Array<T, N> {
+	const ALIGN: usize = {
+		assert!(
+			std::mem::size_of::<T>() % 16 == 0,
+			"std140 array elements must have a size that is a multiple of 16",
+		);
+		16
+	};
+	const SIZE: usize = std::mem::size_of::<T>() * N;
+}
+
+unsafe impl<T: Std140 + Pod, const N: usize> Std430 for Std140Array<T, N> {
+	const ALIGN: usize = <Self as Std140>::ALIGN;
+	const SIZE: usize = <Self as Std140>::SIZE;
+}
+
+/// `T[N]` with the tighter std430 stride, only valid in storage buffers
+/// and push constants.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Std430Array<T: Std430 + Pod, const N: usize> {
+	pub elements: [T; N],
+}
+
+unsafe impl<T: Std430 + Pod, const N: usize> Zeroable for Std430Array<T, N> {}
+unsafe impl<T: Std430 + Pod, const N: usize> Pod for Std430Array<T, N> {}
+
+unsafe impl<T: Std430 + Pod, const N: usize> Std430 for Std430Array<T, N> {
+	const ALIGN: usize = {
+		assert!(
+			std::mem::size_of::<T>() % T::ALIGN == 0,
+			"std430 array elements must have a size that is a multiple of their alignment",
+		);
+		T::ALIGN
+	};
+	const SIZE: usize = std::mem::size_of::<T>() * N;
+}
+
+impl<T: Std140 + Pod, const N: usize> Default for Std140Array<T, N> {
+	fn default() -> Self {
+		Zeroable::zeroed()
+	}
+}
+
+impl<T: Std430 + Pod, const N: usize> Default for Std430Array<T, N> {
+	fn default() -> Self {
+		Zeroable::zeroed()
+	}
+}
+
+impl Std140Vec3 {
+	pub const fn new(
+		value: [f32; 3],
+	) -> Self {
+		Self {
+			value,
+			_padding: 0.0,
+		}
+	}
+}
+
+impl From<Vector2<f32>> for Std140Vec2 {
+	fn from(value: Vector2<f32>) -> Self {
+		Self {
+			value: [value.x, value.y],
+		}
+	}
+}
+
+impl From<Vector3<f32>> for Std140Vec3 {
+	fn from(value: Vector3<f32>) -> Self {
+		Self::new([value.x, value.y, value.z])
+	}
+}
+
+impl From<Vector4<f32>> for Std140Vec4 {
+	fn from(value: Vector4<f32>) -> Self {
+		Self {
+			value: [value.x, value.y, value.z, value.w],
+		}
+	}
+}
+
+impl From<Matrix3<f32>> for Std140Mat3 {
+	fn from(value: Matrix3<f32>) -> Self {
+		let mut columns = [[0.0; 4]; 3];
+		for (i, column) in value.column_iter().enumerate() {
+			columns[i][..3].copy_from_slice(column.as_slice());
+		}
+		Self {
+			columns,
+		}
+	}
+}
+
+impl From<Matrix4<f32>> for Std140Mat4 {
+	fn from(value: Matrix4<f32>) -> Self {
+		let mut columns = [[0.0; 4]; 4];
+		for (i, column) in value.column_iter().enumerate() {
+			columns[i].copy_from_slice(column.as_slice());
+		}
+		Self {
+			columns,
+		}
+	}
+}
+
+impl From<Std140Mat4> for Matrix4<f32> {
+	fn from(value: Std140Mat4) -> Self {
+		Matrix4::from_column_slice(bytemuck::cast_slice(&value.columns))
+	}
+}
+
+/// Uniform updates that only accept layout checked blocks.
+pub trait BlockStateExt {
+	fn update_checked<B: LayoutChecked>(
+		&self,
+		device: &vpb::Device,
+		block: &B,
+		frame: Option<usize>,
+	);
+}
+
+impl BlockStateExt for Arc<vpb::BlockState> {
+	fn update_checked<B: LayoutChecked>(
+		&self,
+		device: &vpb::Device,
+		block: &B,
+		frame: Option<usize>,
+	) {
+		self.update(
+			device,
+			block,
+			frame,
+		)
+	}
+}
\ No newline at end of file
@@ -0,0 +1,238 @@
+use std::sync::Arc;
+
+use vpb::ProgramData;
+
+use crate::{EnginePipeline, EngineBlockState, LayoutChecked, BlockStateExt, Texture, Sampler, pf};
+
+/// Values of a uniform material block, any layout checked block is one.
+pub trait MaterialParameters {
+	fn write(
+		&self,
+		device: &vpb::Device,
+		block_state: &EngineBlockState,
+		frame: Option<usize>,
+	);
+}
+
+impl<B: LayoutChecked> MaterialParameters for B {
+	fn write(
+		&self,
+		device: &vpb::Device,
+		block_state: &EngineBlockState,
+		frame: Option<usize>,
+	) {
+		block_state.uniform().update_checked(
+			device,
+			self,
+			frame,
+		);
+	}
+}
+
+/// Pipeline a family of materials is drawn with, along with the values
+/// every new material of the family starts from. Block indices count from
+/// the first material block of the pipeline's object block structure.
+pub struct MaterialTemplate {
+	pub name: String,
+	pub engine_pipeline: Arc<dyn EnginePipeline>,
+	defaults: Vec<(usize, Arc<dyn MaterialParameters>)>,
+	/// Block, array element and texture bound into every new material.
+	textures: Vec<(usize, usize, Arc<Texture>)>,
+}
+
+impl MaterialTemplate {
+	pub fn new(
+		name: &str,
+		engine_pipeline: Arc<dyn EnginePipeline>,
+	) -> Self {
+		Self {
+			name: name.to_string(),
+			engine_pipeline,
+			defaults: Vec::new(),
+			textures: Vec::new(),
+		}
+	}
+
+	pub fn with_parameters<B: LayoutChecked + 'static>(
+		mut self,
+		block: usize,
+		parameters: B,
+	) -> Self {
+		self.defaults.retain(|x| x.0 != block);
+		self.defaults.push((block, Arc::new(parameters)));
+		self
+	}
+
+	/// Texture new materials start with in `element` of the image block
+	/// `block`, 0 for blocks that aren't arrays.
+	pub fn with_texture(
+		mut self,
+		block: usize,
+		element: usize,
+		texture: Arc<Texture>,
+	) -> Self {
+		self.textures.retain(|x| (x.0, x.1) != (block, element));
+		self.textures.push((block, element, texture));
+		self
+	}
+
+	pub fn material_block_count(
+		&self,
+	) -> usize {
+		self.engine_pipeline.get_object_block_structure().material_block_count
+	}
+
+	/// Destroys the default textures no other owner holds on to. No
+	/// material of the template may still be in use by a submitted frame.
+	pub fn destroy_textures(
+		&mut self,
+		program_data: &ProgramData,
+	) {
+		for (_, _, mut texture) in self.textures.drain(..) {
+			if let Some(texture) = Arc::get_mut(&mut texture) {
+				texture.destroy(program_data);
+			}
+		}
+	}
+}
+
+/// Parameter values and textures for a `MaterialTemplate`. Objects point at
+/// a material with `Bucket::set_object_material`, the bucket then binds the
+/// material's descriptor sets once for all of them.
+pub struct Material {
+	pub name: String,
+	pub template: Arc<MaterialTemplate>,
+	block_states: Vec<EngineBlockState>,
+	parameters: Vec<(usize, Arc<dyn MaterialParameters>)>,
+	frame_count: usize,
+	/// Frames whose uniform blocks still hold old parameters.
+	frames_left: usize,
+}
+
+impl Material {
+	pub fn new(
+		program_data: &ProgramData,
+		template: &Arc<MaterialTemplate>,
+		name: &str,
+	) -> Self {
+		let block_states = pf::create_material_block_states(
+			program_data,
+			&template.engine_pipeline,
+		);
+		for (block, element, texture) in template.textures.iter() {
+			texture.bind_element(
+				&program_data.device,
+				&block_states[*block],
+				*element,
+				None,
+			);
+		}
+		Self {
+			name: name.to_string(),
+			template: template.clone(),
+			block_states,
+			parameters: template.defaults.clone(),
+			frame_count: program_data.frame_count,
+			frames_left: program_data.frame_count,
+		}
+	}
+
+	pub fn block_states(
+		&self,
+	) -> &[EngineBlockState] {
+		&self.block_states
+	}
+
+	/// Replaces the values of the uniform material block `block`, written
+	/// to each frame as the bucket reaches it.
+	pub fn set_parameters<B: LayoutChecked + 'static>(
+		&mut self,
+		block: usize,
+		parameters: B,
+	) {
+		assert!(
+			block < self.block_states.len(),
+			"material \"{}\" has no block {}", self.name, block,
+		);
+		self.parameters.retain(|x| x.0 != block);
+		self.parameters.push((block, Arc::new(parameters)));
+		self.frames_left = self.frame_count;
+	}
+
+	/// Points the image material block `block` at `texture`. `sampler`
+	/// overrides the texture's own sampler.
+	pub fn bind_texture(
+		&mut self,
+		device: &vpb::Device,
+		block: usize,
+		texture: &Texture,
+		sampler: Option<&Sampler>,
+	) {
+		texture.bind_with_sampler(
+			device,
+			&self.block_states[block],
+			sampler.unwrap_or(&texture.sampler),
+		);
+	}
+
+	/// Same as `bind_texture` for one element of an image array block.
+	pub fn bind_texture_element(
+		&mut self,
+		device: &vpb::Device,
+		block: usize,
+		element: usize,
+		texture: &Texture,
+		sampler: Option<&Sampler>,
+	) {
+		texture.bind_element(
+			device,
+			&self.block_states[block],
+			element,
+			sampler,
+		);
+	}
+
+	pub fn update_block_states(
+		&mut self,
+		device: &vpb::Device,
+		frame: usize,
+	) {
+		if self.frames_left == 0 {
+			return;
+		}
+		for (block, parameters) in self.parameters.iter() {
+			parameters.write(
+				device,
+				&self.block_states[*block],
+				Some(frame),
+			);
+		}
+		self.frames_left -= 1;
+	}
+
+	pub fn destroy_memory(
+		&mut self,
+		program_data: &ProgramData,
+	) {
+		for block_state in self.block_states.iter_mut() {
+			block_state.destroy_memory(program_data);
+		}
+	}
+
+	/// Image blocks keep their textures, uniform blocks are rewritten over
+	/// the next frames.
+	pub fn recreate_memory(
+		&mut self,
+		program_data: &ProgramData,
+		frame_count: usize,
+	) {
+		for block_state in self.block_states.iter_mut() {
+			block_state.recreate_memory(
+				program_data,
+				frame_count,
+			);
+		}
+		self.frame_count = frame_count;
+		self.frames_left = frame_count;
+	}
+}
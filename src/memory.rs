@@ -0,0 +1,189 @@
+use ash::vk;
+use vpb::ProgramData;
+
+/// The scene's vk-mem allocator, created in `Scene::new`.
+pub fn allocator(
+	program_data: &ProgramData,
+) -> &vk_mem::Allocator {
+	program_data.allocator.as_ref().as_ref().expect(
+		"the allocator is created with the scene"
+	)
+}
+
+/// Persistently mapped, host coherent buffer. Used for storage buffers and
+/// as the staging side of image uploads.
+pub struct HostBuffer {
+	pub buffer: vk::Buffer,
+	pub allocation: vk_mem::Allocation,
+	pub size: usize,
+	mapped: *mut u8,
+}
+
+impl HostBuffer {
+	pub fn new(
+		program_data: &ProgramData,
+		size: usize,
+		usage: vk::BufferUsageFlags,
+	) -> Self {
+		let buffer_info = vk::BufferCreateInfo::builder()
+			.size(size.max(1) as u64)
+			.usage(usage)
+			.sharing_mode(vk::SharingMode::EXCLUSIVE)
+			.build();
+		let allocation_info = vk_mem::AllocationCreateInfo {
+			usage: vk_mem::MemoryUsage::CpuOnly,
+			flags: vk_mem::AllocationCreateFlags::MAPPED,
+			required_flags: vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+			..Default::default()
+		};
+		let (buffer, allocation, allocation_info) = allocator(program_data).create_buffer(
+			&buffer_info,
+			&allocation_info,
+		).unwrap();
+		let mapped = allocation_info.get_mapped_data();
+		Self {
+			buffer,
+			allocation,
+			size,
+			mapped,
+		}
+	}
+
+	pub fn write(
+		&self,
+		offset: usize,
+		bytes: &[u8],
+	) { unsafe {
+		assert!(
+			offset + bytes.len() <= self.size,
+			"write of {} bytes at {} overflows a {} byte buffer",
+			bytes.len(), offset, self.size,
+		);
+		std::ptr::copy_nonoverlapping(
+			bytes.as_ptr(),
+			self.mapped.add(offset),
+			bytes.len(),
+		);
+	}}
+
+	pub fn destroy(
+		&mut self,
+		program_data: &ProgramData,
+	) {
+		allocator(program_data).destroy_buffer(
+			self.buffer,
+			&self.allocation,
+		).unwrap();
+		self.buffer = vk::Buffer::null();
+		self.mapped = std::ptr::null_mut();
+	}
+}
+
+unsafe impl Send for HostBuffer {}
+unsafe impl Sync for HostBuffer {}
+
+/// Records commands into the setup command buffer, submits them and waits
+/// for the queue, for one off uploads outside the render loop.
+pub fn submit_setup_commands<F: FnOnce(vk::CommandBuffer)>(
+	program_data: &ProgramData,
+	record: F,
+) { unsafe {
+	let device = &program_data.device.device;
+	let command_buffer = &program_data.command_buffer_setup;
+	device.wait_for_fences(
+		&[command_buffer.fence_submit],
+		true,
+		std::u64::MAX,
+	).unwrap();
+	device.reset_fences(
+		&[command_buffer.fence_submit],
+	).unwrap();
+	command_buffer.open(
+		&program_data.device,
+	);
+	record(command_buffer.command_buffer);
+	command_buffer.close(
+		&program_data.device,
+	);
+	let command_buffers = [command_buffer.command_buffer];
+	let submit_info = vk::SubmitInfo::builder()
+		.command_buffers(&command_buffers)
+		.build();
+	device.queue_submit(
+		command_buffer.present_queue,
+		&[submit_info],
+		command_buffer.fence_submit,
+	).unwrap();
+	// Leaves the fence signaled, which is what the next setup submit expects.
+	device.wait_for_fences(
+		&[command_buffer.fence_submit],
+		true,
+		std::u64::MAX,
+	).unwrap();
+}}
+
+/// Buffer in device local memory, filled once through a staging buffer.
+pub struct DeviceBuffer {
+	pub buffer: vk::Buffer,
+	pub allocation: vk_mem::Allocation,
+	pub size: usize,
+}
+
+impl DeviceBuffer {
+	pub fn from_bytes(
+		program_data: &ProgramData,
+		bytes: &[u8],
+		usage: vk::BufferUsageFlags,
+	) -> Self { unsafe {
+		let device = &program_data.device.device;
+		let buffer_info = vk::BufferCreateInfo::builder()
+			.size(bytes.len().max(1) as u64)
+			.usage(usage | vk::BufferUsageFlags::TRANSFER_DST)
+			.sharing_mode(vk::SharingMode::EXCLUSIVE)
+			.build();
+		let allocation_info = vk_mem::AllocationCreateInfo {
+			usage: vk_mem::MemoryUsage::GpuOnly,
+			..Default::default()
+		};
+		let (buffer, allocation, _) = allocator(program_data).create_buffer(
+			&buffer_info,
+			&allocation_info,
+		).unwrap();
+		if !bytes.is_empty() {
+			let mut staging = HostBuffer::new(
+				program_data,
+				bytes.len(),
+				vk::BufferUsageFlags::TRANSFER_SRC,
+			);
+			staging.write(0, bytes);
+			submit_setup_commands(program_data, |command_buffer| {
+				let region = vk::BufferCopy::builder()
+					.size(bytes.len() as u64)
+					.build();
+				device.cmd_copy_buffer(
+					command_buffer,
+					staging.buffer,
+					buffer,
+					&[region],
+				);
+			});
+			staging.destroy(program_data);
+		}
+		Self {
+			buffer,
+			allocation,
+			size: bytes.len(),
+		}
+	}}
+
+	pub fn destroy(
+		&mut self,
+		program_data: &ProgramData,
+	) {
+		allocator(program_data).destroy_buffer(
+			self.buffer,
+			&self.allocation,
+		).unwrap();
+		self.buffer = vk::Buffer::null();
+	}
+}
@@ -1,6 +1,18 @@
 #[macro_use]
 mod buffers;
 pub use buffers::*;
+mod host_mesh;
+pub use host_mesh::*;
+mod device_mesh;
+pub use device_mesh::*;
+mod mesh_object;
+pub use mesh_object::*;
+mod skinned_mesh_object;
+pub use skinned_mesh_object::*;
+mod sprite_batch;
+pub use sprite_batch::*;
+mod skybox;
+pub use skybox::*;
 pub mod r#static;
 pub mod dynamic;
 pub mod rendering;
@@ -3,12 +3,18 @@ use std::sync::Arc;
 use ash::vk;
 use vpb::{VertexBuffer, IndexBuffer, InstanceBuffer, ProgramData};
 
+use crate::{HostMesh, DeviceMesh};
+
 /// Contains vertex and index buffers. Stores different configurations of those.
 pub enum ObjectStateBuffers {
 	// TODO: combine
 	GOIndexed(Arc<vpb::GO_Indexed>),
 	GOIndirect(Arc<vpb::GO_Indirect>),
 	GOInstanced(Arc<vpb::GO_Instanced>),
+	/// Rewritten from the cpu every time it changes, see `HostMesh`.
+	HostIndexed(Arc<HostMesh>),
+	/// Uploaded once, see `DeviceMesh`.
+	DeviceIndexed(Arc<DeviceMesh>),
 
 	// CGO(Arc<vpb::VertexBufferCGO<V>>, Arc<vpb::IndexBufferCGO>),
 }
@@ -97,6 +103,22 @@ pub fn bind_buffers(
 				*command_buffer,
 			);
 		},
+		ObjectStateBuffers::HostIndexed(
+			host_mesh,
+		) => {
+			host_mesh.bind(
+				&program_data.device,
+				*command_buffer,
+			);
+		},
+		ObjectStateBuffers::DeviceIndexed(
+			device_mesh,
+		) => {
+			device_mesh.bind(
+				&program_data.device,
+				*command_buffer,
+			);
+		},
 	}
 }}
 
@@ -119,5 +141,15 @@ pub fn index_count(
 		) => {
 			instance_buffer.index_count as u32
 		},
+		ObjectStateBuffers::HostIndexed(
+			host_mesh,
+		) => {
+			host_mesh.index_count()
+		},
+		ObjectStateBuffers::DeviceIndexed(
+			device_mesh,
+		) => {
+			device_mesh.index_count
+		},
 	}
 }
\ No newline at end of file
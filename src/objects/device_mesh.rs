@@ -0,0 +1,62 @@
+use ash::vk;
+use bytemuck::Pod;
+use vpb::ProgramData;
+
+use crate::DeviceBuffer;
+
+/// Indexed mesh uploaded once into device local memory, for geometry
+/// built or loaded on the cpu that doesn't change afterwards.
+pub struct DeviceMesh {
+	pub vertex_buffer: DeviceBuffer,
+	pub index_buffer: DeviceBuffer,
+	pub index_count: u32,
+}
+
+impl DeviceMesh {
+	pub fn new<V: Pod>(
+		program_data: &ProgramData,
+		vertices: &[V],
+		indices: &[u32],
+	) -> Self {
+		Self {
+			vertex_buffer: DeviceBuffer::from_bytes(
+				program_data,
+				bytemuck::cast_slice(vertices),
+				vk::BufferUsageFlags::VERTEX_BUFFER,
+			),
+			index_buffer: DeviceBuffer::from_bytes(
+				program_data,
+				bytemuck::cast_slice(indices),
+				vk::BufferUsageFlags::INDEX_BUFFER,
+			),
+			index_count: indices.len() as u32,
+		}
+	}
+
+	pub fn bind(
+		&self,
+		device: &vpb::Device,
+		command_buffer: vk::CommandBuffer,
+	) { unsafe {
+		device.device.cmd_bind_vertex_buffers(
+			command_buffer,
+			0,
+			&[self.vertex_buffer.buffer],
+			&[0],
+		);
+		device.device.cmd_bind_index_buffer(
+			command_buffer,
+			self.index_buffer.buffer,
+			0,
+			vk::IndexType::UINT32,
+		);
+	}}
+
+	pub fn destroy(
+		&mut self,
+		program_data: &ProgramData,
+	) {
+		self.vertex_buffer.destroy(program_data);
+		self.index_buffer.destroy(program_data);
+	}
+}
@@ -25,6 +25,9 @@ impl DynamicState {
 				block_states: None,
 				buffers,
 				enabled: true,
+				push_constant: None,
+				material: None,
+				cast_shadows: true,
 			}),
 			dirty_state: DynamicDirtyState::All,
 			bs_left: 0,
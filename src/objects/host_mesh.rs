@@ -0,0 +1,91 @@
+use ash::vk;
+use vpb::ProgramData;
+
+use crate::HostBuffer;
+
+/// Indexed mesh in host visible memory that is rewritten from the cpu,
+/// for buffers vpb doesn't manage. Keeps one copy per frame in flight and
+/// writes round robin so a copy still read by the gpu is never touched.
+pub struct HostMesh {
+	vertex_buffers: Vec<HostBuffer>,
+	index_buffers: Vec<HostBuffer>,
+	index_counts: Vec<u32>,
+	current: usize,
+}
+
+impl HostMesh {
+	pub fn new(
+		program_data: &ProgramData,
+		vertex_capacity: usize,
+		index_capacity: usize,
+	) -> Self {
+		let copies = program_data.frame_count.max(1);
+		Self {
+			vertex_buffers: (0..copies).map(
+				|_|
+				HostBuffer::new(
+					program_data,
+					vertex_capacity,
+					vk::BufferUsageFlags::VERTEX_BUFFER,
+				)
+			).collect(),
+			index_buffers: (0..copies).map(
+				|_|
+				HostBuffer::new(
+					program_data,
+					index_capacity * std::mem::size_of::<u32>(),
+					vk::BufferUsageFlags::INDEX_BUFFER,
+				)
+			).collect(),
+			index_counts: vec![0; copies],
+			current: 0,
+		}
+	}
+
+	/// Writes into the least recently used copy and makes it current.
+	pub fn write(
+		&mut self,
+		vertices: &[u8],
+		indices: &[u32],
+	) {
+		let next = (self.current + 1) % self.vertex_buffers.len();
+		self.vertex_buffers[next].write(0, vertices);
+		self.index_buffers[next].write(0, bytemuck::cast_slice(indices));
+		self.index_counts[next] = indices.len() as u32;
+		self.current = next;
+	}
+
+	pub fn index_count(
+		&self,
+	) -> u32 {
+		self.index_counts[self.current]
+	}
+
+	pub fn bind(
+		&self,
+		device: &vpb::Device,
+		command_buffer: vk::CommandBuffer,
+	) { unsafe {
+		device.device.cmd_bind_vertex_buffers(
+			command_buffer,
+			0,
+			&[self.vertex_buffers[self.current].buffer],
+			&[0],
+		);
+		device.device.cmd_bind_index_buffer(
+			command_buffer,
+			self.index_buffers[self.current].buffer,
+			0,
+			vk::IndexType::UINT32,
+		);
+	}}
+
+	pub fn destroy(
+		&mut self,
+		program_data: &ProgramData,
+	) {
+		for buffer in self.vertex_buffers.iter_mut().chain(self.index_buffers.iter_mut()) {
+			buffer.destroy(program_data);
+		}
+	}
+}
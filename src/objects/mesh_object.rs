@@ -0,0 +1,231 @@
+use std::sync::Arc;
+
+use ash::vk;
+use nalgebra::Matrix4;
+use vpb::ProgramData;
+
+use crate::{DeviceMesh, ObjectStateBuffers, StaticDirtyState, Vertex3d, BlockMeshModel, BlockStateExt, Std140Vec4, BufferSource, ObjectDescription, r#static::{ObjectStatic, state::StaticState}, update::UpdateState};
+
+/// `Vertex3d` mesh uploaded once with its own model block. Block states are
+/// laid out like `PipelineMeshExample`.
+pub struct MeshObject {
+	state: Arc<StaticState>,
+	mesh: Arc<DeviceMesh>,
+	block: BlockMeshModel,
+	model_block: usize,
+	/// Kept so the object can be saved into scene files.
+	source: Option<Arc<BufferSource>>,
+}
+
+impl MeshObject {
+	/// Index of the `BlockMeshModel` in the object's block states, unless
+	/// changed with `with_model_block`.
+	pub const MODEL_BLOCK: usize = 2;
+	/// Kind the object is saved as and registered under by
+	/// `SceneRegistry::with_defaults`.
+	pub const KIND: &'static str = "MeshObject";
+
+	pub fn new(
+		program_data: &ProgramData,
+		name: &str,
+		vertices: &[Vertex3d],
+		indices: &[u32],
+	) -> Self {
+		MeshObject::from_mesh(
+			program_data,
+			name,
+			Arc::new(DeviceMesh::new(
+				program_data,
+				vertices,
+				indices,
+			)),
+		).with_source(Arc::new(BufferSource::new(
+			"Vertex3d",
+			vertices,
+			indices,
+		)))
+	}
+
+	/// Rebuilds a described object, `None` when its buffers don't hold
+	/// `Vertex3d` vertices.
+	pub fn from_description(
+		program_data: &ProgramData,
+		description: &ObjectDescription,
+	) -> Option<Self> {
+		let vertices = description.buffers.read_vertices::<Vertex3d>("Vertex3d")?;
+		let mut object = MeshObject::new(
+			program_data,
+			&description.name,
+			&vertices,
+			&description.buffers.indices,
+		);
+		if let Some([model_block]) = description.parameter::<1>("model_block") {
+			object.model_block = model_block as usize;
+		}
+		object.set_block(MeshObject::block_from_description(description));
+		Some(object)
+	}
+
+	/// Description `describe` saves the object as, split out so it can be
+	/// built without a device.
+	pub fn description(
+		source: &BufferSource,
+		block: &BlockMeshModel,
+		model_block: usize,
+	) -> ObjectDescription {
+		ObjectDescription::new(
+			MeshObject::KIND,
+			source.clone(),
+			&block.model.into(),
+		).with_parameter(
+			"color",
+			&block.color.value,
+		).with_parameter(
+			"specular",
+			&block.specular.value,
+		).with_parameter(
+			"model_block",
+			&[model_block as f32],
+		)
+	}
+
+	/// Model block read back from a description, parameters it doesn't
+	/// carry keep their defaults.
+	pub fn block_from_description(
+		description: &ObjectDescription,
+	) -> BlockMeshModel {
+		let defaults = BlockMeshModel::default();
+		BlockMeshModel {
+			model: description.transform_matrix().into(),
+			color: Std140Vec4 { value: description.parameter("color").unwrap_or(defaults.color.value) },
+			specular: Std140Vec4 { value: description.parameter("specular").unwrap_or(defaults.specular.value) },
+		}
+	}
+
+	/// Shares an already uploaded mesh, one object per placement. Such
+	/// objects are left out of saved scenes unless given the mesh's source
+	/// data with `with_source`.
+	pub fn from_mesh(
+		program_data: &ProgramData,
+		name: &str,
+		mesh: Arc<DeviceMesh>,
+	) -> Self {
+		let state = Arc::new(StaticState::new(
+			program_data,
+			name.to_string(),
+			ObjectStateBuffers::DeviceIndexed(mesh.clone()),
+			true,
+		));
+		Self {
+			state,
+			mesh,
+			block: BlockMeshModel::default(),
+			model_block: MeshObject::MODEL_BLOCK,
+			source: None,
+		}
+	}
+
+	/// Vertices and indices the mesh was uploaded from, shared between the
+	/// objects placing the same mesh.
+	pub fn with_source(
+		mut self,
+		source: Arc<BufferSource>,
+	) -> Self {
+		self.source = Some(source);
+		self
+	}
+
+	/// For pipelines that place the model block elsewhere, such as
+	/// `PipelinePbr::MODEL_BLOCK` or `PipelineMeshExample::SHADOWED_MODEL_BLOCK`.
+	pub fn with_model_block(
+		mut self,
+		model_block: usize,
+	) -> Self {
+		self.model_block = model_block;
+		self
+	}
+
+	pub fn mesh(
+		&self,
+	) -> &Arc<DeviceMesh> {
+		&self.mesh
+	}
+
+	pub fn block(
+		&self,
+	) -> &BlockMeshModel {
+		&self.block
+	}
+
+	pub fn set_block(
+		&mut self,
+		block: BlockMeshModel,
+	) {
+		self.block = block;
+		self.dirty(StaticDirtyState::BS);
+	}
+
+	pub fn set_transform(
+		&mut self,
+		model: Matrix4<f32>,
+	) {
+		self.block.model = model.into();
+		self.dirty(StaticDirtyState::BS);
+	}
+}
+
+impl ObjectStatic for MeshObject {
+	fn state(&self) -> Arc<StaticState> {
+		self.state.clone()
+	}
+
+	fn update_vib(
+		&mut self,
+		_program_data: &ProgramData,
+	) {}
+
+	fn update_bs(
+		&mut self,
+		program_data: &ProgramData,
+		frame: usize,
+	) {
+		let block_states = self.state.sub_state.block_states.as_ref().expect(
+			"attempting to update a mesh that isn't in a bucket"
+		);
+		block_states[self.model_block].uniform().update_checked(
+			&program_data.device,
+			&self.block,
+			Some(frame),
+		);
+	}
+}
+
+impl UpdateState for MeshObject {
+	fn update_block_states(
+		&mut self,
+		program_data: &ProgramData,
+		frame: usize,
+		frame_count: usize,
+		_command_buffer: &vk::CommandBuffer,
+		_pipeline_layout: &vk::PipelineLayout,
+	) {
+		ObjectStatic::update_block_states(
+			self,
+			program_data,
+			frame,
+			frame_count,
+		);
+	}
+
+	/// Objects placed with `from_mesh` and no source are left out.
+	fn describe(
+		&self,
+	) -> Option<ObjectDescription> {
+		let source = self.source.as_ref()?;
+		Some(MeshObject::description(
+			source,
+			&self.block,
+			self.model_block,
+		))
+	}
+}
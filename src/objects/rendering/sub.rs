@@ -1,12 +1,21 @@
 use std::sync::Arc;
 
-use crate::ObjectStateBuffers;
+use crate::{ObjectStateBuffers, PushConstantValue, EngineBlockState, Material};
 
 /// All object states have this sub state. Fundemental
 /// state regardless of object type.
 pub struct SubState {
 	pub name: String,
 	pub enabled: bool,
-	pub block_states: Option<Vec<Arc<vpb::BlockState>>>,
+	pub block_states: Option<Vec<EngineBlockState>>,
 	pub buffers: ObjectStateBuffers,
+	/// Pushed right before the object is drawn, must match a range
+	/// declared on the bucket's pipeline.
+	pub push_constant: Option<PushConstantValue>,
+	/// Supplies the material blocks, required when the bucket's pipeline
+	/// has any.
+	pub material: Option<Arc<Material>>,
+	/// Drawn into the shadow maps when the bucket's pipeline has a shadow
+	/// caster block.
+	pub cast_shadows: bool,
 }
\ No newline at end of file
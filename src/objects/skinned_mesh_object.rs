@@ -0,0 +1,207 @@
+use std::{sync::Arc, time::Instant};
+
+use ash::vk;
+use nalgebra::Matrix4;
+use vpb::ProgramData;
+
+use crate::{DeviceMesh, ObjectStateBuffers, StaticDirtyState, VertexSkinned, BlockMeshModel, BlockJoint, BlockStateExt, Skeleton, Animator, JointPose, MAX_JOINTS, r#static::{ObjectStatic, state::StaticState}, update::UpdateState};
+
+/// `VertexSkinned` mesh posed by its `Animator`. Block states are laid out
+/// like `PipelineMeshExample::skinned`, the joint matrices are sampled and
+/// uploaded every frame. Left out of saved scenes, skeletons and clips
+/// have no description.
+pub struct SkinnedMeshObject {
+	state: Arc<StaticState>,
+	mesh: Arc<DeviceMesh>,
+	block: BlockMeshModel,
+	model_block: usize,
+	skeleton: Arc<Skeleton>,
+	pub animator: Animator,
+	poses: Vec<JointPose>,
+	joints: Vec<BlockJoint>,
+	last_update: Option<Instant>,
+}
+
+impl SkinnedMeshObject {
+	/// Index of the `BlockMeshModel` in the object's block states, the
+	/// joints follow it, unless changed with `with_model_block`.
+	pub const MODEL_BLOCK: usize = 2;
+
+	pub fn new(
+		program_data: &ProgramData,
+		name: &str,
+		vertices: &[VertexSkinned],
+		indices: &[u32],
+		skeleton: Arc<Skeleton>,
+	) -> Self {
+		SkinnedMeshObject::from_mesh(
+			program_data,
+			name,
+			Arc::new(DeviceMesh::new(
+				program_data,
+				vertices,
+				indices,
+			)),
+			skeleton,
+		)
+	}
+
+	/// `mesh` must hold `VertexSkinned` vertices. Panics when the skeleton
+	/// has more than `MAX_JOINTS` joints.
+	pub fn from_mesh(
+		program_data: &ProgramData,
+		name: &str,
+		mesh: Arc<DeviceMesh>,
+		skeleton: Arc<Skeleton>,
+	) -> Self {
+		assert!(
+			skeleton.joints().len() <= MAX_JOINTS,
+			"skeleton has {} joints, at most {} can be skinned", skeleton.joints().len(), MAX_JOINTS,
+		);
+		let state = Arc::new(StaticState::new(
+			program_data,
+			name.to_string(),
+			ObjectStateBuffers::DeviceIndexed(mesh.clone()),
+			true,
+		));
+		Self {
+			state,
+			mesh,
+			block: BlockMeshModel::default(),
+			model_block: SkinnedMeshObject::MODEL_BLOCK,
+			poses: skeleton.rest_pose(),
+			skeleton,
+			animator: Animator::new(),
+			joints: Vec::with_capacity(MAX_JOINTS),
+			last_update: None,
+		}
+	}
+
+	/// For pipelines that place the model block elsewhere, such as
+	/// `PipelineMeshExample::SHADOWED_MODEL_BLOCK`. The joints are at the
+	/// next block.
+	pub fn with_model_block(
+		mut self,
+		model_block: usize,
+	) -> Self {
+		self.model_block = model_block;
+		self
+	}
+
+	pub fn mesh(
+		&self,
+	) -> &Arc<DeviceMesh> {
+		&self.mesh
+	}
+
+	pub fn skeleton(
+		&self,
+	) -> &Arc<Skeleton> {
+		&self.skeleton
+	}
+
+	/// Pose uploaded last frame.
+	pub fn poses(
+		&self,
+	) -> &[JointPose] {
+		&self.poses
+	}
+
+	pub fn block(
+		&self,
+	) -> &BlockMeshModel {
+		&self.block
+	}
+
+	pub fn set_block(
+		&mut self,
+		block: BlockMeshModel,
+	) {
+		self.block = block;
+		self.dirty(StaticDirtyState::BS);
+	}
+
+	pub fn set_transform(
+		&mut self,
+		model: Matrix4<f32>,
+	) {
+		self.block.model = model.into();
+		self.dirty(StaticDirtyState::BS);
+	}
+
+	/// Advances the animator by the time since the last frame and uploads
+	/// the resulting joint matrices into the joint block of `frame`.
+	fn update_joints(
+		&mut self,
+		frame: usize,
+	) {
+		let now = Instant::now();
+		// Capped like the scene's delta time so a stall doesn't skip ahead.
+		let delta_time = self.last_update.map_or(0.0, |x| (now - x).as_secs_f32().min(1.0));
+		self.last_update = Some(now);
+		self.animator.advance(delta_time);
+		self.animator.sample(
+			&self.skeleton,
+			&mut self.poses,
+		);
+		self.joints.clear();
+		self.joints.extend(self.skeleton.joint_matrices(&self.poses).into_iter().map(
+			|x|
+			BlockJoint {
+				matrix: x.into(),
+			}
+		));
+		let block_states = self.state.sub_state.block_states.as_ref().expect(
+			"attempting to update a skinned mesh that isn't in a bucket"
+		);
+		block_states[self.model_block + 1].descriptor().update_storage(
+			&self.joints,
+			Some(frame),
+		);
+	}
+}
+
+impl ObjectStatic for SkinnedMeshObject {
+	fn state(&self) -> Arc<StaticState> {
+		self.state.clone()
+	}
+
+	fn update_vib(
+		&mut self,
+		_program_data: &ProgramData,
+	) {}
+
+	fn update_bs(
+		&mut self,
+		program_data: &ProgramData,
+		frame: usize,
+	) {
+		let block_states = self.state.sub_state.block_states.as_ref().expect(
+			"attempting to update a skinned mesh that isn't in a bucket"
+		);
+		block_states[self.model_block].uniform().update_checked(
+			&program_data.device,
+			&self.block,
+			Some(frame),
+		);
+	}
+}
+
+impl UpdateState for SkinnedMeshObject {
+	fn update_block_states(
+		&mut self,
+		program_data: &ProgramData,
+		frame: usize,
+		frame_count: usize,
+		_command_buffer: &vk::CommandBuffer,
+		_pipeline_layout: &vk::PipelineLayout,
+	) {
+		ObjectStatic::update_block_states(
+			self,
+			program_data,
+			frame,
+			frame_count,
+		);
+		self.update_joints(frame);
+	}
+}
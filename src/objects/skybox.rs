@@ -0,0 +1,85 @@
+use std::sync::Arc;
+
+use ash::vk;
+use nalgebra::Matrix4;
+use vpb::ProgramData;
+
+use crate::{DeviceMesh, ObjectStateBuffers, BufferSource, ObjectDescription, primitives, r#static::{ObjectStatic, state::StaticState}, update::UpdateState};
+
+/// Unit cube the sky of `PipelineSkybox` is drawn on, has no blocks of
+/// its own. One per skybox bucket.
+pub struct Skybox {
+	state: Arc<StaticState>,
+}
+
+impl Skybox {
+	/// Kind the object is saved as and registered under by
+	/// `SceneRegistry::with_defaults`.
+	pub const KIND: &'static str = "Skybox";
+
+	pub fn new(
+		program_data: &ProgramData,
+		name: &str,
+	) -> Self {
+		let (vertices, indices) = primitives::cube(2.0);
+		let mesh = Arc::new(DeviceMesh::new(
+			program_data,
+			&vertices,
+			&indices,
+		));
+		Self {
+			state: Arc::new(StaticState::new(
+				program_data,
+				name.to_string(),
+				ObjectStateBuffers::DeviceIndexed(mesh),
+				true,
+			)),
+		}
+	}
+}
+
+impl ObjectStatic for Skybox {
+	fn state(&self) -> Arc<StaticState> {
+		self.state.clone()
+	}
+
+	fn update_vib(
+		&mut self,
+		_program_data: &ProgramData,
+	) {}
+
+	fn update_bs(
+		&mut self,
+		_program_data: &ProgramData,
+		_frame: usize,
+	) {}
+}
+
+impl UpdateState for Skybox {
+	fn update_block_states(
+		&mut self,
+		program_data: &ProgramData,
+		frame: usize,
+		frame_count: usize,
+		_command_buffer: &vk::CommandBuffer,
+		_pipeline_layout: &vk::PipelineLayout,
+	) {
+		ObjectStatic::update_block_states(
+			self,
+			program_data,
+			frame,
+			frame_count,
+		);
+	}
+
+	/// The cube is generated, so the description carries no buffers.
+	fn describe(
+		&self,
+	) -> Option<ObjectDescription> {
+		Some(ObjectDescription::new(
+			Skybox::KIND,
+			BufferSource::default(),
+			&Matrix4::identity(),
+		))
+	}
+}
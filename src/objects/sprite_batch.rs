@@ -0,0 +1,309 @@
+use std::sync::Arc;
+
+use ash::vk;
+use nalgebra::Matrix4;
+use vpb::ProgramData;
+
+use crate::{HostMesh, ObjectStateBuffers, DynamicDirtyState, VertexUITextured, BlockModelExample, AtlasRegion, BlockStateExt, Std140Vec4, BufferSource, ObjectDescription, dynamic::{ObjectDynamic, state::DynamicState}, update::UpdateState};
+
+#[derive(Clone, Copy, Debug)]
+pub struct Sprite {
+	pub position: [f32; 2],
+	pub size: [f32; 2],
+	pub uv_min: [f32; 2],
+	pub uv_max: [f32; 2],
+	pub color: [f32; 4],
+}
+
+impl Sprite {
+	/// Floats of one sprite in a described batch, its four
+	/// `VertexUITextured` corners.
+	const DESCRIBED_FLOATS: usize = 32;
+
+	pub fn from_region(
+		region: &AtlasRegion,
+		position: [f32; 2],
+		size: [f32; 2],
+		color: [f32; 4],
+	) -> Self {
+		Self {
+			position,
+			size,
+			uv_min: region.uv_min,
+			uv_max: region.uv_max,
+			color,
+		}
+	}
+
+	/// Corners counter clockwise from `position`.
+	fn quad(
+		&self,
+	) -> [VertexUITextured; 4] {
+		let [x, y] = self.position;
+		let [w, h] = self.size;
+		let [u0, v0] = self.uv_min;
+		let [u1, v1] = self.uv_max;
+		[
+			VertexUITextured { position: [x, y], uv: [u0, v0], color: self.color },
+			VertexUITextured { position: [x + w, y], uv: [u1, v0], color: self.color },
+			VertexUITextured { position: [x + w, y + h], uv: [u1, v1], color: self.color },
+			VertexUITextured { position: [x, y + h], uv: [u0, v1], color: self.color },
+		]
+	}
+
+	/// Inverse of `quad` on the flattened corners.
+	fn from_quad(
+		floats: &[f32],
+	) -> Self {
+		let corner = |i: usize| &floats[i * 8..(i + 1) * 8];
+		let (first, opposite) = (corner(0), corner(2));
+		Self {
+			position: [first[0], first[1]],
+			size: [opposite[0] - first[0], opposite[1] - first[1]],
+			uv_min: [first[2], first[3]],
+			uv_max: [opposite[2], opposite[3]],
+			color: [first[4], first[5], first[6], first[7]],
+		}
+	}
+}
+
+/// Every sprite of one atlas in a single dynamic vertex and index buffer,
+/// drawn with one call. Block states are laid out like
+/// `PipelineUITexturedExample`, bind the atlas with `Bucket::bind_texture`.
+pub struct SpriteBatch {
+	state: Arc<DynamicState>,
+	host_mesh: Arc<HostMesh>,
+	sprites: Vec<Sprite>,
+	capacity: usize,
+	vertices: Vec<VertexUITextured>,
+	indices: Vec<u32>,
+	model: BlockModelExample,
+}
+
+impl SpriteBatch {
+	/// Index of the model block in the object's block states.
+	pub const MODEL_BLOCK: usize = 1;
+	/// Kind the object is saved as and registered under by
+	/// `SceneRegistry::with_defaults`.
+	pub const KIND: &'static str = "SpriteBatch";
+
+	pub fn new(
+		program_data: &ProgramData,
+		name: &str,
+		capacity: usize,
+	) -> Self {
+		let host_mesh = Arc::new(HostMesh::new(
+			program_data,
+			capacity * 4 * std::mem::size_of::<VertexUITextured>(),
+			capacity * 6,
+		));
+		let state = Arc::new(DynamicState::new(
+			program_data,
+			name.to_string(),
+			ObjectStateBuffers::HostIndexed(host_mesh.clone()),
+		));
+		Self {
+			state,
+			host_mesh,
+			sprites: Vec::with_capacity(capacity),
+			capacity,
+			vertices: Vec::with_capacity(capacity * 4),
+			indices: Vec::with_capacity(capacity * 6),
+			model: BlockModelExample {
+				model: Matrix4::identity().into(),
+				color: Std140Vec4 { value: [1.0; 4] },
+			},
+		}
+	}
+
+	/// Rebuilds a described batch, `None` when its buffers don't hold
+	/// whole sprites or more than its capacity. Textures have to be bound
+	/// again.
+	pub fn from_description(
+		program_data: &ProgramData,
+		description: &ObjectDescription,
+	) -> Option<Self> {
+		let buffers = &description.buffers;
+		if buffers.vertex_type != "VertexUITextured" || buffers.vertices.len() % Sprite::DESCRIBED_FLOATS != 0 {
+			return None;
+		}
+		let sprites: Vec<Sprite> = buffers.vertices.chunks_exact(Sprite::DESCRIBED_FLOATS).map(Sprite::from_quad).collect();
+		let capacity = description.parameter::<1>("capacity").map_or(sprites.len(), |[x]| x as usize);
+		if sprites.len() > capacity {
+			return None;
+		}
+		let mut batch = SpriteBatch::new(
+			program_data,
+			&description.name,
+			capacity,
+		);
+		batch.set_sprites(&sprites);
+		batch.set_model(BlockModelExample {
+			model: description.transform_matrix().into(),
+			color: Std140Vec4 { value: description.parameter("color").unwrap_or([1.0; 4]) },
+		});
+		Some(batch)
+	}
+
+	pub fn sprites(
+		&self,
+	) -> &[Sprite] {
+		&self.sprites
+	}
+
+	pub fn clear(
+		&mut self,
+	) {
+		self.sprites.clear();
+		self.dirty(DynamicDirtyState::Mesh);
+	}
+
+	pub fn push(
+		&mut self,
+		sprite: Sprite,
+	) {
+		assert!(
+			self.sprites.len() < self.capacity,
+			"sprite batch is full ({} sprites)", self.capacity,
+		);
+		self.sprites.push(sprite);
+		self.dirty(DynamicDirtyState::Mesh);
+	}
+
+	/// Replaces every sprite at once.
+	pub fn set_sprites(
+		&mut self,
+		sprites: &[Sprite],
+	) {
+		assert!(
+			sprites.len() <= self.capacity,
+			"{} sprites overflow a batch of {}", sprites.len(), self.capacity,
+		);
+		self.sprites.clear();
+		self.sprites.extend_from_slice(sprites);
+		self.dirty(DynamicDirtyState::Mesh);
+	}
+
+	pub fn model(
+		&self,
+	) -> &BlockModelExample {
+		&self.model
+	}
+
+	pub fn set_model(
+		&mut self,
+		model: BlockModelExample,
+	) {
+		self.model = model;
+		self.dirty(DynamicDirtyState::Position);
+	}
+}
+
+impl ObjectDynamic for SpriteBatch {
+	fn state(&self) -> Arc<DynamicState> {
+		self.state.clone()
+	}
+
+	/// Writes the indices too, they only change with the sprite count.
+	fn update_vb(
+		&mut self,
+		_device: &vpb::Device,
+	) {
+		self.vertices.clear();
+		self.indices.clear();
+		for (i, sprite) in self.sprites.iter().enumerate() {
+			self.vertices.extend_from_slice(&sprite.quad());
+			self.indices.extend_from_slice(&quad_indices(i));
+		}
+		let vertices: &[u8] = unsafe {
+			std::slice::from_raw_parts(
+				self.vertices.as_ptr() as *const u8,
+				self.vertices.len() * std::mem::size_of::<VertexUITextured>(),
+			)
+		};
+		vpb::gmuc!(self.host_mesh).write(
+			vertices,
+			&self.indices,
+		);
+	}
+
+	fn update_ib(
+		&mut self,
+		_device: &vpb::Device,
+	) {}
+
+	fn update_bs(
+		&mut self,
+		device: &vpb::Device,
+		frame: usize,
+	) {
+		let block_states = self.state.sub_state.block_states.as_ref().expect(
+			"attempting to update a sprite batch that isn't in a bucket"
+		);
+		block_states[SpriteBatch::MODEL_BLOCK].uniform().update_checked(
+			device,
+			&self.model,
+			Some(frame),
+		);
+	}
+}
+
+impl UpdateState for SpriteBatch {
+	fn update_block_states(
+		&mut self,
+		program_data: &ProgramData,
+		frame: usize,
+		frame_count: usize,
+		_command_buffer: &vk::CommandBuffer,
+		_pipeline_layout: &vk::PipelineLayout,
+	) {
+		ObjectDynamic::update_block_states(
+			self,
+			&program_data.device,
+			frame,
+			frame_count,
+		);
+	}
+
+	fn describe(
+		&self,
+	) -> Option<ObjectDescription> {
+		let mut vertices = Vec::with_capacity(self.sprites.len() * Sprite::DESCRIBED_FLOATS);
+		let mut indices = Vec::with_capacity(self.sprites.len() * 6);
+		for (i, sprite) in self.sprites.iter().enumerate() {
+			for corner in sprite.quad() {
+				vertices.extend_from_slice(&corner.position);
+				vertices.extend_from_slice(&corner.uv);
+				vertices.extend_from_slice(&corner.color);
+			}
+			indices.extend_from_slice(&quad_indices(i));
+		}
+		Some(ObjectDescription::new(
+			SpriteBatch::KIND,
+			BufferSource {
+				vertex_type: "VertexUITextured".to_string(),
+				vertices,
+				indices,
+				instances: Vec::new(),
+			},
+			&self.model.model.into(),
+		).with_parameter(
+			"color",
+			&self.model.color.value,
+		).with_parameter(
+			"capacity",
+			&[self.capacity as f32],
+		))
+	}
+}
+
+/// Two triangles of the `i`th sprite's quad.
+fn quad_indices(
+	i: usize,
+) -> [u32; 6] {
+	let base = (i * 4) as u32;
+	[
+		base, base + 1, base + 2,
+		base + 2, base + 3, base,
+	]
+}
@@ -25,6 +25,9 @@ impl StaticState {
 				block_states: None,
 				buffers,
 				enabled,
+				push_constant: None,
+				material: None,
+				cast_shadows: true,
 			}),
 			dirty_state: StaticDirtyState::all(),
 			bs_left: 0,
@@ -1,7 +1,7 @@
 use ash::vk;
 use vpb::ProgramData;
 
-use crate::{InputState, RenderState};
+use crate::{InputState, RenderState, ObjectDescription};
 
 pub trait UpdateState {
 	/// Give the option for the object to update their
@@ -14,4 +14,13 @@ pub trait UpdateState {
 		command_buffer: &vk::CommandBuffer,
 		pipeline_layout: &vk::PipelineLayout,
 	);
+
+	/// Source data used to save the object into a scene file. The
+	/// bucket fills in the name and enabled flag, objects returning `None`
+	/// are left out.
+	fn describe(
+		&self,
+	) -> Option<ObjectDescription> {
+		None
+	}
 }
\ No newline at end of file
@@ -1,17 +1,37 @@
-use std::sync::Arc;
+use std::{sync::Arc, fmt};
 
 use ash::vk;
+use serde::{Serialize, Deserialize};
 use shaderc::ShaderKind;
 
 mod engine_pipeline;
 pub use engine_pipeline::*;
 mod pipeline_info;
 pub use pipeline_info::*;
+mod pipeline_state;
+pub use pipeline_state::*;
 pub mod pf;
 pub use pf::*;
+mod type_registry;
+pub use type_registry::*;
+mod pipeline_description;
+pub use pipeline_description::*;
+mod described_pipeline;
+pub use described_pipeline::*;
+mod shader;
+pub use shader::*;
+mod shader_watcher;
+pub use shader_watcher::*;
+mod reflection;
+pub use reflection::*;
+mod push_constant;
+pub use push_constant::*;
+mod descriptor_block;
+pub use descriptor_block::*;
 use vpb::ProgramData;
 
-#[derive(Copy, Clone)]
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum ViewportDepthRange {
 	UI,
 	WORLD,
@@ -35,39 +55,114 @@ pub fn create_viewport(
 		.build()
 }
 
+/// Why `create_graphics_pipeline` couldn't build a pipeline.
+#[derive(Debug)]
+pub enum PipelineCreateError {
+	Compile { kind: ShaderKind, message: String },
+	/// Only returned in debug builds, release builds log the mismatches
+	/// and build the pipeline anyway.
+	InterfaceMismatch(Vec<String>),
+}
+
+impl fmt::Display for PipelineCreateError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			PipelineCreateError::Compile { kind, message } => write!(f, "failed to compile {} shader\n{}", shader_extension(*kind), message),
+			PipelineCreateError::InterfaceMismatch(mismatches) => write!(f, "shader interface mismatch\n{}", mismatches.join("\n")),
+		}
+	}
+}
+
+impl std::error::Error for PipelineCreateError {}
+
 pub fn create_graphics_pipeline<V: vpb::Vertex>(
 	program_data: &ProgramData,
 	shader_name: &str,
 	pipeline_info: &PipelineInfo,
 	pipeline_block_structure: &Arc<ObjectBlockStructure>,
 	object_block_structure: &Arc<ObjectBlockStructure>,
-) -> (vk::Pipeline, vk::PipelineLayout, [vk::Viewport; 1], [vk::Rect2D; 1]) {
-	let sm_vert = program_data.load_shader(
-		ShaderKind::Vertex,
-		shader_name,
-	);
-	let sm_frag = program_data.load_shader(
-		ShaderKind::Fragment,
+) -> Result<(vk::Pipeline, vk::PipelineLayout, [vk::Viewport; 1], [vk::Rect2D; 1]), PipelineCreateError> {
+	let mut kinds = vec![(ShaderKind::Vertex, vk::ShaderStageFlags::VERTEX)];
+	if pipeline_info.state.depth_only_pass.is_none() {
+		kinds.push((ShaderKind::Fragment, vk::ShaderStageFlags::FRAGMENT));
+	}
+	let codes: Vec<Arc<Vec<u32>>> = kinds.iter().map(
+		|(kind, _)|
+		compile_shader(
+			*kind,
+			shader_name,
+			&pipeline_info.defines,
+		).map_err(|message| PipelineCreateError::Compile { kind: *kind, message })
+	).collect::<Result<_, _>>()?;
+	let mismatches = validate_shader_interface::<V>(
 		shader_name,
+		&codes.iter().map(|x| x.as_slice()).collect::<Vec<&[u32]>>(),
+		pipeline_block_structure,
+		object_block_structure,
+		&pipeline_info.push_constants,
 	);
-	let stages = vpb::create_stage_infos(
-		&[
-			(
-				sm_vert,
-				vk::ShaderStageFlags::VERTEX,
-			),
-			(
-				sm_frag,
-				vk::ShaderStageFlags::FRAGMENT,
+	if !mismatches.is_empty() {
+		if cfg!(debug_assertions) {
+			return Err(PipelineCreateError::InterfaceMismatch(mismatches));
+		}
+		log::warn!("shader interface mismatch\n{}", mismatches.join("\n"));
+	}
+	let shader_modules: Vec<(vk::ShaderModule, vk::ShaderStageFlags)> = codes.iter().zip(kinds.iter()).map(
+		|(code, (_, stage))|
+		(
+			create_shader_module(
+				program_data,
+				code,
 			),
-		]
+			*stage,
+		)
+	).collect();
+	let stages = vpb::create_stage_infos(
+		&shader_modules,
 	);
-	create_pipeline::<V>(
+	let pipeline = create_pipeline::<V>(
 		program_data,
 		&stages,
 		pipeline_info,
 		pipeline_block_structure,
 		object_block_structure,
+	);
+	unsafe {
+		for (shader_module, _) in shader_modules.iter() {
+			program_data.device.device.destroy_shader_module(*shader_module, None);
+		}
+	}
+	Ok(pipeline)
+}
+
+/// Returns every difference between what the shaders declare and the
+/// vertex type and blocks of the pipeline. Structures built without
+/// layouts are skipped.
+pub fn validate_shader_interface<V: vpb::Vertex>(
+	shader_name: &str,
+	codes: &[&[u32]],
+	pipeline_block_structure: &Arc<ObjectBlockStructure>,
+	object_block_structure: &Arc<ObjectBlockStructure>,
+	push_constants: &[vk::PushConstantRange],
+) -> Vec<String> {
+	let structures = [pipeline_block_structure.as_ref(), object_block_structure.as_ref()];
+	if structures.iter().any(|x| x.layouts.len() != x.spawners.len()) {
+		return Vec::new();
+	}
+	let mut reflections = Vec::with_capacity(codes.len());
+	for code in codes.iter() {
+		match ShaderReflection::new(code) {
+			Ok(reflection) => reflections.push(reflection),
+			Err(e) => {
+				return vec![format!("{}: failed to reflect shader: {}", shader_name, e)];
+			},
+		}
+	}
+	ShaderReflection::merge(&reflections).validate(
+		shader_name,
+		&V::attribute_descriptions(),
+		&structures,
+		push_constants,
 	)
 }
 
@@ -84,8 +179,10 @@ fn create_pipeline<V: vpb::Vertex>(
 		.vertex_attribute_descriptions(&attribute_descriptions)
 		.vertex_binding_descriptions(&binding_descriptions)
 		.build();
+	let state = &pipeline_info.state;
 	let assembly_state_info = vk::PipelineInputAssemblyStateCreateInfo::builder()
-		.topology(vk::PrimitiveTopology::TRIANGLE_LIST)
+		.topology(state.topology)
+		.primitive_restart_enable(state.primitive_restart())
 		.build();
 	let viewports = [create_viewport(&program_data.window, pipeline_info.viewport_depth_range)];
 	let scissors = [program_data.window.extent.into()];
@@ -93,11 +190,18 @@ fn create_pipeline<V: vpb::Vertex>(
 		.scissors(&scissors)
 		.viewports(&viewports)
 		.build();
-	let rasterization_info = vk::PipelineRasterizationStateCreateInfo::builder()
-		.front_face(vk::FrontFace::COUNTER_CLOCKWISE)
-		.line_width(1.0)
-		.polygon_mode(pipeline_info.polygon_mode)
+	let mut rasterization_info = vk::PipelineRasterizationStateCreateInfo::builder()
+		.front_face(state.front_face)
+		.cull_mode(state.cull_mode)
+		.line_width(state.line_width)
+		.polygon_mode(state.polygon_mode)
 		.build();
+	if let Some(depth_bias) = state.depth_bias {
+		rasterization_info.depth_bias_enable = vk::TRUE;
+		rasterization_info.depth_bias_constant_factor = depth_bias.constant_factor;
+		rasterization_info.depth_bias_clamp = depth_bias.clamp;
+		rasterization_info.depth_bias_slope_factor = depth_bias.slope_factor;
+	}
 	let multisample_state_info = vk::PipelineMultisampleStateCreateInfo::builder()
 		.rasterization_samples(vk::SampleCountFlags::TYPE_1)
 		.build();
@@ -107,18 +211,10 @@ fn create_pipeline<V: vpb::Vertex>(
 		.depth_fail_op(vk::StencilOp::KEEP)
 		.compare_op(vk::CompareOp::ALWAYS)
 		.build();
-	let color_blend_attachment_states = [
-		vk::PipelineColorBlendAttachmentState::builder()
-			.blend_enable(true)
-			.src_color_blend_factor(vk::BlendFactor::SRC_ALPHA)//SRC_COLOR)
-			.dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)//ONE_MINUS_DST_COLOR)
-			.color_blend_op(vk::BlendOp::ADD)
-			.src_alpha_blend_factor(vk::BlendFactor::SRC_ALPHA)
-			.dst_alpha_blend_factor(vk::BlendFactor::DST_ALPHA)
-			.alpha_blend_op(vk::BlendOp::ADD)
-			.color_write_mask(vk::ColorComponentFlags::RGBA)
-			.build(),
-	];
+	let color_blend_attachment_states = match state.depth_only_pass {
+		Some(_) => Vec::new(),
+		None => vec![state.blend.attachment_state(state.color_write_mask); state.color_attachment_count as usize],
+	};
 	let color_blend_state = vk::PipelineColorBlendStateCreateInfo::builder()
 		.attachments(&color_blend_attachment_states)
 		.build();
@@ -134,17 +230,16 @@ fn create_pipeline<V: vpb::Vertex>(
 		|x|
 		x.layout()
 	));
-	let push_constants = (pipeline_info.push_constants)();
+	let push_constants = &pipeline_info.push_constants;
 	let pipeline_layout_info: vk::PipelineLayoutCreateInfo;
 	if push_constants.is_empty() {
 		pipeline_layout_info = vk::PipelineLayoutCreateInfo::builder()
 		.set_layouts(&descriptor_set_layouts)
 		.build();
 	} else {
-		println!("loading push constants {:?}", push_constants);
 		pipeline_layout_info = vk::PipelineLayoutCreateInfo::builder()
 		.set_layouts(&descriptor_set_layouts)
-		.push_constant_ranges(&push_constants)
+		.push_constant_ranges(push_constants)
 		.build();
 	}
 	let pipeline_layout = program_data.device.device.create_pipeline_layout(
@@ -161,12 +256,12 @@ fn create_pipeline<V: vpb::Vertex>(
 		.color_blend_state(&color_blend_state)
 		.dynamic_state(&dynamic_state_info)
 		.layout(pipeline_layout)
-		.render_pass(program_data.render_pass.render_pass);
-	if pipeline_info.depth {
+		.render_pass(state.depth_only_pass.or(state.render_pass).unwrap_or(program_data.render_pass.render_pass));
+	if state.depth_test || state.depth_write {
 		let depth_state_info = vk::PipelineDepthStencilStateCreateInfo::builder()
-			.depth_test_enable(true)
-			.depth_write_enable(true)
-			.depth_compare_op(vk::CompareOp::LESS)
+			.depth_test_enable(state.depth_test)
+			.depth_write_enable(state.depth_write)
+			.depth_compare_op(state.depth_compare)
 			.front(stencil_state_info)
 			.back(stencil_state_info)
 			.max_depth_bounds(1.0)
@@ -175,14 +270,14 @@ fn create_pipeline<V: vpb::Vertex>(
 			&depth_state_info,
 		).build();
 		(program_data.device.device.create_graphics_pipelines(
-			vk::PipelineCache::null(),
+			pipeline_info.pipeline_cache,
 			&[graphics_pipeline_info],
 			None,
 		).unwrap()[0], pipeline_layout, viewports, scissors)
 	} else {
 		let graphics_pipeline_info = graphics_pipeline_info.build();
 		(program_data.device.device.create_graphics_pipelines(
-			vk::PipelineCache::null(),
+			pipeline_info.pipeline_cache,
 			&[graphics_pipeline_info],
 			None,
 		).unwrap()[0], pipeline_layout, viewports, scissors)
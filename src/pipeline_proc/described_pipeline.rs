@@ -0,0 +1,134 @@
+use std::sync::Arc;
+
+use ash::vk;
+use vpb::ProgramData;
+
+use crate::{PipelineInfo, ObjectBlockStructure, EnginePipeline, InputState, RenderState, Camera, PipelineDescription, PipelineDescriptionError, TypeRegistry, BlockDescription, PipelineCreateError};
+
+/// Generic `EnginePipeline` built from a `PipelineDescription`.
+pub struct DescribedPipeline {
+	pipeline_info: Arc<PipelineInfo>,
+	pipeline_block_structure: Arc<ObjectBlockStructure>,
+	object_block_structure: Arc<ObjectBlockStructure>,
+	camera_block: Option<usize>,
+	camera: Option<Arc<dyn Camera>>,
+}
+
+impl DescribedPipeline {
+	pub fn new(
+		program_data: &ProgramData,
+		pipeline_cache: vk::PipelineCache,
+		description: &PipelineDescription,
+		registry: &TypeRegistry,
+		camera: Option<Arc<dyn Camera>>,
+	) -> Result<Self, PipelineDescriptionError> {
+		let create_fn = registry.vertex(&description.vertex).ok_or_else(
+			|| PipelineDescriptionError::UnknownVertex(description.vertex.clone())
+		)?;
+		let pipeline_block_structure = Arc::new(DescribedPipeline::create_block_structure(
+			program_data,
+			&description.pipeline_blocks,
+			registry,
+		)?);
+		if let Some(camera_block) = description.camera_block {
+			let is_uniform = pipeline_block_structure.layouts.get(camera_block).is_some_and(
+				|x| x.descriptor_type == vk::DescriptorType::UNIFORM_BUFFER
+			);
+			if !is_uniform {
+				return Err(PipelineDescriptionError::InvalidCameraBlock(camera_block));
+			}
+		}
+		let mut object_block_structure = DescribedPipeline::create_block_structure(
+			program_data,
+			&description.material_blocks.iter().chain(description.object_blocks.iter()).cloned().collect::<Vec<_>>(),
+			registry,
+		)?;
+		object_block_structure.material_block_count = description.material_blocks.len();
+		let object_block_structure = Arc::new(object_block_structure);
+		let pipeline_info = Arc::new(PipelineInfo::from_state(
+			program_data,
+			pipeline_cache,
+			&description.shader,
+			description.viewport_depth_range,
+			description.state(),
+			description.defines.clone(),
+			&pipeline_block_structure,
+			&object_block_structure,
+			description.push_constant_ranges(),
+			create_fn,
+		));
+		Ok(Self {
+			pipeline_info,
+			pipeline_block_structure,
+			object_block_structure,
+			camera_block: description.camera_block,
+			camera,
+		})
+	}
+
+	fn create_block_structure(
+		program_data: &ProgramData,
+		blocks: &[BlockDescription],
+		registry: &TypeRegistry,
+	) -> Result<ObjectBlockStructure, PipelineDescriptionError> {
+		let mut structure = ObjectBlockStructure::new();
+		for block in blocks.iter() {
+			let spawn = registry.block(&block.block).ok_or_else(
+				|| PipelineDescriptionError::UnknownBlock(block.block.clone())
+			)?;
+			spawn(
+				&mut structure,
+				&program_data.device,
+				vpb::BindingId(block.binding), vpb::SetId(block.set),
+			);
+		}
+		Ok(structure)
+	}
+}
+
+impl EnginePipeline for DescribedPipeline {
+	fn get_pipeline_info(
+		&self,
+	) -> Arc<PipelineInfo> {
+		self.pipeline_info.clone()
+	}
+
+	fn get_pipeline_block_structure(
+		&self,
+	) -> Arc<ObjectBlockStructure> {
+		self.pipeline_block_structure.clone()
+	}
+
+	fn get_object_block_structure(
+		&self,
+	) -> Arc<ObjectBlockStructure> {
+		self.object_block_structure.clone()
+	}
+
+	fn recreate_pipeline(
+		&mut self,
+		program_data: &ProgramData,
+	) -> Result<(), PipelineCreateError> {
+		let pipeline_info = vpb::gmuc!(self.pipeline_info);
+		pipeline_info.recreate_pipeline(
+			program_data,
+			&self.pipeline_block_structure,
+			&self.object_block_structure,
+		)
+	}
+
+	fn update_block_states(
+		&mut self,
+		program_data: &ProgramData,
+		input_state: &InputState,
+		render_state: &RenderState,
+	) {
+		if let (Some(camera), Some(camera_block)) = (self.camera.as_ref(), self.camera_block) {
+			camera.update(
+				&program_data.device,
+				Some(render_state.frame),
+				self.pipeline_info.block_states[camera_block].uniform(),
+			);
+		}
+	}
+}
\ No newline at end of file
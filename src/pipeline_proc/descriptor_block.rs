@@ -0,0 +1,331 @@
+use std::sync::{Arc, Mutex};
+
+use ash::vk;
+use bytemuck::Pod;
+use vpb::ProgramData;
+
+use crate::{BlockSpawner, EngineBlockState, HostBuffer};
+
+/// Descriptors that vpb's uniform only `BlockState` can't express.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DescriptorBlockKind {
+	CombinedImageSampler,
+	/// `count` combined image samplers in one binding, a glsl array.
+	CombinedImageSamplerArray { count: u32 },
+	SampledImage,
+	StorageImage,
+	/// Host visible storage buffer of `size` bytes, one per frame.
+	StorageBuffer { size: usize },
+}
+
+impl DescriptorBlockKind {
+	pub fn descriptor_type(
+		&self,
+	) -> vk::DescriptorType {
+		match self {
+			DescriptorBlockKind::CombinedImageSampler => vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+			DescriptorBlockKind::CombinedImageSamplerArray { .. } => vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+			DescriptorBlockKind::SampledImage => vk::DescriptorType::SAMPLED_IMAGE,
+			DescriptorBlockKind::StorageImage => vk::DescriptorType::STORAGE_IMAGE,
+			DescriptorBlockKind::StorageBuffer { .. } => vk::DescriptorType::STORAGE_BUFFER,
+		}
+	}
+
+	/// Descriptors in the binding.
+	pub fn count(
+		&self,
+	) -> u32 {
+		match self {
+			DescriptorBlockKind::CombinedImageSamplerArray { count } => *count,
+			_ => 1,
+		}
+	}
+
+	/// Layout images are expected to be in when the descriptor is read.
+	pub fn image_layout(
+		&self,
+	) -> vk::ImageLayout {
+		match self {
+			DescriptorBlockKind::StorageImage => vk::ImageLayout::GENERAL,
+			_ => vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+		}
+	}
+}
+
+pub struct DescriptorBlockSpawner {
+	kind: DescriptorBlockKind,
+	binding: vpb::BindingId,
+	layout: vk::DescriptorSetLayout,
+}
+
+impl DescriptorBlockSpawner {
+	pub fn new(
+		device: &Arc<vpb::Device>,
+		binding: vpb::BindingId,
+		kind: DescriptorBlockKind,
+		stages: vk::ShaderStageFlags,
+	) -> Self { unsafe {
+		let descriptor_set_layout_binding = vk::DescriptorSetLayoutBinding::builder()
+			.binding(binding.0)
+			.descriptor_type(kind.descriptor_type())
+			.stage_flags(stages)
+			.descriptor_count(kind.count())
+			.build();
+		let descriptor_set_layout_info = vk::DescriptorSetLayoutCreateInfo::builder()
+			.bindings(&[
+				descriptor_set_layout_binding,
+			]).build();
+		let layout = device.device.create_descriptor_set_layout(
+			&descriptor_set_layout_info,
+			None,
+		).unwrap();
+		Self {
+			kind,
+			binding,
+			layout,
+		}
+	}}
+}
+
+impl BlockSpawner for DescriptorBlockSpawner {
+	fn layout(
+		&self,
+	) -> vk::DescriptorSetLayout {
+		self.layout
+	}
+
+	fn spawn(
+		&self,
+		program_data: &ProgramData,
+		frame_count: usize,
+	) -> EngineBlockState {
+		EngineBlockState::Descriptor(Arc::new(DescriptorBlockState::new(
+			program_data,
+			self.layout,
+			frame_count,
+			self.binding,
+			self.kind,
+		)))
+	}
+}
+
+/// One descriptor set per frame with its own pool, so it survives the
+/// shared descriptor pool being recreated on resize.
+pub struct DescriptorBlockState {
+	pub kind: DescriptorBlockKind,
+	pub binding: vpb::BindingId,
+	pub descriptor_sets: Vec<vk::DescriptorSet>,
+	layout: vk::DescriptorSetLayout,
+	descriptor_pool: vk::DescriptorPool,
+	buffers: Vec<HostBuffer>,
+	/// One per array element, rewritten into the new sets when the memory
+	/// is recreated.
+	images: Mutex<Vec<Option<vk::DescriptorImageInfo>>>,
+}
+
+impl DescriptorBlockState {
+	pub fn new(
+		program_data: &ProgramData,
+		layout: vk::DescriptorSetLayout,
+		frame_count: usize,
+		binding: vpb::BindingId,
+		kind: DescriptorBlockKind,
+	) -> Self {
+		let mut state = Self {
+			kind,
+			binding,
+			descriptor_sets: Vec::new(),
+			layout,
+			descriptor_pool: vk::DescriptorPool::null(),
+			buffers: Vec::new(),
+			images: Mutex::new(vec![None; kind.count() as usize]),
+		};
+		state.create_memory(
+			program_data,
+			frame_count,
+		);
+		state
+	}
+
+	fn create_memory(
+		&mut self,
+		program_data: &ProgramData,
+		frame_count: usize,
+	) { unsafe {
+		let device = &program_data.device.device;
+		let pool_sizes = [
+			vk::DescriptorPoolSize::builder()
+				.ty(self.kind.descriptor_type())
+				.descriptor_count(frame_count as u32 * self.kind.count())
+				.build(),
+		];
+		let descriptor_pool_info = vk::DescriptorPoolCreateInfo::builder()
+			.pool_sizes(&pool_sizes)
+			.max_sets(frame_count as u32)
+			.build();
+		self.descriptor_pool = device.create_descriptor_pool(
+			&descriptor_pool_info,
+			None,
+		).unwrap();
+		let layouts = vec![self.layout; frame_count];
+		let allocate_info = vk::DescriptorSetAllocateInfo::builder()
+			.descriptor_pool(self.descriptor_pool)
+			.set_layouts(&layouts)
+			.build();
+		self.descriptor_sets = device.allocate_descriptor_sets(
+			&allocate_info,
+		).unwrap();
+		if let DescriptorBlockKind::StorageBuffer { size } = self.kind {
+			self.buffers = (0..frame_count).map(
+				|_|
+				HostBuffer::new(
+					program_data,
+					size,
+					vk::BufferUsageFlags::STORAGE_BUFFER,
+				)
+			).collect();
+			for (descriptor_set, buffer) in self.descriptor_sets.iter().zip(self.buffers.iter()) {
+				let buffer_info = [
+					vk::DescriptorBufferInfo::builder()
+						.buffer(buffer.buffer)
+						.offset(0)
+						.range(vk::WHOLE_SIZE)
+						.build(),
+				];
+				let write = vk::WriteDescriptorSet::builder()
+					.dst_set(*descriptor_set)
+					.dst_binding(self.binding.0)
+					.descriptor_type(self.kind.descriptor_type())
+					.buffer_info(&buffer_info)
+					.build();
+				device.update_descriptor_sets(
+					&[write],
+					&[],
+				);
+			}
+		}
+		let images = self.images.lock().unwrap().clone();
+		for (element, image) in images.iter().enumerate() {
+			if let Some(image) = image {
+				self.write_image_info(
+					&program_data.device,
+					element,
+					*image,
+				);
+			}
+		}
+	}}
+
+	/// Points every frame's descriptor at the image. The image must be in
+	/// `DescriptorBlockKind::image_layout` whenever the set is bound.
+	pub fn write_image(
+		&self,
+		device: &vpb::Device,
+		image_view: vk::ImageView,
+		sampler: vk::Sampler,
+	) {
+		self.write_image_element(
+			device,
+			0,
+			image_view,
+			sampler,
+		);
+	}
+
+	/// Same as `write_image` for one element of an image array.
+	pub fn write_image_element(
+		&self,
+		device: &vpb::Device,
+		element: usize,
+		image_view: vk::ImageView,
+		sampler: vk::Sampler,
+	) {
+		assert!(
+			element < self.kind.count() as usize,
+			"image element {} out of range for {:?}", element, self.kind,
+		);
+		assert!(
+			!matches!(self.kind, DescriptorBlockKind::StorageBuffer { .. }),
+			"attempting to write an image into a storage buffer descriptor",
+		);
+		let image_info = vk::DescriptorImageInfo::builder()
+			.image_view(image_view)
+			.sampler(sampler)
+			.image_layout(self.kind.image_layout())
+			.build();
+		self.images.lock().unwrap()[element] = Some(image_info);
+		self.write_image_info(
+			device,
+			element,
+			image_info,
+		);
+	}
+
+	fn write_image_info(
+		&self,
+		device: &vpb::Device,
+		element: usize,
+		image_info: vk::DescriptorImageInfo,
+	) { unsafe {
+		let image_info = [image_info];
+		let writes: Vec<vk::WriteDescriptorSet> = self.descriptor_sets.iter().map(
+			|x|
+			vk::WriteDescriptorSet::builder()
+				.dst_set(*x)
+				.dst_binding(self.binding.0)
+				.dst_array_element(element as u32)
+				.descriptor_type(self.kind.descriptor_type())
+				.image_info(&image_info)
+				.build()
+		).collect();
+		device.device.update_descriptor_sets(
+			&writes,
+			&[],
+		);
+	}}
+
+	/// Copies `data` into the storage buffer of `frame`, or of every frame
+	/// when `None`.
+	pub fn update_storage<T: Pod>(
+		&self,
+		data: &[T],
+		frame: Option<usize>,
+	) {
+		let bytes: &[u8] = bytemuck::cast_slice(data);
+		match frame {
+			Some(frame) => self.buffers[frame].write(0, bytes),
+			None => {
+				for buffer in self.buffers.iter() {
+					buffer.write(0, bytes);
+				}
+			},
+		}
+	}
+
+	pub fn destroy_memory(
+		&mut self,
+		program_data: &ProgramData,
+	) { unsafe {
+		for buffer in self.buffers.iter_mut() {
+			buffer.destroy(program_data);
+		}
+		self.buffers.clear();
+		program_data.device.device.destroy_descriptor_pool(
+			self.descriptor_pool,
+			None,
+		);
+		self.descriptor_pool = vk::DescriptorPool::null();
+		self.descriptor_sets.clear();
+	}}
+
+	pub fn recreate_memory(
+		&mut self,
+		program_data: &ProgramData,
+		frame_count: usize,
+	) {
+		self.create_memory(
+			program_data,
+			frame_count,
+		);
+	}
+}
@@ -3,10 +3,283 @@ use std::sync::Arc;
 use ash::vk;
 use vpb::ProgramData;
 
-use crate::{PipelineInfo, InputState, RenderState, rendering::RenderingState};
+use crate::{PipelineInfo, InputState, RenderState, rendering::RenderingState, DescriptorBlockKind, DescriptorBlockSpawner, DescriptorBlockState, PipelineCreateError};
+
+/// Rust side description of a block, checked against the shader when
+/// the pipeline is created.
+pub trait BlockReflect {
+	fn block_name(
+	) -> &'static str;
+
+	fn block_size(
+	) -> usize;
+
+	fn descriptor_type(
+	) -> vk::DescriptorType {
+		vk::DescriptorType::UNIFORM_BUFFER
+	}
+
+	fn stage_flags(
+	) -> vk::ShaderStageFlags;
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct BlockLayout {
+	pub name: &'static str,
+	pub set: u32,
+	pub binding: u32,
+	pub size: usize,
+	pub descriptor_type: vk::DescriptorType,
+	pub stages: vk::ShaderStageFlags,
+}
+
+impl BlockLayout {
+	pub fn new<B: BlockReflect>(
+		binding: vpb::BindingId,
+		set: vpb::SetId,
+	) -> Self {
+		Self {
+			name: B::block_name(),
+			set: set.0,
+			binding: binding.0,
+			size: B::block_size(),
+			descriptor_type: B::descriptor_type(),
+			stages: B::stage_flags(),
+		}
+	}
+}
+
+/// Block state of one descriptor set, either vpb's uniform `BlockState`
+/// or a vpe descriptor for images and storage buffers.
+#[derive(Clone)]
+pub enum EngineBlockState {
+	Uniform(Arc<vpb::BlockState>),
+	Descriptor(Arc<DescriptorBlockState>),
+}
+
+impl EngineBlockState {
+	pub fn uniform(
+		&self,
+	) -> &Arc<vpb::BlockState> {
+		match self {
+			EngineBlockState::Uniform(block_state) => block_state,
+			EngineBlockState::Descriptor(_) => panic!("block state is not a uniform block"),
+		}
+	}
+
+	pub fn descriptor(
+		&self,
+	) -> &Arc<DescriptorBlockState> {
+		match self {
+			EngineBlockState::Descriptor(block_state) => block_state,
+			EngineBlockState::Uniform(_) => panic!("block state is not a descriptor block"),
+		}
+	}
+
+	pub fn descriptor_set(
+		&self,
+		frame: usize,
+	) -> vk::DescriptorSet {
+		match self {
+			EngineBlockState::Uniform(block_state) => block_state.descriptor_data.descriptor_sets[frame],
+			EngineBlockState::Descriptor(block_state) => block_state.descriptor_sets[frame],
+		}
+	}
+
+	pub fn destroy_memory(
+		&mut self,
+		program_data: &ProgramData,
+	) { unsafe {
+		match self {
+			EngineBlockState::Uniform(block_state) => {
+				let block_state = vpb::gmuc_ref!(block_state);
+				block_state.destroy_memory(program_data);
+			},
+			EngineBlockState::Descriptor(block_state) => {
+				let block_state = vpb::gmuc_ref!(block_state);
+				block_state.destroy_memory(program_data);
+			},
+		}
+	}}
+
+	pub fn recreate_memory(
+		&mut self,
+		program_data: &ProgramData,
+		frame_count: usize,
+	) { unsafe {
+		match self {
+			EngineBlockState::Uniform(block_state) => {
+				let block_state = vpb::gmuc_ref!(block_state);
+				block_state.recreate_memory(program_data, frame_count);
+			},
+			EngineBlockState::Descriptor(block_state) => {
+				let block_state = vpb::gmuc_ref!(block_state);
+				block_state.recreate_memory(program_data, frame_count);
+			},
+		}
+	}}
+}
+
+pub trait BlockSpawner {
+	fn layout(
+		&self,
+	) -> vk::DescriptorSetLayout;
+
+	fn spawn(
+		&self,
+		program_data: &ProgramData,
+		frame_count: usize,
+	) -> EngineBlockState;
+}
+
+/// Adapts vpb's uniform block spawners.
+pub struct UniformBlockSpawner {
+	spawner: Box<dyn vpb::BlockSpawnerGen>,
+}
+
+impl BlockSpawner for UniformBlockSpawner {
+	fn layout(
+		&self,
+	) -> vk::DescriptorSetLayout {
+		self.spawner.layout()
+	}
+
+	fn spawn(
+		&self,
+		program_data: &ProgramData,
+		frame_count: usize,
+	) -> EngineBlockState {
+		EngineBlockState::Uniform(self.spawner.spawn(
+			program_data,
+			frame_count,
+		))
+	}
+}
 
 pub struct ObjectBlockStructure {
-	pub spawners: Vec<Box<dyn vpb::BlockSpawnerGen>>,
+	pub spawners: Vec<Box<dyn BlockSpawner>>,
+	/// Parallel to `spawners`, used to validate the shader interface.
+	pub layouts: Vec<BlockLayout>,
+	/// Leading spawners owned by a `Material` instead of each object, only
+	/// meaningful for object block structures.
+	pub material_block_count: usize,
+}
+
+impl ObjectBlockStructure {
+	pub fn new(
+	) -> Self {
+		Self {
+			spawners: Vec::with_capacity(4),
+			layouts: Vec::with_capacity(4),
+			material_block_count: 0,
+		}
+	}
+
+	/// Marks every block pushed so far as a material block, so they're
+	/// spawned once per `Material` rather than once per object.
+	pub fn with_material_blocks(
+		mut self,
+	) -> Self {
+		self.material_block_count = self.spawners.len();
+		self
+	}
+
+	pub fn push_block<B: vpb::Block + BlockReflect + 'static>(
+		&mut self,
+		device: &Arc<vpb::Device>,
+		binding: vpb::BindingId,
+		set: vpb::SetId,
+	) {
+		self.layouts.push(BlockLayout::new::<B>(binding, set));
+		self.spawners.push(Box::new(UniformBlockSpawner {
+			spawner: Box::new(vpb::BlockSpawner::<B>::new(
+				device,
+				binding, set,
+			)),
+		}));
+	}
+
+	pub fn with_block<B: vpb::Block + BlockReflect + 'static>(
+		mut self,
+		device: &Arc<vpb::Device>,
+		binding: vpb::BindingId,
+		set: vpb::SetId,
+	) -> Self {
+		self.push_block::<B>(device, binding, set);
+		self
+	}
+
+	/// Image or storage buffer descriptor, `name` is only used in
+	/// shader interface mismatch messages.
+	pub fn push_descriptor(
+		&mut self,
+		device: &Arc<vpb::Device>,
+		name: &'static str,
+		binding: vpb::BindingId,
+		set: vpb::SetId,
+		kind: DescriptorBlockKind,
+		stages: vk::ShaderStageFlags,
+	) {
+		self.layouts.push(BlockLayout {
+			name,
+			set: set.0,
+			binding: binding.0,
+			size: match kind {
+				DescriptorBlockKind::StorageBuffer { size } => size,
+				_ => 0,
+			},
+			descriptor_type: kind.descriptor_type(),
+			stages,
+		});
+		self.spawners.push(Box::new(DescriptorBlockSpawner::new(
+			device,
+			binding,
+			kind,
+			stages,
+		)));
+	}
+
+	pub fn with_descriptor(
+		mut self,
+		device: &Arc<vpb::Device>,
+		name: &'static str,
+		binding: vpb::BindingId,
+		set: vpb::SetId,
+		kind: DescriptorBlockKind,
+		stages: vk::ShaderStageFlags,
+	) -> Self {
+		self.push_descriptor(device, name, binding, set, kind, stages);
+		self
+	}
+
+	/// Storage buffer holding `count` elements of a `#[block(descriptor = storage)]` type.
+	pub fn push_storage_block<B: BlockReflect>(
+		&mut self,
+		device: &Arc<vpb::Device>,
+		binding: vpb::BindingId,
+		set: vpb::SetId,
+		count: usize,
+	) {
+		self.push_descriptor(
+			device,
+			B::block_name(),
+			binding,
+			set,
+			DescriptorBlockKind::StorageBuffer { size: B::block_size() * count },
+			B::stage_flags(),
+		);
+	}
+
+	pub fn with_storage_block<B: BlockReflect>(
+		mut self,
+		device: &Arc<vpb::Device>,
+		binding: vpb::BindingId,
+		set: vpb::SetId,
+		count: usize,
+	) -> Self {
+		self.push_storage_block::<B>(device, binding, set, count);
+		self
+	}
 }
 
 pub trait EnginePipeline {
@@ -22,10 +295,11 @@ pub trait EnginePipeline {
 		&self,
 	) -> Arc<ObjectBlockStructure>;
 
+	/// Keeps the current pipeline when the new one can't be built.
 	fn recreate_pipeline(
 		&mut self,
 		program_data: &ProgramData,
-	);
+	) -> Result<(), PipelineCreateError>;
 	
 	fn update_block_states(
 		&mut self,
@@ -2,14 +2,29 @@ use std::sync::Arc;
 
 use vpb::ProgramData;
 
-use crate::{EnginePipeline};
+use crate::{EnginePipeline, EngineBlockState};
 
+/// Block states of one object, material blocks are left to `Material`.
 pub fn create_object_block_states(
 	program_data: &ProgramData,
 	engine_pipeline: &Arc<dyn EnginePipeline>,
-) -> Vec<Arc<vpb::BlockState>> {
+) -> Vec<EngineBlockState> {
 	let structure = engine_pipeline.get_object_block_structure();
-	structure.spawners.iter().map(
+	structure.spawners.iter().skip(structure.material_block_count).map(
+		|x|
+		x.spawn(
+			program_data,
+			program_data.frame_count,
+		)
+	).collect()
+}
+
+pub fn create_material_block_states(
+	program_data: &ProgramData,
+	engine_pipeline: &Arc<dyn EnginePipeline>,
+) -> Vec<EngineBlockState> {
+	let structure = engine_pipeline.get_object_block_structure();
+	structure.spawners.iter().take(structure.material_block_count).map(
 		|x|
 		x.spawn(
 			program_data,
@@ -0,0 +1,206 @@
+use std::{fs, path::Path, fmt};
+
+use ash::vk;
+use serde::{Serialize, Deserialize};
+
+use crate::{ViewportDepthRange, BlendPreset, PipelineState, ShaderDefines};
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum PolygonModeDescription {
+	FILL,
+	LINE,
+	POINT,
+}
+
+impl From<PolygonModeDescription> for vk::PolygonMode {
+	fn from(polygon_mode: PolygonModeDescription) -> Self {
+		match polygon_mode {
+			PolygonModeDescription::FILL => vk::PolygonMode::FILL,
+			PolygonModeDescription::LINE => vk::PolygonMode::LINE,
+			PolygonModeDescription::POINT => vk::PolygonMode::POINT,
+		}
+	}
+}
+
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize)]
+pub enum TopologyDescription {
+	#[default]
+	TRIANGLE_LIST,
+	TRIANGLE_STRIP,
+	LINE_LIST,
+	LINE_STRIP,
+	POINT_LIST,
+}
+
+impl From<TopologyDescription> for vk::PrimitiveTopology {
+	fn from(topology: TopologyDescription) -> Self {
+		match topology {
+			TopologyDescription::TRIANGLE_LIST => vk::PrimitiveTopology::TRIANGLE_LIST,
+			TopologyDescription::TRIANGLE_STRIP => vk::PrimitiveTopology::TRIANGLE_STRIP,
+			TopologyDescription::LINE_LIST => vk::PrimitiveTopology::LINE_LIST,
+			TopologyDescription::LINE_STRIP => vk::PrimitiveTopology::LINE_STRIP,
+			TopologyDescription::POINT_LIST => vk::PrimitiveTopology::POINT_LIST,
+		}
+	}
+}
+
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize)]
+pub enum CullModeDescription {
+	#[default]
+	NONE,
+	FRONT,
+	BACK,
+}
+
+impl From<CullModeDescription> for vk::CullModeFlags {
+	fn from(cull_mode: CullModeDescription) -> Self {
+		match cull_mode {
+			CullModeDescription::NONE => vk::CullModeFlags::NONE,
+			CullModeDescription::FRONT => vk::CullModeFlags::FRONT,
+			CullModeDescription::BACK => vk::CullModeFlags::BACK,
+		}
+	}
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum ShaderStageDescription {
+	VERTEX,
+	FRAGMENT,
+}
+
+impl From<ShaderStageDescription> for vk::ShaderStageFlags {
+	fn from(stage: ShaderStageDescription) -> Self {
+		match stage {
+			ShaderStageDescription::VERTEX => vk::ShaderStageFlags::VERTEX,
+			ShaderStageDescription::FRAGMENT => vk::ShaderStageFlags::FRAGMENT,
+		}
+	}
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BlockDescription {
+	/// Name the block type was registered under in the `TypeRegistry`.
+	pub block: String,
+	pub binding: u32,
+	pub set: u32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PushConstantDescription {
+	pub stages: Vec<ShaderStageDescription>,
+	pub offset: u32,
+	pub size: u32,
+}
+
+impl PushConstantDescription {
+	pub fn range(
+		&self,
+	) -> vk::PushConstantRange {
+		let stage_flags = self.stages.iter().fold(
+			vk::ShaderStageFlags::empty(),
+			|flags, x| flags | vk::ShaderStageFlags::from(*x),
+		);
+		vk::PushConstantRange::builder()
+			.stage_flags(stage_flags)
+			.offset(self.offset)
+			.size(self.size)
+			.build()
+	}
+}
+
+/// Everything needed to build a pipeline without writing an
+/// `EnginePipeline` by hand. Usually loaded from a ron file.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PipelineDescription {
+	pub shader: String,
+	#[serde(default)]
+	pub defines: ShaderDefines,
+	/// Name the vertex type was registered under in the `TypeRegistry`.
+	pub vertex: String,
+	pub depth: bool,
+	pub viewport_depth_range: ViewportDepthRange,
+	pub polygon_mode: PolygonModeDescription,
+	#[serde(default)]
+	pub topology: TopologyDescription,
+	#[serde(default)]
+	pub cull_mode: CullModeDescription,
+	#[serde(default)]
+	pub blend: BlendPreset,
+	pub pipeline_blocks: Vec<BlockDescription>,
+	/// Blocks shared by every object using the same `Material`, their sets
+	/// come right after the pipeline blocks.
+	#[serde(default)]
+	pub material_blocks: Vec<BlockDescription>,
+	pub object_blocks: Vec<BlockDescription>,
+	#[serde(default)]
+	pub push_constants: Vec<PushConstantDescription>,
+	/// Index into `pipeline_blocks` that receives the scene camera every frame.
+	#[serde(default)]
+	pub camera_block: Option<usize>,
+}
+
+#[derive(Debug)]
+pub enum PipelineDescriptionError {
+	Io(std::io::Error),
+	Ron(ron::error::SpannedError),
+	UnknownVertex(String),
+	UnknownBlock(String),
+	/// `camera_block` is out of range or not a uniform block.
+	InvalidCameraBlock(usize),
+}
+
+impl fmt::Display for PipelineDescriptionError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			PipelineDescriptionError::Io(e) => write!(f, "pipeline description io error: {}", e),
+			PipelineDescriptionError::Ron(e) => write!(f, "pipeline description ron error: {}", e),
+			PipelineDescriptionError::UnknownVertex(vertex) => write!(f, "no vertex type registered as \"{}\"", vertex),
+			PipelineDescriptionError::UnknownBlock(block) => write!(f, "no block type registered as \"{}\"", block),
+			PipelineDescriptionError::InvalidCameraBlock(index) => write!(f, "camera block {} is not a uniform pipeline block", index),
+		}
+	}
+}
+
+impl std::error::Error for PipelineDescriptionError {}
+
+impl PipelineDescription {
+	pub fn from_file<P: AsRef<Path>>(
+		path: P,
+	) -> Result<Self, PipelineDescriptionError> {
+		let data = fs::read_to_string(path).map_err(
+			|e| PipelineDescriptionError::Io(e)
+		)?;
+		PipelineDescription::from_ron(&data)
+	}
+
+	pub fn from_ron(
+		data: &str,
+	) -> Result<Self, PipelineDescriptionError> {
+		ron::from_str(data).map_err(
+			|e| PipelineDescriptionError::Ron(e)
+		)
+	}
+
+	pub fn state(
+		&self,
+	) -> PipelineState {
+		PipelineState {
+			topology: self.topology.into(),
+			polygon_mode: self.polygon_mode.into(),
+			cull_mode: self.cull_mode.into(),
+			depth_test: self.depth,
+			depth_write: self.depth,
+			blend: self.blend,
+			..Default::default()
+		}
+	}
+
+	pub fn push_constant_ranges(
+		&self,
+	) -> Vec<vk::PushConstantRange> {
+		self.push_constants.iter().map(
+			|x|
+			x.range()
+		).collect()
+	}
+}
\ No newline at end of file
@@ -3,31 +3,111 @@ use std::sync::Arc;
 use ash::vk;
 use vpb::ProgramData;
 
-use crate::{ViewportDepthRange, create_graphics_pipeline, ObjectBlockStructure};
+use crate::{ViewportDepthRange, create_graphics_pipeline, ObjectBlockStructure, PipelineState, BlendPreset, DepthBias, ShaderDefines, PushConstant, MAX_PUSH_CONSTANT_SIZE, EngineBlockState, RenderGraph, GraphPassId, PipelineCreateError};
+
+/// Monomorphized `create_graphics_pipeline` for a vertex type. Lets
+/// pipelines be recreated without knowing the vertex type statically.
+pub type CreateGraphicsPipelineFn = fn(
+	&ProgramData,
+	&str,
+	&PipelineInfo,
+	&Arc<ObjectBlockStructure>,
+	&Arc<ObjectBlockStructure>,
+) -> Result<(vk::Pipeline, vk::PipelineLayout, [vk::Viewport; 1], [vk::Rect2D; 1]), PipelineCreateError>;
 
 pub struct PipelineInfo {
 	pub pipeline: vk::Pipeline,
 	pub pipeline_layout: vk::PipelineLayout,
 	pub viewport: [vk::Viewport; 1],
 	pub scissor: [vk::Rect2D; 1],
-	pub depth: bool,
 	pub viewport_depth_range: ViewportDepthRange,
-	pub polygon_mode: vk::PolygonMode,
-	pub block_states: Vec<Arc<vpb::BlockState>>,
+	pub state: PipelineState,
+	pub block_states: Vec<EngineBlockState>,
 	pub name: String,
-	pub push_constants: fn() -> Vec<vk::PushConstantRange>,
+	/// Selects the shader variant, see `compile_shader`.
+	pub defines: ShaderDefines,
+	pub push_constants: Vec<vk::PushConstantRange>,
+	pub create_fn: CreateGraphicsPipelineFn,
+	/// Scene's pipeline cache, used again when the pipeline is recreated.
+	pub pipeline_cache: vk::PipelineCache,
 }
 
 impl PipelineInfo {
 	pub fn new<V: vpb::Vertex>(
 		program_data: &ProgramData,
+		pipeline_cache: vk::PipelineCache,
 		name: &str,
 		depth: bool,
 		viewport_depth_range: ViewportDepthRange,
 		polygon_mode: vk::PolygonMode,
 		pipeline_block_structure: &Arc<ObjectBlockStructure>,
 		object_block_structure: &Arc<ObjectBlockStructure>,
-		push_constants: fn() -> Vec<vk::PushConstantRange>,
+		push_constants: Vec<vk::PushConstantRange>,
+	) -> Self {
+		PipelineInfo::new_dyn(
+			program_data,
+			pipeline_cache,
+			name,
+			depth,
+			viewport_depth_range,
+			polygon_mode,
+			pipeline_block_structure,
+			object_block_structure,
+			push_constants,
+			create_graphics_pipeline::<V>,
+		)
+	}
+
+	/// Same as `new` with the vertex type erased into `create_fn`.
+	pub fn new_dyn(
+		program_data: &ProgramData,
+		pipeline_cache: vk::PipelineCache,
+		name: &str,
+		depth: bool,
+		viewport_depth_range: ViewportDepthRange,
+		polygon_mode: vk::PolygonMode,
+		pipeline_block_structure: &Arc<ObjectBlockStructure>,
+		object_block_structure: &Arc<ObjectBlockStructure>,
+		push_constants: Vec<vk::PushConstantRange>,
+		create_fn: CreateGraphicsPipelineFn,
+	) -> Self {
+		PipelineInfo::from_state(
+			program_data,
+			pipeline_cache,
+			name,
+			viewport_depth_range,
+			PipelineState {
+				polygon_mode,
+				depth_test: depth,
+				depth_write: depth,
+				..Default::default()
+			},
+			ShaderDefines::new(),
+			pipeline_block_structure,
+			object_block_structure,
+			push_constants,
+			create_fn,
+		)
+	}
+
+	/// Start of a `PipelineInfoBuilder` for anything beyond depth and polygon mode.
+	pub fn builder(
+		name: &str,
+	) -> PipelineInfoBuilder {
+		PipelineInfoBuilder::new(name)
+	}
+
+	pub fn from_state(
+		program_data: &ProgramData,
+		pipeline_cache: vk::PipelineCache,
+		name: &str,
+		viewport_depth_range: ViewportDepthRange,
+		state: PipelineState,
+		defines: ShaderDefines,
+		pipeline_block_structure: &Arc<ObjectBlockStructure>,
+		object_block_structure: &Arc<ObjectBlockStructure>,
+		push_constants: Vec<vk::PushConstantRange>,
+		create_fn: CreateGraphicsPipelineFn,
 	) -> Self {
 		let block_states = pipeline_block_structure.spawners.iter().map(
 			|x|
@@ -41,25 +121,27 @@ impl PipelineInfo {
 			pipeline_layout: vk::PipelineLayout::null(),
 			viewport: [vk::Viewport::default()],
 			scissor: [vk::Rect2D::default()],
-			depth,
 			viewport_depth_range,
-			polygon_mode,
+			state,
 			block_states,
 			name: name.to_string(),
+			defines,
 			push_constants,
+			create_fn,
+			pipeline_cache,
 		};
 		let (
 			pipeline,
 			pipeline_layout,
 			viewport,
 			scissor
-		) = create_graphics_pipeline::<V>(
+		) = create_fn(
 			program_data,
 			name,
 			&pipeline_info,
 			pipeline_block_structure,
 			object_block_structure,
-		);
+		).unwrap_or_else(|e| panic!("failed to create pipeline \"{}\"\n{}", name, e));
 		pipeline_info.pipeline = pipeline;
 		pipeline_info.pipeline_layout = pipeline_layout;
 		pipeline_info.viewport = viewport;
@@ -67,28 +149,33 @@ impl PipelineInfo {
 		pipeline_info
 	}
 
-	pub fn recreate_pipeline<V: vpb::Vertex>(
+	/// Builds the pipeline again and destroys the previous one. On error
+	/// the previous pipeline and layout are kept, the device must be idle
+	/// either way.
+	pub fn recreate_pipeline(
 		&mut self,
 		program_data: &ProgramData,
 		pipeline_block_structure: &Arc<ObjectBlockStructure>,
 		object_block_structure: &Arc<ObjectBlockStructure>,
-	) {
+	) -> Result<(), PipelineCreateError> {
 		let (
 			pipeline,
 			pipeline_layout,
 			viewport,
 			scissor
-		) = create_graphics_pipeline::<V>(
+		) = (self.create_fn)(
 			program_data,
 			&self.name,
 			&self,
 			pipeline_block_structure,
 			object_block_structure,
-		);
+		)?;
+		self.destroy_pipeline(program_data);
 		self.pipeline = pipeline;
 		self.pipeline_layout = pipeline_layout;
 		self.viewport = viewport;
 		self.scissor = scissor;
+		Ok(())
 	}
 
 	pub fn destroy_pipeline(
@@ -103,5 +190,247 @@ impl PipelineInfo {
 			self.pipeline_layout,
 			None,
 		);
+		self.pipeline = vk::Pipeline::null();
+		self.pipeline_layout = vk::PipelineLayout::null();
 	}}
+}
+
+pub struct PipelineInfoBuilder {
+	name: String,
+	viewport_depth_range: ViewportDepthRange,
+	state: PipelineState,
+	defines: ShaderDefines,
+	push_constants: Vec<vk::PushConstantRange>,
+}
+
+impl PipelineInfoBuilder {
+	pub fn new(
+		name: &str,
+	) -> Self {
+		Self {
+			name: name.to_string(),
+			viewport_depth_range: ViewportDepthRange::UI,
+			state: PipelineState::default(),
+			defines: ShaderDefines::new(),
+			push_constants: Vec::new(),
+		}
+	}
+
+	pub fn viewport_depth_range(
+		mut self,
+		viewport_depth_range: ViewportDepthRange,
+	) -> Self {
+		self.viewport_depth_range = viewport_depth_range;
+		self
+	}
+
+	pub fn state(
+		mut self,
+		state: PipelineState,
+	) -> Self {
+		self.state = state;
+		self
+	}
+
+	pub fn topology(
+		mut self,
+		topology: vk::PrimitiveTopology,
+	) -> Self {
+		self.state.topology = topology;
+		self
+	}
+
+	pub fn polygon_mode(
+		mut self,
+		polygon_mode: vk::PolygonMode,
+	) -> Self {
+		self.state.polygon_mode = polygon_mode;
+		self
+	}
+
+	pub fn line_width(
+		mut self,
+		line_width: f32,
+	) -> Self {
+		self.state.line_width = line_width;
+		self
+	}
+
+	pub fn cull_mode(
+		mut self,
+		cull_mode: vk::CullModeFlags,
+	) -> Self {
+		self.state.cull_mode = cull_mode;
+		self
+	}
+
+	pub fn front_face(
+		mut self,
+		front_face: vk::FrontFace,
+	) -> Self {
+		self.state.front_face = front_face;
+		self
+	}
+
+	/// Shorthand for enabling or disabling both depth test and depth write.
+	pub fn depth(
+		mut self,
+		depth: bool,
+	) -> Self {
+		self.state.depth_test = depth;
+		self.state.depth_write = depth;
+		self
+	}
+
+	pub fn depth_test(
+		mut self,
+		depth_test: bool,
+	) -> Self {
+		self.state.depth_test = depth_test;
+		self
+	}
+
+	pub fn depth_write(
+		mut self,
+		depth_write: bool,
+	) -> Self {
+		self.state.depth_write = depth_write;
+		self
+	}
+
+	pub fn depth_compare(
+		mut self,
+		depth_compare: vk::CompareOp,
+	) -> Self {
+		self.state.depth_compare = depth_compare;
+		self
+	}
+
+	pub fn depth_bias(
+		mut self,
+		depth_bias: Option<DepthBias>,
+	) -> Self {
+		self.state.depth_bias = depth_bias;
+		self
+	}
+
+	pub fn blend(
+		mut self,
+		blend: BlendPreset,
+	) -> Self {
+		self.state.blend = blend;
+		self
+	}
+
+	pub fn color_write_mask(
+		mut self,
+		color_write_mask: vk::ColorComponentFlags,
+	) -> Self {
+		self.state.color_write_mask = color_write_mask;
+		self
+	}
+
+	/// Draws into `render_pass` with the vertex stage only, for shadow
+	/// and depth prepasses.
+	pub fn depth_only_pass(
+		mut self,
+		render_pass: vk::RenderPass,
+	) -> Self {
+		self.state.depth_only_pass = Some(render_pass);
+		self
+	}
+
+	/// Draws into `pass` of a compiled `RenderGraph`. Panics when the pass
+	/// was culled or draws into the swapchain.
+	pub fn graph_pass(
+		mut self,
+		render_graph: &RenderGraph,
+		pass: GraphPassId,
+	) -> Self {
+		let (render_pass, color_attachment_count) = render_graph.pass_target(pass).unwrap_or_else(
+			|| panic!("render graph pass \"{}\" has no offscreen render pass", render_graph.pass_name(pass))
+		);
+		self.state.render_pass = Some(render_pass);
+		self.state.color_attachment_count = color_attachment_count;
+		self
+	}
+
+	/// Adds a macro to the shader variant, pass an empty value for a bare `#define`.
+	pub fn define(
+		mut self,
+		name: &str,
+		value: &str,
+	) -> Self {
+		self.defines.insert(name.to_string(), value.to_string());
+		self
+	}
+
+	pub fn defines(
+		mut self,
+		defines: ShaderDefines,
+	) -> Self {
+		self.defines = defines;
+		self
+	}
+
+	pub fn push_constants(
+		mut self,
+		push_constants: Vec<vk::PushConstantRange>,
+	) -> Self {
+		self.push_constants = push_constants;
+		self
+	}
+
+	/// Declares a range for `T`, values are then recorded with `PipelineInfo::push`.
+	pub fn push_constant<T: PushConstant>(
+		mut self,
+		offset: u32,
+	) -> Self {
+		let range = T::range(offset);
+		assert!(
+			offset % 4 == 0 && (range.offset + range.size) as usize <= MAX_PUSH_CONSTANT_SIZE,
+			"push constant range {:?} must be 4 byte aligned and fit in {} bytes",
+			range, MAX_PUSH_CONSTANT_SIZE,
+		);
+		self.push_constants.push(range);
+		self
+	}
+
+	pub fn build<V: vpb::Vertex>(
+		self,
+		program_data: &ProgramData,
+		pipeline_cache: vk::PipelineCache,
+		pipeline_block_structure: &Arc<ObjectBlockStructure>,
+		object_block_structure: &Arc<ObjectBlockStructure>,
+	) -> PipelineInfo {
+		self.build_dyn(
+			program_data,
+			pipeline_cache,
+			pipeline_block_structure,
+			object_block_structure,
+			create_graphics_pipeline::<V>,
+		)
+	}
+
+	pub fn build_dyn(
+		self,
+		program_data: &ProgramData,
+		pipeline_cache: vk::PipelineCache,
+		pipeline_block_structure: &Arc<ObjectBlockStructure>,
+		object_block_structure: &Arc<ObjectBlockStructure>,
+		create_fn: CreateGraphicsPipelineFn,
+	) -> PipelineInfo {
+		PipelineInfo::from_state(
+			program_data,
+			pipeline_cache,
+			&self.name,
+			self.viewport_depth_range,
+			self.state,
+			self.defines,
+			pipeline_block_structure,
+			object_block_structure,
+			self.push_constants,
+			create_fn,
+		)
+	}
 }
\ No newline at end of file
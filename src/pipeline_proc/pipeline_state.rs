@@ -0,0 +1,133 @@
+use ash::vk;
+use serde::{Serialize, Deserialize};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum BlendPreset {
+	/// No blending, source overwrites destination.
+	OPAQUE,
+	/// Straight (non premultiplied) alpha.
+	#[default]
+	ALPHA,
+	/// Color is already multiplied by alpha.
+	PREMULTIPLIED,
+	/// Adds onto the destination, for particles and glows.
+	ADDITIVE,
+	/// Multiplies the destination, for darkening and tinting.
+	MULTIPLY,
+}
+
+impl BlendPreset {
+	pub fn attachment_state(
+		&self,
+		color_write_mask: vk::ColorComponentFlags,
+	) -> vk::PipelineColorBlendAttachmentState {
+		let builder = vk::PipelineColorBlendAttachmentState::builder()
+			.color_blend_op(vk::BlendOp::ADD)
+			.alpha_blend_op(vk::BlendOp::ADD)
+			.color_write_mask(color_write_mask);
+		match self {
+			BlendPreset::OPAQUE => builder
+				.blend_enable(false)
+				.src_color_blend_factor(vk::BlendFactor::ONE)
+				.dst_color_blend_factor(vk::BlendFactor::ZERO)
+				.src_alpha_blend_factor(vk::BlendFactor::ONE)
+				.dst_alpha_blend_factor(vk::BlendFactor::ZERO)
+				.build(),
+			BlendPreset::ALPHA => builder
+				.blend_enable(true)
+				.src_color_blend_factor(vk::BlendFactor::SRC_ALPHA)
+				.dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+				.src_alpha_blend_factor(vk::BlendFactor::SRC_ALPHA)
+				.dst_alpha_blend_factor(vk::BlendFactor::DST_ALPHA)
+				.build(),
+			BlendPreset::PREMULTIPLIED => builder
+				.blend_enable(true)
+				.src_color_blend_factor(vk::BlendFactor::ONE)
+				.dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+				.src_alpha_blend_factor(vk::BlendFactor::ONE)
+				.dst_alpha_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+				.build(),
+			BlendPreset::ADDITIVE => builder
+				.blend_enable(true)
+				.src_color_blend_factor(vk::BlendFactor::SRC_ALPHA)
+				.dst_color_blend_factor(vk::BlendFactor::ONE)
+				.src_alpha_blend_factor(vk::BlendFactor::ZERO)
+				.dst_alpha_blend_factor(vk::BlendFactor::ONE)
+				.build(),
+			BlendPreset::MULTIPLY => builder
+				.blend_enable(true)
+				.src_color_blend_factor(vk::BlendFactor::DST_COLOR)
+				.dst_color_blend_factor(vk::BlendFactor::ZERO)
+				.src_alpha_blend_factor(vk::BlendFactor::DST_ALPHA)
+				.dst_alpha_blend_factor(vk::BlendFactor::ZERO)
+				.build(),
+		}
+	}
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct DepthBias {
+	pub constant_factor: f32,
+	pub clamp: f32,
+	pub slope_factor: f32,
+}
+
+/// Fixed function state of a graphics pipeline. The default matches
+/// what vpe used before the state was configurable.
+#[derive(Copy, Clone, Debug)]
+pub struct PipelineState {
+	pub topology: vk::PrimitiveTopology,
+	pub polygon_mode: vk::PolygonMode,
+	pub line_width: f32,
+	pub cull_mode: vk::CullModeFlags,
+	pub front_face: vk::FrontFace,
+	pub depth_test: bool,
+	pub depth_write: bool,
+	pub depth_compare: vk::CompareOp,
+	pub depth_bias: Option<DepthBias>,
+	pub blend: BlendPreset,
+	pub color_write_mask: vk::ColorComponentFlags,
+	/// Render pass with only a depth attachment to draw into instead of the
+	/// swapchain's, the pipeline then has no fragment stage.
+	pub depth_only_pass: Option<vk::RenderPass>,
+	/// Offscreen pass of a `RenderGraph` to draw into instead of the
+	/// swapchain's.
+	pub render_pass: Option<vk::RenderPass>,
+	/// Color attachments of `render_pass`, each blended the same.
+	pub color_attachment_count: u32,
+}
+
+impl Default for PipelineState {
+	fn default() -> Self {
+		Self {
+			topology: vk::PrimitiveTopology::TRIANGLE_LIST,
+			polygon_mode: vk::PolygonMode::FILL,
+			line_width: 1.0,
+			cull_mode: vk::CullModeFlags::NONE,
+			front_face: vk::FrontFace::COUNTER_CLOCKWISE,
+			depth_test: true,
+			depth_write: true,
+			depth_compare: vk::CompareOp::LESS,
+			depth_bias: None,
+			blend: BlendPreset::ALPHA,
+			color_write_mask: vk::ColorComponentFlags::RGBA,
+			depth_only_pass: None,
+			render_pass: None,
+			color_attachment_count: 1,
+		}
+	}
+}
+
+impl PipelineState {
+	/// Strip and fan topologies restart on `u32::MAX` indices.
+	pub fn primitive_restart(
+		&self,
+	) -> bool {
+		match self.topology {
+			vk::PrimitiveTopology::LINE_STRIP |
+			vk::PrimitiveTopology::TRIANGLE_STRIP |
+			vk::PrimitiveTopology::TRIANGLE_FAN => true,
+			_ => false,
+		}
+	}
+}
\ No newline at end of file
@@ -0,0 +1,119 @@
+use ash::vk;
+use bytemuck::Pod;
+
+use crate::{LayoutChecked, PipelineInfo};
+
+/// Vulkan only guarantees this many bytes of push constants.
+pub const MAX_PUSH_CONSTANT_SIZE: usize = 128;
+
+/// Typed push constant block, implemented by `#[derive(PushConstant)]`.
+pub trait PushConstant: LayoutChecked + Pod {
+	fn stage_flags(
+	) -> vk::ShaderStageFlags;
+
+	fn range(
+		offset: u32,
+	) -> vk::PushConstantRange {
+		vk::PushConstantRange::builder()
+			.stage_flags(Self::stage_flags())
+			.offset(offset)
+			.size(std::mem::size_of::<Self>() as u32)
+			.build()
+	}
+}
+
+/// Type erased push constant kept on an object's `SubState` and pushed
+/// by its bucket right before the object is drawn.
+#[derive(Clone, Debug)]
+pub struct PushConstantValue {
+	pub stages: vk::ShaderStageFlags,
+	pub bytes: Vec<u8>,
+}
+
+impl PushConstantValue {
+	pub fn new<T: PushConstant>(
+		value: &T,
+	) -> Self {
+		Self {
+			stages: T::stage_flags(),
+			bytes: bytemuck::bytes_of(value).to_vec(),
+		}
+	}
+
+	pub fn set<T: PushConstant>(
+		&mut self,
+		value: &T,
+	) {
+		assert!(
+			self.stages == T::stage_flags() && self.bytes.len() == std::mem::size_of::<T>(),
+			"push constant value changed type",
+		);
+		self.bytes.copy_from_slice(bytemuck::bytes_of(value));
+	}
+}
+
+impl PipelineInfo {
+	/// Declared range that a value with these stages and size is pushed to.
+	pub fn push_constant_range(
+		&self,
+		stages: vk::ShaderStageFlags,
+		size: usize,
+	) -> Option<vk::PushConstantRange> {
+		self.push_constants.iter().find(
+			|x| x.stage_flags == stages && x.size as usize == size
+		).copied()
+	}
+
+	/// Records `value` into the range declared for `T`, see
+	/// `PipelineInfoBuilder::push_constant`. Panics when the pipeline
+	/// declares no such range.
+	pub fn push<T: PushConstant>(
+		&self,
+		device: &vpb::Device,
+		command_buffer: &vk::CommandBuffer,
+		value: &T,
+	) {
+		self.push_bytes(
+			device,
+			command_buffer,
+			T::stage_flags(),
+			bytemuck::bytes_of(value),
+		)
+	}
+
+	pub fn push_value(
+		&self,
+		device: &vpb::Device,
+		command_buffer: &vk::CommandBuffer,
+		value: &PushConstantValue,
+	) {
+		self.push_bytes(
+			device,
+			command_buffer,
+			value.stages,
+			&value.bytes,
+		)
+	}
+
+	fn push_bytes(
+		&self,
+		device: &vpb::Device,
+		command_buffer: &vk::CommandBuffer,
+		stages: vk::ShaderStageFlags,
+		bytes: &[u8],
+	) { unsafe {
+		let range = self.push_constant_range(stages, bytes.len()).unwrap_or_else(
+			|| panic!(
+				"pipeline \"{}\" declares no {} byte push constant range for {:?}",
+				self.name, bytes.len(), stages,
+			)
+		);
+		device.device.cmd_push_constants(
+			*command_buffer,
+			self.pipeline_layout,
+			range.stage_flags,
+			range.offset,
+			bytes,
+		);
+	}}
+}
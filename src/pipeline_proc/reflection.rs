@@ -0,0 +1,643 @@
+use std::collections::{HashMap, BTreeMap};
+
+use ash::vk;
+
+use crate::{ObjectBlockStructure, BlockLayout, round_up};
+
+// Only the parts of the spir-v spec needed to recover descriptor and
+// vertex input interfaces.
+const SPIRV_MAGIC: u32 = 0x07230203;
+const OP_NAME: u32 = 5;
+const OP_ENTRY_POINT: u32 = 15;
+const OP_TYPE_BOOL: u32 = 20;
+const OP_TYPE_INT: u32 = 21;
+const OP_TYPE_FLOAT: u32 = 22;
+const OP_TYPE_VECTOR: u32 = 23;
+const OP_TYPE_MATRIX: u32 = 24;
+const OP_TYPE_IMAGE: u32 = 25;
+const OP_TYPE_SAMPLER: u32 = 26;
+const OP_TYPE_SAMPLED_IMAGE: u32 = 27;
+const OP_TYPE_ARRAY: u32 = 28;
+const OP_TYPE_RUNTIME_ARRAY: u32 = 29;
+const OP_TYPE_STRUCT: u32 = 30;
+const OP_TYPE_POINTER: u32 = 32;
+const OP_CONSTANT: u32 = 43;
+const OP_VARIABLE: u32 = 59;
+const OP_DECORATE: u32 = 71;
+const OP_MEMBER_DECORATE: u32 = 72;
+const DECORATION_BLOCK: u32 = 2;
+const DECORATION_BUFFER_BLOCK: u32 = 3;
+const DECORATION_ARRAY_STRIDE: u32 = 6;
+const DECORATION_MATRIX_STRIDE: u32 = 7;
+const DECORATION_BUILT_IN: u32 = 11;
+const DECORATION_LOCATION: u32 = 30;
+const DECORATION_BINDING: u32 = 33;
+const DECORATION_DESCRIPTOR_SET: u32 = 34;
+const DECORATION_OFFSET: u32 = 35;
+const STORAGE_CLASS_UNIFORM_CONSTANT: u32 = 0;
+const STORAGE_CLASS_INPUT: u32 = 1;
+const STORAGE_CLASS_UNIFORM: u32 = 2;
+const STORAGE_CLASS_PUSH_CONSTANT: u32 = 9;
+const STORAGE_CLASS_STORAGE_BUFFER: u32 = 12;
+const EXECUTION_MODEL_VERTEX: u32 = 0;
+const EXECUTION_MODEL_GEOMETRY: u32 = 3;
+const EXECUTION_MODEL_FRAGMENT: u32 = 4;
+const EXECUTION_MODEL_COMPUTE: u32 = 5;
+
+#[derive(Clone, Debug)]
+enum SpirvType {
+	Scalar {
+		float: bool,
+		signed: bool,
+		width: u32,
+	},
+	Vector {
+		component: u32,
+		count: u32,
+	},
+	Matrix {
+		column: u32,
+		count: u32,
+	},
+	Array {
+		element: u32,
+		length: u32,
+	},
+	RuntimeArray {
+		element: u32,
+	},
+	Struct {
+		members: Vec<u32>,
+	},
+	Image {
+		sampled: u32,
+	},
+	Sampler,
+	SampledImage,
+	Pointer {
+		storage_class: u32,
+		pointee: u32,
+	},
+	Other,
+}
+
+#[derive(Clone, Debug)]
+pub struct ReflectedDescriptor {
+	pub name: String,
+	pub set: u32,
+	pub binding: u32,
+	pub descriptor_type: vk::DescriptorType,
+	pub count: u32,
+	/// Declared size for buffer blocks, `None` for images and samplers.
+	pub size: Option<usize>,
+	pub stages: vk::ShaderStageFlags,
+}
+
+#[derive(Clone, Debug)]
+pub struct ReflectedInput {
+	pub name: String,
+	pub location: u32,
+	pub format: vk::Format,
+}
+
+/// How a shader reads a vertex attribute. Bit width and normalization of
+/// the attribute format don't change what the shader sees.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum NumericClass {
+	Float,
+	SInt,
+	UInt,
+}
+
+/// Component count and numeric class of a vertex attribute format, `None`
+/// for formats vertex inputs don't use.
+pub fn format_class(
+	format: vk::Format,
+) -> Option<(u32, NumericClass)> {
+	use vk::Format as F;
+	let class = match format {
+		F::R8_UINT | F::R8G8_UINT | F::R8G8B8_UINT | F::R8G8B8A8_UINT |
+		F::R16_UINT | F::R16G16_UINT | F::R16G16B16_UINT | F::R16G16B16A16_UINT |
+		F::R32_UINT | F::R32G32_UINT | F::R32G32B32_UINT | F::R32G32B32A32_UINT |
+		F::A2B10G10R10_UINT_PACK32 => NumericClass::UInt,
+		F::R8_SINT | F::R8G8_SINT | F::R8G8B8_SINT | F::R8G8B8A8_SINT |
+		F::R16_SINT | F::R16G16_SINT | F::R16G16B16_SINT | F::R16G16B16A16_SINT |
+		F::R32_SINT | F::R32G32_SINT | F::R32G32B32_SINT | F::R32G32B32A32_SINT |
+		F::A2B10G10R10_SINT_PACK32 => NumericClass::SInt,
+		F::UNDEFINED => return None,
+		_ => NumericClass::Float,
+	};
+	let components = match format {
+		F::R8_UNORM | F::R8_SNORM | F::R8_USCALED | F::R8_SSCALED | F::R8_UINT | F::R8_SINT | F::R8_SRGB |
+		F::R16_UNORM | F::R16_SNORM | F::R16_USCALED | F::R16_SSCALED | F::R16_UINT | F::R16_SINT | F::R16_SFLOAT |
+		F::R32_UINT | F::R32_SINT | F::R32_SFLOAT => 1,
+		F::R8G8_UNORM | F::R8G8_SNORM | F::R8G8_USCALED | F::R8G8_SSCALED | F::R8G8_UINT | F::R8G8_SINT | F::R8G8_SRGB |
+		F::R16G16_UNORM | F::R16G16_SNORM | F::R16G16_USCALED | F::R16G16_SSCALED | F::R16G16_UINT | F::R16G16_SINT | F::R16G16_SFLOAT |
+		F::R32G32_UINT | F::R32G32_SINT | F::R32G32_SFLOAT => 2,
+		F::R8G8B8_UNORM | F::R8G8B8_SNORM | F::R8G8B8_USCALED | F::R8G8B8_SSCALED | F::R8G8B8_UINT | F::R8G8B8_SINT | F::R8G8B8_SRGB |
+		F::R16G16B16_UNORM | F::R16G16B16_SNORM | F::R16G16B16_USCALED | F::R16G16B16_SSCALED | F::R16G16B16_UINT | F::R16G16B16_SINT | F::R16G16B16_SFLOAT |
+		F::R32G32B32_UINT | F::R32G32B32_SINT | F::R32G32B32_SFLOAT |
+		F::B10G11R11_UFLOAT_PACK32 => 3,
+		F::R8G8B8A8_UNORM | F::R8G8B8A8_SNORM | F::R8G8B8A8_USCALED | F::R8G8B8A8_SSCALED | F::R8G8B8A8_UINT | F::R8G8B8A8_SINT | F::R8G8B8A8_SRGB |
+		F::B8G8R8A8_UNORM | F::B8G8R8A8_SNORM | F::B8G8R8A8_SRGB |
+		F::R16G16B16A16_UNORM | F::R16G16B16A16_SNORM | F::R16G16B16A16_USCALED | F::R16G16B16A16_SSCALED | F::R16G16B16A16_UINT | F::R16G16B16A16_SINT | F::R16G16B16A16_SFLOAT |
+		F::R32G32B32A32_UINT | F::R32G32B32A32_SINT | F::R32G32B32A32_SFLOAT |
+		F::A2B10G10R10_UNORM_PACK32 | F::A2B10G10R10_SNORM_PACK32 | F::A2B10G10R10_UINT_PACK32 | F::A2B10G10R10_SINT_PACK32 => 4,
+		_ => return None,
+	};
+	Some((components, class))
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ShaderReflection {
+	pub stages: vk::ShaderStageFlags,
+	/// Keyed by (set, binding).
+	pub descriptors: BTreeMap<(u32, u32), ReflectedDescriptor>,
+	/// Vertex shader inputs, keyed by location.
+	pub inputs: BTreeMap<u32, ReflectedInput>,
+	pub push_constant_size: Option<usize>,
+}
+
+#[derive(Default)]
+struct SpirvModule {
+	names: HashMap<u32, String>,
+	types: HashMap<u32, SpirvType>,
+	constants: HashMap<u32, u32>,
+	decorations: HashMap<u32, HashMap<u32, u32>>,
+	member_offsets: HashMap<(u32, u32), u32>,
+	member_matrix_strides: HashMap<(u32, u32), u32>,
+	variables: Vec<(u32, u32, u32)>,
+	stages: vk::ShaderStageFlags,
+}
+
+fn parse_string(
+	words: &[u32],
+) -> String {
+	let bytes: Vec<u8> = words.iter().flat_map(|x| x.to_le_bytes()).take_while(|x| *x != 0).collect();
+	String::from_utf8_lossy(&bytes).to_string()
+}
+
+impl SpirvModule {
+	fn parse(
+		code: &[u32],
+	) -> Result<Self, String> {
+		if code.len() < 5 || code[0] != SPIRV_MAGIC {
+			return Err("not a spir-v module".to_string());
+		}
+		let mut module = SpirvModule::default();
+		let mut i = 5;
+		while i < code.len() {
+			let opcode = code[i] & 0xFFFF;
+			let word_count = (code[i] >> 16) as usize;
+			if word_count == 0 || i + word_count > code.len() {
+				return Err(format!("malformed instruction at word {}", i));
+			}
+			let op = &code[i + 1..i + word_count];
+			match opcode {
+				OP_NAME => { module.names.insert(op[0], parse_string(&op[1..])); },
+				OP_ENTRY_POINT => {
+					module.stages |= match op[0] {
+						EXECUTION_MODEL_VERTEX => vk::ShaderStageFlags::VERTEX,
+						EXECUTION_MODEL_GEOMETRY => vk::ShaderStageFlags::GEOMETRY,
+						EXECUTION_MODEL_FRAGMENT => vk::ShaderStageFlags::FRAGMENT,
+						EXECUTION_MODEL_COMPUTE => vk::ShaderStageFlags::COMPUTE,
+						_ => vk::ShaderStageFlags::empty(),
+					};
+				},
+				OP_TYPE_BOOL => { module.types.insert(op[0], SpirvType::Scalar { float: false, signed: false, width: 32 }); },
+				OP_TYPE_INT => { module.types.insert(op[0], SpirvType::Scalar { float: false, signed: op[2] != 0, width: op[1] }); },
+				OP_TYPE_FLOAT => { module.types.insert(op[0], SpirvType::Scalar { float: true, signed: true, width: op[1] }); },
+				OP_TYPE_VECTOR => { module.types.insert(op[0], SpirvType::Vector { component: op[1], count: op[2] }); },
+				OP_TYPE_MATRIX => { module.types.insert(op[0], SpirvType::Matrix { column: op[1], count: op[2] }); },
+				OP_TYPE_IMAGE => { module.types.insert(op[0], SpirvType::Image { sampled: op[6] }); },
+				OP_TYPE_SAMPLER => { module.types.insert(op[0], SpirvType::Sampler); },
+				OP_TYPE_SAMPLED_IMAGE => { module.types.insert(op[0], SpirvType::SampledImage); },
+				OP_TYPE_ARRAY => { module.types.insert(op[0], SpirvType::Array { element: op[1], length: op[2] }); },
+				OP_TYPE_RUNTIME_ARRAY => { module.types.insert(op[0], SpirvType::RuntimeArray { element: op[1] }); },
+				OP_TYPE_STRUCT => { module.types.insert(op[0], SpirvType::Struct { members: op[1..].to_vec() }); },
+				OP_TYPE_POINTER => { module.types.insert(op[0], SpirvType::Pointer { storage_class: op[1], pointee: op[2] }); },
+				OP_CONSTANT => {
+					if op.len() > 2 {
+						module.constants.insert(op[1], op[2]);
+					}
+				},
+				OP_VARIABLE => { module.variables.push((op[0], op[1], op[2])); },
+				OP_DECORATE => {
+					module.decorations.entry(op[0]).or_default().insert(
+						op[1],
+						op.get(2).copied().unwrap_or(0),
+					);
+				},
+				OP_MEMBER_DECORATE => {
+					match op[2] {
+						DECORATION_OFFSET => { module.member_offsets.insert((op[0], op[1]), op[3]); },
+						DECORATION_MATRIX_STRIDE => { module.member_matrix_strides.insert((op[0], op[1]), op[3]); },
+						_ => {},
+					}
+				},
+				_ => {},
+			}
+			i += word_count;
+		}
+		Ok(module)
+	}
+
+	fn decoration(
+		&self,
+		id: u32,
+		decoration: u32,
+	) -> Option<u32> {
+		self.decorations.get(&id)?.get(&decoration).copied()
+	}
+
+	/// Size of a type inside an explicitly laid out block.
+	fn type_size(
+		&self,
+		id: u32,
+		matrix_stride: Option<u32>,
+	) -> usize {
+		match self.types.get(&id) {
+			Some(SpirvType::Scalar { width, .. }) => (*width / 8) as usize,
+			Some(SpirvType::Vector { component, count }) => self.type_size(*component, None) * *count as usize,
+			Some(SpirvType::Matrix { column, count }) => match matrix_stride {
+				Some(stride) => stride as usize * *count as usize,
+				None => self.type_size(*column, None) * *count as usize,
+			},
+			Some(SpirvType::Array { element, length }) => {
+				let length = self.constants.get(length).copied().unwrap_or(1) as usize;
+				match self.decoration(id, DECORATION_ARRAY_STRIDE) {
+					Some(stride) => stride as usize * length,
+					None => self.type_size(*element, matrix_stride) * length,
+				}
+			},
+			Some(SpirvType::RuntimeArray { .. }) => 0,
+			Some(SpirvType::Struct { members }) => members.iter().enumerate().map(
+				|(i, member)| {
+					let offset = self.member_offsets.get(&(id, i as u32)).copied().unwrap_or(0) as usize;
+					offset + self.type_size(
+						*member,
+						self.member_matrix_strides.get(&(id, i as u32)).copied(),
+					)
+				}
+			).max().unwrap_or(0),
+			_ => 0,
+		}
+	}
+
+	fn input_format(
+		&self,
+		id: u32,
+	) -> vk::Format {
+		let (component, count) = match self.types.get(&id) {
+			Some(SpirvType::Vector { component, count }) => (*component, *count),
+			Some(SpirvType::Scalar { .. }) => (id, 1),
+			_ => return vk::Format::UNDEFINED,
+		};
+		let (float, signed, width) = match self.types.get(&component) {
+			Some(SpirvType::Scalar { float, signed, width }) => (*float, *signed, *width),
+			_ => return vk::Format::UNDEFINED,
+		};
+		if width != 32 {
+			return vk::Format::UNDEFINED;
+		}
+		match (float, signed, count) {
+			(true, _, 1) => vk::Format::R32_SFLOAT,
+			(true, _, 2) => vk::Format::R32G32_SFLOAT,
+			(true, _, 3) => vk::Format::R32G32B32_SFLOAT,
+			(true, _, 4) => vk::Format::R32G32B32A32_SFLOAT,
+			(false, true, 1) => vk::Format::R32_SINT,
+			(false, true, 2) => vk::Format::R32G32_SINT,
+			(false, true, 3) => vk::Format::R32G32B32_SINT,
+			(false, true, 4) => vk::Format::R32G32B32A32_SINT,
+			(false, false, 1) => vk::Format::R32_UINT,
+			(false, false, 2) => vk::Format::R32G32_UINT,
+			(false, false, 3) => vk::Format::R32G32B32_UINT,
+			(false, false, 4) => vk::Format::R32G32B32A32_UINT,
+			_ => vk::Format::UNDEFINED,
+		}
+	}
+}
+
+impl ShaderReflection {
+	pub fn new(
+		code: &[u32],
+	) -> Result<Self, String> {
+		let module = SpirvModule::parse(code)?;
+		let mut reflection = ShaderReflection {
+			stages: module.stages,
+			..Default::default()
+		};
+		for (result_type, id, storage_class) in module.variables.iter() {
+			let pointee = match module.types.get(result_type) {
+				Some(SpirvType::Pointer { pointee, .. }) => *pointee,
+				_ => continue,
+			};
+			// Arrays of descriptors are flattened to the element type.
+			let (element, count) = match module.types.get(&pointee) {
+				Some(SpirvType::Array { element, length }) => (*element, module.constants.get(length).copied().unwrap_or(1)),
+				_ => (pointee, 1),
+			};
+			let name = module.names.get(&element).or(module.names.get(id)).cloned().unwrap_or_default();
+			match *storage_class {
+				STORAGE_CLASS_INPUT => {
+					if module.stages != vk::ShaderStageFlags::VERTEX || module.decoration(*id, DECORATION_BUILT_IN).is_some() {
+						continue;
+					}
+					if let Some(location) = module.decoration(*id, DECORATION_LOCATION) {
+						reflection.inputs.insert(location, ReflectedInput {
+							name: module.names.get(id).cloned().unwrap_or_default(),
+							location,
+							format: module.input_format(pointee),
+						});
+					}
+				},
+				STORAGE_CLASS_PUSH_CONSTANT => {
+					reflection.push_constant_size = Some(module.type_size(pointee, None));
+				},
+				STORAGE_CLASS_UNIFORM | STORAGE_CLASS_STORAGE_BUFFER | STORAGE_CLASS_UNIFORM_CONSTANT => {
+					let (set, binding) = match (
+						module.decoration(*id, DECORATION_DESCRIPTOR_SET),
+						module.decoration(*id, DECORATION_BINDING),
+					) {
+						(Some(set), Some(binding)) => (set, binding),
+						_ => continue,
+					};
+					let (descriptor_type, size) = match (*storage_class, module.types.get(&element)) {
+						(STORAGE_CLASS_STORAGE_BUFFER, _) => (vk::DescriptorType::STORAGE_BUFFER, Some(module.type_size(element, None))),
+						(STORAGE_CLASS_UNIFORM, _) if module.decoration(element, DECORATION_BUFFER_BLOCK).is_some() =>
+							(vk::DescriptorType::STORAGE_BUFFER, Some(module.type_size(element, None))),
+						(STORAGE_CLASS_UNIFORM, _) if module.decoration(element, DECORATION_BLOCK).is_some() =>
+							(vk::DescriptorType::UNIFORM_BUFFER, Some(module.type_size(element, None))),
+						(_, Some(SpirvType::SampledImage)) => (vk::DescriptorType::COMBINED_IMAGE_SAMPLER, None),
+						(_, Some(SpirvType::Sampler)) => (vk::DescriptorType::SAMPLER, None),
+						(_, Some(SpirvType::Image { sampled: 2 })) => (vk::DescriptorType::STORAGE_IMAGE, None),
+						(_, Some(SpirvType::Image { .. })) => (vk::DescriptorType::SAMPLED_IMAGE, None),
+						_ => continue,
+					};
+					reflection.descriptors.insert((set, binding), ReflectedDescriptor {
+						name,
+						set,
+						binding,
+						descriptor_type,
+						count,
+						size,
+						stages: module.stages,
+					});
+				},
+				_ => {},
+			}
+		}
+		Ok(reflection)
+	}
+
+	/// Combines the reflections of every stage of a pipeline.
+	pub fn merge(
+		reflections: &[ShaderReflection],
+	) -> Self {
+		let mut merged = ShaderReflection::default();
+		for reflection in reflections.iter() {
+			merged.stages |= reflection.stages;
+			for (key, descriptor) in reflection.descriptors.iter() {
+				merged.descriptors.entry(*key).and_modify(
+					|x| x.stages |= descriptor.stages
+				).or_insert_with(|| descriptor.clone());
+			}
+			if reflection.stages.contains(vk::ShaderStageFlags::VERTEX) {
+				merged.inputs = reflection.inputs.clone();
+			}
+			merged.push_constant_size = merged.push_constant_size.max(reflection.push_constant_size);
+		}
+		merged
+	}
+
+	/// Layout bindings derived from the shader alone, for blocks that
+	/// don't want to hand write their `vk::DescriptorSetLayoutBinding`.
+	pub fn descriptor_set_layout_bindings(
+		&self,
+		set: u32,
+	) -> Vec<vk::DescriptorSetLayoutBinding> {
+		self.descriptors.values().filter(
+			|x| x.set == set
+		).map(
+			|x|
+			vk::DescriptorSetLayoutBinding::builder()
+				.binding(x.binding)
+				.descriptor_type(x.descriptor_type)
+				.stage_flags(x.stages)
+				.descriptor_count(x.count)
+				.build()
+		).collect()
+	}
+
+	/// Compares the shader interface against the rust side of the pipeline.
+	/// Returns one message per mismatch, empty when everything lines up.
+	pub fn validate(
+		&self,
+		shader_name: &str,
+		attribute_descriptions: &[vk::VertexInputAttributeDescription],
+		block_structures: &[&ObjectBlockStructure],
+		push_constants: &[vk::PushConstantRange],
+	) -> Vec<String> {
+		let mut mismatches = Vec::new();
+		let layouts: Vec<&BlockLayout> = block_structures.iter().flat_map(
+			|x| x.layouts.iter()
+		).collect();
+		for layout in layouts.iter() {
+			let descriptor = match self.descriptors.get(&(layout.set, layout.binding)) {
+				Some(descriptor) => descriptor,
+				None => {
+					mismatches.push(format!(
+						"{}: {} is bound to set {} binding {} but the shader declares nothing there",
+						shader_name, layout.name, layout.set, layout.binding,
+					));
+					continue;
+				},
+			};
+			if descriptor.descriptor_type != layout.descriptor_type {
+				mismatches.push(format!(
+					"{}: set {} binding {} \"{}\" is {:?} in the shader but {} uses {:?}",
+					shader_name, layout.set, layout.binding, descriptor.name,
+					descriptor.descriptor_type, layout.name, layout.descriptor_type,
+				));
+			}
+			if let Some(size) = descriptor.size {
+				// A trailing padded vec3 makes the rust struct larger than the glsl block,
+				// storage buffers may also hold more elements than the shader's fixed part.
+				let matches = match layout.descriptor_type {
+					vk::DescriptorType::STORAGE_BUFFER => layout.size >= size,
+					_ => size == layout.size || round_up(size, 16) == layout.size,
+				};
+				if !matches {
+					mismatches.push(format!(
+						"{}: set {} binding {} \"{}\" is {} bytes in the shader but {} is {} bytes",
+						shader_name, layout.set, layout.binding, descriptor.name,
+						size, layout.name, layout.size,
+					));
+				}
+			}
+			if !layout.stages.contains(descriptor.stages) {
+				mismatches.push(format!(
+					"{}: set {} binding {} \"{}\" is used in stages {:?} but {} is only visible to {:?}",
+					shader_name, layout.set, layout.binding, descriptor.name,
+					descriptor.stages, layout.name, layout.stages,
+				));
+			}
+		}
+		for descriptor in self.descriptors.values() {
+			if !layouts.iter().any(|x| x.set == descriptor.set && x.binding == descriptor.binding) {
+				mismatches.push(format!(
+					"{}: set {} binding {} \"{}\" is declared in the shader but no block is bound there",
+					shader_name, descriptor.set, descriptor.binding, descriptor.name,
+				));
+			}
+		}
+		for input in self.inputs.values() {
+			match attribute_descriptions.iter().find(|x| x.location == input.location) {
+				Some(attribute) => {
+					if format_class(attribute.format) != format_class(input.format) {
+						mismatches.push(format!(
+							"{}: vertex input \"{}\" at location {} is {:?} in the shader but {:?} in the vertex type",
+							shader_name, input.name, input.location, input.format, attribute.format,
+						));
+					}
+				},
+				None => {
+					mismatches.push(format!(
+						"{}: vertex input \"{}\" at location {} has no matching vertex attribute",
+						shader_name, input.name, input.location,
+					));
+				},
+			}
+		}
+		if let Some(size) = self.push_constant_size {
+			let declared = push_constants.iter().map(
+				|x| (x.offset + x.size) as usize
+			).max().unwrap_or(0);
+			if declared < size {
+				mismatches.push(format!(
+					"{}: push constant block is {} bytes in the shader but the pipeline only declares {} bytes",
+					shader_name, size, declared,
+				));
+			}
+		}
+		mismatches
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use shaderc::ShaderKind;
+
+	use super::*;
+
+	const VERTEX_SHADER: &str = "
+		#version 450
+		layout(location = 0) in vec3 position;
+		layout(set = 0, binding = 0) uniform BlockCamera {
+			mat4 view;
+			mat4 projection;
+		} camera;
+		layout(push_constant) uniform Push {
+			vec4 offset;
+		} push;
+		void main() {
+			gl_Position = camera.projection * camera.view * vec4(position, 1.0) + push.offset;
+		}
+	";
+
+	fn reflect(
+		source: &str,
+	) -> ShaderReflection {
+		let mut compiler = shaderc::Compiler::new().unwrap();
+		let artifact = compiler.compile_into_spirv(
+			source,
+			ShaderKind::Vertex,
+			"test.vert",
+			"main",
+			None,
+		).unwrap();
+		ShaderReflection::new(artifact.as_binary()).unwrap()
+	}
+
+	fn camera_structure(
+		size: usize,
+	) -> ObjectBlockStructure {
+		let mut structure = ObjectBlockStructure::new();
+		structure.layouts.push(BlockLayout {
+			name: "BlockCamera",
+			set: 0,
+			binding: 0,
+			size,
+			descriptor_type: vk::DescriptorType::UNIFORM_BUFFER,
+			stages: vk::ShaderStageFlags::VERTEX,
+		});
+		structure
+	}
+
+	fn position_attribute(
+		format: vk::Format,
+	) -> vk::VertexInputAttributeDescription {
+		vk::VertexInputAttributeDescription::builder()
+			.location(0)
+			.binding(0)
+			.format(format)
+			.build()
+	}
+
+	fn push_constant(
+		size: u32,
+	) -> vk::PushConstantRange {
+		vk::PushConstantRange::builder()
+			.stage_flags(vk::ShaderStageFlags::VERTEX)
+			.offset(0)
+			.size(size)
+			.build()
+	}
+
+	#[test]
+	fn matching_interface_has_no_mismatches() {
+		let mismatches = reflect(VERTEX_SHADER).validate(
+			"test",
+			&[position_attribute(vk::Format::R32G32B32_SFLOAT)],
+			&[&camera_structure(128)],
+			&[push_constant(16)],
+		);
+		assert!(mismatches.is_empty(), "{:?}", mismatches);
+	}
+
+	#[test]
+	fn wrong_vertex_component_count() {
+		let mismatches = reflect(VERTEX_SHADER).validate(
+			"test",
+			&[position_attribute(vk::Format::R32G32_SFLOAT)],
+			&[&camera_structure(128)],
+			&[push_constant(16)],
+		);
+		assert_eq!(mismatches.len(), 1, "{:?}", mismatches);
+		assert!(mismatches[0].contains("vertex input \"position\" at location 0"), "{}", mismatches[0]);
+	}
+
+	#[test]
+	fn wrong_block_size() {
+		let mismatches = reflect(VERTEX_SHADER).validate(
+			"test",
+			&[position_attribute(vk::Format::R32G32B32_SFLOAT)],
+			&[&camera_structure(64)],
+			&[push_constant(16)],
+		);
+		assert_eq!(mismatches.len(), 1, "{:?}", mismatches);
+		assert!(mismatches[0].contains("is 128 bytes in the shader but BlockCamera is 64 bytes"), "{}", mismatches[0]);
+	}
+
+	#[test]
+	fn missing_push_constant() {
+		let mismatches = reflect(VERTEX_SHADER).validate(
+			"test",
+			&[position_attribute(vk::Format::R32G32B32_SFLOAT)],
+			&[&camera_structure(128)],
+			&[],
+		);
+		assert_eq!(mismatches.len(), 1, "{:?}", mismatches);
+		assert!(mismatches[0].contains("push constant block is 16 bytes in the shader but the pipeline only declares 0 bytes"), "{}", mismatches[0]);
+	}
+}
\ No newline at end of file
@@ -0,0 +1,232 @@
+use std::{fs, fmt, path::{PathBuf, Path}, collections::{BTreeMap, BTreeSet}, sync::{Mutex, Arc}, cell::RefCell};
+
+use ash::vk;
+use shaderc::{ShaderKind, CompileOptions, IncludeType, ResolvedInclude};
+use vpb::ProgramData;
+
+pub const SHADER_DIRECTORY: &str = "res/shaders";
+const MAX_INCLUDE_DEPTH: usize = 32;
+
+/// Macro name to value. An empty value defines the macro without a value.
+pub type ShaderDefines = BTreeMap<String, String>;
+
+struct CompiledShader {
+	kind: ShaderKind,
+	code: Arc<Vec<u32>>,
+	/// Stems of every file pulled in through `#include`.
+	includes: BTreeSet<String>,
+}
+
+/// (shader name, extension, defines)
+type ShaderCacheKey = (String, &'static str, ShaderDefines);
+
+static SHADER_CACHE: Mutex<BTreeMap<ShaderCacheKey, CompiledShader>> = Mutex::new(BTreeMap::new());
+
+/// Variant of a shader that failed to recompile in `reload_shader`.
+#[derive(Clone, Debug)]
+pub struct ShaderReloadError {
+	pub shader_name: String,
+	pub kind: ShaderKind,
+	pub defines: ShaderDefines,
+	/// Compiler output.
+	pub message: String,
+}
+
+impl fmt::Display for ShaderReloadError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{} shader \"{}\" failed to compile\n{}", shader_extension(self.kind), self.shader_name, self.message)
+	}
+}
+
+impl std::error::Error for ShaderReloadError {}
+
+pub fn shader_extension(
+	kind: ShaderKind,
+) -> &'static str {
+	match kind {
+		ShaderKind::Vertex => "vert",
+		ShaderKind::Fragment => "frag",
+		ShaderKind::Compute => "comp",
+		ShaderKind::Geometry => "geom",
+		ShaderKind::TessControl => "tesc",
+		ShaderKind::TessEvaluation => "tese",
+		_ => unimplemented!("unsupported shader kind"),
+	}
+}
+
+pub fn shader_path(
+	kind: ShaderKind,
+	shader_name: &str,
+) -> PathBuf {
+	PathBuf::from(SHADER_DIRECTORY).join(format!(
+		"{}.{}",
+		shader_name,
+		shader_extension(kind),
+	))
+}
+
+/// `"file"` includes resolve next to the including file first, `<file>`
+/// includes and fallbacks resolve from `SHADER_DIRECTORY`.
+fn resolve_include(
+	requested: &str,
+	include_type: IncludeType,
+	requesting: &str,
+) -> Result<PathBuf, String> {
+	if include_type == IncludeType::Relative {
+		if let Some(parent) = Path::new(requesting).parent() {
+			let path = parent.join(requested);
+			if path.is_file() {
+				return Ok(path);
+			}
+		}
+	}
+	let path = PathBuf::from(SHADER_DIRECTORY).join(requested);
+	if path.is_file() {
+		Ok(path)
+	} else {
+		Err(format!("failed to find include \"{}\" requested by \"{}\"", requested, requesting))
+	}
+}
+
+fn compile_shader_uncached(
+	kind: ShaderKind,
+	shader_name: &str,
+	defines: &ShaderDefines,
+) -> Result<CompiledShader, String> {
+	let path = shader_path(kind, shader_name);
+	let source = fs::read_to_string(&path).map_err(
+		|e| format!("{}: {}", path.display(), e)
+	)?;
+	let includes = RefCell::new(BTreeSet::new());
+	let mut compiler = shaderc::Compiler::new().expect("failed to create shader compiler");
+	let mut options = CompileOptions::new().expect("failed to create shader compile options");
+	for (name, value) in defines.iter() {
+		options.add_macro_definition(
+			name,
+			if value.is_empty() { None } else { Some(value.as_str()) },
+		);
+	}
+	options.set_include_callback(
+		|requested, include_type, requesting, depth| {
+			if depth > MAX_INCLUDE_DEPTH {
+				return Err(format!("include depth exceeded {} at \"{}\"", MAX_INCLUDE_DEPTH, requested));
+			}
+			let include_path = resolve_include(requested, include_type, requesting)?;
+			let content = fs::read_to_string(&include_path).map_err(
+				|e| format!("{}: {}", include_path.display(), e)
+			)?;
+			if let Some(stem) = include_path.file_stem().and_then(|x| x.to_str()) {
+				includes.borrow_mut().insert(stem.to_string());
+			}
+			Ok(ResolvedInclude {
+				resolved_name: include_path.to_string_lossy().to_string(),
+				content,
+			})
+		}
+	);
+	let artifact = compiler.compile_into_spirv(
+		&source,
+		kind,
+		&path.to_string_lossy(),
+		"main",
+		Some(&options),
+	).map_err(|e| e.to_string())?;
+	if artifact.get_num_warnings() > 0 {
+		log::warn!("{}", artifact.get_warning_messages());
+	}
+	let code = artifact.as_binary().to_vec();
+	drop(options);
+	Ok(CompiledShader {
+		kind,
+		code: Arc::new(code),
+		includes: includes.into_inner(),
+	})
+}
+
+/// Compiles `<SHADER_DIRECTORY>/<shader_name>.<ext>` into spir-v. Each
+/// variant (set of defines) is compiled once and cached until the shader
+/// or one of its includes is invalidated. The error holds the compiler
+/// output so it can be shown to whoever edits the shader.
+pub fn compile_shader(
+	kind: ShaderKind,
+	shader_name: &str,
+	defines: &ShaderDefines,
+) -> Result<Arc<Vec<u32>>, String> {
+	let key = (shader_name.to_string(), shader_extension(kind), defines.clone());
+	if let Some(compiled) = SHADER_CACHE.lock().unwrap().get(&key) {
+		return Ok(compiled.code.clone());
+	}
+	let compiled = compile_shader_uncached(
+		kind,
+		shader_name,
+		defines,
+	)?;
+	let code = compiled.code.clone();
+	SHADER_CACHE.lock().unwrap().insert(key, compiled);
+	Ok(code)
+}
+
+/// Shader names whose cached variants were built from `file_stem`, either
+/// directly or through an include.
+pub fn shaders_depending_on(
+	file_stem: &str,
+) -> BTreeSet<String> {
+	let mut shaders = BTreeSet::new();
+	shaders.insert(file_stem.to_string());
+	for ((shader_name, _, _), compiled) in SHADER_CACHE.lock().unwrap().iter() {
+		if compiled.includes.contains(file_stem) {
+			shaders.insert(shader_name.clone());
+		}
+	}
+	shaders
+}
+
+/// Recompiles every cached variant of the shader from disk. A variant that
+/// fails keeps its last good code, so pipelines built from the cache
+/// afterwards, for example on resize, still get working shaders.
+pub fn reload_shader(
+	shader_name: &str,
+) -> Vec<ShaderReloadError> {
+	let variants: Vec<(ShaderCacheKey, ShaderKind)> = SHADER_CACHE.lock().unwrap().iter().filter(
+		|((name, _, _), _)| name == shader_name
+	).map(
+		|(key, compiled)| (key.clone(), compiled.kind)
+	).collect();
+	let mut errors = Vec::new();
+	for (key, kind) in variants {
+		match compile_shader_uncached(kind, shader_name, &key.2) {
+			Ok(compiled) => {
+				SHADER_CACHE.lock().unwrap().insert(key, compiled);
+			},
+			Err(message) => errors.push(ShaderReloadError {
+				shader_name: shader_name.to_string(),
+				kind,
+				defines: key.2,
+				message,
+			}),
+		}
+	}
+	errors
+}
+
+/// Drops every cached variant of the shader so the next compile reads it from disk.
+pub fn invalidate_shader(
+	shader_name: &str,
+) {
+	SHADER_CACHE.lock().unwrap().retain(
+		|(name, _, _), _| name != shader_name
+	);
+}
+
+pub fn create_shader_module(
+	program_data: &ProgramData,
+	code: &[u32],
+) -> vk::ShaderModule { unsafe {
+	let shader_module_info = vk::ShaderModuleCreateInfo::builder()
+		.code(code)
+		.build();
+	program_data.device.device.create_shader_module(
+		&shader_module_info,
+		None,
+	).unwrap()
+}}
\ No newline at end of file
@@ -0,0 +1,65 @@
+use std::{collections::{HashMap, BTreeSet}, fs, path::PathBuf, time::{Duration, Instant, SystemTime}};
+
+use crate::SHADER_DIRECTORY;
+
+pub const SHADER_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Polls the shader directory for modified files. Polling keeps this free
+/// of platform specific file notification apis.
+pub struct ShaderWatcher {
+	pub directory: PathBuf,
+	pub interval: Duration,
+	modified: HashMap<PathBuf, SystemTime>,
+	last_poll: Instant,
+}
+
+impl ShaderWatcher {
+	pub fn new(
+	) -> Self {
+		let mut watcher = Self {
+			directory: PathBuf::from(SHADER_DIRECTORY),
+			interval: SHADER_POLL_INTERVAL,
+			modified: HashMap::with_capacity(32),
+			last_poll: Instant::now(),
+		};
+		// Record the current state so nothing reloads on the first poll.
+		watcher.scan();
+		watcher
+	}
+
+	/// Shader names (file stems) that changed since the last poll. Empty
+	/// until `interval` has passed.
+	pub fn poll(
+		&mut self,
+	) -> BTreeSet<String> {
+		if self.last_poll.elapsed() < self.interval {
+			return BTreeSet::new();
+		}
+		self.last_poll = Instant::now();
+		self.scan()
+	}
+
+	fn scan(
+		&mut self,
+	) -> BTreeSet<String> {
+		let mut changed = BTreeSet::new();
+		let entries = match fs::read_dir(&self.directory) {
+			Ok(entries) => entries,
+			Err(_) => return changed,
+		};
+		for entry in entries.flatten() {
+			let path = entry.path();
+			let modified = match entry.metadata().and_then(|x| x.modified()) {
+				Ok(modified) => modified,
+				Err(_) => continue,
+			};
+			let previous = self.modified.insert(path.clone(), modified);
+			if previous.is_some() && previous != Some(modified) {
+				if let Some(stem) = path.file_stem().and_then(|x| x.to_str()) {
+					changed.insert(stem.to_string());
+				}
+			}
+		}
+		changed
+	}
+}
\ No newline at end of file
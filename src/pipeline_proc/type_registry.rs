@@ -0,0 +1,152 @@
+use std::{sync::Arc, collections::HashMap};
+
+use ash::vk;
+
+use crate::{CreateGraphicsPipelineFn, create_graphics_pipeline, VertexUI, VertexUITextured, Vertex3d, VertexSkinned, BlockCamera2d, BlockCamera3d, BlockModelExample, BlockText, BlockLights, BlockMeshModel, BlockEnvironment, BlockPbrMaterial, BlockSky, PipelineDescription, PipelineDescriptionError, ObjectBlockStructure, BlockReflect, DescriptorBlockKind};
+
+/// Adds a spawner for the registered block type to the structure.
+pub type BlockSpawnerFn = fn(
+	&mut ObjectBlockStructure,
+	&Arc<vpb::Device>,
+	vpb::BindingId,
+	vpb::SetId,
+);
+
+fn spawn_block<B: vpb::Block + BlockReflect + 'static>(
+	structure: &mut ObjectBlockStructure,
+	device: &Arc<vpb::Device>,
+	binding: vpb::BindingId,
+	set: vpb::SetId,
+) {
+	structure.push_block::<B>(
+		device,
+		binding, set,
+	);
+}
+
+/// Combined image sampler read by the fragment stage, see `Texture::bind`.
+fn spawn_texture_block(
+	structure: &mut ObjectBlockStructure,
+	device: &Arc<vpb::Device>,
+	binding: vpb::BindingId,
+	set: vpb::SetId,
+) {
+	structure.push_descriptor(
+		device,
+		"Texture",
+		binding, set,
+		DescriptorBlockKind::CombinedImageSampler,
+		vk::ShaderStageFlags::FRAGMENT,
+	);
+}
+
+/// Named vertex and block types that pipeline descriptions can refer to.
+pub struct TypeRegistry {
+	vertices: HashMap<String, CreateGraphicsPipelineFn>,
+	blocks: HashMap<String, BlockSpawnerFn>,
+}
+
+impl TypeRegistry {
+	pub fn new(
+	) -> Self {
+		Self {
+			vertices: HashMap::with_capacity(16),
+			blocks: HashMap::with_capacity(16),
+		}
+	}
+
+	/// Registry with every vertex and block type vpe ships with.
+	pub fn with_defaults(
+	) -> Self {
+		let mut registry = TypeRegistry::new();
+		registry.register_vertex::<VertexUI>("VertexUI");
+		registry.register_vertex::<VertexUITextured>("VertexUITextured");
+		registry.register_vertex::<Vertex3d>("Vertex3d");
+		registry.register_vertex::<VertexSkinned>("VertexSkinned");
+		registry.register_block::<BlockCamera2d>("BlockCamera2d");
+		registry.register_block::<BlockCamera3d>("BlockCamera3d");
+		registry.register_block::<BlockModelExample>("BlockModelExample");
+		registry.register_block::<BlockText>("BlockText");
+		registry.register_block::<BlockLights>("BlockLights");
+		registry.register_block::<BlockMeshModel>("BlockMeshModel");
+		registry.register_block::<BlockEnvironment>("BlockEnvironment");
+		registry.register_block::<BlockPbrMaterial>("BlockPbrMaterial");
+		registry.register_block::<BlockSky>("BlockSky");
+		registry.register_block_spawner("Texture", spawn_texture_block);
+		registry
+	}
+
+	pub fn register_vertex<V: vpb::Vertex>(
+		&mut self,
+		name: &str,
+	) {
+		self.vertices.insert(
+			name.to_string(),
+			create_graphics_pipeline::<V>,
+		);
+	}
+
+	pub fn register_block<B: vpb::Block + BlockReflect + 'static>(
+		&mut self,
+		name: &str,
+	) {
+		self.blocks.insert(
+			name.to_string(),
+			spawn_block::<B>,
+		);
+	}
+
+	/// Registers a block that isn't a `vpb::Block`, such as a descriptor.
+	pub fn register_block_spawner(
+		&mut self,
+		name: &str,
+		spawner: BlockSpawnerFn,
+	) {
+		self.blocks.insert(
+			name.to_string(),
+			spawner,
+		);
+	}
+
+	pub fn vertex(
+		&self,
+		name: &str,
+	) -> Option<CreateGraphicsPipelineFn> {
+		self.vertices.get(name).copied()
+	}
+
+	pub fn block(
+		&self,
+		name: &str,
+	) -> Option<BlockSpawnerFn> {
+		self.blocks.get(name).copied()
+	}
+
+	/// Checks that every type the description names is registered and
+	/// that the camera block is in range.
+	pub fn validate(
+		&self,
+		description: &PipelineDescription,
+	) -> Result<(), PipelineDescriptionError> {
+		if !self.vertices.contains_key(&description.vertex) {
+			return Err(PipelineDescriptionError::UnknownVertex(description.vertex.clone()));
+		}
+		for block in description.pipeline_blocks.iter().chain(description.object_blocks.iter()) {
+			if !self.blocks.contains_key(&block.block) {
+				return Err(PipelineDescriptionError::UnknownBlock(block.block.clone()));
+			}
+		}
+		if let Some(camera_block) = description.camera_block {
+			if camera_block >= description.pipeline_blocks.len() {
+				return Err(PipelineDescriptionError::InvalidCameraBlock(camera_block));
+			}
+		}
+		Ok(())
+	}
+}
+
+impl Default for TypeRegistry {
+	fn default() -> Self {
+		Self::with_defaults()
+	}
+}
\ No newline at end of file
@@ -1 +1,6 @@
-pub mod ui_example;
\ No newline at end of file
+pub mod ui_example;
+pub mod ui_textured_example;
+pub mod text_example;
+pub mod mesh_example;
+pub mod pbr;
+pub mod skybox;
\ No newline at end of file
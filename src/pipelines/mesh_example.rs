@@ -0,0 +1,211 @@
+use std::sync::Arc;
+
+use ash::vk;
+use vpb::ProgramData;
+
+use crate::{ViewportDepthRange, PipelineInfo, BlockCamera3d, BlockLights, BlockMeshModel, BlockJoint, EnginePipeline, ObjectBlockStructure, Vertex3d, VertexSkinned, InputState, RenderState, Camera, DescriptorBlockKind, ShadowMaps, MeshObject, MAX_JOINTS, PipelineCreateError};
+
+/// Blinn-Phong `Vertex3d` meshes lit by `Scene::lights` and seen through
+/// a `CameraState3d`, see `MeshObject`. Built `with_shadows` the objects
+/// sit at `SHADOWED_MODEL_BLOCK` instead, see
+/// `MeshObject::with_model_block`. The `skinned` variants draw
+/// `SkinnedMeshObject`s instead, their joints follow the model block.
+pub struct PipelineMeshExample {
+	pipeline_info: Arc<PipelineInfo>,
+	pipeline_block_structure: Arc<ObjectBlockStructure>,
+	object_block_structure: Arc<ObjectBlockStructure>,
+	camera: Arc<dyn Camera>,
+}
+
+impl PipelineMeshExample {
+	pub const LIGHT_BLOCK: usize = 1;
+	pub const SHADOW_ATLAS_BLOCK: usize = 2;
+	/// Index of the `BlockMeshModel` in the object's block states, in place
+	/// of `MeshObject::MODEL_BLOCK` when built `with_shadows`.
+	pub const SHADOWED_MODEL_BLOCK: usize = 3;
+
+	pub fn new(
+		program_data: &ProgramData,
+		pipeline_cache: vk::PipelineCache,
+		camera: Arc<dyn Camera>,
+	) -> Self {
+		PipelineMeshExample::create(
+			program_data,
+			pipeline_cache,
+			camera,
+			None,
+			false,
+		)
+	}
+
+	/// Directional and spot lights marked as shadow casters are shadowed by
+	/// the objects of buckets registered with
+	/// `Bucket::set_shadow_caster_block`, `SHADOWED_MODEL_BLOCK` for this
+	/// pipeline.
+	pub fn with_shadows(
+		program_data: &ProgramData,
+		pipeline_cache: vk::PipelineCache,
+		camera: Arc<dyn Camera>,
+		shadow_maps: &ShadowMaps,
+	) -> Self {
+		PipelineMeshExample::create(
+			program_data,
+			pipeline_cache,
+			camera,
+			Some(shadow_maps),
+			false,
+		)
+	}
+
+	/// `VertexSkinned` meshes posed by joint matrices, see
+	/// `SkinnedMeshObject`.
+	pub fn skinned(
+		program_data: &ProgramData,
+		pipeline_cache: vk::PipelineCache,
+		camera: Arc<dyn Camera>,
+	) -> Self {
+		PipelineMeshExample::create(
+			program_data,
+			pipeline_cache,
+			camera,
+			None,
+			true,
+		)
+	}
+
+	/// Skinned meshes cast shadows once their bucket is registered with
+	/// `Bucket::set_shadow_caster_block(Some(SHADOWED_MODEL_BLOCK))` and
+	/// `Bucket::set_shadow_joint_block(Some(SHADOWED_MODEL_BLOCK + 1))`.
+	pub fn skinned_with_shadows(
+		program_data: &ProgramData,
+		pipeline_cache: vk::PipelineCache,
+		camera: Arc<dyn Camera>,
+		shadow_maps: &ShadowMaps,
+	) -> Self {
+		PipelineMeshExample::create(
+			program_data,
+			pipeline_cache,
+			camera,
+			Some(shadow_maps),
+			true,
+		)
+	}
+
+	fn create(
+		program_data: &ProgramData,
+		pipeline_cache: vk::PipelineCache,
+		camera: Arc<dyn Camera>,
+		shadow_maps: Option<&ShadowMaps>,
+		skinned: bool,
+	) -> Self {
+		let mut pipeline_block_structure = ObjectBlockStructure::new().with_block::<BlockCamera3d>(
+			&program_data.device,
+			vpb::BindingId(0), vpb::SetId(0),
+		).with_block::<BlockLights>(
+			&program_data.device,
+			vpb::BindingId(0), vpb::SetId(1),
+		);
+		let mut builder = PipelineInfo::builder("mesh")
+			.viewport_depth_range(ViewportDepthRange::WORLD)
+			.cull_mode(vk::CullModeFlags::BACK);
+		let model_block = match shadow_maps {
+			Some(_) => {
+				pipeline_block_structure = pipeline_block_structure.with_descriptor(
+					&program_data.device,
+					"ShadowAtlas",
+					vpb::BindingId(0), vpb::SetId(2),
+					DescriptorBlockKind::CombinedImageSampler,
+					vk::ShaderStageFlags::FRAGMENT,
+				);
+				builder = builder.define("SHADOWS", "");
+				PipelineMeshExample::SHADOWED_MODEL_BLOCK
+			},
+			None => MeshObject::MODEL_BLOCK,
+		};
+		let pipeline_block_structure = Arc::new(pipeline_block_structure);
+		let mut object_block_structure = ObjectBlockStructure::new().with_block::<BlockMeshModel>(
+			&program_data.device,
+			vpb::BindingId(0), vpb::SetId(model_block as u32),
+		);
+		let pipeline_info = match skinned {
+			true => {
+				object_block_structure = object_block_structure.with_storage_block::<BlockJoint>(
+					&program_data.device,
+					vpb::BindingId(0), vpb::SetId(model_block as u32 + 1),
+					MAX_JOINTS,
+				);
+				builder.define("SKINNED", "").build::<VertexSkinned>(
+					program_data,
+					pipeline_cache,
+					&pipeline_block_structure,
+					&object_block_structure,
+				)
+			},
+			false => builder.build::<Vertex3d>(
+				program_data,
+				pipeline_cache,
+				&pipeline_block_structure,
+				&object_block_structure,
+			),
+		};
+		let pipeline_info = Arc::new(pipeline_info);
+		let object_block_structure = Arc::new(object_block_structure);
+		if let Some(shadow_maps) = shadow_maps {
+			shadow_maps.bind(
+				&program_data.device,
+				&pipeline_info.block_states[PipelineMeshExample::SHADOW_ATLAS_BLOCK],
+			);
+		}
+		Self {
+			pipeline_info,
+			pipeline_block_structure,
+			object_block_structure,
+			camera,
+		}
+	}
+}
+
+impl EnginePipeline for PipelineMeshExample {
+	fn get_pipeline_info(
+		&self,
+	) -> Arc<PipelineInfo> {
+		self.pipeline_info.clone()
+	}
+
+	fn get_pipeline_block_structure(
+		&self,
+	) -> Arc<ObjectBlockStructure> {
+		self.pipeline_block_structure.clone()
+	}
+
+	fn get_object_block_structure(
+		&self,
+	) -> Arc<ObjectBlockStructure> {
+		self.object_block_structure.clone()
+	}
+
+	fn recreate_pipeline(
+		&mut self,
+		program_data: &ProgramData,
+	) -> Result<(), PipelineCreateError> {
+		let pipeline_info = vpb::gmuc!(self.pipeline_info);
+		pipeline_info.recreate_pipeline(
+			program_data,
+			&self.pipeline_block_structure,
+			&self.object_block_structure,
+		)
+	}
+
+	fn update_block_states(
+		&mut self,
+		program_data: &ProgramData,
+		input_state: &InputState,
+		render_state: &RenderState,
+	) {
+		self.camera.update(
+			&program_data.device,
+			Some(render_state.frame),
+			self.pipeline_info.block_states[0].uniform(),
+		);
+	}
+}
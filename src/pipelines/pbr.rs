@@ -0,0 +1,292 @@
+use std::sync::Arc;
+
+use ash::vk;
+use vpb::ProgramData;
+
+use crate::{ViewportDepthRange, PipelineInfo, BlockCamera3d, BlockLights, BlockEnvironment, BlockPbrMaterial, BlockMeshModel, BlockStateExt, EnginePipeline, ObjectBlockStructure, Vertex3d, InputState, RenderState, Camera, DescriptorBlockKind, Environment, MaterialTemplate, Texture, TextureOptions, ShadowMaps, PipelineCreateError};
+
+/// Metallic-roughness `Vertex3d` meshes lit by `Scene::lights` and an
+/// `Environment`, tonemapped with ACES. Objects are `MeshObject`s placed
+/// at `PipelinePbr::MODEL_BLOCK` and drawn with a material made from
+/// `PipelinePbr::material_template`. Built `with_shadows` the shadow
+/// casting lights are shadowed by the objects of buckets registered with
+/// `Bucket::set_shadow_caster_block`, `MODEL_BLOCK` for this pipeline.
+pub struct PipelinePbr {
+	pipeline_info: Arc<PipelineInfo>,
+	pipeline_block_structure: Arc<ObjectBlockStructure>,
+	object_block_structure: Arc<ObjectBlockStructure>,
+	camera: Arc<dyn Camera>,
+	shadows: bool,
+	/// Exposure and intensity, the prefiltered mip count is kept in sync
+	/// with the bound environment.
+	pub environment: BlockEnvironment,
+}
+
+impl PipelinePbr {
+	pub const LIGHT_BLOCK: usize = 1;
+	pub const ENVIRONMENT_BLOCK: usize = 2;
+	/// Irradiance at element 0, prefiltered radiance at element 1.
+	pub const ENVIRONMENT_MAPS_BLOCK: usize = 3;
+	/// BRDF lookup table at element 0, the shadow atlas at element 1 when
+	/// built `with_shadows`.
+	pub const LIGHTING_MAPS_BLOCK: usize = 4;
+	/// Index of the `BlockMeshModel` in the object's block states.
+	pub const MODEL_BLOCK: usize = 5;
+
+	/// Material block holding the `BlockPbrMaterial`.
+	pub const MATERIAL_BLOCK: usize = 0;
+	/// Material image array, indexed by the `*_TEXTURE` elements.
+	pub const MATERIAL_TEXTURES_BLOCK: usize = 1;
+	pub const BASE_COLOR_TEXTURE: usize = 0;
+	/// Roughness in green, metallic in blue.
+	pub const METALLIC_ROUGHNESS_TEXTURE: usize = 1;
+	pub const NORMAL_TEXTURE: usize = 2;
+	pub const OCCLUSION_TEXTURE: usize = 3;
+	pub const EMISSIVE_TEXTURE: usize = 4;
+
+	pub fn new(
+		program_data: &ProgramData,
+		pipeline_cache: vk::PipelineCache,
+		camera: Arc<dyn Camera>,
+		environment: &Environment,
+	) -> Self {
+		PipelinePbr::create(
+			program_data,
+			pipeline_cache,
+			camera,
+			environment,
+			None,
+		)
+	}
+
+	pub fn with_shadows(
+		program_data: &ProgramData,
+		pipeline_cache: vk::PipelineCache,
+		camera: Arc<dyn Camera>,
+		environment: &Environment,
+		shadow_maps: &ShadowMaps,
+	) -> Self {
+		PipelinePbr::create(
+			program_data,
+			pipeline_cache,
+			camera,
+			environment,
+			Some(shadow_maps),
+		)
+	}
+
+	fn create(
+		program_data: &ProgramData,
+		pipeline_cache: vk::PipelineCache,
+		camera: Arc<dyn Camera>,
+		environment: &Environment,
+		shadow_maps: Option<&ShadowMaps>,
+	) -> Self {
+		let pipeline_block_structure = Arc::new(ObjectBlockStructure::new().with_block::<BlockCamera3d>(
+			&program_data.device,
+			vpb::BindingId(0), vpb::SetId(0),
+		).with_block::<BlockLights>(
+			&program_data.device,
+			vpb::BindingId(0), vpb::SetId(1),
+		).with_block::<BlockEnvironment>(
+			&program_data.device,
+			vpb::BindingId(0), vpb::SetId(2),
+		).with_descriptor(
+			&program_data.device,
+			"EnvironmentMaps",
+			vpb::BindingId(0), vpb::SetId(3),
+			DescriptorBlockKind::CombinedImageSamplerArray { count: 2 },
+			vk::ShaderStageFlags::FRAGMENT,
+		).with_descriptor(
+			&program_data.device,
+			"BrdfLut",
+			vpb::BindingId(0), vpb::SetId(4),
+			DescriptorBlockKind::CombinedImageSampler,
+			vk::ShaderStageFlags::FRAGMENT,
+		));
+		let object_block_structure = Arc::new(ObjectBlockStructure::new().with_block::<BlockPbrMaterial>(
+			&program_data.device,
+			vpb::BindingId(0), vpb::SetId(5),
+		).with_descriptor(
+			&program_data.device,
+			"MaterialTextures",
+			vpb::BindingId(0), vpb::SetId(6),
+			DescriptorBlockKind::CombinedImageSamplerArray { count: 5 },
+			vk::ShaderStageFlags::FRAGMENT,
+		).with_material_blocks().with_block::<BlockMeshModel>(
+			&program_data.device,
+			vpb::BindingId(0), vpb::SetId(7),
+		));
+		let mut builder = PipelineInfo::builder("pbr")
+			.viewport_depth_range(ViewportDepthRange::WORLD)
+			.cull_mode(vk::CullModeFlags::BACK);
+		if shadow_maps.is_some() {
+			builder = builder.define("SHADOWS", "");
+		}
+		let pipeline_info = Arc::new(builder.build::<Vertex3d>(
+			program_data,
+			pipeline_cache,
+			&pipeline_block_structure,
+			&object_block_structure,
+		));
+		if let Some(shadow_maps) = shadow_maps {
+			shadow_maps.bind_element(
+				&program_data.device,
+				&pipeline_info.block_states[PipelinePbr::LIGHTING_MAPS_BLOCK],
+				1,
+			);
+		}
+		let mut pipeline = Self {
+			pipeline_info,
+			pipeline_block_structure,
+			object_block_structure,
+			camera,
+			shadows: shadow_maps.is_some(),
+			environment: BlockEnvironment::default(),
+		};
+		pipeline.set_environment(
+			&program_data.device,
+			environment,
+		);
+		pipeline
+	}
+
+	/// Points the pipeline at other environment maps, the previous ones
+	/// must stay alive until the frames using them are done.
+	pub fn set_environment(
+		&mut self,
+		device: &vpb::Device,
+		environment: &Environment,
+	) {
+		let block_states = &self.pipeline_info.block_states;
+		environment.irradiance.bind_element(
+			device,
+			&block_states[PipelinePbr::ENVIRONMENT_MAPS_BLOCK],
+			0,
+		);
+		environment.prefiltered.bind_element(
+			device,
+			&block_states[PipelinePbr::ENVIRONMENT_MAPS_BLOCK],
+			1,
+		);
+		environment.brdf_lut.bind_element(
+			device,
+			&block_states[PipelinePbr::LIGHTING_MAPS_BLOCK],
+			0,
+			None,
+		);
+		// Every element has to be written even when nothing reads it.
+		if !self.shadows {
+			environment.brdf_lut.bind_element(
+				device,
+				&block_states[PipelinePbr::LIGHTING_MAPS_BLOCK],
+				1,
+				None,
+			);
+		}
+		self.environment.params.value[2] = environment.prefiltered.mip_levels as f32;
+	}
+
+	/// Template whose materials start out white, flat and fully rough,
+	/// with 1x1 default textures in every slot. The template owns the
+	/// default textures, see `MaterialTemplate::destroy_textures`.
+	pub fn material_template(
+		program_data: &ProgramData,
+		name: &str,
+		engine_pipeline: Arc<dyn EnginePipeline>,
+	) -> MaterialTemplate {
+		let white = Arc::new(Texture::from_rgba8(
+			program_data,
+			1, 1,
+			&[255; 4],
+			&TextureOptions {
+				mipmaps: false,
+				..Default::default()
+			},
+		));
+		let flat_normal = Arc::new(Texture::from_rgba8(
+			program_data,
+			1, 1,
+			&[128, 128, 255, 255],
+			&TextureOptions {
+				mipmaps: false,
+				srgb: false,
+				..Default::default()
+			},
+		));
+		let mut template = MaterialTemplate::new(
+			name,
+			engine_pipeline,
+		).with_parameters(
+			PipelinePbr::MATERIAL_BLOCK,
+			BlockPbrMaterial::default(),
+		);
+		for element in [
+			PipelinePbr::BASE_COLOR_TEXTURE,
+			PipelinePbr::METALLIC_ROUGHNESS_TEXTURE,
+			PipelinePbr::OCCLUSION_TEXTURE,
+			PipelinePbr::EMISSIVE_TEXTURE,
+		] {
+			template = template.with_texture(
+				PipelinePbr::MATERIAL_TEXTURES_BLOCK,
+				element,
+				white.clone(),
+			);
+		}
+		template.with_texture(
+			PipelinePbr::MATERIAL_TEXTURES_BLOCK,
+			PipelinePbr::NORMAL_TEXTURE,
+			flat_normal,
+		)
+	}
+}
+
+impl EnginePipeline for PipelinePbr {
+	fn get_pipeline_info(
+		&self,
+	) -> Arc<PipelineInfo> {
+		self.pipeline_info.clone()
+	}
+
+	fn get_pipeline_block_structure(
+		&self,
+	) -> Arc<ObjectBlockStructure> {
+		self.pipeline_block_structure.clone()
+	}
+
+	fn get_object_block_structure(
+		&self,
+	) -> Arc<ObjectBlockStructure> {
+		self.object_block_structure.clone()
+	}
+
+	fn recreate_pipeline(
+		&mut self,
+		program_data: &ProgramData,
+	) -> Result<(), PipelineCreateError> {
+		let pipeline_info = vpb::gmuc!(self.pipeline_info);
+		pipeline_info.recreate_pipeline(
+			program_data,
+			&self.pipeline_block_structure,
+			&self.object_block_structure,
+		)
+	}
+
+	fn update_block_states(
+		&mut self,
+		program_data: &ProgramData,
+		input_state: &InputState,
+		render_state: &RenderState,
+	) {
+		self.camera.update(
+			&program_data.device,
+			Some(render_state.frame),
+			self.pipeline_info.block_states[0].uniform(),
+		);
+		self.pipeline_info.block_states[PipelinePbr::ENVIRONMENT_BLOCK].uniform().update_checked(
+			&program_data.device,
+			&self.environment,
+			Some(render_state.frame),
+		);
+	}
+}
@@ -0,0 +1,157 @@
+use std::sync::Arc;
+
+use ash::vk;
+use vpb::ProgramData;
+
+use crate::{ViewportDepthRange, PipelineInfo, BlockCamera3d, BlockSky, BlockStateExt, BlendPreset, EnginePipeline, ObjectBlockStructure, Vertex3d, InputState, RenderState, Camera, DescriptorBlockKind, Cubemap, TextureOptions, PipelineCreateError};
+
+/// Sky drawn on the far plane around a `Skybox` object, seen through the
+/// rotation of the camera only. Samples a `Cubemap` when given one and
+/// draws the `BlockSky` gradient otherwise. Fragments are only kept where
+/// nothing else was drawn, so the bucket can come before or after the
+/// opaque buckets but must come before transparent ones.
+pub struct PipelineSkybox {
+	pipeline_info: Arc<PipelineInfo>,
+	pipeline_block_structure: Arc<ObjectBlockStructure>,
+	object_block_structure: Arc<ObjectBlockStructure>,
+	camera: Arc<dyn Camera>,
+	/// Bound in place of a cubemap while drawing the gradient.
+	placeholder: Cubemap,
+	pub sky: BlockSky,
+}
+
+impl PipelineSkybox {
+	pub const SKY_BLOCK: usize = 1;
+	pub const CUBEMAP_BLOCK: usize = 2;
+
+	pub fn new(
+		program_data: &ProgramData,
+		pipeline_cache: vk::PipelineCache,
+		camera: Arc<dyn Camera>,
+		cubemap: Option<&Cubemap>,
+	) -> Self {
+		let pipeline_block_structure = Arc::new(ObjectBlockStructure::new().with_block::<BlockCamera3d>(
+			&program_data.device,
+			vpb::BindingId(0), vpb::SetId(0),
+		).with_block::<BlockSky>(
+			&program_data.device,
+			vpb::BindingId(0), vpb::SetId(1),
+		).with_descriptor(
+			&program_data.device,
+			"SkyCubemap",
+			vpb::BindingId(0), vpb::SetId(2),
+			DescriptorBlockKind::CombinedImageSampler,
+			vk::ShaderStageFlags::FRAGMENT,
+		));
+		let object_block_structure = Arc::new(ObjectBlockStructure::new());
+		let pipeline_info = Arc::new(PipelineInfo::builder("skybox")
+			.viewport_depth_range(ViewportDepthRange::WORLD)
+			.depth_write(false)
+			.depth_compare(vk::CompareOp::LESS_OR_EQUAL)
+			.blend(BlendPreset::OPAQUE)
+			.build::<Vertex3d>(
+				program_data,
+				pipeline_cache,
+				&pipeline_block_structure,
+				&object_block_structure,
+			)
+		);
+		let placeholder = Cubemap::from_rgba8(
+			program_data,
+			1,
+			&[0; 4 * 6],
+			&TextureOptions {
+				mipmaps: false,
+				..Default::default()
+			},
+		);
+		let mut pipeline = Self {
+			pipeline_info,
+			pipeline_block_structure,
+			object_block_structure,
+			camera,
+			placeholder,
+			sky: BlockSky::default(),
+		};
+		pipeline.set_cubemap(
+			&program_data.device,
+			cubemap,
+		);
+		pipeline
+	}
+
+	/// Switches between the cubemap and the gradient. The previous cubemap
+	/// must stay alive until the frames using it are done.
+	pub fn set_cubemap(
+		&mut self,
+		device: &vpb::Device,
+		cubemap: Option<&Cubemap>,
+	) {
+		cubemap.unwrap_or(&self.placeholder).bind(
+			device,
+			&self.pipeline_info.block_states[PipelineSkybox::CUBEMAP_BLOCK],
+		);
+		self.sky.params.value[0] = match cubemap {
+			Some(_) => 1.0,
+			None => 0.0,
+		};
+	}
+
+	/// The pipeline must no longer be in use by any submitted frame.
+	pub fn destroy_placeholder(
+		&mut self,
+		program_data: &ProgramData,
+	) {
+		self.placeholder.destroy(program_data);
+	}
+}
+
+impl EnginePipeline for PipelineSkybox {
+	fn get_pipeline_info(
+		&self,
+	) -> Arc<PipelineInfo> {
+		self.pipeline_info.clone()
+	}
+
+	fn get_pipeline_block_structure(
+		&self,
+	) -> Arc<ObjectBlockStructure> {
+		self.pipeline_block_structure.clone()
+	}
+
+	fn get_object_block_structure(
+		&self,
+	) -> Arc<ObjectBlockStructure> {
+		self.object_block_structure.clone()
+	}
+
+	fn recreate_pipeline(
+		&mut self,
+		program_data: &ProgramData,
+	) -> Result<(), PipelineCreateError> {
+		let pipeline_info = vpb::gmuc!(self.pipeline_info);
+		pipeline_info.recreate_pipeline(
+			program_data,
+			&self.pipeline_block_structure,
+			&self.object_block_structure,
+		)
+	}
+
+	fn update_block_states(
+		&mut self,
+		program_data: &ProgramData,
+		input_state: &InputState,
+		render_state: &RenderState,
+	) {
+		self.camera.update(
+			&program_data.device,
+			Some(render_state.frame),
+			self.pipeline_info.block_states[0].uniform(),
+		);
+		self.pipeline_info.block_states[PipelineSkybox::SKY_BLOCK].uniform().update_checked(
+			&program_data.device,
+			&self.sky,
+			Some(render_state.frame),
+		);
+	}
+}
@@ -0,0 +1,83 @@
+use std::sync::Arc;
+
+use ash::vk;
+use vpb::ProgramData;
+
+use crate::{DescribedPipeline, PipelineDescription, TypeRegistry, Camera, EnginePipeline, PipelineInfo, ObjectBlockStructure, InputState, RenderState, PipelineCreateError};
+
+/// Signed distance field text, see `Text`. The font atlas of each object
+/// is bound at set 2 with `Bucket::bind_texture`. Built from
+/// `res/pipelines/text.ron`, depth writes are off since they would clip
+/// the antialiased edges of overlapping glyphs.
+pub struct PipelineTextExample {
+	pipeline: DescribedPipeline,
+}
+
+impl PipelineTextExample {
+	pub const FONT_BLOCK: usize = 2;
+	pub const DESCRIPTION: &'static str = include_str!("../../res/pipelines/text.ron");
+
+	pub fn description(
+	) -> PipelineDescription {
+		PipelineDescription::from_ron(PipelineTextExample::DESCRIPTION).expect(
+			"invalid text pipeline description"
+		)
+	}
+
+	pub fn new(
+		program_data: &ProgramData,
+		pipeline_cache: vk::PipelineCache,
+		camera: Arc<dyn Camera>,
+	) -> Self {
+		let pipeline = DescribedPipeline::new(
+			program_data,
+			pipeline_cache,
+			&PipelineTextExample::description(),
+			&TypeRegistry::with_defaults(),
+			Some(camera),
+		).expect("failed to build the text pipeline");
+		Self {
+			pipeline,
+		}
+	}
+}
+
+impl EnginePipeline for PipelineTextExample {
+	fn get_pipeline_info(
+		&self,
+	) -> Arc<PipelineInfo> {
+		self.pipeline.get_pipeline_info()
+	}
+
+	fn get_pipeline_block_structure(
+		&self,
+	) -> Arc<ObjectBlockStructure> {
+		self.pipeline.get_pipeline_block_structure()
+	}
+
+	fn get_object_block_structure(
+		&self,
+	) -> Arc<ObjectBlockStructure> {
+		self.pipeline.get_object_block_structure()
+	}
+
+	fn recreate_pipeline(
+		&mut self,
+		program_data: &ProgramData,
+	) -> Result<(), PipelineCreateError> {
+		self.pipeline.recreate_pipeline(program_data)
+	}
+
+	fn update_block_states(
+		&mut self,
+		program_data: &ProgramData,
+		input_state: &InputState,
+		render_state: &RenderState,
+	) {
+		self.pipeline.update_block_states(
+			program_data,
+			input_state,
+			render_state,
+		);
+	}
+}
@@ -3,86 +3,66 @@ use std::sync::Arc;
 use ash::vk;
 use vpb::ProgramData;
 
-use crate::{ViewportDepthRange, PipelineInfo, BlockCamera2d, BlockModelExample, EnginePipeline, ObjectBlockStructure, VertexUI, InputState, RenderState, CameraState2d, Camera};
+use crate::{DescribedPipeline, PipelineDescription, TypeRegistry, Camera, EnginePipeline, PipelineInfo, ObjectBlockStructure, InputState, RenderState, PipelineCreateError};
 
+/// Flat colored `VertexUI` geometry seen through a `CameraState2d`, built
+/// from `res/pipelines/ui_geometry.ron`.
 pub struct PipelineUIExample {
-	pipeline_info: Arc<PipelineInfo>,
-	pipeline_block_structure: Arc<ObjectBlockStructure>,
-	object_block_structure: Arc<ObjectBlockStructure>,
-	camera: Arc<dyn Camera>,
+	pipeline: DescribedPipeline,
 }
 
 impl PipelineUIExample {
+	pub const DESCRIPTION: &'static str = include_str!("../../res/pipelines/ui_geometry.ron");
+
+	pub fn description(
+	) -> PipelineDescription {
+		PipelineDescription::from_ron(PipelineUIExample::DESCRIPTION).expect(
+			"invalid ui_geometry pipeline description"
+		)
+	}
+
 	pub fn new(
 		program_data: &ProgramData,
+		pipeline_cache: vk::PipelineCache,
 		camera: Arc<dyn Camera>,
-	) -> Self { unsafe {
-		let pipeline_block_structure = Arc::new(ObjectBlockStructure {
-			spawners: vec![
-				Box::new(vpb::BlockSpawner::<BlockCamera2d>::new(
-					&program_data.device,
-					vpb::BindingId(0), vpb::SetId(0),
-				))
-			],
-		});
-		let object_block_structure = Arc::new(ObjectBlockStructure {
-			spawners: vec![
-				Box::new(vpb::BlockSpawner::<BlockModelExample>::new(
-					&program_data.device,
-					vpb::BindingId(1), vpb::SetId(1),
-				))
-			],
-		});
-		let pipeline_info = Arc::new(PipelineInfo::new::<VertexUI>(
+	) -> Self {
+		let pipeline = DescribedPipeline::new(
 			program_data,
-			"ui_geometry",
-			true,
-			ViewportDepthRange::UI,
-			vk::PolygonMode::FILL,
-			&pipeline_block_structure,
-			&object_block_structure,
-			|| {
-				vec![]
-			},
-		));
+			pipeline_cache,
+			&PipelineUIExample::description(),
+			&TypeRegistry::with_defaults(),
+			Some(camera),
+		).expect("failed to build the ui_geometry pipeline");
 		Self {
-			pipeline_info,
-			pipeline_block_structure,
-			object_block_structure,
-			camera,
+			pipeline,
 		}
-	}}
+	}
 }
 
 impl EnginePipeline for PipelineUIExample {
 	fn get_pipeline_info(
 		&self,
 	) -> Arc<PipelineInfo> {
-		self.pipeline_info.clone()
+		self.pipeline.get_pipeline_info()
 	}
 
 	fn get_pipeline_block_structure(
 		&self,
 	) -> Arc<ObjectBlockStructure> {
-		self.pipeline_block_structure.clone()
+		self.pipeline.get_pipeline_block_structure()
 	}
 
 	fn get_object_block_structure(
 		&self,
 	) -> Arc<ObjectBlockStructure> {
-		self.object_block_structure.clone()
+		self.pipeline.get_object_block_structure()
 	}
 
 	fn recreate_pipeline(
 		&mut self,
 		program_data: &ProgramData,
-	) {
-		let pipeline_info = vpb::gmuc!(self.pipeline_info);
-		pipeline_info.recreate_pipeline::<VertexUI>(
-			program_data,
-			&self.pipeline_block_structure,
-			&self.object_block_structure,
-		);
+	) -> Result<(), PipelineCreateError> {
+		self.pipeline.recreate_pipeline(program_data)
 	}
 
 	fn update_block_states(
@@ -91,10 +71,10 @@ impl EnginePipeline for PipelineUIExample {
 		input_state: &InputState,
 		render_state: &RenderState,
 	) {
-		self.camera.update(
-			&program_data.device,
-			Some(render_state.frame),
-			&self.pipeline_info.block_states[0],
-		)
+		self.pipeline.update_block_states(
+			program_data,
+			input_state,
+			render_state,
+		);
 	}
 }
\ No newline at end of file
@@ -0,0 +1,81 @@
+use std::sync::Arc;
+
+use ash::vk;
+use vpb::ProgramData;
+
+use crate::{DescribedPipeline, PipelineDescription, TypeRegistry, Camera, EnginePipeline, PipelineInfo, ObjectBlockStructure, InputState, RenderState, PipelineCreateError};
+
+/// `PipelineUIExample` with a texture per object at set 2, see
+/// `Bucket::bind_texture`. Built from `res/pipelines/ui_textured.ron`.
+pub struct PipelineUITexturedExample {
+	pipeline: DescribedPipeline,
+}
+
+impl PipelineUITexturedExample {
+	pub const TEXTURE_BLOCK: usize = 2;
+	pub const DESCRIPTION: &'static str = include_str!("../../res/pipelines/ui_textured.ron");
+
+	pub fn description(
+	) -> PipelineDescription {
+		PipelineDescription::from_ron(PipelineUITexturedExample::DESCRIPTION).expect(
+			"invalid ui_textured pipeline description"
+		)
+	}
+
+	pub fn new(
+		program_data: &ProgramData,
+		pipeline_cache: vk::PipelineCache,
+		camera: Arc<dyn Camera>,
+	) -> Self {
+		let pipeline = DescribedPipeline::new(
+			program_data,
+			pipeline_cache,
+			&PipelineUITexturedExample::description(),
+			&TypeRegistry::with_defaults(),
+			Some(camera),
+		).expect("failed to build the ui_textured pipeline");
+		Self {
+			pipeline,
+		}
+	}
+}
+
+impl EnginePipeline for PipelineUITexturedExample {
+	fn get_pipeline_info(
+		&self,
+	) -> Arc<PipelineInfo> {
+		self.pipeline.get_pipeline_info()
+	}
+
+	fn get_pipeline_block_structure(
+		&self,
+	) -> Arc<ObjectBlockStructure> {
+		self.pipeline.get_pipeline_block_structure()
+	}
+
+	fn get_object_block_structure(
+		&self,
+	) -> Arc<ObjectBlockStructure> {
+		self.pipeline.get_object_block_structure()
+	}
+
+	fn recreate_pipeline(
+		&mut self,
+		program_data: &ProgramData,
+	) -> Result<(), PipelineCreateError> {
+		self.pipeline.recreate_pipeline(program_data)
+	}
+
+	fn update_block_states(
+		&mut self,
+		program_data: &ProgramData,
+		input_state: &InputState,
+		render_state: &RenderState,
+	) {
+		self.pipeline.update_block_states(
+			program_data,
+			input_state,
+			render_state,
+		);
+	}
+}
@@ -10,6 +10,8 @@ use crate::{Scene, pipelines::ui_example::PipelineUIExample, EnginePipeline};
 
 mod macros;
 pub use macros::*;
+mod pipeline_cache;
+pub use pipeline_cache::*;
 
 #[derive(Copy, Clone)]
 pub enum TickResult {
@@ -28,7 +30,7 @@ impl Program {
 	pub fn new<FC>(
 		name: &str,
 		initial_pipeline: (&str, FC),
-	) -> Self where FC: Fn(&ProgramData) -> Arc<dyn EnginePipeline> {
+	) -> Self where FC: Fn(&ProgramData, vk::PipelineCache) -> Arc<dyn EnginePipeline> {
 		let mut window = vpb::Window::new(
 			name,
 		);
@@ -224,4 +226,13 @@ impl Program {
 		};
 		TickResult::CONTINUE
 	}
+}
+
+impl Drop for Program {
+	fn drop(&mut self) {
+		self.scene.idle();
+		vpb::gmuc!(self.scene).pipeline_cache.save(
+			&self.program_data,
+		);
+	}
 }
\ No newline at end of file
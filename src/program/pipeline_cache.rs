@@ -0,0 +1,113 @@
+use std::{fs, path::{Path, PathBuf}, time::Instant};
+
+use ash::vk;
+use vpb::ProgramData;
+
+pub const PIPELINE_CACHE_PATH: &str = "pipeline.cache";
+const PIPELINE_CACHE_MAGIC: [u8; 4] = *b"VPEC";
+/// magic, vendor id, device id, driver version, pipeline cache uuid
+const PIPELINE_CACHE_HEADER_SIZE: usize = 4 + 4 + 4 + 4 + vk::UUID_SIZE;
+
+/// `vk::PipelineCache` shared by every pipeline created on the device.
+/// `ProgramData` lives in vpb, so the `Scene` owns the cache and passes
+/// the handle to whatever creates pipelines. The handle is null after
+/// `PipelineCache::save`.
+pub struct PipelineCache {
+	pub pipeline_cache: vk::PipelineCache,
+	pub path: PathBuf,
+}
+
+fn cache_header(
+	properties: &vk::PhysicalDeviceProperties,
+) -> Vec<u8> {
+	let mut header = Vec::with_capacity(PIPELINE_CACHE_HEADER_SIZE);
+	header.extend_from_slice(&PIPELINE_CACHE_MAGIC);
+	header.extend_from_slice(&properties.vendor_id.to_le_bytes());
+	header.extend_from_slice(&properties.device_id.to_le_bytes());
+	header.extend_from_slice(&properties.driver_version.to_le_bytes());
+	header.extend_from_slice(&properties.pipeline_cache_uuid);
+	header
+}
+
+impl PipelineCache {
+	/// Creates the cache, seeded from `path` when the file was written by
+	/// the same vendor, device and driver version.
+	pub fn load<P: AsRef<Path>>(
+		program_data: &ProgramData,
+		path: P,
+	) -> Self { unsafe {
+		let timer = Instant::now();
+		let path = path.as_ref().to_path_buf();
+		let properties = program_data.instance.instance.get_physical_device_properties(
+			program_data.device.physical_device,
+		);
+		let header = cache_header(&properties);
+		let initial_data = match fs::read(&path) {
+			Ok(data) => {
+				if data.len() > PIPELINE_CACHE_HEADER_SIZE && data[..PIPELINE_CACHE_HEADER_SIZE] == header[..] {
+					data[PIPELINE_CACHE_HEADER_SIZE..].to_vec()
+				} else {
+					log::warn!("pipeline cache \"{}\" is from another device or driver, ignoring", path.display());
+					Vec::new()
+				}
+			},
+			Err(_) => Vec::new(),
+		};
+		let pipeline_cache_info = vk::PipelineCacheCreateInfo::builder()
+			.initial_data(&initial_data)
+			.build();
+		let pipeline_cache = match program_data.device.device.create_pipeline_cache(
+			&pipeline_cache_info,
+			None,
+		) {
+			Ok(pipeline_cache) => pipeline_cache,
+			Err(_) => {
+				// Driver rejected the data, start from an empty cache instead.
+				let pipeline_cache_info = vk::PipelineCacheCreateInfo::builder().build();
+				program_data.device.device.create_pipeline_cache(
+					&pipeline_cache_info,
+					None,
+				).unwrap()
+			},
+		};
+		log::info!(
+			"loaded pipeline cache ({} bytes) in {:.3}ms",
+			initial_data.len(),
+			timer.elapsed().as_micros() as f32 / 1_000.0,
+		);
+		Self {
+			pipeline_cache,
+			path,
+		}
+	}}
+
+	/// Writes the cache to the file it was loaded from and destroys it.
+	pub fn save(
+		&mut self,
+		program_data: &ProgramData,
+	) { unsafe {
+		if self.pipeline_cache == vk::PipelineCache::null() {
+			return;
+		}
+		let properties = program_data.instance.instance.get_physical_device_properties(
+			program_data.device.physical_device,
+		);
+		match program_data.device.device.get_pipeline_cache_data(
+			self.pipeline_cache,
+		) {
+			Ok(data) => {
+				let mut file_data = cache_header(&properties);
+				file_data.extend_from_slice(&data);
+				if let Err(e) = fs::write(&self.path, file_data) {
+					log::error!("failed to write pipeline cache \"{}\": {}", self.path.display(), e);
+				}
+			},
+			Err(e) => { log::error!("failed to read pipeline cache data: {}", e); },
+		}
+		program_data.device.device.destroy_pipeline_cache(
+			self.pipeline_cache,
+			None,
+		);
+		self.pipeline_cache = vk::PipelineCache::null();
+	}}
+}
\ No newline at end of file
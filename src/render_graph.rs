@@ -0,0 +1,46 @@
+use std::fmt;
+
+mod resources;
+pub use resources::*;
+mod pass;
+pub use pass::*;
+mod graph;
+pub use graph::*;
+
+#[derive(Debug)]
+pub enum RenderGraphError {
+	/// A pass uses an image or buffer of another graph.
+	UnknownResource(String),
+	/// The pass drawing into the swapchain has other attachments.
+	SwapchainAttachments(String),
+	MultipleSwapchainPasses,
+	SampledSwapchain(String),
+	/// A pass samples one of its own attachments.
+	FeedbackLoop { pass: String, image: String },
+	/// A pass samples a graph image before any pass writes it.
+	ReadBeforeWrite { pass: String, image: String },
+	/// A depth format used as a color attachment or the other way round.
+	AttachmentFormat { pass: String, image: String },
+	/// Attachments of one pass with different sizes.
+	ExtentMismatch(String),
+	/// Buckets assigned to a pass without attachments to draw into.
+	NoAttachments(String),
+}
+
+impl fmt::Display for RenderGraphError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			RenderGraphError::UnknownResource(pass) => write!(f, "pass \"{}\" uses a resource the graph doesn't have", pass),
+			RenderGraphError::SwapchainAttachments(pass) => write!(f, "pass \"{}\" draws into the swapchain along with other attachments", pass),
+			RenderGraphError::MultipleSwapchainPasses => write!(f, "more than one pass draws into the swapchain"),
+			RenderGraphError::SampledSwapchain(pass) => write!(f, "pass \"{}\" samples the swapchain", pass),
+			RenderGraphError::FeedbackLoop { pass, image } => write!(f, "pass \"{}\" samples its own attachment \"{}\"", pass, image),
+			RenderGraphError::ReadBeforeWrite { pass, image } => write!(f, "pass \"{}\" samples \"{}\" before any pass writes it", pass, image),
+			RenderGraphError::AttachmentFormat { pass, image } => write!(f, "pass \"{}\" attaches \"{}\" with the wrong kind of format", pass, image),
+			RenderGraphError::ExtentMismatch(pass) => write!(f, "attachments of pass \"{}\" differ in size", pass),
+			RenderGraphError::NoAttachments(pass) => write!(f, "pass \"{}\" has buckets but no attachments", pass),
+		}
+	}
+}
+
+impl std::error::Error for RenderGraphError {}
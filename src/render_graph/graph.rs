@@ -0,0 +1,1020 @@
+use std::collections::HashSet;
+
+use ash::vk;
+use vpb::ProgramData;
+
+use crate::{Bucket, EngineBlockState, Sampler, SamplerDescription, GraphPass, GraphPassId, GraphImageId, GraphBufferId, GraphImageDescription, GraphImportedImage, RenderGraphError};
+use super::resources::{GraphImage, GraphBuffer, ImageSource, is_depth_format};
+
+struct ImageBarrier {
+	image: GraphImageId,
+	old_layout: vk::ImageLayout,
+	new_layout: vk::ImageLayout,
+	src_access: vk::AccessFlags,
+	dst_access: vk::AccessFlags,
+}
+
+struct BufferBarrier {
+	buffer: GraphBufferId,
+	src_access: vk::AccessFlags,
+	dst_access: vk::AccessFlags,
+}
+
+/// Everything one `cmd_pipeline_barrier` before a pass waits on.
+#[derive(Default)]
+struct Barriers {
+	src_stages: vk::PipelineStageFlags,
+	dst_stages: vk::PipelineStageFlags,
+	images: Vec<ImageBarrier>,
+	buffers: Vec<BufferBarrier>,
+}
+
+impl Barriers {
+	fn is_empty(
+		&self,
+	) -> bool {
+		self.images.is_empty() && self.buffers.is_empty()
+	}
+}
+
+/// Last access to a resource while planning barriers.
+#[derive(Clone, Copy)]
+struct AccessState {
+	layout: vk::ImageLayout,
+	stages: vk::PipelineStageFlags,
+	access: vk::AccessFlags,
+}
+
+struct CompiledPass {
+	pass: usize,
+	barriers: Barriers,
+	/// `None` for the swapchain pass and passes without attachments.
+	render_pass: Option<vk::RenderPass>,
+	framebuffer: vk::Framebuffer,
+	extent: vk::Extent2D,
+	swapchain: bool,
+}
+
+/// Passes that declare the images and buffers they read and write.
+/// `compile` drops passes nothing uses, allocates the transient images
+/// and works out the layout transitions and barriers between passes,
+/// `Scene::render` then runs the graph in place of its single render pass.
+///
+/// Pipelines drawing into an offscreen pass are built with
+/// `PipelineInfoBuilder::graph_pass` after the graph is compiled. Buckets
+/// are drawn in the passes naming them, the rest in the swapchain pass.
+pub struct RenderGraph {
+	images: Vec<GraphImage>,
+	buffers: Vec<GraphBuffer>,
+	passes: Vec<GraphPass>,
+	/// Kept passes in the order they run.
+	compiled: Vec<CompiledPass>,
+	/// Puts imported images back into their layout after the last pass.
+	final_barriers: Barriers,
+	sampler: Option<Sampler>,
+	/// Descriptors written by `bind`, rewritten when images are recreated.
+	bindings: Vec<(GraphImageId, EngineBlockState, usize)>,
+}
+
+impl Default for RenderGraph {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl RenderGraph {
+	/// The scene's presentation image. A pass drawing into it runs in the
+	/// scene's own render pass along with its depth buffer.
+	pub const SWAPCHAIN: GraphImageId = GraphImageId(0);
+
+	pub fn new(
+	) -> Self {
+		Self {
+			images: vec![GraphImage {
+				name: "swapchain".to_string(),
+				source: ImageSource::Swapchain,
+				usage: vk::ImageUsageFlags::empty(),
+				allocated: None,
+			}],
+			buffers: Vec::new(),
+			passes: Vec::new(),
+			compiled: Vec::new(),
+			final_barriers: Barriers::default(),
+			sampler: None,
+			bindings: Vec::new(),
+		}
+	}
+
+	pub fn create_image(
+		&mut self,
+		name: &str,
+		description: GraphImageDescription,
+	) -> GraphImageId {
+		self.push_image(name, ImageSource::Transient(description))
+	}
+
+	pub fn import_image(
+		&mut self,
+		name: &str,
+		image: GraphImportedImage,
+	) -> GraphImageId {
+		self.push_image(name, ImageSource::Imported(image))
+	}
+
+	fn push_image(
+		&mut self,
+		name: &str,
+		source: ImageSource,
+	) -> GraphImageId {
+		self.images.push(GraphImage {
+			name: name.to_string(),
+			source,
+			usage: vk::ImageUsageFlags::empty(),
+			allocated: None,
+		});
+		GraphImageId(self.images.len() - 1)
+	}
+
+	/// Buffers are owned outside the graph, passes writing one are never
+	/// culled.
+	pub fn import_buffer(
+		&mut self,
+		name: &str,
+		buffer: vk::Buffer,
+	) -> GraphBufferId {
+		self.buffers.push(GraphBuffer {
+			name: name.to_string(),
+			buffer,
+		});
+		GraphBufferId(self.buffers.len() - 1)
+	}
+
+	/// Passes run in the order they're added, `compile` fails when a pass
+	/// samples a graph image no earlier pass wrote.
+	pub fn add_pass(
+		&mut self,
+		pass: GraphPass,
+	) -> GraphPassId {
+		self.passes.push(pass);
+		GraphPassId(self.passes.len() - 1)
+	}
+
+	pub fn pass_name(
+		&self,
+		pass: GraphPassId,
+	) -> &str {
+		&self.passes[pass.0].name
+	}
+
+	pub fn image_name(
+		&self,
+		image: GraphImageId,
+	) -> &str {
+		&self.images[image.0].name
+	}
+
+	pub fn buffer_name(
+		&self,
+		buffer: GraphBufferId,
+	) -> &str {
+		&self.buffers[buffer.0].name
+	}
+
+	/// Dropped by the last `compile` because nothing uses what it writes.
+	pub fn is_culled(
+		&self,
+		pass: GraphPassId,
+	) -> bool {
+		!self.compiled.iter().any(|x| x.pass == pass.0)
+	}
+
+	/// Render pass and color attachment count pipelines drawing into an
+	/// offscreen pass are built for, see `PipelineInfoBuilder::graph_pass`.
+	pub fn pass_target(
+		&self,
+		pass: GraphPassId,
+	) -> Option<(vk::RenderPass, u32)> {
+		let compiled = self.compiled.iter().find(|x| x.pass == pass.0)?;
+		Some((compiled.render_pass?, self.passes[pass.0].colors.len() as u32))
+	}
+
+	/// Current view of an image, `None` for the swapchain and transient
+	/// images of culled passes.
+	pub fn image_view(
+		&self,
+		image: GraphImageId,
+	) -> Option<vk::ImageView> {
+		self.images[image.0].handles().map(|(_, image_view, _)| image_view)
+	}
+
+	/// Writes a sampled image into a combined image sampler block state,
+	/// again whenever the image is recreated. Call after `compile`. The
+	/// binding is dropped once a later compile culls every pass using the
+	/// image.
+	pub fn bind(
+		&mut self,
+		device: &vpb::Device,
+		image: GraphImageId,
+		block_state: &EngineBlockState,
+		element: usize,
+	) {
+		assert!(
+			self.image_view(image).is_some(),
+			"render graph image \"{}\" isn't allocated", self.images[image.0].name,
+		);
+		self.bindings.push((image, block_state.clone(), element));
+		self.write_binding(device, self.bindings.len() - 1);
+	}
+
+	/// Points every binding at its recreated image.
+	fn rewrite_bindings(
+		&mut self,
+		device: &vpb::Device,
+	) {
+		let images = &self.images;
+		self.bindings.retain(
+			|(image, _, _)|
+			images[image.0].handles().is_some()
+		);
+		for i in 0..self.bindings.len() {
+			self.write_binding(device, i);
+		}
+	}
+
+	fn write_binding(
+		&self,
+		device: &vpb::Device,
+		binding: usize,
+	) {
+		let (image, block_state, element) = &self.bindings[binding];
+		let sampler = self.sampler.as_ref().expect("attempting to bind an image of a render graph that isn't compiled");
+		let image_view = match self.image_view(*image) {
+			Some(image_view) => image_view,
+			None => return,
+		};
+		block_state.descriptor().write_image_element(
+			device,
+			*element,
+			image_view,
+			sampler.sampler,
+		);
+	}
+
+	/// Culls, allocates and plans the graph. Compiling again starts over,
+	/// pipelines built for the old render passes must be rebuilt while the
+	/// `bind`ings are rewritten.
+	pub fn compile(
+		&mut self,
+		program_data: &ProgramData,
+	) -> Result<(), RenderGraphError> {
+		if !self.passes.iter().any(|x| x.colors.iter().any(|x| x.image == RenderGraph::SWAPCHAIN)) {
+			self.passes.push(GraphPass::new("swapchain").color(RenderGraph::SWAPCHAIN));
+		}
+		self.validate(program_data.window.extent)?;
+		self.destroy_compiled(program_data);
+		let kept = self.cull();
+		for image in self.images.iter_mut() {
+			image.usage = vk::ImageUsageFlags::empty();
+		}
+		for pass in self.passes.iter().enumerate().filter(|(i, _)| kept[*i]).map(|(_, x)| x) {
+			for color in pass.colors.iter() {
+				self.images[color.image.0].usage |= vk::ImageUsageFlags::COLOR_ATTACHMENT;
+			}
+			if let Some(depth) = pass.depth {
+				self.images[depth.image.0].usage |= vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT;
+			}
+			for image in pass.sampled.iter() {
+				self.images[image.0].usage |= vk::ImageUsageFlags::SAMPLED;
+			}
+		}
+		for image in self.images.iter_mut() {
+			image.allocate(program_data);
+		}
+		self.plan(&kept);
+		for i in 0..self.compiled.len() {
+			let pass = &self.passes[self.compiled[i].pass];
+			if !self.compiled[i].swapchain && pass.attachment_count() > 0 {
+				self.compiled[i].render_pass = Some(self.create_render_pass(program_data, pass));
+			}
+		}
+		self.create_framebuffers(program_data);
+		if self.sampler.is_none() {
+			self.sampler = Some(Sampler::new(
+				program_data,
+				SamplerDescription::default(),
+			));
+		}
+		// The images were recreated, the old views are destroyed.
+		self.rewrite_bindings(&program_data.device);
+		Ok(())
+	}
+
+	fn validate(
+		&self,
+		window: vk::Extent2D,
+	) -> Result<(), RenderGraphError> {
+		let mut swapchain_passes = 0;
+		let mut written = HashSet::new();
+		for pass in self.passes.iter() {
+			let name = || pass.name.clone();
+			if pass.images().any(|x| x.0 >= self.images.len()) || pass.buffers.iter().any(|x| x.buffer.0 >= self.buffers.len()) {
+				return Err(RenderGraphError::UnknownResource(name()));
+			}
+			if pass.sampled.contains(&RenderGraph::SWAPCHAIN) {
+				return Err(RenderGraphError::SampledSwapchain(name()));
+			}
+			let attachments: Vec<GraphImageId> = pass.images().take(pass.attachment_count()).collect();
+			// Passes aren't reordered, so the write has to come first.
+			let unwritten = pass.sampled.iter().find(
+				|x|
+				matches!(self.images[x.0].source, ImageSource::Transient(_)) && !written.contains(*x)
+			);
+			if let Some(image) = unwritten {
+				return Err(RenderGraphError::ReadBeforeWrite { pass: name(), image: self.images[image.0].name.clone() });
+			}
+			written.extend(attachments.iter().copied());
+			if attachments.contains(&RenderGraph::SWAPCHAIN) {
+				swapchain_passes += 1;
+				if attachments.len() > 1 || pass.depth.is_some() {
+					return Err(RenderGraphError::SwapchainAttachments(name()));
+				}
+				continue;
+			}
+			if let Some(image) = attachments.iter().find(|x| pass.sampled.contains(*x)) {
+				return Err(RenderGraphError::FeedbackLoop { pass: name(), image: self.images[image.0].name.clone() });
+			}
+			let misformatted = pass.colors.iter().map(|x| (x.image, false)).chain(pass.depth.iter().map(|x| (x.image, true))).find(
+				|(image, depth)|
+				is_depth_format(self.images[image.0].format()) != *depth
+			);
+			if let Some((image, _)) = misformatted {
+				return Err(RenderGraphError::AttachmentFormat { pass: name(), image: self.images[image.0].name.clone() });
+			}
+			let mut extents = attachments.iter().map(|x| self.extent(*x, window));
+			if let Some(extent) = extents.next() {
+				if extents.any(|x| x != extent) {
+					return Err(RenderGraphError::ExtentMismatch(name()));
+				}
+			} else if !pass.buckets.is_empty() {
+				return Err(RenderGraphError::NoAttachments(name()));
+			}
+		}
+		match swapchain_passes {
+			0 | 1 => Ok(()),
+			_ => Err(RenderGraphError::MultipleSwapchainPasses),
+		}
+	}
+
+	fn extent(
+		&self,
+		image: GraphImageId,
+		window: vk::Extent2D,
+	) -> vk::Extent2D {
+		match &self.images[image.0].source {
+			ImageSource::Swapchain => window,
+			ImageSource::Transient(description) => description.size.extent(window),
+			ImageSource::Imported(imported) => imported.extent,
+		}
+	}
+
+	/// Walks the passes backwards keeping the ones that write something a
+	/// kept pass reads, or something outside the graph.
+	fn cull(
+		&self,
+	) -> Vec<bool> {
+		let mut kept = vec![false; self.passes.len()];
+		let mut needed = HashSet::new();
+		for (i, pass) in self.passes.iter().enumerate().rev() {
+			let attachments = pass.colors.iter().map(|x| (x.image, x.clear.is_some())).chain(pass.depth.iter().map(|x| (x.image, x.clear.is_some())));
+			let external = pass.buffers.iter().any(|x| x.write) || attachments.clone().any(
+				|(image, _)|
+				!matches!(self.images[image.0].source, ImageSource::Transient(_))
+			);
+			if !pass.keep && !external && !attachments.clone().any(|(image, _)| needed.contains(&image)) {
+				continue;
+			}
+			kept[i] = true;
+			// Cleared attachments don't need what earlier passes wrote.
+			for (image, clear) in attachments {
+				match clear {
+					true => needed.remove(&image),
+					false => needed.insert(image),
+				};
+			}
+			needed.extend(pass.sampled.iter().copied());
+		}
+		kept
+	}
+
+	/// Works out the barriers before each kept pass, in the order the
+	/// passes were added, and the ones putting imported images back into
+	/// their layout at the end.
+	fn plan(
+		&mut self,
+		kept: &[bool],
+	) {
+		let mut images: Vec<AccessState> = self.images.iter().map(
+			|x|
+			match &x.source {
+				ImageSource::Imported(imported) => AccessState {
+					layout: imported.layout,
+					stages: vk::PipelineStageFlags::ALL_COMMANDS,
+					access: vk::AccessFlags::MEMORY_WRITE,
+				},
+				_ => AccessState {
+					layout: vk::ImageLayout::UNDEFINED,
+					stages: vk::PipelineStageFlags::empty(),
+					access: vk::AccessFlags::empty(),
+				},
+			}
+		).collect();
+		let mut buffers: Vec<Option<AccessState>> = vec![None; self.buffers.len()];
+		for (i, pass) in self.passes.iter().enumerate().filter(|(i, _)| kept[*i]) {
+			let mut barriers = Barriers::default();
+			let swapchain = pass.colors.iter().any(|x| x.image == RenderGraph::SWAPCHAIN);
+			let uses = pass.colors.iter().map(
+				|x|
+				(x.image, x.clear.is_some(), COLOR_ATTACHMENT)
+			).chain(pass.depth.iter().map(
+				|x|
+				(x.image, x.clear.is_some(), DEPTH_ATTACHMENT)
+			)).chain(pass.sampled.iter().map(
+				|x|
+				(*x, false, SAMPLED)
+			));
+			for (image, clear, next) in uses {
+				// The scene's render pass transitions the swapchain itself.
+				if image == RenderGraph::SWAPCHAIN {
+					continue;
+				}
+				let state = &mut images[image.0];
+				if state.layout == next.layout && !is_write(state.access) && !is_write(next.access) {
+					state.stages |= next.stages;
+					state.access |= next.access;
+					continue;
+				}
+				barriers.src_stages |= state.stages;
+				barriers.dst_stages |= next.stages;
+				barriers.images.push(ImageBarrier {
+					image,
+					old_layout: match clear {
+						true => vk::ImageLayout::UNDEFINED,
+						false => state.layout,
+					},
+					new_layout: next.layout,
+					src_access: state.access,
+					dst_access: next.access,
+				});
+				*state = next;
+			}
+			for access in pass.buffers.iter() {
+				let next = AccessState {
+					layout: vk::ImageLayout::UNDEFINED,
+					stages: access.stages,
+					access: buffer_access(access.stages, access.write),
+				};
+				let state = &mut buffers[access.buffer.0];
+				match state {
+					Some(state) if is_write(state.access) || access.write => {
+						barriers.src_stages |= state.stages;
+						barriers.dst_stages |= next.stages;
+						barriers.buffers.push(BufferBarrier {
+							buffer: access.buffer,
+							src_access: state.access,
+							dst_access: next.access,
+						});
+						*state = next;
+					},
+					Some(state) => {
+						state.stages |= next.stages;
+						state.access |= next.access;
+					},
+					None => *state = Some(next),
+				}
+			}
+			self.compiled.push(CompiledPass {
+				pass: i,
+				barriers,
+				render_pass: None,
+				framebuffer: vk::Framebuffer::null(),
+				extent: vk::Extent2D::default(),
+				swapchain,
+			});
+		}
+		self.final_barriers = Barriers::default();
+		for (i, image) in self.images.iter().enumerate() {
+			if let ImageSource::Imported(imported) = &image.source {
+				let state = images[i];
+				if state.layout == imported.layout && !is_write(state.access) {
+					continue;
+				}
+				self.final_barriers.src_stages |= state.stages;
+				self.final_barriers.dst_stages |= vk::PipelineStageFlags::BOTTOM_OF_PIPE;
+				self.final_barriers.images.push(ImageBarrier {
+					image: GraphImageId(i),
+					old_layout: state.layout,
+					new_layout: imported.layout,
+					src_access: state.access,
+					dst_access: vk::AccessFlags::empty(),
+				});
+			}
+		}
+	}
+
+	/// Attachments stay in their attachment layout for the whole pass, the
+	/// barriers before it do the transitions.
+	fn create_render_pass(
+		&self,
+		program_data: &ProgramData,
+		pass: &GraphPass,
+	) -> vk::RenderPass { unsafe {
+		let load_op = |clear: bool| match clear {
+			true => vk::AttachmentLoadOp::CLEAR,
+			false => vk::AttachmentLoadOp::LOAD,
+		};
+		let attachment = |image: GraphImageId, clear: bool, layout: vk::ImageLayout| {
+			vk::AttachmentDescription::builder()
+				.format(self.images[image.0].format())
+				.samples(vk::SampleCountFlags::TYPE_1)
+				.load_op(load_op(clear))
+				.store_op(vk::AttachmentStoreOp::STORE)
+				.stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+				.stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+				.initial_layout(layout)
+				.final_layout(layout)
+				.build()
+		};
+		let mut attachments: Vec<vk::AttachmentDescription> = pass.colors.iter().map(
+			|x|
+			attachment(x.image, x.clear.is_some(), COLOR_ATTACHMENT.layout)
+		).collect();
+		let color_references: Vec<vk::AttachmentReference> = (0..pass.colors.len()).map(
+			|x|
+			vk::AttachmentReference {
+				attachment: x as u32,
+				layout: COLOR_ATTACHMENT.layout,
+			}
+		).collect();
+		let depth_reference = vk::AttachmentReference {
+			attachment: attachments.len() as u32,
+			layout: DEPTH_ATTACHMENT.layout,
+		};
+		let mut subpass = vk::SubpassDescription::builder()
+			.pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
+			.color_attachments(&color_references);
+		if let Some(depth) = pass.depth {
+			attachments.push(attachment(depth.image, depth.clear.is_some(), DEPTH_ATTACHMENT.layout));
+			subpass = subpass.depth_stencil_attachment(&depth_reference);
+		}
+		let subpasses = [subpass.build()];
+		let render_pass_info = vk::RenderPassCreateInfo::builder()
+			.attachments(&attachments)
+			.subpasses(&subpasses)
+			.build();
+		program_data.device.device.create_render_pass(
+			&render_pass_info,
+			None,
+		).unwrap()
+	}}
+
+	fn create_framebuffers(
+		&mut self,
+		program_data: &ProgramData,
+	) { unsafe {
+		for compiled in self.compiled.iter_mut() {
+			let pass = &self.passes[compiled.pass];
+			let handles: Vec<(vk::Image, vk::ImageView, vk::Extent2D)> = pass.images().take(pass.attachment_count()).filter_map(
+				|x|
+				self.images[x.0].handles()
+			).collect();
+			compiled.extent = handles.first().map_or(program_data.window.extent, |x| x.2);
+			let render_pass = match compiled.render_pass {
+				Some(render_pass) => render_pass,
+				None => continue,
+			};
+			let attachments: Vec<vk::ImageView> = handles.iter().map(|x| x.1).collect();
+			let framebuffer_info = vk::FramebufferCreateInfo::builder()
+				.render_pass(render_pass)
+				.attachments(&attachments)
+				.width(compiled.extent.width)
+				.height(compiled.extent.height)
+				.layers(1)
+				.build();
+			compiled.framebuffer = program_data.device.device.create_framebuffer(
+				&framebuffer_info,
+				None,
+			).unwrap();
+		}
+	}}
+
+	fn destroy_framebuffers(
+		&mut self,
+		program_data: &ProgramData,
+	) { unsafe {
+		for compiled in self.compiled.iter_mut() {
+			if compiled.framebuffer != vk::Framebuffer::null() {
+				program_data.device.device.destroy_framebuffer(
+					compiled.framebuffer,
+					None,
+				);
+				compiled.framebuffer = vk::Framebuffer::null();
+			}
+		}
+	}}
+
+	/// Recreates the window sized images and the framebuffers, the render
+	/// passes and the pipelines built for them stay valid.
+	pub fn resize(
+		&mut self,
+		program_data: &ProgramData,
+	) {
+		self.destroy_framebuffers(program_data);
+		for image in self.images.iter_mut() {
+			image.free(program_data);
+			image.allocate(program_data);
+		}
+		self.create_framebuffers(program_data);
+		self.rewrite_bindings(&program_data.device);
+	}
+
+	/// Records every kept pass into the scene's draw command buffer.
+	/// `framebuffer` is the scene's framebuffer of this frame's swapchain
+	/// image.
+	pub fn execute(
+		&mut self,
+		program_data: &ProgramData,
+		framebuffer: vk::Framebuffer,
+		frame: usize,
+		buckets: &mut [Box<Bucket>],
+	) { unsafe {
+		assert!(!self.compiled.is_empty(), "attempting to execute a render graph that isn't compiled");
+		let device: &vpb::Device = &program_data.device;
+		let command_buffer = program_data.command_buffer_draw.command_buffer;
+		let RenderGraph { images, buffers, passes, compiled: compiled_passes, final_barriers, .. } = self;
+		for compiled in compiled_passes.iter() {
+			record_barriers(
+				device,
+				command_buffer,
+				&compiled.barriers,
+				images,
+				buffers,
+			);
+			let named = |name: &String| passes.iter().any(|x| x.buckets.contains(name));
+			let assigned: Vec<usize> = buckets.iter().enumerate().filter(
+				|(_, bucket)|
+				passes[compiled.pass].buckets.contains(&bucket.name) || (compiled.swapchain && !named(&bucket.name))
+			).map(|(i, _)| i).collect();
+			let pass = &mut passes[compiled.pass];
+			if compiled.swapchain {
+				program_data.render_pass.open(
+					&program_data.device,
+					&program_data.window.extent,
+					&framebuffer,
+					&command_buffer,
+				);
+				for i in assigned {
+					buckets[i].render(
+						device,
+						command_buffer,
+						frame,
+					);
+				}
+				if let Some(recorder) = pass.recorder.as_mut() {
+					recorder(device, command_buffer, frame);
+				}
+				program_data.render_pass.close(
+					&program_data.device,
+					&program_data.command_buffer_draw,
+				);
+				continue;
+			}
+			if let Some(render_pass) = compiled.render_pass {
+				let clear_values: Vec<vk::ClearValue> = pass.colors.iter().map(
+					|x|
+					vk::ClearValue { color: vk::ClearColorValue { float32: x.clear.unwrap_or_default() } }
+				).chain(pass.depth.iter().map(
+					|x|
+					vk::ClearValue { depth_stencil: vk::ClearDepthStencilValue { depth: x.clear.unwrap_or(1.0), stencil: 0 } }
+				)).collect();
+				let render_pass_begin_info = vk::RenderPassBeginInfo::builder()
+					.render_pass(render_pass)
+					.framebuffer(compiled.framebuffer)
+					.render_area(compiled.extent.into())
+					.clear_values(&clear_values)
+					.build();
+				device.device.cmd_begin_render_pass(
+					command_buffer,
+					&render_pass_begin_info,
+					vk::SubpassContents::INLINE,
+				);
+			}
+			for i in assigned {
+				buckets[i].render_into(
+					device,
+					command_buffer,
+					frame,
+					Some(compiled.extent),
+				);
+			}
+			if let Some(recorder) = pass.recorder.as_mut() {
+				recorder(device, command_buffer, frame);
+			}
+			if compiled.render_pass.is_some() {
+				device.device.cmd_end_render_pass(command_buffer);
+			}
+		}
+		record_barriers(
+			device,
+			command_buffer,
+			final_barriers,
+			images,
+			buffers,
+		);
+	}}
+
+	fn destroy_compiled(
+		&mut self,
+		program_data: &ProgramData,
+	) { unsafe {
+		self.destroy_framebuffers(program_data);
+		for compiled in self.compiled.drain(..) {
+			if let Some(render_pass) = compiled.render_pass {
+				program_data.device.device.destroy_render_pass(
+					render_pass,
+					None,
+				);
+			}
+		}
+		for image in self.images.iter_mut() {
+			image.free(program_data);
+		}
+	}}
+
+	/// Frees the images, render passes and framebuffers. Buckets with
+	/// pipelines built for the graph's passes must be destroyed first.
+	pub fn destroy(
+		&mut self,
+		program_data: &ProgramData,
+	) {
+		self.destroy_compiled(program_data);
+		if let Some(mut sampler) = self.sampler.take() {
+			sampler.destroy(program_data);
+		}
+		self.bindings.clear();
+	}
+}
+
+const COLOR_ATTACHMENT: AccessState = AccessState {
+	layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+	stages: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+	access: vk::AccessFlags::from_raw(
+		vk::AccessFlags::COLOR_ATTACHMENT_READ.as_raw() |
+		vk::AccessFlags::COLOR_ATTACHMENT_WRITE.as_raw()
+	),
+};
+
+const DEPTH_ATTACHMENT: AccessState = AccessState {
+	layout: vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+	stages: vk::PipelineStageFlags::from_raw(
+		vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS.as_raw() |
+		vk::PipelineStageFlags::LATE_FRAGMENT_TESTS.as_raw()
+	),
+	access: vk::AccessFlags::from_raw(
+		vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ.as_raw() |
+		vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE.as_raw()
+	),
+};
+
+const SAMPLED: AccessState = AccessState {
+	layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+	stages: vk::PipelineStageFlags::from_raw(
+		vk::PipelineStageFlags::VERTEX_SHADER.as_raw() |
+		vk::PipelineStageFlags::FRAGMENT_SHADER.as_raw()
+	),
+	access: vk::AccessFlags::SHADER_READ,
+};
+
+fn is_write(
+	access: vk::AccessFlags,
+) -> bool {
+	access.intersects(
+		vk::AccessFlags::COLOR_ATTACHMENT_WRITE |
+		vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE |
+		vk::AccessFlags::SHADER_WRITE |
+		vk::AccessFlags::TRANSFER_WRITE |
+		vk::AccessFlags::HOST_WRITE |
+		vk::AccessFlags::MEMORY_WRITE
+	)
+}
+
+/// Accesses a buffer can see in `stages`, so the barrier only names ones
+/// the stages support.
+fn buffer_access(
+	stages: vk::PipelineStageFlags,
+	write: bool,
+) -> vk::AccessFlags {
+	let shader_stages =
+		vk::PipelineStageFlags::VERTEX_SHADER |
+		vk::PipelineStageFlags::FRAGMENT_SHADER |
+		vk::PipelineStageFlags::COMPUTE_SHADER;
+	let mut access = vk::AccessFlags::empty();
+	if stages.intersects(shader_stages) {
+		access |= match write {
+			true => vk::AccessFlags::SHADER_WRITE,
+			false => vk::AccessFlags::SHADER_READ | vk::AccessFlags::UNIFORM_READ,
+		};
+	}
+	if stages.contains(vk::PipelineStageFlags::TRANSFER) {
+		access |= match write {
+			true => vk::AccessFlags::TRANSFER_WRITE,
+			false => vk::AccessFlags::TRANSFER_READ,
+		};
+	}
+	if stages.contains(vk::PipelineStageFlags::VERTEX_INPUT) && !write {
+		access |= vk::AccessFlags::VERTEX_ATTRIBUTE_READ | vk::AccessFlags::INDEX_READ;
+	}
+	if stages.contains(vk::PipelineStageFlags::DRAW_INDIRECT) && !write {
+		access |= vk::AccessFlags::INDIRECT_COMMAND_READ;
+	}
+	match access.is_empty() {
+		true => match write {
+			true => vk::AccessFlags::MEMORY_WRITE,
+			false => vk::AccessFlags::MEMORY_READ,
+		},
+		false => access,
+	}
+}
+
+fn record_barriers(
+	device: &vpb::Device,
+	command_buffer: vk::CommandBuffer,
+	barriers: &Barriers,
+	images: &[GraphImage],
+	buffers: &[GraphBuffer],
+) { unsafe {
+	if barriers.is_empty() {
+		return;
+	}
+	let image_barriers: Vec<vk::ImageMemoryBarrier> = barriers.images.iter().filter_map(
+		|x| {
+			let image = &images[x.image.0];
+			let (handle, _, _) = image.handles()?;
+			// Depth and stencil of a combined format transition together.
+			let aspect = match image.format() {
+				vk::Format::D16_UNORM_S8_UINT | vk::Format::D24_UNORM_S8_UINT | vk::Format::D32_SFLOAT_S8_UINT => vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL,
+				_ => image.aspect(),
+			};
+			Some(vk::ImageMemoryBarrier::builder()
+				.image(handle)
+				.old_layout(x.old_layout)
+				.new_layout(x.new_layout)
+				.src_access_mask(x.src_access)
+				.dst_access_mask(x.dst_access)
+				.src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+				.dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+				.subresource_range(
+					vk::ImageSubresourceRange::builder()
+						.aspect_mask(aspect)
+						.level_count(vk::REMAINING_MIP_LEVELS)
+						.layer_count(vk::REMAINING_ARRAY_LAYERS)
+						.build()
+				).build())
+		}
+	).collect();
+	let buffer_barriers: Vec<vk::BufferMemoryBarrier> = barriers.buffers.iter().map(
+		|x|
+		vk::BufferMemoryBarrier::builder()
+			.buffer(buffers[x.buffer.0].buffer)
+			.offset(0)
+			.size(vk::WHOLE_SIZE)
+			.src_access_mask(x.src_access)
+			.dst_access_mask(x.dst_access)
+			.src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+			.dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+			.build()
+	).collect();
+	let or_top = |x: vk::PipelineStageFlags| match x.is_empty() {
+		true => vk::PipelineStageFlags::TOP_OF_PIPE,
+		false => x,
+	};
+	device.device.cmd_pipeline_barrier(
+		command_buffer,
+		or_top(barriers.src_stages),
+		or_top(barriers.dst_stages),
+		vk::DependencyFlags::empty(),
+		&[],
+		&buffer_barriers,
+		&image_barriers,
+	);
+}}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const WINDOW: vk::Extent2D = vk::Extent2D { width: 800, height: 600 };
+
+	fn color_image(
+		graph: &mut RenderGraph,
+		name: &str,
+	) -> GraphImageId {
+		graph.create_image(
+			name,
+			GraphImageDescription::color(vk::Format::R8G8B8A8_UNORM),
+		)
+	}
+
+	fn shadow_image(
+		graph: &mut RenderGraph,
+	) -> GraphImageId {
+		graph.import_image(
+			"shadow",
+			GraphImportedImage {
+				image: vk::Image::null(),
+				image_view: vk::ImageView::null(),
+				format: vk::Format::D32_SFLOAT,
+				extent: vk::Extent2D { width: 1024, height: 1024 },
+				layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+			},
+		)
+	}
+
+	fn transitions(
+		barriers: &Barriers,
+	) -> Vec<(GraphImageId, vk::ImageLayout, vk::ImageLayout)> {
+		barriers.images.iter().map(|x| (x.image, x.old_layout, x.new_layout)).collect()
+	}
+
+	#[test]
+	fn culls_passes_nothing_reads() {
+		let mut graph = RenderGraph::new();
+		let unused = color_image(&mut graph, "unused");
+		let scene = color_image(&mut graph, "scene");
+		graph.add_pass(GraphPass::new("unused").clear_color(unused, [0.0; 4]));
+		graph.add_pass(GraphPass::new("kept").clear_color(unused, [0.0; 4]).keep());
+		graph.add_pass(GraphPass::new("scene").clear_color(scene, [0.0; 4]));
+		graph.add_pass(GraphPass::new("present").color(RenderGraph::SWAPCHAIN).sample(scene));
+		assert_eq!(graph.cull(), vec![false, true, true, true]);
+	}
+
+	#[test]
+	fn keeps_passes_writing_outside_the_graph() {
+		let mut graph = RenderGraph::new();
+		let shadow = shadow_image(&mut graph);
+		let particles = graph.import_buffer("particles", vk::Buffer::null());
+		graph.add_pass(GraphPass::new("shadow").clear_depth(shadow, 1.0));
+		graph.add_pass(GraphPass::new("simulate").write_buffer(particles, vk::PipelineStageFlags::COMPUTE_SHADER));
+		graph.add_pass(GraphPass::new("read").read_buffer(particles, vk::PipelineStageFlags::VERTEX_SHADER));
+		graph.add_pass(GraphPass::new("present").color(RenderGraph::SWAPCHAIN));
+		assert_eq!(graph.cull(), vec![true, true, false, true]);
+	}
+
+	#[test]
+	fn rejects_reads_before_writes() {
+		let mut graph = RenderGraph::new();
+		let scene = color_image(&mut graph, "scene");
+		graph.add_pass(GraphPass::new("present").color(RenderGraph::SWAPCHAIN).sample(scene));
+		graph.add_pass(GraphPass::new("scene").clear_color(scene, [0.0; 4]));
+		assert!(matches!(
+			graph.validate(WINDOW),
+			Err(RenderGraphError::ReadBeforeWrite { pass, image }) if pass == "present" && image == "scene"
+		));
+	}
+
+	#[test]
+	fn plans_transitions_between_passes() {
+		let mut graph = RenderGraph::new();
+		let shadow = shadow_image(&mut graph);
+		let scene = color_image(&mut graph, "scene");
+		let particles = graph.import_buffer("particles", vk::Buffer::null());
+		graph.add_pass(GraphPass::new("shadow").clear_depth(shadow, 1.0).write_buffer(particles, vk::PipelineStageFlags::COMPUTE_SHADER));
+		graph.add_pass(GraphPass::new("scene").clear_color(scene, [0.0; 4]).sample(shadow).read_buffer(particles, vk::PipelineStageFlags::VERTEX_SHADER));
+		graph.add_pass(GraphPass::new("present").color(RenderGraph::SWAPCHAIN).sample(scene));
+		assert!(graph.validate(WINDOW).is_ok());
+		let kept = graph.cull();
+		graph.plan(&kept);
+		assert_eq!(graph.compiled.len(), 3);
+		assert_eq!(
+			transitions(&graph.compiled[0].barriers),
+			vec![(shadow, vk::ImageLayout::UNDEFINED, vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)],
+		);
+		assert_eq!(
+			transitions(&graph.compiled[1].barriers),
+			vec![
+				(scene, vk::ImageLayout::UNDEFINED, vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL),
+				(shadow, vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL),
+			],
+		);
+		assert!(graph.compiled[0].barriers.buffers.is_empty());
+		assert_eq!(graph.compiled[1].barriers.buffers.len(), 1);
+		assert_eq!(graph.compiled[1].barriers.buffers[0].src_access, vk::AccessFlags::SHADER_WRITE);
+		assert!(graph.compiled[1].barriers.src_stages.contains(vk::PipelineStageFlags::COMPUTE_SHADER));
+		assert_eq!(
+			transitions(&graph.compiled[2].barriers),
+			vec![(scene, vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)],
+		);
+		assert!(graph.compiled[2].swapchain);
+		// The shadow map ends up sampled, already the layout it was imported in.
+		assert!(graph.final_barriers.is_empty());
+	}
+}
@@ -0,0 +1,185 @@
+use ash::vk;
+
+use crate::{GraphImageId, GraphBufferId};
+
+/// Pass of a `RenderGraph`, returned by `add_pass`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct GraphPassId(pub(crate) usize);
+
+/// Records commands of a pass after its buckets, inside its render pass
+/// when it has attachments. Gets the frame index.
+pub type GraphPassRecorder = Box<dyn FnMut(&vpb::Device, vk::CommandBuffer, usize)>;
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct ColorAttachment {
+	pub image: GraphImageId,
+	/// Cleared to this at the start of the pass, loaded when `None`.
+	pub clear: Option<[f32; 4]>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct DepthAttachment {
+	pub image: GraphImageId,
+	pub clear: Option<f32>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct BufferAccess {
+	pub buffer: GraphBufferId,
+	pub stages: vk::PipelineStageFlags,
+	pub write: bool,
+}
+
+/// What a pass draws into and what it reads, built up before being added
+/// to a `RenderGraph`. Every attachment of a pass must have the same size.
+pub struct GraphPass {
+	pub(crate) name: String,
+	pub(crate) colors: Vec<ColorAttachment>,
+	pub(crate) depth: Option<DepthAttachment>,
+	pub(crate) sampled: Vec<GraphImageId>,
+	pub(crate) buffers: Vec<BufferAccess>,
+	pub(crate) buckets: Vec<String>,
+	pub(crate) keep: bool,
+	pub(crate) recorder: Option<GraphPassRecorder>,
+}
+
+impl GraphPass {
+	pub fn new(
+		name: &str,
+	) -> Self {
+		Self {
+			name: name.to_string(),
+			colors: Vec::new(),
+			depth: None,
+			sampled: Vec::new(),
+			buffers: Vec::new(),
+			buckets: Vec::new(),
+			keep: false,
+			recorder: None,
+		}
+	}
+
+	/// Draws over the image's current contents. `RenderGraph::SWAPCHAIN`
+	/// makes this the pass into the scene's own render pass, which must
+	/// be its only attachment.
+	pub fn color(
+		mut self,
+		image: GraphImageId,
+	) -> Self {
+		self.colors.push(ColorAttachment {
+			image,
+			clear: None,
+		});
+		self
+	}
+
+	pub fn clear_color(
+		mut self,
+		image: GraphImageId,
+		clear: [f32; 4],
+	) -> Self {
+		self.colors.push(ColorAttachment {
+			image,
+			clear: Some(clear),
+		});
+		self
+	}
+
+	pub fn depth(
+		mut self,
+		image: GraphImageId,
+	) -> Self {
+		self.depth = Some(DepthAttachment {
+			image,
+			clear: None,
+		});
+		self
+	}
+
+	pub fn clear_depth(
+		mut self,
+		image: GraphImageId,
+		clear: f32,
+	) -> Self {
+		self.depth = Some(DepthAttachment {
+			image,
+			clear: Some(clear),
+		});
+		self
+	}
+
+	/// Reads the image in vertex or fragment shaders, bind it with
+	/// `RenderGraph::bind`.
+	pub fn sample(
+		mut self,
+		image: GraphImageId,
+	) -> Self {
+		self.sampled.push(image);
+		self
+	}
+
+	pub fn read_buffer(
+		mut self,
+		buffer: GraphBufferId,
+		stages: vk::PipelineStageFlags,
+	) -> Self {
+		self.buffers.push(BufferAccess {
+			buffer,
+			stages,
+			write: false,
+		});
+		self
+	}
+
+	pub fn write_buffer(
+		mut self,
+		buffer: GraphBufferId,
+		stages: vk::PipelineStageFlags,
+	) -> Self {
+		self.buffers.push(BufferAccess {
+			buffer,
+			stages,
+			write: true,
+		});
+		self
+	}
+
+	/// Draws the named bucket in this pass. Buckets no pass names are
+	/// drawn in the swapchain pass.
+	pub fn bucket(
+		mut self,
+		name: &str,
+	) -> Self {
+		self.buckets.push(name.to_string());
+		self
+	}
+
+	/// Never culled, even when nothing reads what it writes.
+	pub fn keep(
+		mut self,
+	) -> Self {
+		self.keep = true;
+		self
+	}
+
+	pub fn record<F: FnMut(&vpb::Device, vk::CommandBuffer, usize) + 'static>(
+		mut self,
+		recorder: F,
+	) -> Self {
+		self.recorder = Some(Box::new(recorder));
+		self
+	}
+
+	/// Every image the pass uses, attachments first.
+	pub(crate) fn images(
+		&self,
+	) -> impl Iterator<Item = GraphImageId> + '_ {
+		self.colors.iter().map(|x| x.image).chain(self.depth.iter().map(|x| x.image)).chain(self.sampled.iter().copied())
+	}
+
+	pub(crate) fn attachment_count(
+		&self,
+	) -> usize {
+		self.colors.len() + self.depth.iter().count()
+	}
+}
@@ -0,0 +1,229 @@
+use ash::vk;
+use vpb::ProgramData;
+
+use crate::{create_device_image, destroy_device_image};
+
+/// Image of a `RenderGraph`, `RenderGraph::SWAPCHAIN` or one made with
+/// `create_image` or `import_image`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct GraphImageId(pub(crate) usize);
+
+/// Buffer of a `RenderGraph` made with `import_buffer`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct GraphBufferId(pub(crate) usize);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ImageSize {
+	/// Follows the window, `scale` of its size.
+	Window { scale: f32 },
+	Fixed { width: u32, height: u32 },
+}
+
+impl ImageSize {
+	pub fn extent(
+		&self,
+		window: vk::Extent2D,
+	) -> vk::Extent2D {
+		match *self {
+			ImageSize::Window { scale } => vk::Extent2D {
+				width: ((window.width as f32 * scale) as u32).max(1),
+				height: ((window.height as f32 * scale) as u32).max(1),
+			},
+			ImageSize::Fixed { width, height } => vk::Extent2D { width, height },
+		}
+	}
+}
+
+/// Image the graph allocates itself. Its contents only live from the pass
+/// that first writes it to the last one that reads it each frame.
+#[derive(Clone, Copy, Debug)]
+pub struct GraphImageDescription {
+	pub format: vk::Format,
+	pub size: ImageSize,
+}
+
+impl GraphImageDescription {
+	pub fn new(
+		format: vk::Format,
+		size: ImageSize,
+	) -> Self {
+		Self {
+			format,
+			size,
+		}
+	}
+
+	/// Window sized color target.
+	pub fn color(
+		format: vk::Format,
+	) -> Self {
+		GraphImageDescription::new(
+			format,
+			ImageSize::Window { scale: 1.0 },
+		)
+	}
+
+	/// Window sized depth target.
+	pub fn depth(
+	) -> Self {
+		GraphImageDescription::new(
+			vk::Format::D32_SFLOAT,
+			ImageSize::Window { scale: 1.0 },
+		)
+	}
+}
+
+/// Image owned outside the graph, such as the shadow atlas. It's expected
+/// in `layout` when the graph starts and is put back into it at the end.
+#[derive(Clone, Copy, Debug)]
+pub struct GraphImportedImage {
+	pub image: vk::Image,
+	pub image_view: vk::ImageView,
+	pub format: vk::Format,
+	pub extent: vk::Extent2D,
+	pub layout: vk::ImageLayout,
+}
+
+pub(crate) enum ImageSource {
+	Swapchain,
+	Transient(GraphImageDescription),
+	Imported(GraphImportedImage),
+}
+
+/// Memory of a transient image, recreated with the window.
+pub(crate) struct AllocatedImage {
+	pub image: vk::Image,
+	pub allocation: vk_mem::Allocation,
+	pub image_view: vk::ImageView,
+	pub extent: vk::Extent2D,
+}
+
+pub(crate) struct GraphImage {
+	pub name: String,
+	pub source: ImageSource,
+	/// Every way a kept pass uses the image, gathered by `compile`.
+	pub usage: vk::ImageUsageFlags,
+	pub allocated: Option<AllocatedImage>,
+}
+
+impl GraphImage {
+	pub fn format(
+		&self,
+	) -> vk::Format {
+		match &self.source {
+			ImageSource::Swapchain => vk::Format::UNDEFINED,
+			ImageSource::Transient(description) => description.format,
+			ImageSource::Imported(imported) => imported.format,
+		}
+	}
+
+	pub fn aspect(
+		&self,
+	) -> vk::ImageAspectFlags {
+		match is_depth_format(self.format()) {
+			true => vk::ImageAspectFlags::DEPTH,
+			false => vk::ImageAspectFlags::COLOR,
+		}
+	}
+
+	/// `None` for the swapchain, or a transient image before `compile`.
+	pub fn handles(
+		&self,
+	) -> Option<(vk::Image, vk::ImageView, vk::Extent2D)> {
+		match (&self.source, &self.allocated) {
+			(ImageSource::Imported(imported), _) => Some((imported.image, imported.image_view, imported.extent)),
+			(ImageSource::Transient(_), Some(allocated)) => Some((allocated.image, allocated.image_view, allocated.extent)),
+			_ => None,
+		}
+	}
+
+	/// Allocates a transient image for the current window size, other
+	/// images are left alone.
+	pub fn allocate(
+		&mut self,
+		program_data: &ProgramData,
+	) { unsafe {
+		let description = match &self.source {
+			ImageSource::Transient(description) => *description,
+			_ => return,
+		};
+		if self.usage.is_empty() {
+			return;
+		}
+		let extent = description.size.extent(program_data.window.extent);
+		let image_info = vk::ImageCreateInfo::builder()
+			.image_type(vk::ImageType::TYPE_2D)
+			.format(description.format)
+			.extent(vk::Extent3D { width: extent.width, height: extent.height, depth: 1 })
+			.mip_levels(1)
+			.array_layers(1)
+			.samples(vk::SampleCountFlags::TYPE_1)
+			.tiling(vk::ImageTiling::OPTIMAL)
+			.usage(self.usage)
+			.sharing_mode(vk::SharingMode::EXCLUSIVE)
+			.initial_layout(vk::ImageLayout::UNDEFINED)
+			.build();
+		let (image, allocation) = create_device_image(
+			program_data,
+			&image_info,
+		);
+		let image_view_info = vk::ImageViewCreateInfo::builder()
+			.image(image)
+			.view_type(vk::ImageViewType::TYPE_2D)
+			.format(description.format)
+			.subresource_range(
+				vk::ImageSubresourceRange::builder()
+					.aspect_mask(self.aspect())
+					.level_count(1)
+					.layer_count(1)
+					.build()
+			).build();
+		let image_view = program_data.device.device.create_image_view(
+			&image_view_info,
+			None,
+		).unwrap();
+		self.allocated = Some(AllocatedImage {
+			image,
+			allocation,
+			image_view,
+			extent,
+		});
+	}}
+
+	pub fn free(
+		&mut self,
+		program_data: &ProgramData,
+	) { unsafe {
+		if let Some(allocated) = self.allocated.take() {
+			let device = &program_data.device.device;
+			device.destroy_image_view(
+				allocated.image_view,
+				None,
+			);
+			destroy_device_image(
+				program_data,
+				allocated.image,
+				&allocated.allocation,
+			);
+		}
+	}}
+}
+
+pub(crate) struct GraphBuffer {
+	pub name: String,
+	pub buffer: vk::Buffer,
+}
+
+pub fn is_depth_format(
+	format: vk::Format,
+) -> bool {
+	matches!(
+		format,
+		vk::Format::D16_UNORM |
+		vk::Format::X8_D24_UNORM_PACK32 |
+		vk::Format::D32_SFLOAT |
+		vk::Format::D16_UNORM_S8_UINT |
+		vk::Format::D24_UNORM_S8_UINT |
+		vk::Format::D32_SFLOAT_S8_UINT
+	)
+}
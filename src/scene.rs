@@ -1,12 +1,18 @@
 mod bucket;
+mod registry;
+pub use registry::*;
+mod serialization;
+pub use serialization::*;
 
-use std::{sync::Arc, marker::PhantomData, time::Instant};
+use std::{sync::Arc, marker::PhantomData, time::Instant, collections::BTreeSet};
 
 use ash::{vk, prelude::VkResult};
 pub use bucket::*;
 use vpb::{create_depth_image, create_presentation_images, ProgramData};
 
-use crate::{VertexUI, pd_vdevice, pd_device, InputState, RenderState, RenderStateLocal, pipelines::ui_example::PipelineUIExample, EnginePipeline, CameraState3d, Camera};
+use shaderc::ShaderKind;
+
+use crate::{VertexUI, pd_vdevice, pd_device, InputState, RenderState, RenderStateLocal, pipelines::ui_example::PipelineUIExample, EnginePipeline, CameraState3d, Camera, ShaderWatcher, ShadowMaps, ShadowOptions, BlockLights, Timeline, RenderGraph, ShaderReloadError, PipelineCache, PIPELINE_CACHE_PATH, shaders_depending_on, reload_shader};
 
 pub struct Scene {
 	pub program_data: ProgramData,
@@ -20,13 +26,27 @@ pub struct Scene {
 	pub render_state: RenderState,
 	render_state_local: RenderStateLocal,
 	pub camera: Option<Arc<dyn Camera>>,
+	shader_watcher: Option<ShaderWatcher>,
+	/// Rendered before the main render pass when set, see `enable_shadows`.
+	pub shadow_maps: Option<Arc<ShadowMaps>>,
+	/// Written every frame into the lights block of each bucket, see
+	/// `Bucket::set_lights_block`. Assigned their shadow maps first.
+	pub lights: BlockLights,
+	/// Tweens and other tracks, advanced at the start of every `render`.
+	pub timeline: Timeline,
+	/// Runs in place of the single render pass into the swapchain when
+	/// set, see `set_render_graph`.
+	pub render_graph: Option<RenderGraph>,
+	/// Passed to every pipeline the scene builds, saved by the `Program`
+	/// on exit.
+	pub pipeline_cache: PipelineCache,
 }
 
 impl Scene {
 	pub fn new<FC>(
 		mut program_data: ProgramData,
 		initial_pipeline: (&str, FC),
-	) -> (Self, usize) where FC: Fn(&ProgramData) -> Arc<dyn EnginePipeline> { unsafe {
+	) -> (Self, usize) where FC: Fn(&ProgramData, vk::PipelineCache) -> Arc<dyn EnginePipeline> { unsafe {
 		let semaphore_create_info = vk::SemaphoreCreateInfo::builder().build();
 		let semaphore_present = program_data.device.device.create_semaphore(
 			&semaphore_create_info,
@@ -51,6 +71,10 @@ impl Scene {
 			program_data.device.physical_device,
 			program_data.frame_count,
 		));
+		let pipeline_cache = PipelineCache::load(
+			&program_data,
+			PIPELINE_CACHE_PATH,
+		);
 		let mut buckets: Vec<Box<Bucket>> = Vec::with_capacity(8);
 		let frame_count = program_data.frame_count;
 		let mut scene = Self {
@@ -67,6 +91,12 @@ impl Scene {
 				delta_timer: Instant::now(),
 			},
 			camera: None,
+			shader_watcher: None,
+			shadow_maps: None,
+			lights: BlockLights::default(),
+			timeline: Timeline::new(),
+			render_graph: None,
+			pipeline_cache,
 		};
 		scene.add_bucket(
 			initial_pipeline.0,
@@ -87,6 +117,103 @@ impl Scene {
 		self.build_view();
 	}
 
+	/// Creates the shadow atlas, replacing and destroying the previous one.
+	/// Pipelines sampling it are built with the returned maps, e.g.
+	/// `PipelineMeshExample::with_shadows`, the ones built with the
+	/// previous maps must be rebuilt.
+	pub fn enable_shadows(
+		&mut self,
+		options: ShadowOptions,
+	) -> Arc<ShadowMaps> {
+		self.disable_shadows();
+		let shadow_maps = Arc::new(ShadowMaps::new(
+			&self.program_data,
+			self.pipeline_cache.pipeline_cache,
+			options,
+		));
+		self.shadow_maps = Some(shadow_maps.clone());
+		shadow_maps
+	}
+
+	/// Destroys the shadow atlas. Buckets sampling it must be removed
+	/// first.
+	pub fn disable_shadows(
+		&mut self,
+	) {
+		if let Some(mut old) = self.shadow_maps.take() {
+			self.idle();
+			vpb::gmuc!(old).destroy(&self.program_data);
+		}
+	}
+
+	/// Renders through a graph compiled for this scene, replacing and
+	/// destroying the previous one. Buckets drawn into its offscreen passes
+	/// need pipelines built with `PipelineInfoBuilder::graph_pass`.
+	pub fn set_render_graph(
+		&mut self,
+		render_graph: Option<RenderGraph>,
+	) {
+		if let Some(mut old) = self.render_graph.take() {
+			self.idle();
+			old.destroy(&self.program_data);
+		}
+		self.render_graph = render_graph;
+	}
+
+	/// Starts polling the shader directory, pipelines get rebuilt when
+	/// their shaders change.
+	pub fn watch_shaders(
+		&mut self,
+	) {
+		self.shader_watcher = Some(ShaderWatcher::new());
+	}
+
+	/// Rebuilds the pipelines of changed shaders. A shader variant that
+	/// fails to compile keeps its last good code and the pipelines using
+	/// it keep running, the failures are returned. Pipelines that can't be
+	/// rebuilt, for example on an interface mismatch, are kept and logged.
+	pub fn reload_shaders(
+		&mut self,
+	) -> Vec<ShaderReloadError> {
+		let changed = match self.shader_watcher.as_mut() {
+			Some(shader_watcher) => shader_watcher.poll(),
+			None => return Vec::new(),
+		};
+		if changed.is_empty() {
+			return Vec::new();
+		}
+		// Changing an include reloads every shader that pulled it in.
+		let mut shader_names = BTreeSet::new();
+		for file_stem in changed.iter() {
+			shader_names.extend(shaders_depending_on(file_stem));
+		}
+		let mut errors = Vec::new();
+		for shader_name in shader_names.iter() {
+			errors.extend(reload_shader(shader_name));
+		}
+		let mut idle = false;
+		for bucket in self.buckets.iter_mut() {
+			let pipeline_info = bucket.engine_pipeline.get_pipeline_info();
+			if !shader_names.contains(&pipeline_info.name) {
+				continue;
+			}
+			if errors.iter().any(|x| x.shader_name == pipeline_info.name && x.defines == pipeline_info.defines) {
+				continue;
+			}
+			if !idle {
+				// Can't call `idle` while borrowing the bucket.
+				unsafe { self.program_data.device.device.device_wait_idle().unwrap(); }
+				idle = true;
+			}
+			drop(pipeline_info);
+			match bucket.recreate_pipeline() {
+				Ok(()) => log::info!("reloaded shader \"{}\" for bucket \"{}\"", bucket.engine_pipeline.get_pipeline_info().name, bucket.name),
+				Err(e) => log::warn!("bucket \"{}\" keeps its previous pipeline: {}", bucket.name, e),
+			}
+		}
+		errors
+	}
+
 	pub fn create_framebuffers(
 		program_data: &mut ProgramData,
 	) -> (Vec<vk::Framebuffer>, Vec<vk::ImageView>, vk::ImageView, vk::Image) { unsafe {
@@ -122,14 +249,66 @@ impl Scene {
 		&mut self,
 		name: &str,
 		creator: FC,
-	) where FC: Fn(&ProgramData) -> Arc<dyn EnginePipeline> {
+	) where FC: Fn(&ProgramData, vk::PipelineCache) -> Arc<dyn EnginePipeline> {
 		self.buckets.push(Box::new(Bucket::new(
 			name,
-			creator(&self.program_data),
+			creator(&self.program_data, self.pipeline_cache.pipeline_cache),
 			self.program_data.clone(),
 		)));
 	}
 
+	/// Adds a bucket whose pipeline is built from the registry, so the
+	/// bucket can be written to and read back from scene files.
+	pub fn add_registered_bucket(
+		&mut self,
+		name: &str,
+		pipeline_id: &str,
+		registry: &SceneRegistry,
+	) -> Result<(), SceneError> {
+		let bucket = self.create_registered_bucket(
+			name,
+			pipeline_id,
+			registry,
+			self.camera.clone(),
+		)?;
+		self.buckets.push(Box::new(bucket));
+		Ok(())
+	}
+
+	fn create_registered_bucket(
+		&self,
+		name: &str,
+		pipeline_id: &str,
+		registry: &SceneRegistry,
+		camera: Option<Arc<dyn Camera>>,
+	) -> Result<Bucket, SceneError> {
+		let engine_pipeline = registry.create_pipeline(
+			pipeline_id,
+			&self.program_data,
+			self.pipeline_cache.pipeline_cache,
+			camera,
+		).ok_or_else(|| SceneError::UnknownPipeline(pipeline_id.to_string()))?;
+		let mut bucket = Bucket::new(
+			name,
+			engine_pipeline,
+			self.program_data.clone(),
+		);
+		bucket.pipeline_id = Some(pipeline_id.to_string());
+		Ok(bucket)
+	}
+
+	/// Destroys every bucket along with its pipeline and block state memory.
+	pub fn clear_buckets(
+		&mut self,
+	) {
+		self.idle();
+		for bucket in self.buckets.iter_mut() {
+			bucket.destroy_block_state_memory();
+			bucket.destroy_pipeline();
+		}
+		self.buckets.clear();
+	}
+
 	pub fn get_bucket(
 		&mut self,
 		name: &str,
@@ -216,6 +395,12 @@ impl Scene {
 	pub fn render(
 		&mut self,
 	) {
+		for error in self.reload_shaders() {
+			log::error!("{}, keeping the last good code", error);
+		}
+		// Last frame's delta time, so the view built below already sees
+		// tweened cameras.
+		self.timeline.update(&self.render_state);
 		self.build_view();
 		let resize: bool;
 		let present_index = match self.acquire_next_image() {
@@ -245,30 +430,60 @@ impl Scene {
 		self.render_state.delta_time = elapsed_micros as f32 / 1_000_000.0;
 		self.render_state.delta_time = self.render_state.delta_time.min(1.0);
 		// println!("{:.3}ms", self.render_state.delta_time * 1_000.0);
+		if let (Some(shadow_maps), Some(camera)) = (self.shadow_maps.as_ref(), self.camera.as_ref()) {
+			let wa_shadow_maps = vpb::gmuc_ref!(shadow_maps);
+			wa_shadow_maps.begin_frame();
+			wa_shadow_maps.assign(
+				&mut self.lights,
+				camera.as_ref(),
+			);
+		}
 		for bucket in self.buckets.iter_mut() {
 			bucket.update_blocks(
 				&self.input_state,
 				&self.render_state,
 				&self.program_data.command_buffer_draw.command_buffer,
 			);
+			bucket.update_lights(
+				&self.lights,
+				present_index,
+			);
 		}
-		self.program_data.render_pass.open(
-			&self.program_data.device,
-			&self.program_data.window.extent,
-			&self.framebuffers[present_index],
-			&self.program_data.command_buffer_draw.command_buffer,
-		);
-		for bucket in self.buckets.iter_mut() {
-			bucket.render(
+		if let Some(shadow_maps) = self.shadow_maps.as_ref() {
+			shadow_maps.render(
 				&self.program_data.device,
 				self.program_data.command_buffer_draw.command_buffer,
 				present_index,
+				&self.buckets,
 			);
 		}
-		self.program_data.render_pass.close(
-			&self.program_data.device,
-			&self.program_data.command_buffer_draw,
-		);
+		match self.render_graph.as_mut() {
+			Some(render_graph) => render_graph.execute(
+				&self.program_data,
+				self.framebuffers[present_index],
+				present_index,
+				&mut self.buckets,
+			),
+			None => {
+				self.program_data.render_pass.open(
+					&self.program_data.device,
+					&self.program_data.window.extent,
+					&self.framebuffers[present_index],
+					&self.program_data.command_buffer_draw.command_buffer,
+				);
+				for bucket in self.buckets.iter_mut() {
+					bucket.render(
+						&self.program_data.device,
+						self.program_data.command_buffer_draw.command_buffer,
+						present_index,
+					);
+				}
+				self.program_data.render_pass.close(
+					&self.program_data.device,
+					&self.program_data.command_buffer_draw,
+				);
+			},
+		}
 		self.program_data.command_buffer_draw.close(
 			&self.program_data.device,
 		);
@@ -381,9 +596,17 @@ impl Scene {
 		self.framebuffer_imageviews = present_image_views;
 		self.depth_image_view = depth_image_view;
 		// PIPELINES
+		let pipeline_timer = Instant::now();
 		for bucket in self.buckets.iter_mut() {
-			bucket.recreate_pipeline();
+			bucket.recreate_pipeline().unwrap_or_else(
+				|e| panic!("failed to recreate the pipeline of bucket \"{}\"\n{}", bucket.name, e)
+			);
 		}
+		log::debug!(
+			"recreated {} pipelines in {:.3}ms",
+			self.buckets.len(),
+			pipeline_timer.elapsed().as_micros() as f32 / 1_000.0,
+		);
 		// DESCRIPTOR POOL
 		let descriptor_pool = vpb::gmuc!(
 			self.program_data.descriptor_pool
@@ -396,6 +619,10 @@ impl Scene {
 		for bucket in self.buckets.iter_mut() {
 			bucket.recreate_block_state_memory();
 		}
+		// RENDER GRAPH
+		if let Some(render_graph) = self.render_graph.as_mut() {
+			render_graph.resize(&self.program_data);
+		}
 		// COMMAND BUFFERS
 		let command_buffer_draw = vpb::CommandBuffer::new(
 			&program_data.device,
@@ -3,15 +3,26 @@ use std::sync::Arc;
 use ash::vk;
 use vpb::ProgramData;
 
-use crate::{EnginePipeline, pf, InputState, RenderState, rendering::{RenderingState, sub}, r#static::{ObjectStatic, state::StaticState}, dynamic::{ObjectDynamic, state::DynamicState}, update::{UpdateState}, ObjectStateBuffers};
+use crate::{EnginePipeline, pf, InputState, RenderState, rendering::{RenderingState, sub}, r#static::{ObjectStatic, state::StaticState}, dynamic::{ObjectDynamic, state::DynamicState}, update::{UpdateState}, ObjectStateBuffers, BucketDescription, Texture, Sampler, Material, BlockLights, BlockReflect, BlockStateExt, PipelineCreateError};
 
 pub struct Bucket {
 	pub name: String,
 	pub engine_pipeline: Arc<dyn EnginePipeline>,
 	pub program_data: ProgramData,
+	/// Registry identifier of the pipeline, only set for buckets that can be serialized.
+	pub pipeline_id: Option<String>,
 	object_names: Vec<String>,
 	objects_rs: Vec<Arc<dyn RenderingState>>,
 	objects_us: Vec<Arc<dyn UpdateState>>,
+	materials: Vec<Arc<Material>>,
+	/// Enabled objects grouped by material, rebuilt every render.
+	draw_order: Vec<usize>,
+	/// Pipeline block state the scene's lights are written into.
+	lights_block: Option<usize>,
+	/// Object block state the shadow pass binds for every caster.
+	shadow_caster_block: Option<usize>,
+	/// Object block state holding the joints of skinned casters.
+	shadow_joint_block: Option<usize>,
 }
 
 impl Bucket {
@@ -23,13 +34,67 @@ impl Bucket {
 		let name = name.to_string();
 		let objects_rs: Vec<Arc<dyn RenderingState>> = Vec::with_capacity(1024);
 		let objects_us: Vec<Arc<dyn UpdateState>> = Vec::with_capacity(1024);
+		let lights_block = pipeline_engine.get_pipeline_block_structure().layouts.iter().position(
+			|x|
+			x.name == BlockLights::block_name()
+		);
 		Self {
 			name,
 			engine_pipeline: pipeline_engine,
 			program_data,
+			pipeline_id: None,
 			object_names: Vec::with_capacity(128),
 			objects_rs,
 			objects_us,
+			materials: Vec::with_capacity(16),
+			draw_order: Vec::with_capacity(1024),
+			lights_block,
+			shadow_caster_block: None,
+			shadow_joint_block: None,
+		}
+	}
+
+	/// Pipeline block state `Scene::lights` is written into every frame.
+	/// Defaults to the pipeline's first `BlockLights` block.
+	pub fn set_lights_block(
+		&mut self,
+		lights_block: Option<usize>,
+	) {
+		self.lights_block = lights_block;
+	}
+
+	/// Draws the bucket's objects into the scene's shadow maps, binding the
+	/// object block state `shadow_caster_block` as set 0 of the shadow
+	/// pass. It must hold the object's `BlockMeshModel` and the pipeline
+	/// must draw `Vertex3d` meshes, or `VertexSkinned` ones when a shadow
+	/// joint block is set. Buckets cast no shadows by default.
+	pub fn set_shadow_caster_block(
+		&mut self,
+		shadow_caster_block: Option<usize>,
+	) {
+		self.shadow_caster_block = shadow_caster_block;
+	}
+
+	/// Object block state holding the `BlockJoint` array of skinned
+	/// casters, bound as set 1 of the skinned shadow pass.
+	pub fn set_shadow_joint_block(
+		&mut self,
+		shadow_joint_block: Option<usize>,
+	) {
+		self.shadow_joint_block = shadow_joint_block;
+	}
+
+	pub fn update_lights(
+		&self,
+		lights: &BlockLights,
+		frame: usize,
+	) {
+		if let Some(lights_block) = self.lights_block {
+			self.engine_pipeline.get_pipeline_info().block_states[lights_block].uniform().update_checked(
+				&self.program_data.device,
+				lights,
+				Some(frame),
+			);
 		}
 	}
 
@@ -114,6 +179,162 @@ impl Bucket {
 		self.objects_us.swap_remove(i);
 	}
 
+	pub fn has_object(
+		&self,
+		name: &str,
+	) -> bool {
+		self.object_names.iter().any(|x| x == name)
+	}
+
+	pub fn set_object_enabled(
+		&mut self,
+		name: &str,
+		enabled: bool,
+	) {
+		let (i, _) = self.object_names.iter().enumerate().find(
+			|(_, obj_name)| {
+				*obj_name == name
+			}
+		).expect(format!("failed to find object {}", name).as_str());
+		let mut sub_state = self.objects_rs[i].sub_state();
+		vpb::gmuc!(sub_state).enabled = enabled;
+	}
+
+	/// Objects cast shadows unless opted out here.
+	pub fn set_object_cast_shadows(
+		&mut self,
+		name: &str,
+		cast_shadows: bool,
+	) {
+		let (i, _) = self.object_names.iter().enumerate().find(
+			|(_, obj_name)| {
+				*obj_name == name
+			}
+		).expect(format!("failed to find object {}", name).as_str());
+		let mut sub_state = self.objects_rs[i].sub_state();
+		vpb::gmuc!(sub_state).cast_shadows = cast_shadows;
+	}
+
+	/// Points the object's image block state at `texture`. `block` is the
+	/// index of the block state, pipeline blocks first. `sampler` overrides
+	/// the texture's own sampler for this binding.
+	pub fn bind_texture(
+		&mut self,
+		name: &str,
+		block: usize,
+		texture: &Texture,
+		sampler: Option<&Sampler>,
+	) {
+		let (i, _) = self.object_names.iter().enumerate().find(
+			|(_, obj_name)| {
+				*obj_name == name
+			}
+		).expect(format!("failed to find object {}", name).as_str());
+		let sub_state = self.objects_rs[i].sub_state();
+		let block_states = sub_state.block_states.as_ref().expect(
+			"attempting to bind a texture to an object with no block states"
+		);
+		texture.bind_with_sampler(
+			&self.program_data.device,
+			&block_states[block],
+			sampler.unwrap_or(&texture.sampler),
+		);
+	}
+
+	/// Keeps the material's block states alive and updated with the bucket.
+	/// The material's template must use the bucket's pipeline.
+	pub fn add_material(
+		&mut self,
+		material: Arc<Material>,
+	) {
+		assert!(
+			Arc::ptr_eq(&material.template.engine_pipeline, &self.engine_pipeline),
+			"material \"{}\" belongs to another pipeline than bucket \"{}\"", material.name, self.name,
+		);
+		if self.materials.iter().any(|x| Arc::ptr_eq(x, &material)) {
+			return;
+		}
+		self.materials.push(material);
+	}
+
+	pub fn get_material(
+		&self,
+		name: &str,
+	) -> Arc<Material> {
+		self.materials.iter().find(
+			|x| x.name == name
+		).expect(format!("failed to find material {}", name).as_str()).clone()
+	}
+
+	/// Removes the material and destroys its block state memory. Returns
+	/// `false` and keeps the material when no material has that name or
+	/// while objects or callers still hold it, see `get_material`. No
+	/// submitted frame may still use it, see `Scene::idle`.
+	pub fn remove_material(
+		&mut self,
+		name: &str,
+	) -> bool {
+		let i = match self.materials.iter().position(|x| x.name == name) {
+			Some(i) => i,
+			None => return false,
+		};
+		// Objects share the same `Arc`, so only the bucket's own may be left.
+		if Arc::strong_count(&self.materials[i]) > 1 {
+			return false;
+		}
+		let mut material = self.materials.swap_remove(i);
+		vpb::gmuc!(material).destroy_memory(&self.program_data);
+		true
+	}
+
+	/// Draws the object with `material`'s blocks, adding the material to the
+	/// bucket when it isn't yet.
+	pub fn set_object_material(
+		&mut self,
+		name: &str,
+		material: Option<Arc<Material>>,
+	) {
+		let (i, _) = self.object_names.iter().enumerate().find(
+			|(_, obj_name)| {
+				*obj_name == name
+			}
+		).expect(format!("failed to find object {}", name).as_str());
+		if let Some(material) = &material {
+			self.add_material(material.clone());
+		}
+		let mut sub_state = self.objects_rs[i].sub_state();
+		vpb::gmuc!(sub_state).material = material;
+	}
+
+	/// Describes the bucket and all objects that can describe themselves,
+	/// the others are listed in `skipped`. Returns `None` when the bucket
+	/// has no pipeline identifier.
+	pub fn describe(
+		&self,
+	) -> Option<BucketDescription> {
+		let pipeline = self.pipeline_id.clone()?;
+		let mut objects = Vec::with_capacity(self.object_names.len());
+		let mut skipped = Vec::new();
+		for (i, name) in self.object_names.iter().enumerate() {
+			let mut description = match self.objects_us[i].describe() {
+				Some(description) => description,
+				None => {
+					skipped.push(name.clone());
+					continue;
+				},
+			};
+			description.name = name.clone();
+			description.enabled = self.objects_rs[i].sub_state().enabled;
+			objects.push(description);
+		}
+		Some(BucketDescription {
+			name: self.name.clone(),
+			pipeline,
+			objects,
+			skipped,
+		})
+	}
+
 	pub fn update_blocks(
 		&mut self,
 		input_state: &InputState,
@@ -125,6 +346,12 @@ impl Bucket {
 			input_state,
 			render_state,
 		);
+		for material in self.materials.iter_mut() {
+			vpb::gmuc_ref!(material).update_block_states(
+				&self.program_data.device,
+				render_state.frame,
+			);
+		}
 		let pipeline_layout = self.engine_pipeline.get_pipeline_info().pipeline_layout;
 		for object in self.objects_us.iter_mut() {
 			let wa_object = vpb::gmuc_ref!(object);
@@ -138,94 +365,201 @@ impl Bucket {
 		}
 	}
 
+	/// Binds the pipeline and its blocks once, then the blocks of each
+	/// material once for all the objects sharing it. Objects without a
+	/// material keep the order they were added in.
 	pub fn render(
 		&mut self,
 		device: &vpb::Device,
 		command_buffer: vk::CommandBuffer,
 		frame: usize,
+	) {
+		self.render_into(
+			device,
+			command_buffer,
+			frame,
+			None,
+		);
+	}
+
+	/// Like `render` with the viewport and scissor covering `extent`
+	/// instead of the window, for offscreen passes of another size.
+	pub fn render_into(
+		&mut self,
+		device: &vpb::Device,
+		command_buffer: vk::CommandBuffer,
+		frame: usize,
+		extent: Option<vk::Extent2D>,
 	) { unsafe {
+		let pipeline_info = self.engine_pipeline.get_pipeline_info();
+		let pipeline_block_count = pipeline_info.block_states.len();
+		let material_block_count = self.engine_pipeline.get_object_block_structure().material_block_count;
+		let (viewport, scissor) = match extent {
+			Some(extent) => (
+				[vk::Viewport {
+					width: extent.width as f32,
+					height: extent.height as f32,
+					..pipeline_info.viewport[0]
+				}],
+				[extent.into()],
+			),
+			None => (pipeline_info.viewport, pipeline_info.scissor),
+		};
 		device.device.cmd_bind_pipeline(
 			command_buffer,
 			vk::PipelineBindPoint::GRAPHICS,
-			self.engine_pipeline.get_pipeline_info().pipeline,
+			pipeline_info.pipeline,
 		);
 		device.device.cmd_set_viewport(
 			command_buffer,
 			0,
-			&self.engine_pipeline.get_pipeline_info().viewport,
+			&viewport,
 		);
 		device.device.cmd_set_scissor(
 			command_buffer,
 			0,
-			&self.engine_pipeline.get_pipeline_info().scissor,
+			&scissor,
 		);
-		for object in self.objects_rs.iter() {
+		if pipeline_block_count > 0 {
+			let pipeline_sets: Vec<vk::DescriptorSet> = pipeline_info.block_states.iter().map(
+				|x| {
+					x.descriptor_set(frame)
+				}
+			).collect();
+			device.device.cmd_bind_descriptor_sets(
+				command_buffer,
+				vk::PipelineBindPoint::GRAPHICS,
+				pipeline_info.pipeline_layout,
+				0,
+				&pipeline_sets,
+				&[],
+			);
+		}
+		let objects_rs = &self.objects_rs;
+		self.draw_order.clear();
+		self.draw_order.extend((0..objects_rs.len()).filter(
+			|x|
+			objects_rs[*x].sub_state().enabled
+		));
+		// Stable, so objects sharing a material keep their relative order.
+		self.draw_order.sort_by_key(
+			|x|
+			objects_rs[*x].sub_state().material.as_ref().map_or(0, |x| Arc::as_ptr(x) as usize)
+		);
+		let mut bound_material: Option<*const Material> = None;
+		for i in self.draw_order.iter() {
+			let object = &self.objects_rs[*i];
 			let mut sub_state = object.sub_state();
-			if !sub_state.enabled {
-				continue;
-			}
 			let sub_state = vpb::gmuc!(sub_state);
+			if material_block_count > 0 {
+				let material = sub_state.material.as_ref().unwrap_or_else(
+					|| panic!("object \"{}\" needs a material to be drawn in bucket \"{}\"", sub_state.name, self.name)
+				);
+				if bound_material != Some(Arc::as_ptr(material)) {
+					let material_sets: Vec<vk::DescriptorSet> = material.block_states().iter().map(
+						|x| {
+							x.descriptor_set(frame)
+						}
+					).collect();
+					device.device.cmd_bind_descriptor_sets(
+						command_buffer,
+						vk::PipelineBindPoint::GRAPHICS,
+						pipeline_info.pipeline_layout,
+						pipeline_block_count as u32,
+						&material_sets,
+						&[],
+					);
+					bound_material = Some(Arc::as_ptr(material));
+				}
+			}
 			let block_states = &sub_state.block_states.as_ref().expect(
 				"attempting to bind no block states during rendering"
 			);
-			let block_state_layouts: Vec<vk::DescriptorSet> = block_states.iter().map(
+			// The leading pipeline block states are bound above.
+			let object_sets: Vec<vk::DescriptorSet> = block_states.iter().skip(pipeline_block_count).map(
 				|x| {
-					x.descriptor_data.descriptor_sets[frame]
+					x.descriptor_set(frame)
 				}
 			).collect();
+			if !object_sets.is_empty() {
+				device.device.cmd_bind_descriptor_sets(
+					command_buffer,
+					vk::PipelineBindPoint::GRAPHICS,
+					pipeline_info.pipeline_layout,
+					(pipeline_block_count + material_block_count) as u32,
+					&object_sets,
+					&[],
+				);
+			}
+			object.bind_buffers(
+				&self.program_data,
+				&command_buffer,
+			);
+			if let Some(push_constant) = &sub_state.push_constant {
+				pipeline_info.push_value(
+					device,
+					&command_buffer,
+					push_constant,
+				);
+			}
+			draw_object(
+				device,
+				command_buffer,
+				&object.sub_state().buffers,
+			);
+		}
+	}}
+
+	/// Draws the enabled objects that cast shadows with a shadow pass
+	/// pipeline whose set 0 takes the bucket's shadow caster block, see
+	/// `ShadowMaps::render`. Skinned buckets are drawn only by the skinned
+	/// shadow pipeline and the others only by the static one.
+	pub fn render_shadow_casters(
+		&self,
+		device: &vpb::Device,
+		command_buffer: vk::CommandBuffer,
+		frame: usize,
+		shadow_pipeline_layout: vk::PipelineLayout,
+		skinned: bool,
+	) { unsafe {
+		let caster_block = match self.shadow_caster_block {
+			Some(caster_block) => caster_block,
+			None => return,
+		};
+		if self.shadow_joint_block.is_some() != skinned {
+			return;
+		}
+		for object in self.objects_rs.iter() {
+			let sub_state = object.sub_state();
+			if !sub_state.enabled || !sub_state.cast_shadows {
+				continue;
+			}
+			let block_states = sub_state.block_states.as_ref().expect(
+				"attempting to bind no block states during rendering"
+			);
 			device.device.cmd_bind_descriptor_sets(
 				command_buffer,
 				vk::PipelineBindPoint::GRAPHICS,
-				self.engine_pipeline.get_pipeline_info().pipeline_layout,
+				shadow_pipeline_layout,
 				0,
-				&block_state_layouts,
+				&match self.shadow_joint_block {
+					Some(joint_block) => vec![
+						block_states[caster_block].descriptor_set(frame),
+						block_states[joint_block].descriptor_set(frame),
+					],
+					None => vec![block_states[caster_block].descriptor_set(frame)],
+				},
 				&[],
 			);
 			object.bind_buffers(
 				&self.program_data,
 				&command_buffer,
 			);
-			match &object.sub_state().buffers {
-				ObjectStateBuffers::GOIndexed(
-					indexed_buffer,
-				) => {
-					device.device.cmd_draw_indexed(
-						command_buffer,
-						indexed_buffer.index_count as u32,
-						1,
-						0,
-						0,
-						0,
-					);
-				},
-				ObjectStateBuffers::GOIndirect(
-					indirect_buffer,
-				) => {
-					let buffer = match &indirect_buffer.indirect.buffer {
-						vpb::BufferType::Buffer(buffer) => buffer,
-						vpb::BufferType::Image(_) => unreachable!(),
-					};
-					device.device.cmd_draw_indexed_indirect(
-						command_buffer,
-						buffer.buffer,
-						buffer.buffer_offset as u64,
-						indirect_buffer.indirect_count as u32,
-						std::mem::size_of::<vk::DrawIndexedIndirectCommand>() as u32,
-					);
-				},
-				ObjectStateBuffers::GOInstanced(
-					instance_buffer,
-				) => {
-					device.device.cmd_draw_indexed(
-						command_buffer,
-						instance_buffer.index_count as u32,
-						instance_buffer.instance_count as u32,
-						0,
-						0,
-						0,
-					);
-				},
-			}
+			draw_object(
+				device,
+				command_buffer,
+				&sub_state.buffers,
+			);
 		}
 	}}
 
@@ -235,20 +569,23 @@ impl Bucket {
 		let mut pipeline_info = self.engine_pipeline.get_pipeline_info();
 		let pipeline_info = vpb::gmuc!(pipeline_info);
 		for block_state in pipeline_info.block_states.iter_mut() {
-			let block_state = vpb::gmuc!(*block_state);
 			block_state.destroy_memory(&self.program_data);
 		}
+		let pipeline_block_count = pipeline_info.block_states.len();
 		for object in self.objects_rs.iter() {
 			let mut state = object.sub_state();
 			let state = vpb::gmuc!(state);
 			let block_states = state.block_states.as_mut().expect(
 				"attempting to recreate block states when there are none",
 			);
-			for block_state in block_states.iter_mut().skip(1) {
-				let block_state = vpb::gmuc_ref!(block_state);
+			// The leading pipeline block states are shared and handled above.
+			for block_state in block_states.iter_mut().skip(pipeline_block_count) {
 				block_state.destroy_memory(&self.program_data);
 			}
 		}
+		for material in self.materials.iter_mut() {
+			vpb::gmuc_ref!(material).destroy_memory(&self.program_data);
+		}
 	}}
 	
 	pub fn recreate_block_state_memory(
@@ -257,26 +594,31 @@ impl Bucket {
 		let mut pipeline_info = self.engine_pipeline.get_pipeline_info();
 		let pipeline_info = vpb::gmuc!(pipeline_info);
 		for block_state in pipeline_info.block_states.iter_mut() {
-			let block_state = vpb::gmuc_ref!(block_state);
 			block_state.recreate_memory(
 				&self.program_data,
 				self.program_data.frame_count,
 			);
 		}
+		let pipeline_block_count = pipeline_info.block_states.len();
 		for object in self.objects_rs.iter() {
 			let mut state = object.sub_state();
 			let state = vpb::gmuc!(state);
 			let block_states = state.block_states.as_mut().expect(
 				"attempting to recreate block states when there are none",
 			);
-			for block_state in block_states.iter_mut().skip(1) {
-				let block_state = vpb::gmuc_ref!(block_state);
+			for block_state in block_states.iter_mut().skip(pipeline_block_count) {
 				block_state.recreate_memory(
 					&self.program_data,
 					self.program_data.frame_count,
 				);
 			}
 		}
+		for material in self.materials.iter_mut() {
+			vpb::gmuc_ref!(material).recreate_memory(
+				&self.program_data,
+				self.program_data.frame_count,
+			);
+		}
 	}}
 
 	pub fn destroy_pipeline(
@@ -289,10 +631,83 @@ impl Bucket {
 		);
 	}}
 
+	/// Keeps the current pipeline when the new one can't be built.
 	pub fn recreate_pipeline(
 		&mut self,
-	) { unsafe {
+	) -> Result<(), PipelineCreateError> { unsafe {
 		let engine_pipeline = vpb::gmuc!(self.engine_pipeline);
-		engine_pipeline.recreate_pipeline(&self.program_data);
+		engine_pipeline.recreate_pipeline(&self.program_data)
 	}}
-}
\ No newline at end of file
+}
+
+fn draw_object(
+	device: &vpb::Device,
+	command_buffer: vk::CommandBuffer,
+	buffers: &ObjectStateBuffers,
+) { unsafe {
+	match buffers {
+		ObjectStateBuffers::GOIndexed(
+			indexed_buffer,
+		) => {
+			device.device.cmd_draw_indexed(
+				command_buffer,
+				indexed_buffer.index_count as u32,
+				1,
+				0,
+				0,
+				0,
+			);
+		},
+		ObjectStateBuffers::GOIndirect(
+			indirect_buffer,
+		) => {
+			let buffer = match &indirect_buffer.indirect.buffer {
+				vpb::BufferType::Buffer(buffer) => buffer,
+				vpb::BufferType::Image(_) => unreachable!(),
+			};
+			device.device.cmd_draw_indexed_indirect(
+				command_buffer,
+				buffer.buffer,
+				buffer.buffer_offset as u64,
+				indirect_buffer.indirect_count as u32,
+				std::mem::size_of::<vk::DrawIndexedIndirectCommand>() as u32,
+			);
+		},
+		ObjectStateBuffers::GOInstanced(
+			instance_buffer,
+		) => {
+			device.device.cmd_draw_indexed(
+				command_buffer,
+				instance_buffer.index_count as u32,
+				instance_buffer.instance_count as u32,
+				0,
+				0,
+				0,
+			);
+		},
+		ObjectStateBuffers::HostIndexed(
+			host_mesh,
+		) => {
+			device.device.cmd_draw_indexed(
+				command_buffer,
+				host_mesh.index_count(),
+				1,
+				0,
+				0,
+				0,
+			);
+		},
+		ObjectStateBuffers::DeviceIndexed(
+			device_mesh,
+		) => {
+			device.device.cmd_draw_indexed(
+				command_buffer,
+				device_mesh.index_count,
+				1,
+				0,
+				0,
+				0,
+			);
+		},
+	}
+}}
\ No newline at end of file
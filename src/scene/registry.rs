@@ -0,0 +1,170 @@
+use std::{sync::Arc, collections::HashMap, path::Path};
+
+use ash::vk;
+use vpb::ProgramData;
+
+use crate::{EnginePipeline, Camera, Bucket, ObjectDescription, PipelineDescription, PipelineDescriptionError, TypeRegistry, DescribedPipeline, MeshObject, SpriteBatch, Skybox, r#static::ObjectStatic, dynamic::ObjectDynamic};
+
+pub type PipelineConstructor = Box<dyn Fn(&ProgramData, vk::PipelineCache, Option<Arc<dyn Camera>>) -> Arc<dyn EnginePipeline>>;
+pub type ObjectConstructor = Box<dyn Fn(&ProgramData, &mut Bucket, &ObjectDescription)>;
+
+/// Maps the identifiers stored in scene files back to the code that
+/// rebuilds them. Pipelines are keyed by their pipeline identifier and
+/// objects by their kind.
+pub struct SceneRegistry {
+	pipelines: HashMap<String, PipelineConstructor>,
+	objects: HashMap<String, ObjectConstructor>,
+}
+
+impl SceneRegistry {
+	pub fn new(
+	) -> Self {
+		Self {
+			pipelines: HashMap::with_capacity(16),
+			objects: HashMap::with_capacity(16),
+		}
+	}
+
+	/// Registry that can rebuild every object vpe ships with that
+	/// describes itself, `MeshObject`, `SpriteBatch` and `Skybox`.
+	pub fn with_defaults(
+	) -> Self {
+		let mut registry = SceneRegistry::new();
+		registry.register_object(
+			MeshObject::KIND,
+			|program_data, bucket, description| {
+				if let Some(object) = MeshObject::from_description(program_data, description) {
+					let object = Arc::new(object);
+					bucket.add_static_object(description.name.clone(), object.state(), object);
+				}
+			},
+		);
+		registry.register_object(
+			SpriteBatch::KIND,
+			|program_data, bucket, description| {
+				if let Some(object) = SpriteBatch::from_description(program_data, description) {
+					let object = Arc::new(object);
+					bucket.add_dynamic_object(description.name.clone(), object.state(), object);
+				}
+			},
+		);
+		registry.register_object(
+			Skybox::KIND,
+			|program_data, bucket, description| {
+				let object = Arc::new(Skybox::new(program_data, &description.name));
+				bucket.add_static_object(description.name.clone(), object.state(), object);
+			},
+		);
+		registry
+	}
+
+	pub fn register_pipeline<FC>(
+		&mut self,
+		pipeline_id: &str,
+		constructor: FC,
+	) where FC: Fn(&ProgramData, vk::PipelineCache, Option<Arc<dyn Camera>>) -> Arc<dyn EnginePipeline> + 'static {
+		self.pipelines.insert(
+			pipeline_id.to_string(),
+			Box::new(constructor),
+		);
+	}
+
+	/// Registers a data driven pipeline. The description is checked against
+	/// the type registry here so building the pipeline later can't fail.
+	pub fn register_pipeline_description(
+		&mut self,
+		pipeline_id: &str,
+		description: PipelineDescription,
+		types: Arc<TypeRegistry>,
+	) -> Result<(), PipelineDescriptionError> {
+		types.validate(&description)?;
+		self.register_pipeline(
+			pipeline_id,
+			move |program_data, pipeline_cache, camera| {
+				Arc::new(DescribedPipeline::new(
+					program_data,
+					pipeline_cache,
+					&description,
+					&types,
+					camera,
+				).expect("validated pipeline description failed to build"))
+			},
+		);
+		Ok(())
+	}
+
+	pub fn register_pipeline_file<P: AsRef<Path>>(
+		&mut self,
+		pipeline_id: &str,
+		path: P,
+		types: Arc<TypeRegistry>,
+	) -> Result<(), PipelineDescriptionError> {
+		let description = PipelineDescription::from_file(path)?;
+		self.register_pipeline_description(
+			pipeline_id,
+			description,
+			types,
+		)
+	}
+
+	/// The constructor is responsible for creating the object's buffers
+	/// and adding it to the bucket under the description's name, loading
+	/// fails when it doesn't.
+	pub fn register_object<FC>(
+		&mut self,
+		kind: &str,
+		constructor: FC,
+	) where FC: Fn(&ProgramData, &mut Bucket, &ObjectDescription) + 'static {
+		self.objects.insert(
+			kind.to_string(),
+			Box::new(constructor),
+		);
+	}
+
+	pub fn has_pipeline(
+		&self,
+		pipeline_id: &str,
+	) -> bool {
+		self.pipelines.contains_key(pipeline_id)
+	}
+
+	pub fn has_object(
+		&self,
+		kind: &str,
+	) -> bool {
+		self.objects.contains_key(kind)
+	}
+
+	pub fn create_pipeline(
+		&self,
+		pipeline_id: &str,
+		program_data: &ProgramData,
+		pipeline_cache: vk::PipelineCache,
+		camera: Option<Arc<dyn Camera>>,
+	) -> Option<Arc<dyn EnginePipeline>> {
+		let constructor = self.pipelines.get(pipeline_id)?;
+		Some(constructor(program_data, pipeline_cache, camera))
+	}
+
+	pub fn create_object(
+		&self,
+		kind: &str,
+		program_data: &ProgramData,
+		bucket: &mut Bucket,
+		object_description: &ObjectDescription,
+	) -> bool {
+		match self.objects.get(kind) {
+			Some(constructor) => {
+				constructor(program_data, bucket, object_description);
+				true
+			},
+			None => false,
+		}
+	}
+}
+
+impl Default for SceneRegistry {
+	fn default() -> Self {
+		Self::with_defaults()
+	}
+}
\ No newline at end of file
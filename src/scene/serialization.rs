@@ -0,0 +1,523 @@
+use std::{collections::BTreeMap, fs, path::Path, sync::Arc, fmt};
+
+use bytemuck::Pod;
+use nalgebra::{vector, Matrix4};
+use serde::{Serialize, Deserialize};
+
+use crate::{Scene, SceneRegistry, Bucket, Camera, CameraState2d, CameraState3d};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SceneFormat {
+	RON,
+	JSON,
+}
+
+impl SceneFormat {
+	/// `.json` files are read as json, everything else as ron.
+	pub fn from_path(
+		path: &Path,
+	) -> Self {
+		match path.extension().and_then(|x| x.to_str()) {
+			Some("json") => SceneFormat::JSON,
+			_ => SceneFormat::RON,
+		}
+	}
+}
+
+#[derive(Debug)]
+pub enum SceneError {
+	Io(std::io::Error),
+	Ron(ron::Error),
+	Json(serde_json::Error),
+	/// Bucket was not added through a registry so it has no pipeline identifier.
+	MissingPipelineId(String),
+	UnknownPipeline(String),
+	UnknownObject(String),
+	/// The registered constructor returned without adding the object,
+	/// usually because its source data didn't fit the object kind.
+	ObjectNotAdded { bucket: String, object: String },
+}
+
+impl fmt::Display for SceneError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			SceneError::Io(e) => write!(f, "scene io error: {}", e),
+			SceneError::Ron(e) => write!(f, "scene ron error: {}", e),
+			SceneError::Json(e) => write!(f, "scene json error: {}", e),
+			SceneError::MissingPipelineId(bucket) => write!(f, "bucket \"{}\" has no pipeline identifier", bucket),
+			SceneError::UnknownPipeline(pipeline) => write!(f, "no pipeline registered as \"{}\"", pipeline),
+			SceneError::UnknownObject(kind) => write!(f, "no object registered as \"{}\"", kind),
+			SceneError::ObjectNotAdded { bucket, object } => write!(f, "object \"{}\" was not added to bucket \"{}\"", object, bucket),
+		}
+	}
+}
+
+impl std::error::Error for SceneError {}
+
+impl From<std::io::Error> for SceneError {
+	fn from(e: std::io::Error) -> Self {
+		SceneError::Io(e)
+	}
+}
+
+impl From<ron::Error> for SceneError {
+	fn from(e: ron::Error) -> Self {
+		SceneError::Ron(e)
+	}
+}
+
+impl From<ron::error::SpannedError> for SceneError {
+	fn from(e: ron::error::SpannedError) -> Self {
+		SceneError::Ron(e.code)
+	}
+}
+
+impl From<serde_json::Error> for SceneError {
+	fn from(e: serde_json::Error) -> Self {
+		SceneError::Json(e)
+	}
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct SceneDescription {
+	pub camera: Option<CameraDescription>,
+	pub buckets: Vec<BucketDescription>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BucketDescription {
+	pub name: String,
+	/// Identifier the pipeline was registered under in the `SceneRegistry`.
+	pub pipeline: String,
+	pub objects: Vec<ObjectDescription>,
+	/// Names of the objects left out because they can't describe
+	/// themselves, not written to scene files.
+	#[serde(skip)]
+	pub skipped: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ObjectDescription {
+	pub name: String,
+	/// Identifier the object constructor was registered under in the `SceneRegistry`.
+	pub kind: String,
+	pub enabled: bool,
+	pub buffers: BufferSource,
+	pub transform: [f32; 16],
+	/// Free form block values such as colors.
+	#[serde(default)]
+	pub parameters: BTreeMap<String, Vec<f32>>,
+}
+
+/// Source data the object's gpu buffers were created from.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct BufferSource {
+	pub vertex_type: String,
+	pub vertices: Vec<f32>,
+	pub indices: Vec<u32>,
+	#[serde(default)]
+	pub instances: Vec<f32>,
+}
+
+impl ObjectDescription {
+	/// The bucket fills in the name and enabled flag when describing.
+	pub fn new(
+		kind: &str,
+		buffers: BufferSource,
+		transform: &Matrix4<f32>,
+	) -> Self {
+		let mut columns = [0.0; 16];
+		columns.copy_from_slice(transform.as_slice());
+		Self {
+			name: String::new(),
+			kind: kind.to_string(),
+			enabled: true,
+			buffers,
+			transform: columns,
+			parameters: BTreeMap::new(),
+		}
+	}
+
+	pub fn with_parameter(
+		mut self,
+		name: &str,
+		values: &[f32],
+	) -> Self {
+		self.parameters.insert(
+			name.to_string(),
+			values.to_vec(),
+		);
+		self
+	}
+
+	/// `transform` as a matrix, it is stored column major.
+	pub fn transform_matrix(
+		&self,
+	) -> Matrix4<f32> {
+		Matrix4::from_column_slice(&self.transform)
+	}
+
+	/// `None` when the parameter is missing or doesn't have `N` values.
+	pub fn parameter<const N: usize>(
+		&self,
+		name: &str,
+	) -> Option<[f32; N]> {
+		self.parameters.get(name)?.as_slice().try_into().ok()
+	}
+}
+
+impl BufferSource {
+	/// Flattens vertices made of 32 bit floats, `vertex_type` is the name
+	/// the type is registered under in the `TypeRegistry`.
+	pub fn new<V: Pod>(
+		vertex_type: &str,
+		vertices: &[V],
+		indices: &[u32],
+	) -> Self {
+		Self {
+			vertex_type: vertex_type.to_string(),
+			vertices: bytemuck::cast_slice(vertices).to_vec(),
+			indices: indices.to_vec(),
+			instances: Vec::new(),
+		}
+	}
+
+	/// Reads the vertices back, `None` when `vertex_type` isn't the
+	/// expected one or the floats don't divide into whole vertices.
+	pub fn read_vertices<V: Pod>(
+		&self,
+		vertex_type: &str,
+	) -> Option<Vec<V>> {
+		if self.vertex_type != vertex_type {
+			return None;
+		}
+		bytemuck::try_cast_slice(&self.vertices).ok().map(<[V]>::to_vec)
+	}
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum CameraDescription {
+	Camera2d {
+		position: [f32; 2],
+		zoom_index: i16,
+	},
+	Camera3d {
+		position: [f32; 3],
+		rotation: [f32; 2],
+	},
+}
+
+impl CameraDescription {
+	pub fn create_camera(
+		&self,
+	) -> Arc<dyn Camera> {
+		match self {
+			CameraDescription::Camera2d {
+				position,
+				zoom_index,
+			} => {
+				let mut camera = CameraState2d::new(vector![
+					position[0],
+					position[1]
+				]);
+				camera.set_zoom_index(*zoom_index);
+				Arc::new(camera)
+			},
+			CameraDescription::Camera3d {
+				position,
+				rotation,
+			} => {
+				let mut camera = CameraState3d::new(*position);
+				camera.camera_rotation = vector![
+					rotation[0],
+					rotation[1]
+				];
+				Arc::new(camera)
+			},
+		}
+	}
+}
+
+impl SceneDescription {
+	pub fn from_str(
+		data: &str,
+		format: SceneFormat,
+	) -> Result<Self, SceneError> {
+		Ok(match format {
+			SceneFormat::RON => ron::from_str(data)?,
+			SceneFormat::JSON => serde_json::from_str(data)?,
+		})
+	}
+
+	pub fn to_string(
+		&self,
+		format: SceneFormat,
+	) -> Result<String, SceneError> {
+		Ok(match format {
+			SceneFormat::RON => ron::ser::to_string_pretty(
+				self,
+				ron::ser::PrettyConfig::new().struct_names(true),
+			)?,
+			SceneFormat::JSON => serde_json::to_string_pretty(self)?,
+		})
+	}
+}
+
+impl Scene {
+	/// Snapshot of every bucket, object and the active camera. Objects
+	/// that don't describe themselves are left out and listed in
+	/// `BucketDescription::skipped`.
+	pub fn describe(
+		&self,
+	) -> Result<SceneDescription, SceneError> {
+		let mut buckets = Vec::with_capacity(self.buckets.len());
+		for bucket in self.buckets.iter() {
+			buckets.push(bucket.describe().ok_or_else(
+				|| SceneError::MissingPipelineId(bucket.name.clone())
+			)?);
+		}
+		Ok(SceneDescription {
+			camera: self.camera.as_ref().and_then(|x| x.describe()),
+			buckets,
+		})
+	}
+
+	pub fn save<P: AsRef<Path>>(
+		&self,
+		path: P,
+	) -> Result<(), SceneError> {
+		let path = path.as_ref();
+		let description = self.describe()?;
+		for bucket in description.buckets.iter() {
+			for object in bucket.skipped.iter() {
+				log::warn!("object \"{}\" in bucket \"{}\" can't be described and isn't saved", object, bucket.name);
+			}
+		}
+		let data = description.to_string(
+			SceneFormat::from_path(path),
+		)?;
+		fs::write(path, data)?;
+		Ok(())
+	}
+
+	/// Replaces all buckets and the camera with the contents of the file.
+	pub fn load<P: AsRef<Path>>(
+		&mut self,
+		path: P,
+		registry: &SceneRegistry,
+	) -> Result<(), SceneError> {
+		let path = path.as_ref();
+		let data = fs::read_to_string(path)?;
+		let description = SceneDescription::from_str(
+			&data,
+			SceneFormat::from_path(path),
+		)?;
+		self.load_description(
+			&description,
+			registry,
+		)
+	}
+
+	/// Builds every bucket of the description before replacing the current
+	/// ones, so on error the scene is left as it was.
+	pub fn load_description(
+		&mut self,
+		description: &SceneDescription,
+		registry: &SceneRegistry,
+	) -> Result<(), SceneError> {
+		for bucket in description.buckets.iter() {
+			if !registry.has_pipeline(&bucket.pipeline) {
+				return Err(SceneError::UnknownPipeline(bucket.pipeline.clone()));
+			}
+			for object in bucket.objects.iter() {
+				if !registry.has_object(&object.kind) {
+					return Err(SceneError::UnknownObject(object.kind.clone()));
+				}
+			}
+		}
+		let camera = description.camera.as_ref().map(|x| x.create_camera());
+		let mut buckets = Vec::with_capacity(description.buckets.len());
+		for bucket_description in description.buckets.iter() {
+			let bucket = self.create_registered_bucket(
+				&bucket_description.name,
+				&bucket_description.pipeline,
+				registry,
+				camera.clone().or_else(|| self.camera.clone()),
+			);
+			match bucket {
+				Ok(bucket) => buckets.push(Box::new(bucket)),
+				Err(e) => {
+					destroy_buckets(&mut buckets);
+					return Err(e);
+				},
+			}
+			let bucket = buckets.last_mut().unwrap();
+			for object in bucket_description.objects.iter() {
+				registry.create_object(
+					&object.kind,
+					&self.program_data,
+					bucket,
+					object,
+				);
+				if !bucket.has_object(&object.name) {
+					destroy_buckets(&mut buckets);
+					return Err(SceneError::ObjectNotAdded {
+						bucket: bucket_description.name.clone(),
+						object: object.name.clone(),
+					});
+				}
+				bucket.set_object_enabled(
+					&object.name,
+					object.enabled,
+				);
+			}
+		}
+		self.clear_buckets();
+		self.buckets = buckets;
+		if let Some(camera) = camera {
+			self.set_camera_state(camera);
+		}
+		Ok(())
+	}
+}
+
+/// Frees buckets that were never added to the scene.
+fn destroy_buckets(
+	buckets: &mut Vec<Box<Bucket>>,
+) {
+	for bucket in buckets.iter_mut() {
+		bucket.destroy_block_state_memory();
+		bucket.destroy_pipeline();
+	}
+	buckets.clear();
+}
+
+#[cfg(test)]
+mod tests {
+	use nalgebra::Vector3;
+
+	use crate::{Vertex3d, MeshObject, BlockMeshModel, Std140Vec4, primitives};
+
+	use super::*;
+
+	fn cube_scene(
+	) -> (SceneDescription, Vec<Vertex3d>, Vec<u32>, Matrix4<f32>) {
+		let (vertices, indices) = primitives::cube(2.0);
+		let transform = Matrix4::new_translation(&Vector3::new(1.0, -2.0, 0.25)) * Matrix4::new_scaling(3.0);
+		let mut object = ObjectDescription::new(
+			MeshObject::KIND,
+			BufferSource::new("Vertex3d", &vertices, &indices),
+			&transform,
+		).with_parameter(
+			"color",
+			&[0.1, 0.2, 0.3, 1.0],
+		);
+		object.name = "cube".to_string();
+		object.enabled = false;
+		let description = SceneDescription {
+			camera: Some(CameraDescription::Camera3d {
+				position: [0.0, 1.0, -5.0],
+				rotation: [0.5, -0.25],
+			}),
+			buckets: vec![BucketDescription {
+				name: "meshes".to_string(),
+				pipeline: "mesh".to_string(),
+				objects: vec![object],
+				skipped: Vec::new(),
+			}],
+		};
+		(description, vertices, indices, transform)
+	}
+
+	fn assert_round_trip(
+		format: SceneFormat,
+	) {
+		let (description, vertices, indices, transform) = cube_scene();
+		let data = description.to_string(format).unwrap();
+		let loaded = SceneDescription::from_str(&data, format).unwrap();
+		match loaded.camera {
+			Some(CameraDescription::Camera3d { position, rotation }) => {
+				assert_eq!(position, [0.0, 1.0, -5.0]);
+				assert_eq!(rotation, [0.5, -0.25]);
+			},
+			camera => panic!("expected a 3d camera, got {:?}", camera),
+		}
+		assert_eq!(loaded.buckets.len(), 1);
+		assert_eq!(loaded.buckets[0].name, "meshes");
+		assert_eq!(loaded.buckets[0].pipeline, "mesh");
+		assert_eq!(loaded.buckets[0].objects.len(), 1);
+		let object = &loaded.buckets[0].objects[0];
+		assert_eq!(object.name, "cube");
+		assert_eq!(object.kind, MeshObject::KIND);
+		assert!(!object.enabled);
+		assert_eq!(object.transform_matrix(), transform);
+		assert_eq!(object.parameter::<4>("color"), Some([0.1, 0.2, 0.3, 1.0]));
+		let loaded_vertices = object.buffers.read_vertices::<Vertex3d>("Vertex3d").unwrap();
+		assert_eq!(
+			bytemuck::cast_slice::<Vertex3d, f32>(&loaded_vertices),
+			bytemuck::cast_slice::<Vertex3d, f32>(&vertices),
+		);
+		assert_eq!(object.buffers.indices, indices);
+	}
+
+	#[test]
+	fn ron_round_trip() {
+		assert_round_trip(SceneFormat::RON);
+	}
+
+	#[test]
+	fn json_round_trip() {
+		assert_round_trip(SceneFormat::JSON);
+	}
+
+	#[test]
+	fn mesh_object_round_trip() {
+		let (vertices, indices) = primitives::cube(1.0);
+		let source = BufferSource::new("Vertex3d", &vertices, &indices);
+		let transform = Matrix4::new_translation(&Vector3::new(-4.0, 0.5, 2.0));
+		let block = BlockMeshModel {
+			model: transform.into(),
+			color: Std140Vec4 { value: [0.5, 0.25, 1.0, 0.75] },
+			specular: Std140Vec4 { value: [0.2, 0.3, 0.4, 16.0] },
+		};
+		let data = MeshObject::description(
+			&source,
+			&block,
+			3,
+		);
+		let data = ron::to_string(&data).unwrap();
+		let object: ObjectDescription = ron::from_str(&data).unwrap();
+		assert_eq!(object.kind, MeshObject::KIND);
+		assert_eq!(object.parameter::<1>("model_block"), Some([3.0]));
+		let loaded = MeshObject::block_from_description(&object);
+		assert_eq!(Matrix4::from(loaded.model), transform);
+		assert_eq!(loaded.color.value, block.color.value);
+		assert_eq!(loaded.specular.value, block.specular.value);
+		assert_eq!(object.buffers.read_vertices::<Vertex3d>("Vertex3d").unwrap().len(), vertices.len());
+		assert_eq!(object.buffers.indices, indices);
+	}
+
+	#[test]
+	fn read_vertices_checks_the_type() {
+		let (vertices, indices) = primitives::cube(1.0);
+		let mut source = BufferSource::new("Vertex3d", &vertices, &indices);
+		assert!(source.read_vertices::<Vertex3d>("VertexUI").is_none());
+		source.vertices.pop();
+		assert!(source.read_vertices::<Vertex3d>("Vertex3d").is_none());
+	}
+
+	#[test]
+	fn parameter_needs_the_right_length() {
+		let object = ObjectDescription::new(
+			MeshObject::KIND,
+			BufferSource::default(),
+			&Matrix4::identity(),
+		).with_parameter(
+			"color",
+			&[1.0, 1.0, 1.0],
+		);
+		assert_eq!(object.parameter::<3>("color"), Some([1.0; 3]));
+		assert_eq!(object.parameter::<4>("color"), None);
+		assert_eq!(object.parameter::<1>("missing"), None);
+	}
+}
\ No newline at end of file
@@ -0,0 +1,583 @@
+use std::sync::Arc;
+
+use ash::vk;
+use bytemuck::{Zeroable, Pod};
+use nalgebra::{Matrix4, Point3, Vector3, Vector4};
+use vpb::ProgramData;
+
+use crate::{PushConstant, PipelineInfo, ObjectBlockStructure, BlockMeshModel, BlockJoint, BlockLights, Vertex3d, VertexSkinned, ViewportDepthRange, DepthBias, EngineBlockState, Sampler, SamplerDescription, Std140Mat4, Std140Vec4, Camera, Bucket, create_device_image, destroy_device_image, MAX_DIRECTIONAL_LIGHTS, MAX_SPOT_LIGHTS, MAX_SHADOW_MAPS, MAX_JOINTS};
+
+/// Tiles of the shadow atlas, matches `res/shaders/shadow.glsl`.
+pub const SHADOW_ATLAS_COLUMNS: u32 = 4;
+pub const SHADOW_ATLAS_ROWS: u32 = 2;
+pub const MAX_SHADOW_CASCADES: u32 = 4;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Pod, Zeroable, PushConstant)]
+#[push_constant(stages(vertex))]
+pub struct ShadowPushConstant {
+	pub light_view_projection: Std140Mat4,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ShadowOptions {
+	/// Size of one shadow map, the atlas is 4 by 2 maps.
+	pub map_size: u32,
+	/// Maps per directional light, each covering a slice of the view
+	/// frustum. Clamped to `MAX_SHADOW_CASCADES`.
+	pub cascade_count: u32,
+	/// Blend between uniform (0) and logarithmic (1) cascade splits.
+	pub cascade_split_lambda: f32,
+	/// View distance where the first cascade starts.
+	pub near: f32,
+	/// View distance past which directional shadows end.
+	pub max_distance: f32,
+	/// How far towards a directional light casters outside the view are
+	/// still caught.
+	pub caster_distance: f32,
+	/// Rasterization depth bias of the shadow pass.
+	pub depth_bias: DepthBias,
+	/// Bias subtracted from the receiver's depth before the comparison.
+	pub compare_bias: f32,
+	/// Receivers are moved along their normal by this many map texels.
+	pub normal_offset: f32,
+	/// Percentage closer filtering over (2r + 1)² texels.
+	pub pcf_radius: u32,
+}
+
+impl Default for ShadowOptions {
+	fn default() -> Self {
+		Self {
+			map_size: 1024,
+			cascade_count: 3,
+			cascade_split_lambda: 0.75,
+			near: 0.1,
+			max_distance: 100.0,
+			caster_distance: 100.0,
+			depth_bias: DepthBias {
+				constant_factor: 1.25,
+				clamp: 0.0,
+				slope_factor: 1.75,
+			},
+			compare_bias: 0.0005,
+			normal_offset: 1.0,
+			pcf_radius: 1,
+		}
+	}
+}
+
+/// Depth atlas the shadow casting `Scene::lights` are rendered into
+/// before the main render pass. Pipelines built with shadows sample it,
+/// lights opt in with `BlockLights::set_directional_shadow` and
+/// `BlockLights::set_spot_shadow`, buckets opt in to casting with
+/// `Bucket::set_shadow_caster_block`.
+pub struct ShadowMaps {
+	pub options: ShadowOptions,
+	pub image: vk::Image,
+	pub allocation: vk_mem::Allocation,
+	pub image_view: vk::ImageView,
+	pub sampler: Sampler,
+	render_pass: vk::RenderPass,
+	framebuffer: vk::Framebuffer,
+	pipeline_info: PipelineInfo,
+	/// Draws the casters of buckets with a shadow joint block.
+	skinned_pipeline_info: PipelineInfo,
+	/// Light view projection of every map in use this frame.
+	views: Vec<Matrix4<f32>>,
+}
+
+impl ShadowMaps {
+	pub fn new(
+		program_data: &ProgramData,
+		pipeline_cache: vk::PipelineCache,
+		options: ShadowOptions,
+	) -> Self { unsafe {
+		let device = &program_data.device.device;
+		let options = ShadowOptions {
+			cascade_count: options.cascade_count.clamp(1, MAX_SHADOW_CASCADES),
+			..options
+		};
+		let format = vk::Format::D32_SFLOAT;
+		let extent = vk::Extent2D {
+			width: options.map_size * SHADOW_ATLAS_COLUMNS,
+			height: options.map_size * SHADOW_ATLAS_ROWS,
+		};
+		let image_info = vk::ImageCreateInfo::builder()
+			.image_type(vk::ImageType::TYPE_2D)
+			.format(format)
+			.extent(vk::Extent3D { width: extent.width, height: extent.height, depth: 1 })
+			.mip_levels(1)
+			.array_layers(1)
+			.samples(vk::SampleCountFlags::TYPE_1)
+			.tiling(vk::ImageTiling::OPTIMAL)
+			.usage(vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT | vk::ImageUsageFlags::SAMPLED)
+			.sharing_mode(vk::SharingMode::EXCLUSIVE)
+			.initial_layout(vk::ImageLayout::UNDEFINED)
+			.build();
+		let (image, allocation) = create_device_image(
+			program_data,
+			&image_info,
+		);
+		let image_view_info = vk::ImageViewCreateInfo::builder()
+			.image(image)
+			.view_type(vk::ImageViewType::TYPE_2D)
+			.format(format)
+			.subresource_range(
+				vk::ImageSubresourceRange::builder()
+					.aspect_mask(vk::ImageAspectFlags::DEPTH)
+					.level_count(1)
+					.layer_count(1)
+					.build()
+			).build();
+		let image_view = device.create_image_view(
+			&image_view_info,
+			None,
+		).unwrap();
+		let attachments = [
+			vk::AttachmentDescription::builder()
+				.format(format)
+				.samples(vk::SampleCountFlags::TYPE_1)
+				.load_op(vk::AttachmentLoadOp::CLEAR)
+				.store_op(vk::AttachmentStoreOp::STORE)
+				.stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+				.stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+				.initial_layout(vk::ImageLayout::UNDEFINED)
+				.final_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+				.build(),
+		];
+		let depth_reference = vk::AttachmentReference::builder()
+			.attachment(0)
+			.layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
+			.build();
+		let subpasses = [
+			vk::SubpassDescription::builder()
+				.pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
+				.depth_stencil_attachment(&depth_reference)
+				.build(),
+		];
+		// Last frame's lighting must be done reading before the maps are
+		// cleared, and this frame's lighting waits for the writes.
+		let dependencies = [
+			vk::SubpassDependency::builder()
+				.src_subpass(vk::SUBPASS_EXTERNAL)
+				.dst_subpass(0)
+				.src_stage_mask(vk::PipelineStageFlags::FRAGMENT_SHADER)
+				.dst_stage_mask(vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS)
+				.src_access_mask(vk::AccessFlags::SHADER_READ)
+				.dst_access_mask(vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE)
+				.build(),
+			vk::SubpassDependency::builder()
+				.src_subpass(0)
+				.dst_subpass(vk::SUBPASS_EXTERNAL)
+				.src_stage_mask(vk::PipelineStageFlags::LATE_FRAGMENT_TESTS)
+				.dst_stage_mask(vk::PipelineStageFlags::FRAGMENT_SHADER)
+				.src_access_mask(vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE)
+				.dst_access_mask(vk::AccessFlags::SHADER_READ)
+				.build(),
+		];
+		let render_pass_info = vk::RenderPassCreateInfo::builder()
+			.attachments(&attachments)
+			.subpasses(&subpasses)
+			.dependencies(&dependencies)
+			.build();
+		let render_pass = device.create_render_pass(
+			&render_pass_info,
+			None,
+		).unwrap();
+		let framebuffer_attachments = [image_view];
+		let framebuffer_info = vk::FramebufferCreateInfo::builder()
+			.render_pass(render_pass)
+			.attachments(&framebuffer_attachments)
+			.width(extent.width)
+			.height(extent.height)
+			.layers(1)
+			.build();
+		let framebuffer = device.create_framebuffer(
+			&framebuffer_info,
+			None,
+		).unwrap();
+		let pipeline_block_structure = Arc::new(ObjectBlockStructure::new());
+		// Same layout as the caster blocks of the lit pipelines, so their
+		// descriptor sets can be bound here as they are.
+		let object_block_structure = Arc::new(ObjectBlockStructure::new().with_block::<BlockMeshModel>(
+			&program_data.device,
+			vpb::BindingId(0), vpb::SetId(0),
+		));
+		let pipeline_info = PipelineInfo::builder("shadow")
+			.viewport_depth_range(ViewportDepthRange::WORLD)
+			.depth_only_pass(render_pass)
+			.depth_bias(Some(options.depth_bias))
+			.push_constant::<ShadowPushConstant>(0)
+			.build::<Vertex3d>(
+				program_data,
+				pipeline_cache,
+				&pipeline_block_structure,
+				&object_block_structure,
+			);
+		// The joints follow at set 1, like the skinned mesh pipelines.
+		let skinned_object_block_structure = Arc::new(ObjectBlockStructure::new().with_block::<BlockMeshModel>(
+			&program_data.device,
+			vpb::BindingId(0), vpb::SetId(0),
+		).with_storage_block::<BlockJoint>(
+			&program_data.device,
+			vpb::BindingId(0), vpb::SetId(1),
+			MAX_JOINTS,
+		));
+		let skinned_pipeline_info = PipelineInfo::builder("shadow")
+			.viewport_depth_range(ViewportDepthRange::WORLD)
+			.depth_only_pass(render_pass)
+			.depth_bias(Some(options.depth_bias))
+			.push_constant::<ShadowPushConstant>(0)
+			.define("SKINNED", "")
+			.build::<VertexSkinned>(
+				program_data,
+				pipeline_cache,
+				&pipeline_block_structure,
+				&skinned_object_block_structure,
+			);
+		Self {
+			options,
+			image,
+			allocation,
+			image_view,
+			sampler: Sampler::new(
+				program_data,
+				SamplerDescription::nearest(),
+			),
+			render_pass,
+			framebuffer,
+			pipeline_info,
+			skinned_pipeline_info,
+			views: Vec::with_capacity(MAX_SHADOW_MAPS),
+		}
+	}}
+
+	/// Writes the atlas into a combined image sampler block state.
+	pub fn bind(
+		&self,
+		device: &vpb::Device,
+		block_state: &EngineBlockState,
+	) {
+		self.bind_element(
+			device,
+			block_state,
+			0,
+		);
+	}
+
+	pub fn bind_element(
+		&self,
+		device: &vpb::Device,
+		block_state: &EngineBlockState,
+		element: usize,
+	) {
+		block_state.descriptor().write_image_element(
+			device,
+			element,
+			self.image_view,
+			self.sampler.sampler,
+		);
+	}
+
+	/// Frees every map, called once per frame before the lights are assigned.
+	pub fn begin_frame(
+		&mut self,
+	) {
+		self.views.clear();
+	}
+
+	/// Gives every shadow casting light of `lights` its maps and writes the
+	/// matrices and shadow settings into `lights`. Lights assigned more
+	/// than once a frame share their maps. Lights left without a map once the atlas is full
+	/// and directional lights seen through a camera without a projection
+	/// are unshadowed.
+	pub fn assign(
+		&mut self,
+		lights: &mut BlockLights,
+		camera: &dyn Camera,
+	) {
+		let options = &self.options;
+		lights.shadow_params = Std140Vec4 { value: [
+			options.normal_offset,
+			options.compare_bias,
+			options.pcf_radius as f32,
+			options.cascade_count as f32,
+		] };
+		let (view, _) = camera.get_view();
+		let projection = camera.get_projection();
+		for i in 0..(lights.counts[0] as usize).min(MAX_DIRECTIONAL_LIGHTS) {
+			let light = &mut lights.directional.elements[i];
+			light.direction.value[3] = -1.0;
+			let projection = match projection {
+				Some(projection) if lights.shadow_casters[0] & (1 << i) != 0 => projection,
+				_ => continue,
+			};
+			let [x, y, z, _] = light.direction.value;
+			let matrices = self.cascade_matrices(
+				Vector3::new(x, y, z),
+				&view,
+				&projection,
+			);
+			if let Some(first) = self.allocate(&matrices) {
+				light.direction.value[3] = first as f32;
+				for (j, matrix) in matrices.iter().enumerate() {
+					lights.shadow_matrices.elements[first + j] = (*matrix).into();
+				}
+			}
+		}
+		for i in 0..(lights.counts[2] as usize).min(MAX_SPOT_LIGHTS) {
+			let light = &mut lights.spot.elements[i];
+			light.direction.value[3] = -1.0;
+			if lights.shadow_casters[1] & (1 << i) == 0 {
+				continue;
+			}
+			let matrix = spot_matrix(
+				Vector3::from_column_slice(&light.position.value[..3]),
+				Vector3::from_column_slice(&light.direction.value[..3]),
+				light.cone.value[1].acos(),
+				light.position.value[3],
+			);
+			if let Some(map) = self.allocate(&[matrix]) {
+				light.direction.value[3] = map as f32;
+				lights.shadow_matrices.elements[map] = matrix.into();
+			}
+		}
+	}
+
+	/// Maps holding exactly these views already, or the next free ones.
+	fn allocate(
+		&mut self,
+		matrices: &[Matrix4<f32>],
+	) -> Option<usize> {
+		if let Some(first) = self.views.windows(matrices.len()).position(|x| x == matrices) {
+			return Some(first);
+		}
+		if self.views.len() + matrices.len() > MAX_SHADOW_MAPS {
+			return None;
+		}
+		self.views.extend_from_slice(matrices);
+		Some(self.views.len() - matrices.len())
+	}
+
+	/// One orthographic view per cascade, each fit around a bounding
+	/// sphere of its slice of the frustum and snapped to whole texels so
+	/// the shadow edges don't shimmer as the camera moves.
+	fn cascade_matrices(
+		&self,
+		direction: Vector3<f32>,
+		view: &Matrix4<f32>,
+		projection: &Matrix4<f32>,
+	) -> Vec<Matrix4<f32>> {
+		let options = &self.options;
+		let inverse_view = view.try_inverse().unwrap_or_else(Matrix4::identity);
+		let inverse_projection = projection.try_inverse().unwrap_or_else(Matrix4::identity);
+		// Frustum edges in view space, scaled to one unit along -z.
+		let rays: Vec<Vector3<f32>> = [[-1.0, -1.0], [1.0, -1.0], [-1.0, 1.0], [1.0, 1.0]].iter().map(
+			|[x, y]| {
+				let far = inverse_projection * Vector4::new(*x, *y, 1.0, 1.0);
+				let far = far.xyz() / far.w;
+				far / -far.z
+			}
+		).collect();
+		let near = options.near;
+		let far = options.max_distance.max(near + 0.001);
+		let count = options.cascade_count;
+		let split = |i: u32| {
+			let t = i as f32 / count as f32;
+			let logarithmic = near * (far / near).powf(t);
+			let uniform = near + (far - near) * t;
+			options.cascade_split_lambda * logarithmic + (1.0 - options.cascade_split_lambda) * uniform
+		};
+		let direction = direction.normalize();
+		let up = match direction.y.abs() > 0.99 {
+			true => Vector3::x(),
+			false => Vector3::y(),
+		};
+		let rotation = Matrix4::look_at_rh(
+			&Point3::origin(),
+			&Point3::from(direction),
+			&up,
+		);
+		(0..count).map(
+			|i| {
+				let (start, end) = (split(i), split(i + 1));
+				let corners: Vec<Vector3<f32>> = rays.iter().flat_map(
+					|ray| [ray * start, ray * end]
+				).map(
+					|x| inverse_view.transform_point(&Point3::from(x)).coords
+				).collect();
+				let center = corners.iter().sum::<Vector3<f32>>() / corners.len() as f32;
+				let radius = corners.iter().map(|x| (x - center).norm()).fold(0.0, f32::max);
+				// A radius quantized to 1/16 keeps the texel size constant.
+				let radius = (radius * 16.0).ceil() / 16.0;
+				let texel = 2.0 * radius / options.map_size as f32;
+				let center = rotation.transform_point(&Point3::from(center));
+				let x = (center.x / texel).floor() * texel;
+				let y = (center.y / texel).floor() * texel;
+				let z_near = center.z + radius + options.caster_distance;
+				let z_far = center.z - radius;
+				orthographic(x, y, radius, z_near, z_far) * rotation
+			}
+		).collect()
+	}
+
+	/// Clears the atlas and draws the casters of every registered bucket
+	/// into each map assigned this frame. Recorded outside of any render
+	/// pass.
+	pub fn render(
+		&self,
+		device: &vpb::Device,
+		command_buffer: vk::CommandBuffer,
+		frame: usize,
+		buckets: &[Box<Bucket>],
+	) { unsafe {
+		let size = self.options.map_size;
+		let clear_values = [
+			vk::ClearValue {
+				depth_stencil: vk::ClearDepthStencilValue { depth: 1.0, stencil: 0 },
+			},
+		];
+		let render_pass_begin_info = vk::RenderPassBeginInfo::builder()
+			.render_pass(self.render_pass)
+			.framebuffer(self.framebuffer)
+			.render_area(vk::Rect2D {
+				offset: vk::Offset2D { x: 0, y: 0 },
+				extent: vk::Extent2D {
+					width: size * SHADOW_ATLAS_COLUMNS,
+					height: size * SHADOW_ATLAS_ROWS,
+				},
+			})
+			.clear_values(&clear_values)
+			.build();
+		device.device.cmd_begin_render_pass(
+			command_buffer,
+			&render_pass_begin_info,
+			vk::SubpassContents::INLINE,
+		);
+		for (i, view) in self.views.iter().enumerate() {
+			let offset = vk::Offset2D {
+				x: ((i as u32 % SHADOW_ATLAS_COLUMNS) * size) as i32,
+				y: ((i as u32 / SHADOW_ATLAS_COLUMNS) * size) as i32,
+			};
+			device.device.cmd_set_viewport(
+				command_buffer,
+				0,
+				&[vk::Viewport {
+					x: offset.x as f32,
+					y: offset.y as f32,
+					width: size as f32,
+					height: size as f32,
+					min_depth: 0.0,
+					max_depth: 1.0,
+				}],
+			);
+			device.device.cmd_set_scissor(
+				command_buffer,
+				0,
+				&[vk::Rect2D {
+					offset,
+					extent: vk::Extent2D { width: size, height: size },
+				}],
+			);
+			for (pipeline_info, skinned) in [(&self.pipeline_info, false), (&self.skinned_pipeline_info, true)] {
+				device.device.cmd_bind_pipeline(
+					command_buffer,
+					vk::PipelineBindPoint::GRAPHICS,
+					pipeline_info.pipeline,
+				);
+				pipeline_info.push(
+					device,
+					&command_buffer,
+					&ShadowPushConstant {
+						light_view_projection: (*view).into(),
+					},
+				);
+				for bucket in buckets.iter() {
+					bucket.render_shadow_casters(
+						device,
+						command_buffer,
+						frame,
+						pipeline_info.pipeline_layout,
+						skinned,
+					);
+				}
+			}
+		}
+		device.device.cmd_end_render_pass(command_buffer);
+	}}
+
+	/// The maps must no longer be in use by any submitted frame.
+	pub fn destroy(
+		&mut self,
+		program_data: &ProgramData,
+	) { unsafe {
+		let device = &program_data.device.device;
+		self.pipeline_info.destroy_pipeline(program_data);
+		self.skinned_pipeline_info.destroy_pipeline(program_data);
+		device.destroy_framebuffer(
+			self.framebuffer,
+			None,
+		);
+		device.destroy_render_pass(
+			self.render_pass,
+			None,
+		);
+		self.sampler.destroy(program_data);
+		device.destroy_image_view(
+			self.image_view,
+			None,
+		);
+		destroy_device_image(
+			program_data,
+			self.image,
+			&self.allocation,
+		);
+	}}
+}
+
+/// Light space box centered on `x`, `y`, depth 0 at `z_near` and 1 at
+/// `z_far` (both negative towards the far side, right handed).
+fn orthographic(
+	x: f32,
+	y: f32,
+	radius: f32,
+	z_near: f32,
+	z_far: f32,
+) -> Matrix4<f32> {
+	let depth = z_near - z_far;
+	Matrix4::new(
+		1.0 / radius, 0.0, 0.0, -x / radius,
+		0.0, 1.0 / radius, 0.0, -y / radius,
+		0.0, 0.0, -1.0 / depth, z_near / depth,
+		0.0, 0.0, 0.0, 1.0,
+	)
+}
+
+/// Perspective view of a spot light's cone, depth 0 to 1 over the range.
+fn spot_matrix(
+	position: Vector3<f32>,
+	direction: Vector3<f32>,
+	outer_angle: f32,
+	range: f32,
+) -> Matrix4<f32> {
+	let direction = direction.normalize();
+	let up = match direction.y.abs() > 0.99 {
+		true => Vector3::x(),
+		false => Vector3::y(),
+	};
+	let view = Matrix4::look_at_rh(
+		&Point3::from(position),
+		&Point3::from(position + direction),
+		&up,
+	);
+	let near = (range * 0.01).max(0.05);
+	let far = range.max(near + 0.001);
+	let focal = 1.0 / outer_angle.clamp(0.01, 1.48).tan();
+	let projection = Matrix4::new(
+		focal, 0.0, 0.0, 0.0,
+		0.0, focal, 0.0, 0.0,
+		0.0, 0.0, far / (near - far), near * far / (near - far),
+		0.0, 0.0, -1.0, 0.0,
+	);
+	projection * view
+}
@@ -0,0 +1,368 @@
+use std::{collections::HashMap, fmt, path::Path};
+
+use ab_glyph::{Font as _, FontVec, GlyphId, PxScale, PxScaleFont, ScaleFont};
+use vpb::ProgramData;
+
+use crate::{Texture, TextureOptions, SamplerDescription, AtlasError, AtlasRegion, PackedRect, pack_rects};
+
+mod layout;
+pub use layout::*;
+mod label;
+pub use label::*;
+
+#[derive(Debug)]
+pub enum FontError {
+	Io(std::io::Error),
+	InvalidFont,
+	Atlas(AtlasError),
+}
+
+impl fmt::Display for FontError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			FontError::Io(e) => write!(f, "font io error: {}", e),
+			FontError::InvalidFont => write!(f, "not a valid ttf or otf font"),
+			FontError::Atlas(e) => write!(f, "font atlas error: {}", e),
+		}
+	}
+}
+
+impl std::error::Error for FontError {}
+
+impl From<std::io::Error> for FontError {
+	fn from(e: std::io::Error) -> Self {
+		FontError::Io(e)
+	}
+}
+
+impl From<ab_glyph::InvalidFont> for FontError {
+	fn from(_: ab_glyph::InvalidFont) -> Self {
+		FontError::InvalidFont
+	}
+}
+
+impl From<AtlasError> for FontError {
+	fn from(e: AtlasError) -> Self {
+		FontError::Atlas(e)
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct SdfFontOptions {
+	/// Pixel height glyphs are rasterized at before the distance transform.
+	pub px_size: f32,
+	/// Distance in atlas pixels the field covers on each side of an edge,
+	/// also the widest outline the font can draw.
+	pub range: u32,
+	/// Characters baked into the atlas.
+	pub charset: String,
+	/// Drawn in place of characters missing from the atlas.
+	pub fallback: char,
+	pub max_atlas_size: u32,
+}
+
+impl Default for SdfFontOptions {
+	fn default() -> Self {
+		Self {
+			px_size: 48.0,
+			range: 6,
+			// Printable ASCII and Latin-1.
+			charset: (' '..='~').chain('\u{a0}'..='\u{ff}').collect(),
+			fallback: '?',
+			max_atlas_size: 4096,
+		}
+	}
+}
+
+/// Glyph baked into an `SdfFont` atlas. Offsets and sizes are in atlas
+/// pixels, scale them by `size / SdfFont::px_size` to lay out text.
+#[derive(Clone, Copy, Debug)]
+pub struct SdfGlyph {
+	pub region: AtlasRegion,
+	/// Top left of the quad from the pen position on the baseline.
+	pub offset: [f32; 2],
+	pub size: [f32; 2],
+}
+
+/// TTF or OTF font with its glyphs baked into a signed distance field
+/// atlas. The field is stored in alpha, 0.5 is the glyph edge and larger
+/// values are inside.
+pub struct SdfFont {
+	pub font: FontVec,
+	pub texture: Texture,
+	pub glyphs: HashMap<GlyphId, SdfGlyph>,
+	pub px_size: f32,
+	pub range: u32,
+	fallback: GlyphId,
+}
+
+impl SdfFont {
+	pub fn from_file<P: AsRef<Path>>(
+		program_data: &ProgramData,
+		path: P,
+		options: &SdfFontOptions,
+	) -> Result<Self, FontError> {
+		SdfFont::from_bytes(
+			program_data,
+			std::fs::read(path)?,
+			options,
+		)
+	}
+
+	pub fn from_bytes(
+		program_data: &ProgramData,
+		bytes: Vec<u8>,
+		options: &SdfFontOptions,
+	) -> Result<Self, FontError> {
+		let font = FontVec::try_from_vec(bytes)?;
+		let range = options.range as i32;
+		let mut ids: Vec<GlyphId> = options.charset.chars().chain(
+			std::iter::once(options.fallback)
+		).map(
+			|x|
+			font.glyph_id(x)
+		).collect();
+		ids.sort();
+		ids.dedup();
+		// (id, offset, width, height, field)
+		let mut bitmaps = Vec::with_capacity(ids.len());
+		for id in ids.iter() {
+			let outlined = match font.outline_glyph(id.with_scale(options.px_size)) {
+				Some(outlined) => outlined,
+				// Whitespace, only the advance matters.
+				None => {
+					bitmaps.push((*id, [0.0; 2], 0, 0, Vec::new()));
+					continue;
+				},
+			};
+			let bounds = outlined.px_bounds();
+			let width = bounds.width() as i32 + range * 2;
+			let height = bounds.height() as i32 + range * 2;
+			let mut coverage = vec![0.0; (width * height) as usize];
+			outlined.draw(|x, y, c| {
+				coverage[((y as i32 + range) * width + x as i32 + range) as usize] = c;
+			});
+			bitmaps.push((
+				*id,
+				[bounds.min.x - range as f32, bounds.min.y - range as f32],
+				width as u32,
+				height as u32,
+				distance_field(&coverage, width as usize, height as usize, options.range),
+			));
+		}
+		let sizes: Vec<(u32, u32)> = bitmaps.iter().map(|x| (x.2, x.3)).collect();
+		let (atlas_size, rects) = pack_rects(
+			&sizes,
+			options.max_atlas_size,
+			1,
+		)?;
+		let mut pixels = vec![0u8; (atlas_size * atlas_size * 4) as usize];
+		let mut glyphs = HashMap::with_capacity(bitmaps.len());
+		for ((id, offset, width, height, field), rect) in bitmaps.iter().zip(rects.iter()) {
+			for (i, distance) in field.iter().enumerate() {
+				let x = rect.x + i as u32 % width;
+				let y = rect.y + i as u32 / width;
+				let start = ((y * atlas_size + x) * 4) as usize;
+				pixels[start..start + 4].copy_from_slice(&[255, 255, 255, *distance]);
+			}
+			glyphs.insert(*id, SdfGlyph {
+				region: AtlasRegion::new(
+					PackedRect { width: *width, height: *height, ..*rect },
+					atlas_size,
+				),
+				offset: *offset,
+				size: [*width as f32, *height as f32],
+			});
+		}
+		let texture = Texture::from_rgba8(
+			program_data,
+			atlas_size,
+			atlas_size,
+			&pixels,
+			&TextureOptions {
+				mipmaps: false,
+				sampler: SamplerDescription::default(),
+				srgb: false,
+			},
+		);
+		Ok(Self {
+			fallback: font.glyph_id(options.fallback),
+			font,
+			texture,
+			glyphs,
+			px_size: options.px_size,
+			range: options.range,
+		})
+	}
+
+	/// Glyph drawn for `c`, the fallback when it isn't in the atlas.
+	pub fn glyph_id(
+		&self,
+		c: char,
+	) -> GlyphId {
+		let id = self.font.glyph_id(c);
+		match self.glyphs.contains_key(&id) {
+			true => id,
+			false => self.fallback,
+		}
+	}
+
+	pub fn glyph(
+		&self,
+		id: GlyphId,
+	) -> Option<&SdfGlyph> {
+		self.glyphs.get(&id)
+	}
+
+	/// Metrics at `size` pixels from ascent to descent.
+	pub fn scaled(
+		&self,
+		size: f32,
+	) -> PxScaleFont<&FontVec> {
+		self.font.as_scaled(PxScale::from(size))
+	}
+
+	pub fn destroy(
+		&mut self,
+		program_data: &ProgramData,
+	) {
+		self.texture.destroy(program_data);
+	}
+}
+
+impl LayoutFont for SdfFont {
+	fn glyph_id(
+		&self,
+		c: char,
+	) -> GlyphId {
+		SdfFont::glyph_id(self, c)
+	}
+
+	fn glyph(
+		&self,
+		id: GlyphId,
+	) -> Option<&SdfGlyph> {
+		SdfFont::glyph(self, id)
+	}
+
+	fn px_size(
+		&self,
+	) -> f32 {
+		self.px_size
+	}
+
+	fn h_advance(
+		&self,
+		id: GlyphId,
+		size: f32,
+	) -> f32 {
+		self.scaled(size).h_advance(id)
+	}
+
+	fn kern(
+		&self,
+		first: GlyphId,
+		second: GlyphId,
+		size: f32,
+	) -> f32 {
+		self.scaled(size).kern(first, second)
+	}
+
+	fn v_metrics(
+		&self,
+		size: f32,
+	) -> [f32; 3] {
+		let scaled = self.scaled(size);
+		[scaled.ascent(), scaled.descent(), scaled.line_gap()]
+	}
+}
+
+/// Signed distance of every pixel to the coverage edge, mapped so `range`
+/// pixels outside is 0 and `range` pixels inside is 255.
+fn distance_field(
+	coverage: &[f32],
+	width: usize,
+	height: usize,
+	range: u32,
+) -> Vec<u8> {
+	let inside: Vec<bool> = coverage.iter().map(|x| *x >= 0.5).collect();
+	let outside: Vec<bool> = inside.iter().map(|x| !x).collect();
+	let to_inside = squared_distances(&inside, width, height);
+	let to_outside = squared_distances(&outside, width, height);
+	(0..coverage.len()).map(
+		|i| {
+			// Pixel centers are half a pixel away from the edge they touch.
+			let distance = match inside[i] {
+				true => to_outside[i].sqrt() - 0.5,
+				false => 0.5 - to_inside[i].sqrt(),
+			};
+			let value = 0.5 + distance / (2.0 * range as f64);
+			(value.clamp(0.0, 1.0) * 255.0).round() as u8
+		}
+	).collect()
+}
+
+const FAR: f64 = 1e20;
+
+/// Squared euclidean distance from every pixel to the nearest set pixel,
+/// as separable 1d passes over the columns then the rows (Felzenszwalb and
+/// Huttenlocher).
+fn squared_distances(
+	grid: &[bool],
+	width: usize,
+	height: usize,
+) -> Vec<f64> {
+	let mut distances: Vec<f64> = grid.iter().map(|x| if *x { 0.0 } else { FAR }).collect();
+	let mut line = vec![0.0; width.max(height)];
+	let mut result = vec![0.0; width.max(height)];
+	for x in 0..width {
+		for y in 0..height {
+			line[y] = distances[y * width + x];
+		}
+		squared_distances_1d(&line[..height], &mut result[..height]);
+		for y in 0..height {
+			distances[y * width + x] = result[y];
+		}
+	}
+	for y in 0..height {
+		line[..width].copy_from_slice(&distances[y * width..(y + 1) * width]);
+		squared_distances_1d(&line[..width], &mut result[..width]);
+		distances[y * width..(y + 1) * width].copy_from_slice(&result[..width]);
+	}
+	distances
+}
+
+/// Lower envelope of the parabolas rooted at every sample of `f`.
+fn squared_distances_1d(
+	f: &[f64],
+	d: &mut [f64],
+) {
+	let n = f.len();
+	let mut v = vec![0usize; n];
+	let mut z = vec![0.0; n + 1];
+	let mut k = 0;
+	z[0] = -FAR;
+	z[1] = FAR;
+	let intersection = |q: usize, p: usize| {
+		((f[q] + (q * q) as f64) - (f[p] + (p * p) as f64)) / (2.0 * q as f64 - 2.0 * p as f64)
+	};
+	for q in 1..n {
+		let mut s = intersection(q, v[k]);
+		while s <= z[k] {
+			k -= 1;
+			s = intersection(q, v[k]);
+		}
+		k += 1;
+		v[k] = q;
+		z[k] = s;
+		z[k + 1] = FAR;
+	}
+	k = 0;
+	for (q, d) in d.iter_mut().enumerate() {
+		while z[k + 1] < q as f64 {
+			k += 1;
+		}
+		let delta = q as f64 - v[k] as f64;
+		*d = delta * delta + f[v[k]];
+	}
+}
@@ -0,0 +1,240 @@
+use std::sync::Arc;
+
+use ash::vk;
+use nalgebra::Matrix4;
+use vpb::ProgramData;
+
+use crate::{HostMesh, ObjectStateBuffers, DynamicDirtyState, VertexUITextured, BlockText, BlockStateExt, SdfFont, TextLayout, TextLayoutOptions, dynamic::{ObjectDynamic, state::DynamicState}, update::UpdateState};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TextSpace {
+	/// Window pixels, unaffected by the camera's pan and zoom.
+	Screen,
+	/// World units, moves and scales with the camera.
+	#[default]
+	World,
+}
+
+/// Laid out string drawn from an `SdfFont` atlas. Block states are laid
+/// out like `PipelineTextExample`, bind the font texture with
+/// `Bucket::bind_texture`. Left out of saved scenes, fonts have no
+/// description.
+pub struct Text {
+	state: Arc<DynamicState>,
+	host_mesh: Arc<HostMesh>,
+	font: Arc<SdfFont>,
+	text: String,
+	options: TextLayoutOptions,
+	layout: TextLayout,
+	capacity: usize,
+	vertices: Vec<VertexUITextured>,
+	indices: Vec<u32>,
+	block: BlockText,
+}
+
+impl Text {
+	/// Index of the `BlockText` in the object's block states.
+	pub const TEXT_BLOCK: usize = 1;
+
+	/// `capacity` is the most glyphs the text can ever show, the rest are
+	/// dropped from the end of the layout.
+	pub fn new(
+		program_data: &ProgramData,
+		name: &str,
+		font: Arc<SdfFont>,
+		capacity: usize,
+	) -> Self {
+		let host_mesh = Arc::new(HostMesh::new(
+			program_data,
+			capacity * 4 * std::mem::size_of::<VertexUITextured>(),
+			capacity * 6,
+		));
+		let state = Arc::new(DynamicState::new(
+			program_data,
+			name.to_string(),
+			ObjectStateBuffers::HostIndexed(host_mesh.clone()),
+		));
+		let mut block = BlockText::default();
+		block.params.value[1] = font.range as f32;
+		Self {
+			state,
+			host_mesh,
+			font,
+			text: String::new(),
+			options: TextLayoutOptions::default(),
+			layout: TextLayout::default(),
+			capacity,
+			vertices: Vec::with_capacity(capacity * 4),
+			indices: Vec::with_capacity(capacity * 6),
+			block,
+		}
+	}
+
+	pub fn text(
+		&self,
+	) -> &str {
+		&self.text
+	}
+
+	/// Current layout, for measuring or hit testing the text.
+	pub fn layout(
+		&self,
+	) -> &TextLayout {
+		&self.layout
+	}
+
+	/// Returns false when the text has more glyphs than the capacity and
+	/// was cut short.
+	pub fn set_text(
+		&mut self,
+		text: &str,
+	) -> bool {
+		self.text.clear();
+		self.text.push_str(text);
+		self.relayout()
+	}
+
+	/// Returns false when the text no longer fits the capacity, see
+	/// `set_text`.
+	pub fn set_options(
+		&mut self,
+		options: TextLayoutOptions,
+	) -> bool {
+		self.options = options;
+		self.relayout()
+	}
+
+	/// Places the top left of the layout, in pixels for `TextSpace::Screen`.
+	pub fn set_transform(
+		&mut self,
+		model: Matrix4<f32>,
+	) {
+		self.block.model = model.into();
+		self.dirty(DynamicDirtyState::Position);
+	}
+
+	pub fn set_space(
+		&mut self,
+		space: TextSpace,
+	) {
+		self.block.params.value[0] = match space {
+			TextSpace::Screen => 1.0,
+			TextSpace::World => 0.0,
+		};
+		self.dirty(DynamicDirtyState::Position);
+	}
+
+	pub fn set_color(
+		&mut self,
+		color: [f32; 4],
+	) {
+		self.block.color.value = color;
+		self.dirty(DynamicDirtyState::Position);
+	}
+
+	/// Outline `width` atlas pixels wide, at most the font's range.
+	pub fn set_outline(
+		&mut self,
+		color: [f32; 4],
+		width: f32,
+	) {
+		self.block.outline_color.value = color;
+		self.block.params.value[2] = width.clamp(0.0, self.font.range as f32);
+		self.dirty(DynamicDirtyState::Position);
+	}
+
+	fn relayout(
+		&mut self,
+	) -> bool {
+		let (layout, fits) = TextLayout::new_truncated(
+			self.font.as_ref(),
+			&self.text,
+			&self.options,
+			self.capacity,
+		);
+		self.layout = layout;
+		self.dirty(DynamicDirtyState::Mesh);
+		fits
+	}
+}
+
+impl ObjectDynamic for Text {
+	fn state(&self) -> Arc<DynamicState> {
+		self.state.clone()
+	}
+
+	/// Writes the indices too, they only change with the glyph count.
+	fn update_vb(
+		&mut self,
+		_device: &vpb::Device,
+	) {
+		self.vertices.clear();
+		self.indices.clear();
+		let color = [1.0; 4];
+		for (i, glyph) in self.layout.glyphs.iter().enumerate() {
+			let [x, y] = glyph.position;
+			let [w, h] = glyph.size;
+			let [u0, v0] = glyph.uv_min;
+			let [u1, v1] = glyph.uv_max;
+			self.vertices.extend_from_slice(&[
+				VertexUITextured { position: [x, y], uv: [u0, v0], color },
+				VertexUITextured { position: [x + w, y], uv: [u1, v0], color },
+				VertexUITextured { position: [x + w, y + h], uv: [u1, v1], color },
+				VertexUITextured { position: [x, y + h], uv: [u0, v1], color },
+			]);
+			let base = (i * 4) as u32;
+			self.indices.extend_from_slice(&[
+				base, base + 1, base + 2,
+				base + 2, base + 3, base,
+			]);
+		}
+		let vertices: &[u8] = unsafe {
+			std::slice::from_raw_parts(
+				self.vertices.as_ptr() as *const u8,
+				self.vertices.len() * std::mem::size_of::<VertexUITextured>(),
+			)
+		};
+		vpb::gmuc!(self.host_mesh).write(
+			vertices,
+			&self.indices,
+		);
+	}
+
+	fn update_ib(
+		&mut self,
+		_device: &vpb::Device,
+	) {}
+
+	fn update_bs(
+		&mut self,
+		device: &vpb::Device,
+		frame: usize,
+	) {
+		let block_states = self.state.sub_state.block_states.as_ref().expect(
+			"attempting to update a text that isn't in a bucket"
+		);
+		block_states[Text::TEXT_BLOCK].uniform().update_checked(
+			device,
+			&self.block,
+			Some(frame),
+		);
+	}
+}
+
+impl UpdateState for Text {
+	fn update_block_states(
+		&mut self,
+		program_data: &ProgramData,
+		frame: usize,
+		frame_count: usize,
+		_command_buffer: &vk::CommandBuffer,
+		_pipeline_layout: &vk::PipelineLayout,
+	) {
+		ObjectDynamic::update_block_states(
+			self,
+			&program_data.device,
+			frame,
+			frame_count,
+		);
+	}
+}
@@ -0,0 +1,396 @@
+use ab_glyph::GlyphId;
+
+use crate::SdfGlyph;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TextAlign {
+	#[default]
+	Left,
+	Center,
+	Right,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct TextLayoutOptions {
+	/// Pixels from ascent to descent, same as `ab_glyph::PxScale`.
+	pub size: f32,
+	/// Wraps at spaces past this width. Words longer than a line are split.
+	pub max_width: Option<f32>,
+	/// Lines are aligned inside `max_width`, or the widest line without it.
+	pub align: TextAlign,
+	/// Multiplies the font's line height.
+	pub line_spacing: f32,
+}
+
+impl Default for TextLayoutOptions {
+	fn default() -> Self {
+		Self {
+			size: 24.0,
+			max_width: None,
+			align: TextAlign::Left,
+			line_spacing: 1.0,
+		}
+	}
+}
+
+/// Glyph metrics `TextLayout` reads, implemented by `SdfFont`. `size` is
+/// in pixels from ascent to descent.
+pub trait LayoutFont {
+	/// Glyph drawn for `c`.
+	fn glyph_id(
+		&self,
+		c: char,
+	) -> GlyphId;
+
+	/// Quad of the glyph, `None` for glyphs that draw nothing.
+	fn glyph(
+		&self,
+		id: GlyphId,
+	) -> Option<&SdfGlyph>;
+
+	/// Pixel height the glyph quads are measured at.
+	fn px_size(
+		&self,
+	) -> f32;
+
+	fn h_advance(
+		&self,
+		id: GlyphId,
+		size: f32,
+	) -> f32;
+
+	fn kern(
+		&self,
+		first: GlyphId,
+		second: GlyphId,
+		size: f32,
+	) -> f32;
+
+	/// Ascent, descent and line gap, the descent is negative.
+	fn v_metrics(
+		&self,
+		size: f32,
+	) -> [f32; 3];
+}
+
+/// Quad of one glyph, in pixels from the top left of the layout.
+#[derive(Clone, Copy, Debug)]
+pub struct LaidOutGlyph {
+	pub position: [f32; 2],
+	pub size: [f32; 2],
+	pub uv_min: [f32; 2],
+	pub uv_max: [f32; 2],
+	/// Byte index of the character in the source text.
+	pub byte_index: usize,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct PenGlyph {
+	id: GlyphId,
+	x: f32,
+	byte_index: usize,
+}
+
+#[derive(Default)]
+struct Line {
+	glyphs: Vec<PenGlyph>,
+	/// Up to the last glyph that isn't whitespace.
+	width: f32,
+}
+
+/// UTF-8 text broken into lines and positioned glyph quads. Kerning comes
+/// from the font, explicit line breaks are `\n`.
+#[derive(Clone, Debug, Default)]
+pub struct TextLayout {
+	pub glyphs: Vec<LaidOutGlyph>,
+	pub line_widths: Vec<f32>,
+	pub width: f32,
+	pub height: f32,
+}
+
+impl TextLayout {
+	pub fn new<F: LayoutFont>(
+		font: &F,
+		text: &str,
+		options: &TextLayoutOptions,
+	) -> Self {
+		let mut lines = Vec::new();
+		let mut line = Line::default();
+		let mut pen = 0.0;
+		let mut previous: Option<GlyphId> = None;
+		// Glyph index the current word starts at and the line width before
+		// the spaces preceding it, where the line can be wrapped.
+		let mut word_start: Option<(usize, f32)> = None;
+		let mut after_space = false;
+		for (byte_index, c) in text.char_indices() {
+			if c == '\n' {
+				lines.push(std::mem::take(&mut line));
+				pen = 0.0;
+				previous = None;
+				word_start = None;
+				after_space = false;
+				continue;
+			}
+			if c.is_control() {
+				continue;
+			}
+			let id = font.glyph_id(c);
+			if let Some(previous) = previous {
+				pen += font.kern(previous, id, options.size);
+			}
+			let advance = font.h_advance(id, options.size);
+			previous = Some(id);
+			if c.is_whitespace() {
+				pen += advance;
+				after_space = true;
+				continue;
+			}
+			if after_space {
+				word_start = Some((line.glyphs.len(), line.width));
+				after_space = false;
+			}
+			let overflows = options.max_width.map_or(false, |x| pen + advance > x);
+			if overflows && !line.glyphs.is_empty() {
+				let mut next = Line::default();
+				match word_start {
+					Some((start, width)) if start > 0 => {
+						next.glyphs = line.glyphs.split_off(start);
+						let shift = next.glyphs[0].x;
+						for glyph in next.glyphs.iter_mut() {
+							glyph.x -= shift;
+						}
+						next.width = line.width - shift;
+						line.width = width;
+						pen -= shift;
+					},
+					_ => {
+						pen = 0.0;
+					},
+				}
+				lines.push(std::mem::replace(&mut line, next));
+				word_start = None;
+			}
+			line.glyphs.push(PenGlyph {
+				id,
+				x: pen,
+				byte_index,
+			});
+			pen += advance;
+			line.width = pen;
+		}
+		lines.push(line);
+
+		let [ascent, descent, line_gap] = font.v_metrics(options.size);
+		let line_height = (ascent - descent + line_gap) * options.line_spacing;
+		let widest = lines.iter().fold(0.0f32, |a, x| a.max(x.width));
+		let box_width = options.max_width.unwrap_or(widest);
+		let scale = options.size / font.px_size();
+		let mut glyphs = Vec::with_capacity(text.len());
+		for (i, line) in lines.iter().enumerate() {
+			let baseline = ascent + i as f32 * line_height;
+			let offset = match options.align {
+				TextAlign::Left => 0.0,
+				TextAlign::Center => (box_width - line.width) * 0.5,
+				TextAlign::Right => box_width - line.width,
+			};
+			for glyph in line.glyphs.iter() {
+				let sdf = match font.glyph(glyph.id) {
+					Some(sdf) if sdf.size[0] > 0.0 => sdf,
+					_ => continue,
+				};
+				glyphs.push(LaidOutGlyph {
+					position: [
+						offset + glyph.x + sdf.offset[0] * scale,
+						baseline + sdf.offset[1] * scale,
+					],
+					size: [sdf.size[0] * scale, sdf.size[1] * scale],
+					uv_min: sdf.region.uv_min,
+					uv_max: sdf.region.uv_max,
+					byte_index: glyph.byte_index,
+				});
+			}
+		}
+		Self {
+			glyphs,
+			line_widths: lines.iter().map(|x| x.width).collect(),
+			width: widest,
+			height: lines.len() as f32 * line_height,
+		}
+	}
+
+	/// Same as `new` keeping the first `max_glyphs` glyphs, measured and
+	/// aligned as if the text ended there. Returns false when glyphs were
+	/// dropped.
+	pub fn new_truncated<F: LayoutFont>(
+		font: &F,
+		text: &str,
+		options: &TextLayoutOptions,
+		max_glyphs: usize,
+	) -> (Self, bool) {
+		let layout = TextLayout::new(
+			font,
+			text,
+			options,
+		);
+		match layout.glyphs.get(max_glyphs) {
+			// Line breaks and pen positions only depend on the text before
+			// a glyph, so the kept glyphs land on the same lines.
+			Some(dropped) => (TextLayout::new(
+				font,
+				text[..dropped.byte_index].trim_end(),
+				options,
+			), false),
+			None => (layout, true),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::HashMap;
+
+	use crate::{AtlasRegion, PackedRect};
+
+	use super::*;
+
+	/// Monospaced, every printable ASCII character advances by the size,
+	/// "AV" is kerned together by three tenths of it.
+	struct TestFont {
+		glyphs: HashMap<GlyphId, SdfGlyph>,
+	}
+
+	impl TestFont {
+		fn new(
+		) -> Self {
+			let glyph = SdfGlyph {
+				region: AtlasRegion::new(
+					PackedRect { x: 0, y: 0, width: 10, height: 10 },
+					64,
+				),
+				offset: [0.0, -8.0],
+				size: [10.0, 10.0],
+			};
+			Self {
+				glyphs: ('!'..='~').map(|c| (GlyphId(c as u16), glyph)).collect(),
+			}
+		}
+	}
+
+	impl LayoutFont for TestFont {
+		fn glyph_id(
+			&self,
+			c: char,
+		) -> GlyphId {
+			GlyphId(c as u16)
+		}
+
+		fn glyph(
+			&self,
+			id: GlyphId,
+		) -> Option<&SdfGlyph> {
+			self.glyphs.get(&id)
+		}
+
+		fn px_size(
+			&self,
+		) -> f32 {
+			10.0
+		}
+
+		fn h_advance(
+			&self,
+			_id: GlyphId,
+			size: f32,
+		) -> f32 {
+			size
+		}
+
+		fn kern(
+			&self,
+			first: GlyphId,
+			second: GlyphId,
+			size: f32,
+		) -> f32 {
+			match (first, second) {
+				(GlyphId(0x41), GlyphId(0x56)) => -3.0 * size / 10.0,
+				_ => 0.0,
+			}
+		}
+
+		fn v_metrics(
+			&self,
+			size: f32,
+		) -> [f32; 3] {
+			[8.0 * size / 10.0, -2.0 * size / 10.0, 0.0]
+		}
+	}
+
+	fn options(
+		max_width: Option<f32>,
+		align: TextAlign,
+	) -> TextLayoutOptions {
+		TextLayoutOptions {
+			size: 10.0,
+			max_width,
+			align,
+			..Default::default()
+		}
+	}
+
+	fn positions(
+		layout: &TextLayout,
+	) -> Vec<[f32; 2]> {
+		layout.glyphs.iter().map(|x| x.position).collect()
+	}
+
+	#[test]
+	fn kerning_moves_the_pen() {
+		let layout = TextLayout::new(&TestFont::new(), "AVA", &options(None, TextAlign::Left));
+		assert_eq!(positions(&layout), vec![[0.0, 0.0], [7.0, 0.0], [17.0, 0.0]]);
+		assert_eq!(layout.width, 27.0);
+	}
+
+	#[test]
+	fn wraps_at_word_starts() {
+		let layout = TextLayout::new(&TestFont::new(), "aa bb cc", &options(Some(45.0), TextAlign::Left));
+		assert_eq!(
+			positions(&layout),
+			vec![[0.0, 0.0], [10.0, 0.0], [0.0, 10.0], [10.0, 10.0], [0.0, 20.0], [10.0, 20.0]],
+		);
+		assert_eq!(layout.line_widths, vec![20.0; 3]);
+		assert_eq!(layout.height, 30.0);
+		assert_eq!(layout.glyphs[2].byte_index, 3);
+	}
+
+	#[test]
+	fn splits_words_longer_than_a_line() {
+		let layout = TextLayout::new(&TestFont::new(), "aaaaa", &options(Some(25.0), TextAlign::Left));
+		assert_eq!(layout.line_widths, vec![20.0, 20.0, 10.0]);
+	}
+
+	#[test]
+	fn aligns_lines() {
+		let font = TestFont::new();
+		let xs = |layout: TextLayout| layout.glyphs.iter().map(|x| x.position[0]).collect::<Vec<f32>>();
+		assert_eq!(xs(TextLayout::new(&font, "a\nbbb", &options(None, TextAlign::Center))), vec![10.0, 0.0, 10.0, 20.0]);
+		assert_eq!(xs(TextLayout::new(&font, "a\nbbb", &options(None, TextAlign::Right))), vec![20.0, 0.0, 10.0, 20.0]);
+		assert_eq!(xs(TextLayout::new(&font, "a\nbbb", &options(Some(50.0), TextAlign::Center))), vec![20.0, 10.0, 20.0, 30.0]);
+		assert_eq!(xs(TextLayout::new(&font, "a\nbbb", &options(Some(50.0), TextAlign::Right))), vec![40.0, 20.0, 30.0, 40.0]);
+	}
+
+	#[test]
+	fn truncation_measures_the_kept_glyphs() {
+		let font = TestFont::new();
+		let (layout, fits) = TextLayout::new_truncated(&font, "aaa\nb cc", &options(None, TextAlign::Right), 5);
+		assert!(!fits);
+		assert_eq!(layout.glyphs.len(), 5);
+		assert_eq!(layout.line_widths, vec![30.0, 30.0]);
+		assert_eq!(layout.width, 30.0);
+		assert_eq!(layout.glyphs[3].position[0], 0.0);
+		let (layout, fits) = TextLayout::new_truncated(&font, "aaa\nb cc", &options(None, TextAlign::Right), 6);
+		assert!(fits);
+		assert_eq!(layout.width, 40.0);
+		let (layout, _) = TextLayout::new_truncated(&font, "ab\ncd", &options(None, TextAlign::Left), 2);
+		assert_eq!(layout.height, 10.0);
+	}
+}
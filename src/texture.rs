@@ -0,0 +1,675 @@
+use std::{fmt, fs::File, path::Path};
+
+use ash::vk;
+use vpb::ProgramData;
+
+use crate::{HostBuffer, EngineBlockState, allocator, submit_setup_commands};
+
+mod atlas;
+pub use atlas::*;
+mod hdr;
+pub use hdr::*;
+mod cubemap;
+pub use cubemap::*;
+
+#[derive(Debug)]
+pub enum TextureError {
+	Io(std::io::Error),
+	Png(png::DecodingError),
+	UnsupportedFormat(png::ColorType, png::BitDepth),
+	/// Malformed or unsupported Radiance hdr file.
+	Hdr(&'static str),
+	/// Cubemap face index and its size, faces must be square and all the
+	/// same size.
+	CubemapFace(usize, u32, u32),
+}
+
+impl fmt::Display for TextureError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			TextureError::Io(e) => write!(f, "texture io error: {}", e),
+			TextureError::Png(e) => write!(f, "texture png error: {}", e),
+			TextureError::UnsupportedFormat(color_type, bit_depth) => write!(
+				f, "unsupported png format {:?} with {:?} bit depth", color_type, bit_depth,
+			),
+			TextureError::Hdr(e) => write!(f, "texture hdr error: {}", e),
+			TextureError::CubemapFace(face, width, height) => write!(
+				f, "cubemap face {} is {}x{}, faces must be square and the same size", face, width, height,
+			),
+		}
+	}
+}
+
+impl std::error::Error for TextureError {}
+
+impl From<std::io::Error> for TextureError {
+	fn from(e: std::io::Error) -> Self {
+		TextureError::Io(e)
+	}
+}
+
+impl From<png::DecodingError> for TextureError {
+	fn from(e: png::DecodingError) -> Self {
+		TextureError::Png(e)
+	}
+}
+
+/// Decodes a png into tightly packed RGBA8 pixels.
+pub fn decode_png<P: AsRef<Path>>(
+	path: P,
+) -> Result<(u32, u32, Vec<u8>), TextureError> {
+	let mut decoder = png::Decoder::new(File::open(path)?);
+	decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+	let mut reader = decoder.read_info()?;
+	let mut buffer = vec![0; reader.output_buffer_size()];
+	let info = reader.next_frame(&mut buffer)?;
+	let bytes = &buffer[..info.buffer_size()];
+	let pixels = match info.color_type {
+		png::ColorType::Rgba => bytes.to_vec(),
+		png::ColorType::Rgb => bytes.chunks_exact(3).flat_map(
+			|x| [x[0], x[1], x[2], 255]
+		).collect(),
+		png::ColorType::GrayscaleAlpha => bytes.chunks_exact(2).flat_map(
+			|x| [x[0], x[0], x[0], x[1]]
+		).collect(),
+		png::ColorType::Grayscale => bytes.iter().flat_map(
+			|x| [*x, *x, *x, 255]
+		).collect(),
+		color_type => return Err(TextureError::UnsupportedFormat(color_type, info.bit_depth)),
+	};
+	Ok((info.width, info.height, pixels))
+}
+
+pub fn transition_image_layout(
+	device: &vpb::Device,
+	command_buffer: vk::CommandBuffer,
+	image: vk::Image,
+	old_layout: vk::ImageLayout,
+	new_layout: vk::ImageLayout,
+	mip_levels: u32,
+) {
+	transition_image_layers(
+		device,
+		command_buffer,
+		image,
+		old_layout,
+		new_layout,
+		mip_levels,
+		1,
+	);
+}
+
+/// Same as `transition_image_layout` for array and cube images. Pairs
+/// other than uploads wait on all earlier commands.
+pub fn transition_image_layers(
+	device: &vpb::Device,
+	command_buffer: vk::CommandBuffer,
+	image: vk::Image,
+	old_layout: vk::ImageLayout,
+	new_layout: vk::ImageLayout,
+	mip_levels: u32,
+	layers: u32,
+) { unsafe {
+	let (src_access, dst_access, src_stage, dst_stage) = match (old_layout, new_layout) {
+		(vk::ImageLayout::UNDEFINED, vk::ImageLayout::TRANSFER_DST_OPTIMAL) => (
+			vk::AccessFlags::empty(),
+			vk::AccessFlags::TRANSFER_WRITE,
+			vk::PipelineStageFlags::TOP_OF_PIPE,
+			vk::PipelineStageFlags::TRANSFER,
+		),
+		(vk::ImageLayout::TRANSFER_DST_OPTIMAL, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL) => (
+			vk::AccessFlags::TRANSFER_WRITE,
+			vk::AccessFlags::SHADER_READ,
+			vk::PipelineStageFlags::TRANSFER,
+			vk::PipelineStageFlags::FRAGMENT_SHADER,
+		),
+		_ => (
+			vk::AccessFlags::MEMORY_WRITE,
+			vk::AccessFlags::MEMORY_READ | vk::AccessFlags::MEMORY_WRITE,
+			vk::PipelineStageFlags::ALL_COMMANDS,
+			vk::PipelineStageFlags::ALL_COMMANDS,
+		),
+	};
+	let barrier = vk::ImageMemoryBarrier::builder()
+		.image(image)
+		.old_layout(old_layout)
+		.new_layout(new_layout)
+		.src_access_mask(src_access)
+		.dst_access_mask(dst_access)
+		.src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+		.dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+		.subresource_range(
+			vk::ImageSubresourceRange::builder()
+				.aspect_mask(vk::ImageAspectFlags::COLOR)
+				.level_count(mip_levels)
+				.layer_count(layers)
+				.build()
+		).build();
+	device.device.cmd_pipeline_barrier(
+		command_buffer,
+		src_stage,
+		dst_stage,
+		vk::DependencyFlags::empty(),
+		&[],
+		&[],
+		&[barrier],
+	);
+}}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SamplerDescription {
+	pub mag_filter: vk::Filter,
+	pub min_filter: vk::Filter,
+	pub mipmap_mode: vk::SamplerMipmapMode,
+	pub address_mode: vk::SamplerAddressMode,
+	/// Clamped to the device limit, ignored when the device doesn't
+	/// support anisotropic filtering.
+	pub anisotropy: Option<f32>,
+	pub lod_bias: f32,
+}
+
+impl Default for SamplerDescription {
+	fn default() -> Self {
+		Self {
+			mag_filter: vk::Filter::LINEAR,
+			min_filter: vk::Filter::LINEAR,
+			mipmap_mode: vk::SamplerMipmapMode::LINEAR,
+			address_mode: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+			anisotropy: None,
+			lod_bias: 0.0,
+		}
+	}
+}
+
+impl SamplerDescription {
+	/// Nearest filtering without mip blending, for pixel art.
+	pub fn nearest(
+	) -> Self {
+		Self {
+			mag_filter: vk::Filter::NEAREST,
+			min_filter: vk::Filter::NEAREST,
+			mipmap_mode: vk::SamplerMipmapMode::NEAREST,
+			..Default::default()
+		}
+	}
+}
+
+pub struct Sampler {
+	pub sampler: vk::Sampler,
+	pub description: SamplerDescription,
+}
+
+impl Sampler {
+	pub fn new(
+		program_data: &ProgramData,
+		description: SamplerDescription,
+	) -> Self { unsafe {
+		let instance = &program_data.instance.instance;
+		let physical_device = program_data.device.physical_device;
+		let anisotropy = match description.anisotropy {
+			Some(anisotropy) if instance.get_physical_device_features(physical_device).sampler_anisotropy == vk::TRUE => {
+				let limits = instance.get_physical_device_properties(physical_device).limits;
+				Some(anisotropy.clamp(1.0, limits.max_sampler_anisotropy))
+			},
+			_ => None,
+		};
+		let sampler_info = vk::SamplerCreateInfo::builder()
+			.mag_filter(description.mag_filter)
+			.min_filter(description.min_filter)
+			.mipmap_mode(description.mipmap_mode)
+			.address_mode_u(description.address_mode)
+			.address_mode_v(description.address_mode)
+			.address_mode_w(description.address_mode)
+			.anisotropy_enable(anisotropy.is_some())
+			.max_anisotropy(anisotropy.unwrap_or(1.0))
+			.mip_lod_bias(description.lod_bias)
+			.max_lod(vk::LOD_CLAMP_NONE)
+			.build();
+		let sampler = program_data.device.device.create_sampler(
+			&sampler_info,
+			None,
+		).unwrap();
+		Self {
+			sampler,
+			description,
+		}
+	}}
+
+	pub fn destroy(
+		&mut self,
+		program_data: &ProgramData,
+	) { unsafe {
+		program_data.device.device.destroy_sampler(
+			self.sampler,
+			None,
+		);
+	}}
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct TextureOptions {
+	/// Generates the full mip chain with blits at upload time.
+	pub mipmaps: bool,
+	/// Default sampler, bindings can override it with `Texture::bind_with_sampler`.
+	pub sampler: SamplerDescription,
+	/// Decodes the pixels from sRGB when sampled. Turn off for data that
+	/// isn't color, like distance fields or normal maps.
+	pub srgb: bool,
+}
+
+impl Default for TextureOptions {
+	fn default() -> Self {
+		Self {
+			mipmaps: true,
+			sampler: SamplerDescription::default(),
+			srgb: true,
+		}
+	}
+}
+
+pub fn mip_level_count(
+	width: u32,
+	height: u32,
+) -> u32 {
+	32 - width.max(height).max(1).leading_zeros()
+}
+
+/// Records blits from each level into the next for every layer. Every
+/// level must be in `TRANSFER_DST_OPTIMAL` and all end up in
+/// `SHADER_READ_ONLY_OPTIMAL`.
+pub fn generate_mipmaps(
+	device: &vpb::Device,
+	command_buffer: vk::CommandBuffer,
+	image: vk::Image,
+	width: u32,
+	height: u32,
+	mip_levels: u32,
+	layers: u32,
+) { unsafe {
+	let barrier = |level: u32, old_layout, new_layout, src_access, dst_access| {
+		vk::ImageMemoryBarrier::builder()
+			.image(image)
+			.old_layout(old_layout)
+			.new_layout(new_layout)
+			.src_access_mask(src_access)
+			.dst_access_mask(dst_access)
+			.src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+			.dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+			.subresource_range(
+				vk::ImageSubresourceRange::builder()
+					.aspect_mask(vk::ImageAspectFlags::COLOR)
+					.base_mip_level(level)
+					.level_count(1)
+					.layer_count(layers)
+					.build()
+			).build()
+	};
+	let mut level_width = width as i32;
+	let mut level_height = height as i32;
+	for level in 1..mip_levels {
+		device.device.cmd_pipeline_barrier(
+			command_buffer,
+			vk::PipelineStageFlags::TRANSFER,
+			vk::PipelineStageFlags::TRANSFER,
+			vk::DependencyFlags::empty(),
+			&[],
+			&[],
+			&[barrier(
+				level - 1,
+				vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+				vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+				vk::AccessFlags::TRANSFER_WRITE,
+				vk::AccessFlags::TRANSFER_READ,
+			)],
+		);
+		let next_width = (level_width / 2).max(1);
+		let next_height = (level_height / 2).max(1);
+		let blit = vk::ImageBlit::builder()
+			.src_offsets([
+				vk::Offset3D { x: 0, y: 0, z: 0 },
+				vk::Offset3D { x: level_width, y: level_height, z: 1 },
+			])
+			.src_subresource(
+				vk::ImageSubresourceLayers::builder()
+					.aspect_mask(vk::ImageAspectFlags::COLOR)
+					.mip_level(level - 1)
+					.layer_count(layers)
+					.build()
+			)
+			.dst_offsets([
+				vk::Offset3D { x: 0, y: 0, z: 0 },
+				vk::Offset3D { x: next_width, y: next_height, z: 1 },
+			])
+			.dst_subresource(
+				vk::ImageSubresourceLayers::builder()
+					.aspect_mask(vk::ImageAspectFlags::COLOR)
+					.mip_level(level)
+					.layer_count(layers)
+					.build()
+			).build();
+		device.device.cmd_blit_image(
+			command_buffer,
+			image,
+			vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+			image,
+			vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+			&[blit],
+			vk::Filter::LINEAR,
+		);
+		device.device.cmd_pipeline_barrier(
+			command_buffer,
+			vk::PipelineStageFlags::TRANSFER,
+			vk::PipelineStageFlags::FRAGMENT_SHADER,
+			vk::DependencyFlags::empty(),
+			&[],
+			&[],
+			&[barrier(
+				level - 1,
+				vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+				vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+				vk::AccessFlags::TRANSFER_READ,
+				vk::AccessFlags::SHADER_READ,
+			)],
+		);
+		level_width = next_width;
+		level_height = next_height;
+	}
+	device.device.cmd_pipeline_barrier(
+		command_buffer,
+		vk::PipelineStageFlags::TRANSFER,
+		vk::PipelineStageFlags::FRAGMENT_SHADER,
+		vk::DependencyFlags::empty(),
+		&[],
+		&[],
+		&[barrier(
+			mip_levels - 1,
+			vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+			vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+			vk::AccessFlags::TRANSFER_WRITE,
+			vk::AccessFlags::SHADER_READ,
+		)],
+	);
+}}
+
+/// Creates the image in device local memory of the scene's allocator.
+pub fn create_device_image(
+	program_data: &ProgramData,
+	image_info: &vk::ImageCreateInfo,
+) -> (vk::Image, vk_mem::Allocation) {
+	let allocation_info = vk_mem::AllocationCreateInfo {
+		usage: vk_mem::MemoryUsage::GpuOnly,
+		..Default::default()
+	};
+	let (image, allocation, _) = allocator(program_data).create_image(
+		image_info,
+		&allocation_info,
+	).unwrap();
+	(image, allocation)
+}
+
+/// Destroys an image made by `create_device_image` and frees its memory.
+pub fn destroy_device_image(
+	program_data: &ProgramData,
+	image: vk::Image,
+	allocation: &vk_mem::Allocation,
+) {
+	allocator(program_data).destroy_image(
+		image,
+		allocation,
+	).unwrap();
+}
+
+/// Sampled 2d image in device local memory.
+pub struct Texture {
+	pub image: vk::Image,
+	pub allocation: vk_mem::Allocation,
+	pub image_view: vk::ImageView,
+	pub sampler: Sampler,
+	pub width: u32,
+	pub height: u32,
+	pub mip_levels: u32,
+	pub format: vk::Format,
+}
+
+impl Texture {
+	pub fn from_png<P: AsRef<Path>>(
+		program_data: &ProgramData,
+		path: P,
+		options: &TextureOptions,
+	) -> Result<Self, TextureError> {
+		let (width, height, pixels) = decode_png(path)?;
+		Ok(Texture::from_rgba8(
+			program_data,
+			width,
+			height,
+			&pixels,
+			options,
+		))
+	}
+
+	/// Uploads tightly packed RGBA8 pixels through a staging buffer.
+	pub fn from_rgba8(
+		program_data: &ProgramData,
+		width: u32,
+		height: u32,
+		pixels: &[u8],
+		options: &TextureOptions,
+	) -> Self {
+		assert_eq!(
+			pixels.len(), width as usize * height as usize * 4,
+			"texture pixels don't match a {}x{} RGBA8 image", width, height,
+		);
+		let format = if options.srgb {
+			vk::Format::R8G8B8A8_SRGB
+		} else {
+			vk::Format::R8G8B8A8_UNORM
+		};
+		Texture::from_bytes(
+			program_data,
+			width,
+			height,
+			format,
+			pixels,
+			options,
+		)
+	}
+
+	/// Loads a Radiance `.hdr` as an RGBA16F texture, `options.srgb` is ignored.
+	pub fn from_hdr<P: AsRef<Path>>(
+		program_data: &ProgramData,
+		path: P,
+		options: &TextureOptions,
+	) -> Result<Self, TextureError> {
+		let (width, height, pixels) = decode_hdr(path)?;
+		let halves: Vec<u16> = pixels.iter().map(|x| f16_bits(*x)).collect();
+		Ok(Texture::from_bytes(
+			program_data,
+			width,
+			height,
+			vk::Format::R16G16B16A16_SFLOAT,
+			bytemuck::cast_slice(&halves),
+			options,
+		))
+	}
+
+	/// Uploads tightly packed pixels of any uncompressed `format`,
+	/// `options.srgb` is ignored.
+	pub fn from_bytes(
+		program_data: &ProgramData,
+		width: u32,
+		height: u32,
+		format: vk::Format,
+		pixels: &[u8],
+		options: &TextureOptions,
+	) -> Self { unsafe {
+		assert!(
+			width > 0 && height > 0 && pixels.len() % (width as usize * height as usize) == 0,
+			"texture pixels don't match a {}x{} image", width, height,
+		);
+		let device = &program_data.device.device;
+		let format_properties = program_data.instance.instance.get_physical_device_format_properties(
+			program_data.device.physical_device,
+			format,
+		);
+		let mip_levels = if !options.mipmaps {
+			1
+		} else if !format_properties.optimal_tiling_features.contains(vk::FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR) {
+			log::warn!("{:?} doesn't support linear blits, skipping mipmaps", format);
+			1
+		} else {
+			mip_level_count(width, height)
+		};
+		let image_info = vk::ImageCreateInfo::builder()
+			.image_type(vk::ImageType::TYPE_2D)
+			.format(format)
+			.extent(vk::Extent3D { width, height, depth: 1 })
+			.mip_levels(mip_levels)
+			.array_layers(1)
+			.samples(vk::SampleCountFlags::TYPE_1)
+			.tiling(vk::ImageTiling::OPTIMAL)
+			.usage(
+				vk::ImageUsageFlags::TRANSFER_SRC |
+				vk::ImageUsageFlags::TRANSFER_DST |
+				vk::ImageUsageFlags::SAMPLED
+			)
+			.sharing_mode(vk::SharingMode::EXCLUSIVE)
+			.initial_layout(vk::ImageLayout::UNDEFINED)
+			.build();
+		let (image, allocation) = create_device_image(
+			program_data,
+			&image_info,
+		);
+		let mut staging = HostBuffer::new(
+			program_data,
+			pixels.len(),
+			vk::BufferUsageFlags::TRANSFER_SRC,
+		);
+		staging.write(0, pixels);
+		submit_setup_commands(program_data, |command_buffer| {
+			transition_image_layout(
+				&program_data.device,
+				command_buffer,
+				image,
+				vk::ImageLayout::UNDEFINED,
+				vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+				mip_levels,
+			);
+			let region = vk::BufferImageCopy::builder()
+				.image_subresource(
+					vk::ImageSubresourceLayers::builder()
+						.aspect_mask(vk::ImageAspectFlags::COLOR)
+						.layer_count(1)
+						.build()
+				)
+				.image_extent(vk::Extent3D { width, height, depth: 1 })
+				.build();
+			device.cmd_copy_buffer_to_image(
+				command_buffer,
+				staging.buffer,
+				image,
+				vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+				&[region],
+			);
+			generate_mipmaps(
+				&program_data.device,
+				command_buffer,
+				image,
+				width,
+				height,
+				mip_levels,
+				1,
+			);
+		});
+		staging.destroy(program_data);
+		let image_view_info = vk::ImageViewCreateInfo::builder()
+			.image(image)
+			.view_type(vk::ImageViewType::TYPE_2D)
+			.format(format)
+			.subresource_range(
+				vk::ImageSubresourceRange::builder()
+					.aspect_mask(vk::ImageAspectFlags::COLOR)
+					.level_count(mip_levels)
+					.layer_count(1)
+					.build()
+			).build();
+		let image_view = device.create_image_view(
+			&image_view_info,
+			None,
+		).unwrap();
+		let sampler = Sampler::new(
+			program_data,
+			options.sampler,
+		);
+		Self {
+			image,
+			allocation,
+			image_view,
+			sampler,
+			width,
+			height,
+			mip_levels,
+			format,
+		}
+	}}
+
+	/// Writes the texture into a combined image sampler block state.
+	pub fn bind(
+		&self,
+		device: &vpb::Device,
+		block_state: &EngineBlockState,
+	) {
+		self.bind_with_sampler(
+			device,
+			block_state,
+			&self.sampler,
+		);
+	}
+
+	/// Writes the texture into one element of an image array block state.
+	pub fn bind_element(
+		&self,
+		device: &vpb::Device,
+		block_state: &EngineBlockState,
+		element: usize,
+		sampler: Option<&Sampler>,
+	) {
+		block_state.descriptor().write_image_element(
+			device,
+			element,
+			self.image_view,
+			sampler.unwrap_or(&self.sampler).sampler,
+		);
+	}
+
+	/// Same as `bind` with a sampler other than the texture's own.
+	pub fn bind_with_sampler(
+		&self,
+		device: &vpb::Device,
+		block_state: &EngineBlockState,
+		sampler: &Sampler,
+	) {
+		block_state.descriptor().write_image(
+			device,
+			self.image_view,
+			sampler.sampler,
+		);
+	}
+
+	/// The texture must no longer be in use by any submitted frame.
+	pub fn destroy(
+		&mut self,
+		program_data: &ProgramData,
+	) { unsafe {
+		let device = &program_data.device.device;
+		self.sampler.destroy(program_data);
+		device.destroy_image_view(
+			self.image_view,
+			None,
+		);
+		destroy_device_image(
+			program_data,
+			self.image,
+			&self.allocation,
+		);
+	}}
+}
@@ -0,0 +1,232 @@
+use std::{collections::HashMap, path::Path};
+
+use vpb::ProgramData;
+
+use crate::{Texture, TextureError, TextureOptions, decode_png};
+
+/// Pixel rectangle inside a packer or atlas.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PackedRect {
+	pub x: u32,
+	pub y: u32,
+	pub width: u32,
+	pub height: u32,
+}
+
+/// Shelf packer, rectangles are placed left to right in rows as tall as
+/// the tallest rectangle of the row. Packing tallest first keeps the
+/// wasted space small.
+pub struct RectPacker {
+	pub width: u32,
+	pub height: u32,
+	/// Empty pixels kept around every rectangle so filtering doesn't bleed.
+	pub padding: u32,
+	shelf_x: u32,
+	shelf_y: u32,
+	shelf_height: u32,
+}
+
+impl RectPacker {
+	pub fn new(
+		width: u32,
+		height: u32,
+		padding: u32,
+	) -> Self {
+		Self {
+			width,
+			height,
+			padding,
+			shelf_x: padding,
+			shelf_y: padding,
+			shelf_height: 0,
+		}
+	}
+
+	pub fn pack(
+		&mut self,
+		width: u32,
+		height: u32,
+	) -> Option<PackedRect> {
+		if self.shelf_x + width + self.padding > self.width {
+			self.shelf_x = self.padding;
+			self.shelf_y += self.shelf_height + self.padding;
+			self.shelf_height = 0;
+		}
+		if self.shelf_x + width + self.padding > self.width ||
+			self.shelf_y + height + self.padding > self.height {
+			return None;
+		}
+		let rect = PackedRect {
+			x: self.shelf_x,
+			y: self.shelf_y,
+			width,
+			height,
+		};
+		self.shelf_x += width + self.padding;
+		self.shelf_height = self.shelf_height.max(height);
+		Some(rect)
+	}
+}
+
+/// Packs `(width, height)` rectangles into the smallest power of two square
+/// from 256 up to `max_size`. Returns the side and the rectangles in the
+/// order they were given.
+pub fn pack_rects(
+	sizes: &[(u32, u32)],
+	max_size: u32,
+	padding: u32,
+) -> Result<(u32, Vec<PackedRect>), AtlasError> {
+	let mut order: Vec<usize> = (0..sizes.len()).collect();
+	order.sort_by(|a, b| sizes[*b].1.cmp(&sizes[*a].1).then(sizes[*b].0.cmp(&sizes[*a].0)));
+	let mut size = TextureAtlas::MIN_SIZE.min(max_size);
+	loop {
+		let mut packer = RectPacker::new(size, size, padding);
+		let mut rects = vec![PackedRect { x: 0, y: 0, width: 0, height: 0 }; sizes.len()];
+		let mut packed = true;
+		for i in order.iter() {
+			match packer.pack(sizes[*i].0, sizes[*i].1) {
+				Some(rect) => rects[*i] = rect,
+				None => {
+					packed = false;
+					break;
+				},
+			}
+		}
+		match packed {
+			true => return Ok((size, rects)),
+			false if size < max_size => size = (size * 2).min(max_size),
+			false => return Err(AtlasError::TooLarge(max_size)),
+		}
+	}
+}
+
+/// Region of an atlas with its normalized texture coordinates.
+#[derive(Clone, Copy, Debug)]
+pub struct AtlasRegion {
+	pub rect: PackedRect,
+	pub uv_min: [f32; 2],
+	pub uv_max: [f32; 2],
+}
+
+impl AtlasRegion {
+	/// Region of `rect` inside a square atlas of side `atlas_size`.
+	pub fn new(
+		rect: PackedRect,
+		atlas_size: u32,
+	) -> Self {
+		let size = atlas_size as f32;
+		Self {
+			rect,
+			uv_min: [rect.x as f32 / size, rect.y as f32 / size],
+			uv_max: [
+				(rect.x + rect.width) as f32 / size,
+				(rect.y + rect.height) as f32 / size,
+			],
+		}
+	}
+}
+
+#[derive(Debug)]
+pub enum AtlasError {
+	Texture(TextureError),
+	/// The images don't fit in an atlas of the maximum size.
+	TooLarge(u32),
+}
+
+impl std::fmt::Display for AtlasError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			AtlasError::Texture(e) => write!(f, "{}", e),
+			AtlasError::TooLarge(max_size) => write!(f, "images don't fit in a {}x{} atlas", max_size, max_size),
+		}
+	}
+}
+
+impl std::error::Error for AtlasError {}
+
+impl From<TextureError> for AtlasError {
+	fn from(e: TextureError) -> Self {
+		AtlasError::Texture(e)
+	}
+}
+
+pub struct TextureAtlas {
+	pub texture: Texture,
+	pub regions: HashMap<String, AtlasRegion>,
+}
+
+impl TextureAtlas {
+	const MIN_SIZE: u32 = 256;
+	const PADDING: u32 = 2;
+
+	/// Packs every `(name, path)` png into the smallest power of two square
+	/// atlas up to `max_size`.
+	pub fn from_pngs<P: AsRef<Path>>(
+		program_data: &ProgramData,
+		images: &[(&str, P)],
+		max_size: u32,
+		options: &TextureOptions,
+	) -> Result<Self, AtlasError> {
+		let mut decoded = Vec::with_capacity(images.len());
+		for (name, path) in images.iter() {
+			let (width, height, pixels) = decode_png(path)?;
+			decoded.push((name.to_string(), width, height, pixels));
+		}
+		TextureAtlas::from_rgba8(
+			program_data,
+			decoded,
+			max_size,
+			options,
+		)
+	}
+
+	/// Same as `from_pngs` for already decoded `(name, width, height, RGBA8 pixels)`.
+	pub fn from_rgba8(
+		program_data: &ProgramData,
+		images: Vec<(String, u32, u32, Vec<u8>)>,
+		max_size: u32,
+		options: &TextureOptions,
+	) -> Result<Self, AtlasError> {
+		let sizes: Vec<(u32, u32)> = images.iter().map(|(_, width, height, _)| (*width, *height)).collect();
+		let (size, rects) = pack_rects(
+			&sizes,
+			max_size,
+			TextureAtlas::PADDING,
+		)?;
+		let mut pixels = vec![0u8; (size * size * 4) as usize];
+		let mut regions = HashMap::with_capacity(images.len());
+		for ((name, width, _, image), rect) in images.iter().zip(rects.iter()) {
+			let row = (*width * 4) as usize;
+			for (y, source) in image.chunks_exact(row).enumerate() {
+				let start = (((rect.y + y as u32) * size + rect.x) * 4) as usize;
+				pixels[start..start + row].copy_from_slice(source);
+			}
+			regions.insert(name.clone(), AtlasRegion::new(*rect, size));
+		}
+		let texture = Texture::from_rgba8(
+			program_data,
+			size,
+			size,
+			&pixels,
+			options,
+		);
+		Ok(Self {
+			texture,
+			regions,
+		})
+	}
+
+	pub fn region(
+		&self,
+		name: &str,
+	) -> Option<&AtlasRegion> {
+		self.regions.get(name)
+	}
+
+	pub fn destroy(
+		&mut self,
+		program_data: &ProgramData,
+	) {
+		self.texture.destroy(program_data);
+	}
+}
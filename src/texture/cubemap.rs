@@ -0,0 +1,256 @@
+use std::path::Path;
+
+use ash::vk;
+use vpb::ProgramData;
+
+use crate::{EngineBlockState, Sampler, SamplerDescription, TextureError, TextureOptions, HostBuffer, create_device_image, destroy_device_image, decode_png, mip_level_count, transition_image_layers, generate_mipmaps, submit_setup_commands};
+
+/// Six square faces sampled as a `samplerCube`, in device local memory.
+/// Layers are the +x, -x, +y, -y, +z and -z faces.
+pub struct Cubemap {
+	pub image: vk::Image,
+	pub allocation: vk_mem::Allocation,
+	pub image_view: vk::ImageView,
+	pub sampler: Sampler,
+	pub size: u32,
+	pub mip_levels: u32,
+	pub format: vk::Format,
+}
+
+impl Cubemap {
+	/// Uninitialized cubemap, every level starts out `UNDEFINED`. `usage`
+	/// is added to `SAMPLED`.
+	pub fn new(
+		program_data: &ProgramData,
+		size: u32,
+		mip_levels: u32,
+		format: vk::Format,
+		usage: vk::ImageUsageFlags,
+		sampler: SamplerDescription,
+	) -> Self { unsafe {
+		let image_info = vk::ImageCreateInfo::builder()
+			.flags(vk::ImageCreateFlags::CUBE_COMPATIBLE)
+			.image_type(vk::ImageType::TYPE_2D)
+			.format(format)
+			.extent(vk::Extent3D { width: size, height: size, depth: 1 })
+			.mip_levels(mip_levels)
+			.array_layers(6)
+			.samples(vk::SampleCountFlags::TYPE_1)
+			.tiling(vk::ImageTiling::OPTIMAL)
+			.usage(usage | vk::ImageUsageFlags::SAMPLED)
+			.sharing_mode(vk::SharingMode::EXCLUSIVE)
+			.initial_layout(vk::ImageLayout::UNDEFINED)
+			.build();
+		let (image, allocation) = create_device_image(
+			program_data,
+			&image_info,
+		);
+		let image_view_info = vk::ImageViewCreateInfo::builder()
+			.image(image)
+			.view_type(vk::ImageViewType::CUBE)
+			.format(format)
+			.subresource_range(
+				vk::ImageSubresourceRange::builder()
+					.aspect_mask(vk::ImageAspectFlags::COLOR)
+					.level_count(mip_levels)
+					.layer_count(6)
+					.build()
+			).build();
+		let image_view = program_data.device.device.create_image_view(
+			&image_view_info,
+			None,
+		).unwrap();
+		Self {
+			image,
+			allocation,
+			image_view,
+			sampler: Sampler::new(
+				program_data,
+				sampler,
+			),
+			size,
+			mip_levels,
+			format,
+		}
+	}}
+
+	/// Loads six square pngs of one size, in face order.
+	pub fn from_faces<P: AsRef<Path>>(
+		program_data: &ProgramData,
+		paths: [P; 6],
+		options: &TextureOptions,
+	) -> Result<Self, TextureError> {
+		let mut size = 0;
+		let mut pixels = Vec::new();
+		for (face, path) in paths.iter().enumerate() {
+			let (width, height, face_pixels) = decode_png(path)?;
+			if face == 0 {
+				size = width;
+			}
+			if width != height || width != size {
+				return Err(TextureError::CubemapFace(face, width, height));
+			}
+			pixels.extend_from_slice(&face_pixels);
+		}
+		Ok(Cubemap::from_rgba8(
+			program_data,
+			size,
+			&pixels,
+			options,
+		))
+	}
+
+	/// Uploads tightly packed RGBA8 faces, one after another in face order,
+	/// through a staging buffer.
+	pub fn from_rgba8(
+		program_data: &ProgramData,
+		size: u32,
+		pixels: &[u8],
+		options: &TextureOptions,
+	) -> Self { unsafe {
+		assert_eq!(
+			pixels.len(), (size * size * 4 * 6) as usize,
+			"cubemap pixels don't match six {}x{} RGBA8 faces", size, size,
+		);
+		let format = if options.srgb {
+			vk::Format::R8G8B8A8_SRGB
+		} else {
+			vk::Format::R8G8B8A8_UNORM
+		};
+		let mip_levels = match options.mipmaps {
+			true => mip_level_count(size, size),
+			false => 1,
+		};
+		let cubemap = Cubemap::new(
+			program_data,
+			size,
+			mip_levels,
+			format,
+			vk::ImageUsageFlags::TRANSFER_SRC | vk::ImageUsageFlags::TRANSFER_DST,
+			options.sampler,
+		);
+		let mut staging = HostBuffer::new(
+			program_data,
+			pixels.len(),
+			vk::BufferUsageFlags::TRANSFER_SRC,
+		);
+		staging.write(0, pixels);
+		submit_setup_commands(program_data, |command_buffer| {
+			transition_image_layers(
+				&program_data.device,
+				command_buffer,
+				cubemap.image,
+				vk::ImageLayout::UNDEFINED,
+				vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+				mip_levels,
+				6,
+			);
+			let region = vk::BufferImageCopy::builder()
+				.image_subresource(
+					vk::ImageSubresourceLayers::builder()
+						.aspect_mask(vk::ImageAspectFlags::COLOR)
+						.layer_count(6)
+						.build()
+				)
+				.image_extent(vk::Extent3D { width: size, height: size, depth: 1 })
+				.build();
+			program_data.device.device.cmd_copy_buffer_to_image(
+				command_buffer,
+				staging.buffer,
+				cubemap.image,
+				vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+				&[region],
+			);
+			generate_mipmaps(
+				&program_data.device,
+				command_buffer,
+				cubemap.image,
+				size,
+				size,
+				mip_levels,
+				6,
+			);
+		});
+		staging.destroy(program_data);
+		cubemap
+	}}
+
+	/// Size of the faces of mip `level`.
+	pub fn level_size(
+		&self,
+		level: u32,
+	) -> u32 {
+		(self.size >> level).max(1)
+	}
+
+	/// The faces of one mip level as a 2d array, for storage writes. The
+	/// caller destroys the view.
+	pub fn level_view(
+		&self,
+		program_data: &ProgramData,
+		level: u32,
+	) -> vk::ImageView { unsafe {
+		let image_view_info = vk::ImageViewCreateInfo::builder()
+			.image(self.image)
+			.view_type(vk::ImageViewType::TYPE_2D_ARRAY)
+			.format(self.format)
+			.subresource_range(
+				vk::ImageSubresourceRange::builder()
+					.aspect_mask(vk::ImageAspectFlags::COLOR)
+					.base_mip_level(level)
+					.level_count(1)
+					.layer_count(6)
+					.build()
+			).build();
+		program_data.device.device.create_image_view(
+			&image_view_info,
+			None,
+		).unwrap()
+	}}
+
+	/// Writes the cubemap into a combined image sampler block state.
+	pub fn bind(
+		&self,
+		device: &vpb::Device,
+		block_state: &EngineBlockState,
+	) {
+		self.bind_element(
+			device,
+			block_state,
+			0,
+		);
+	}
+
+	/// Writes the cubemap into one element of an image array block state.
+	pub fn bind_element(
+		&self,
+		device: &vpb::Device,
+		block_state: &EngineBlockState,
+		element: usize,
+	) {
+		block_state.descriptor().write_image_element(
+			device,
+			element,
+			self.image_view,
+			self.sampler.sampler,
+		);
+	}
+
+	/// The cubemap must no longer be in use by any submitted frame.
+	pub fn destroy(
+		&mut self,
+		program_data: &ProgramData,
+	) { unsafe {
+		let device = &program_data.device.device;
+		self.sampler.destroy(program_data);
+		device.destroy_image_view(
+			self.image_view,
+			None,
+		);
+		destroy_device_image(
+			program_data,
+			self.image,
+			&self.allocation,
+		);
+	}}
+}
@@ -0,0 +1,145 @@
+use std::path::Path;
+
+use crate::TextureError;
+
+/// Decodes a Radiance `.hdr` (RGBE, flat or run length encoded scanlines)
+/// into tightly packed linear RGBA32F pixels with alpha 1.
+pub fn decode_hdr<P: AsRef<Path>>(
+	path: P,
+) -> Result<(u32, u32, Vec<f32>), TextureError> {
+	decode_hdr_bytes(&std::fs::read(path)?)
+}
+
+pub fn decode_hdr_bytes(
+	bytes: &[u8],
+) -> Result<(u32, u32, Vec<f32>), TextureError> {
+	let mut cursor = 0;
+	let next_line = |cursor: &mut usize| -> Result<&str, TextureError> {
+		let start = *cursor;
+		let end = bytes[start..].iter().position(|x| *x == b'\n').ok_or(
+			TextureError::Hdr("header ends before the resolution")
+		)? + start;
+		*cursor = end + 1;
+		std::str::from_utf8(&bytes[start..end]).map_err(|_| TextureError::Hdr("header is not text"))
+	};
+	let magic = next_line(&mut cursor)?;
+	if !magic.starts_with("#?RADIANCE") && !magic.starts_with("#?RGBE") {
+		return Err(TextureError::Hdr("missing #?RADIANCE signature"));
+	}
+	loop {
+		let line = next_line(&mut cursor)?.trim();
+		if line.is_empty() {
+			break;
+		}
+		if let Some(format) = line.strip_prefix("FORMAT=") {
+			if format != "32-bit_rle_rgbe" {
+				return Err(TextureError::Hdr("only 32-bit_rle_rgbe pixels are supported"));
+			}
+		}
+	}
+	let resolution: Vec<&str> = next_line(&mut cursor)?.split_whitespace().collect();
+	let (width, height) = match resolution.as_slice() {
+		["-Y", height, "+X", width] => (
+			width.parse::<u32>().map_err(|_| TextureError::Hdr("invalid width"))?,
+			height.parse::<u32>().map_err(|_| TextureError::Hdr("invalid height"))?,
+		),
+		_ => return Err(TextureError::Hdr("only -Y h +X w orientation is supported")),
+	};
+	let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+	let mut scanline = vec![[0u8; 4]; width as usize];
+	for _ in 0..height {
+		read_scanline(bytes, &mut cursor, &mut scanline)?;
+		for [r, g, b, e] in scanline.iter() {
+			let scale = match *e {
+				0 => 0.0,
+				e => 2f32.powi(e as i32 - 136),
+			};
+			pixels.extend_from_slice(&[*r as f32 * scale, *g as f32 * scale, *b as f32 * scale, 1.0]);
+		}
+	}
+	Ok((width, height, pixels))
+}
+
+fn read_scanline(
+	bytes: &[u8],
+	cursor: &mut usize,
+	scanline: &mut [[u8; 4]],
+) -> Result<(), TextureError> {
+	let width = scanline.len();
+	let header = bytes.get(*cursor..*cursor + 4).ok_or(TextureError::Hdr("pixel data is truncated"))?;
+	// Run length encoded lines start with 2 2 and the width, every
+	// channel is then stored separately.
+	if (8..0x8000).contains(&width) && header[0] == 2 && header[1] == 2 && header[2] & 0x80 == 0 {
+		if ((header[2] as usize) << 8 | header[3] as usize) != width {
+			return Err(TextureError::Hdr("scanline width mismatch"));
+		}
+		*cursor += 4;
+		for channel in 0..4 {
+			let mut x = 0;
+			while x < width {
+				let count = *bytes.get(*cursor).ok_or(TextureError::Hdr("pixel data is truncated"))? as usize;
+				*cursor += 1;
+				if count > 128 {
+					let count = count - 128;
+					let value = *bytes.get(*cursor).ok_or(TextureError::Hdr("pixel data is truncated"))?;
+					*cursor += 1;
+					if count == 0 || x + count > width {
+						return Err(TextureError::Hdr("run overflows the scanline"));
+					}
+					scanline[x..x + count].iter_mut().for_each(|p| p[channel] = value);
+					x += count;
+				} else {
+					if count == 0 || x + count > width {
+						return Err(TextureError::Hdr("run overflows the scanline"));
+					}
+					let values = bytes.get(*cursor..*cursor + count).ok_or(TextureError::Hdr("pixel data is truncated"))?;
+					*cursor += count;
+					scanline[x..x + count].iter_mut().zip(values).for_each(|(p, v)| p[channel] = *v);
+					x += count;
+				}
+			}
+		}
+		return Ok(());
+	}
+	let values = bytes.get(*cursor..*cursor + width * 4).ok_or(TextureError::Hdr("pixel data is truncated"))?;
+	if values.chunks_exact(4).any(|x| x[0] == 1 && x[1] == 1 && x[2] == 1) {
+		return Err(TextureError::Hdr("old style run length encoding is not supported"));
+	}
+	*cursor += width * 4;
+	for (pixel, value) in scanline.iter_mut().zip(values.chunks_exact(4)) {
+		pixel.copy_from_slice(value);
+	}
+	Ok(())
+}
+
+/// Nearest IEEE half of `x`, infinities and NaN included.
+pub fn f16_bits(
+	x: f32,
+) -> u16 {
+	let bits = x.to_bits();
+	let sign = ((bits >> 16) & 0x8000) as u16;
+	let exponent = ((bits >> 23) & 0xff) as i32;
+	let mantissa = bits & 0x7f_ffff;
+	if exponent == 0xff {
+		return sign | 0x7c00 | if mantissa != 0 { 0x200 } else { 0 };
+	}
+	let exponent = exponent - 127 + 15;
+	if exponent >= 0x1f {
+		return sign | 0x7c00;
+	}
+	if exponent <= 0 {
+		if exponent < -10 {
+			return sign;
+		}
+		// Subnormal, shift the implicit bit in and round to nearest.
+		let mantissa = mantissa | 0x80_0000;
+		let shift = (14 - exponent) as u32;
+		let half = mantissa >> shift;
+		let round = (mantissa >> (shift - 1)) & 1;
+		return sign | (half + round) as u16;
+	}
+	let half = ((exponent as u32) << 10) | (mantissa >> 13);
+	let round = (mantissa >> 12) & 1;
+	// A carry out of the mantissa correctly bumps the exponent.
+	sign | (half + round) as u16
+}
@@ -0,0 +1,8 @@
+mod easing;
+pub use easing::*;
+mod property;
+pub use property::*;
+mod track;
+pub use track::*;
+mod timeline;
+pub use timeline::*;
\ No newline at end of file
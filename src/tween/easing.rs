@@ -0,0 +1,177 @@
+use std::f32::consts::{PI, FRAC_PI_2};
+
+use nalgebra::{UnitQuaternion, Vector2, Vector3, Vector4};
+
+use crate::{JointPose, nlerp};
+
+/// Shape of a tween's progress over its duration.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Easing {
+	#[default]
+	Linear,
+	QuadIn,
+	QuadOut,
+	QuadInOut,
+	CubicIn,
+	CubicOut,
+	CubicInOut,
+	SineIn,
+	SineOut,
+	SineInOut,
+	ExpoIn,
+	ExpoOut,
+	ExpoInOut,
+	/// Pulls back slightly before moving forward.
+	BackIn,
+	/// Overshoots the end slightly before settling.
+	BackOut,
+	BackInOut,
+	/// Springs past the end a few times before settling.
+	ElasticOut,
+	BounceOut,
+}
+
+impl Easing {
+	/// Eased progress for linear progress `t` in [0, 1], 0 and 1 map to
+	/// themselves.
+	pub fn apply(
+		&self,
+		t: f32,
+	) -> f32 {
+		let t = t.clamp(0.0, 1.0);
+		let in_out = |f: fn(f32) -> f32| match t < 0.5 {
+			true => f(t * 2.0) * 0.5,
+			false => 1.0 - f((1.0 - t) * 2.0) * 0.5,
+		};
+		match self {
+			Easing::Linear => t,
+			Easing::QuadIn => t * t,
+			Easing::QuadOut => 1.0 - (1.0 - t) * (1.0 - t),
+			Easing::QuadInOut => in_out(|x| x * x),
+			Easing::CubicIn => t * t * t,
+			Easing::CubicOut => 1.0 - (1.0 - t).powi(3),
+			Easing::CubicInOut => in_out(|x| x * x * x),
+			Easing::SineIn => 1.0 - (t * FRAC_PI_2).cos(),
+			Easing::SineOut => (t * FRAC_PI_2).sin(),
+			Easing::SineInOut => 0.5 - (t * PI).cos() * 0.5,
+			Easing::ExpoIn => expo_in(t),
+			Easing::ExpoOut => 1.0 - expo_in(1.0 - t),
+			Easing::ExpoInOut => in_out(expo_in),
+			Easing::BackIn => back_in(t),
+			Easing::BackOut => 1.0 - back_in(1.0 - t),
+			Easing::BackInOut => in_out(back_in),
+			Easing::ElasticOut => match t {
+				x if x <= 0.0 || x >= 1.0 => x,
+				x => 2.0f32.powf(-10.0 * x) * ((x * 10.0 - 0.75) * (2.0 * PI / 3.0)).sin() + 1.0,
+			},
+			Easing::BounceOut => bounce_out(t),
+		}
+	}
+}
+
+fn expo_in(
+	t: f32,
+) -> f32 {
+	match t <= 0.0 {
+		true => 0.0,
+		false => 2.0f32.powf(10.0 * t - 10.0),
+	}
+}
+
+fn back_in(
+	t: f32,
+) -> f32 {
+	const OVERSHOOT: f32 = 1.70158;
+	(OVERSHOOT + 1.0) * t * t * t - OVERSHOOT * t * t
+}
+
+fn bounce_out(
+	t: f32,
+) -> f32 {
+	const N: f32 = 7.5625;
+	const D: f32 = 2.75;
+	if t < 1.0 / D {
+		N * t * t
+	} else if t < 2.0 / D {
+		let t = t - 1.5 / D;
+		N * t * t + 0.75
+	} else if t < 2.5 / D {
+		let t = t - 2.25 / D;
+		N * t * t + 0.9375
+	} else {
+		let t = t - 2.625 / D;
+		N * t * t + 0.984375
+	}
+}
+
+/// Value a `Tween` can move between two ends of.
+pub trait Tweenable: Copy {
+	/// `t` of the way from `self` to `to`. Eased `t` can leave [0, 1] and
+	/// extrapolates.
+	fn tween(
+		&self,
+		to: &Self,
+		t: f32,
+	) -> Self;
+}
+
+impl Tweenable for f32 {
+	fn tween(
+		&self,
+		to: &Self,
+		t: f32,
+	) -> Self {
+		self + (to - self) * t
+	}
+}
+
+impl Tweenable for Vector2<f32> {
+	fn tween(
+		&self,
+		to: &Self,
+		t: f32,
+	) -> Self {
+		self.lerp(to, t)
+	}
+}
+
+impl Tweenable for Vector3<f32> {
+	fn tween(
+		&self,
+		to: &Self,
+		t: f32,
+	) -> Self {
+		self.lerp(to, t)
+	}
+}
+
+impl Tweenable for Vector4<f32> {
+	fn tween(
+		&self,
+		to: &Self,
+		t: f32,
+	) -> Self {
+		self.lerp(to, t)
+	}
+}
+
+impl Tweenable for UnitQuaternion<f32> {
+	fn tween(
+		&self,
+		to: &Self,
+		t: f32,
+	) -> Self {
+		nlerp(self, to, t)
+	}
+}
+
+/// Transforms tween their translation, rotation and scale separately.
+impl Tweenable for JointPose {
+	fn tween(
+		&self,
+		to: &Self,
+		t: f32,
+	) -> Self {
+		self.blend(to, t)
+	}
+}
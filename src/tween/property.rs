@@ -0,0 +1,216 @@
+use nalgebra::{Matrix4, Vector2, Vector3, Vector4};
+
+use crate::{BlockModelExample, BlockMeshModel, SpriteBatch, MeshObject, SkinnedMeshObject, CameraState2d, CameraState3d, JointPose, Std140Vec4};
+
+/// Reads and writes one value of `S` for a `Tween`. Setters should go
+/// through the target's own setters so it gets marked dirty.
+pub struct Property<S, T> {
+	pub get: fn(&S) -> T,
+	pub set: fn(&mut S, T),
+}
+
+impl<S, T> Clone for Property<S, T> {
+	fn clone(
+		&self,
+	) -> Self {
+		*self
+	}
+}
+
+impl<S, T> Copy for Property<S, T> {}
+
+impl<S, T> Property<S, T> {
+	pub fn new(
+		get: fn(&S) -> T,
+		set: fn(&mut S, T),
+	) -> Self {
+		Self {
+			get,
+			set,
+		}
+	}
+}
+
+/// Anything drawn with a model matrix and a color, `Property::transform`
+/// and `Property::color` tween these.
+pub trait ModelTarget {
+	fn model_matrix(
+		&self,
+	) -> Matrix4<f32>;
+
+	fn set_model_matrix(
+		&mut self,
+		model: Matrix4<f32>,
+	);
+
+	fn model_color(
+		&self,
+	) -> Vector4<f32>;
+
+	fn set_model_color(
+		&mut self,
+		color: Vector4<f32>,
+	);
+}
+
+impl ModelTarget for BlockModelExample {
+	fn model_matrix(
+		&self,
+	) -> Matrix4<f32> {
+		self.model.into()
+	}
+
+	fn set_model_matrix(
+		&mut self,
+		model: Matrix4<f32>,
+	) {
+		self.model = model.into();
+	}
+
+	fn model_color(
+		&self,
+	) -> Vector4<f32> {
+		Vector4::from(self.color.value)
+	}
+
+	fn set_model_color(
+		&mut self,
+		color: Vector4<f32>,
+	) {
+		self.color = color.into();
+	}
+}
+
+impl ModelTarget for SpriteBatch {
+	fn model_matrix(
+		&self,
+	) -> Matrix4<f32> {
+		self.model().model_matrix()
+	}
+
+	fn set_model_matrix(
+		&mut self,
+		model: Matrix4<f32>,
+	) {
+		let mut block = *self.model();
+		block.set_model_matrix(model);
+		self.set_model(block);
+	}
+
+	fn model_color(
+		&self,
+	) -> Vector4<f32> {
+		self.model().model_color()
+	}
+
+	fn set_model_color(
+		&mut self,
+		color: Vector4<f32>,
+	) {
+		let mut block = *self.model();
+		block.set_model_color(color);
+		self.set_model(block);
+	}
+}
+
+macro_rules! mesh_model_target {
+	($object:ty) => {
+		impl ModelTarget for $object {
+			fn model_matrix(
+				&self,
+			) -> Matrix4<f32> {
+				self.block().model.into()
+			}
+
+			fn set_model_matrix(
+				&mut self,
+				model: Matrix4<f32>,
+			) {
+				self.set_transform(model);
+			}
+
+			fn model_color(
+				&self,
+			) -> Vector4<f32> {
+				Vector4::from(self.block().color.value)
+			}
+
+			fn set_model_color(
+				&mut self,
+				color: Vector4<f32>,
+			) {
+				self.set_block(BlockMeshModel {
+					color: Std140Vec4::from(color),
+					..*self.block()
+				});
+			}
+		}
+	};
+}
+
+mesh_model_target!(MeshObject);
+mesh_model_target!(SkinnedMeshObject);
+
+impl<S: ModelTarget> Property<S, JointPose> {
+	/// Model matrix as translation, rotation and scale.
+	pub fn transform(
+	) -> Self {
+		Property::new(
+			|x| JointPose::from_matrix(&x.model_matrix()),
+			|x, value| x.set_model_matrix(value.matrix()),
+		)
+	}
+}
+
+impl<S: ModelTarget> Property<S, Vector3<f32>> {
+	/// Translation of the model matrix, leaving rotation and scale.
+	pub fn translation(
+	) -> Self {
+		Property::new(
+			|x| x.model_matrix().fixed_slice::<3, 1>(0, 3).into_owned(),
+			|x, value| {
+				let mut model = x.model_matrix();
+				model.fixed_slice_mut::<3, 1>(0, 3).copy_from(&value);
+				x.set_model_matrix(model);
+			},
+		)
+	}
+}
+
+impl<S: ModelTarget> Property<S, Vector4<f32>> {
+	pub fn color(
+	) -> Self {
+		Property::new(
+			|x| x.model_color(),
+			|x, value| x.set_model_color(value),
+		)
+	}
+}
+
+impl Property<CameraState3d, Vector3<f32>> {
+	/// Moves the camera directly, skipping the smoothing of keyboard
+	/// movement.
+	pub fn camera3d_position(
+	) -> Self {
+		Property::new(
+			|x| x.camera_preposition,
+			|x, value| {
+				x.camera_preposition = value;
+				x.camera_postposition = value;
+			},
+		)
+	}
+}
+
+impl Property<CameraState2d, Vector2<f32>> {
+	pub fn camera2d_position(
+	) -> Self {
+		Property::new(
+			|x| x.camera_preposition,
+			|x, value| {
+				x.camera_preposition = value;
+				x.camera_postposition = value;
+			},
+		)
+	}
+}
@@ -0,0 +1,166 @@
+use crate::{RenderState, Track};
+
+struct TimelineEntry {
+	id: usize,
+	track: Box<dyn Track>,
+	on_complete: Option<Box<dyn FnMut()>>,
+	paused: bool,
+}
+
+/// Plays tracks alongside each other by the frame's delta time and drops
+/// them once they finish. `Scene` owns one and updates it every frame.
+#[derive(Default)]
+pub struct Timeline {
+	entries: Vec<TimelineEntry>,
+	next_id: usize,
+}
+
+impl Timeline {
+	pub fn new(
+	) -> Self {
+		Self::default()
+	}
+
+	/// Returns an id to `stop` or `pause` the track with.
+	pub fn play<T: Track + 'static>(
+		&mut self,
+		track: T,
+	) -> usize {
+		self.push(Box::new(track), None)
+	}
+
+	/// Like `play`, calling `on_complete` once the track finishes. Stopped
+	/// tracks don't complete.
+	pub fn play_then<T: Track + 'static, F: FnMut() + 'static>(
+		&mut self,
+		track: T,
+		on_complete: F,
+	) -> usize {
+		self.push(Box::new(track), Some(Box::new(on_complete)))
+	}
+
+	fn push(
+		&mut self,
+		track: Box<dyn Track>,
+		on_complete: Option<Box<dyn FnMut()>>,
+	) -> usize {
+		let id = self.next_id;
+		self.next_id += 1;
+		self.entries.push(TimelineEntry {
+			id,
+			track,
+			on_complete,
+			paused: false,
+		});
+		id
+	}
+
+	/// Drops the track where it is, its targets keep their current values.
+	pub fn stop(
+		&mut self,
+		id: usize,
+	) {
+		self.entries.retain(|x| x.id != id);
+	}
+
+	pub fn set_paused(
+		&mut self,
+		id: usize,
+		paused: bool,
+	) {
+		if let Some(entry) = self.entries.iter_mut().find(|x| x.id == id) {
+			entry.paused = paused;
+		}
+	}
+
+	/// Whether the track is still playing, or paused.
+	pub fn is_playing(
+		&self,
+		id: usize,
+	) -> bool {
+		self.entries.iter().any(|x| x.id == id)
+	}
+
+	pub fn clear(
+		&mut self,
+	) {
+		self.entries.clear();
+	}
+
+	/// Advances every unpaused track by `render_state.delta_time`.
+	pub fn update(
+		&mut self,
+		render_state: &RenderState,
+	) {
+		self.advance(render_state.delta_time);
+	}
+
+	pub fn advance(
+		&mut self,
+		delta_time: f32,
+	) {
+		self.entries.retain_mut(
+			|entry| {
+				if entry.paused || entry.track.advance(delta_time).is_none() {
+					return true;
+				}
+				if let Some(on_complete) = entry.on_complete.as_mut() {
+					on_complete();
+				}
+				false
+			}
+		);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{rc::Rc, cell::Cell};
+
+	use crate::Delay;
+
+	use super::*;
+
+	fn counter(
+	) -> (Rc<Cell<u32>>, impl FnMut() + 'static) {
+		let calls = Rc::new(Cell::new(0));
+		let counted = calls.clone();
+		(calls, move || counted.set(counted.get() + 1))
+	}
+
+	#[test]
+	fn play_then_calls_back_once_finished() {
+		let (calls, on_complete) = counter();
+		let mut timeline = Timeline::new();
+		let id = timeline.play_then(Delay::new(1.0), on_complete);
+		timeline.advance(0.5);
+		assert!(timeline.is_playing(id));
+		assert_eq!(calls.get(), 0);
+		timeline.advance(0.5);
+		assert!(!timeline.is_playing(id));
+		assert_eq!(calls.get(), 1);
+		timeline.advance(1.0);
+		assert_eq!(calls.get(), 1);
+	}
+
+	#[test]
+	fn paused_tracks_wait_and_stopped_tracks_never_complete() {
+		let (calls, on_complete) = counter();
+		let mut timeline = Timeline::new();
+		let paused = timeline.play_then(Delay::new(1.0), on_complete);
+		timeline.set_paused(paused, true);
+		timeline.advance(2.0);
+		assert!(timeline.is_playing(paused));
+		assert_eq!(calls.get(), 0);
+		timeline.set_paused(paused, false);
+		timeline.advance(1.0);
+		assert_eq!(calls.get(), 1);
+
+		let (calls, on_complete) = counter();
+		let stopped = timeline.play_then(Delay::new(1.0), on_complete);
+		timeline.stop(stopped);
+		timeline.advance(2.0);
+		assert!(!timeline.is_playing(stopped));
+		assert_eq!(calls.get(), 0);
+	}
+}
@@ -0,0 +1,400 @@
+use std::sync::Arc;
+
+use crate::{Easing, Property, Tweenable};
+
+/// Anything a `Timeline` can play. Tracks nest, so a `Sequence` of
+/// `Parallel` groups is itself a track.
+pub trait Track {
+	/// Moves the track `delta_time` seconds forward. Returns the part of
+	/// `delta_time` left over once the track finishes, `None` while it's
+	/// still running.
+	fn advance(
+		&mut self,
+		delta_time: f32,
+	) -> Option<f32>;
+
+	/// Rewinds to the start for another play.
+	fn reset(
+		&mut self,
+	);
+}
+
+/// Moves one property of a shared target towards `to`.
+pub struct Tween<S, T: Tweenable> {
+	target: Arc<S>,
+	property: Property<S, T>,
+	from: Option<T>,
+	to: T,
+	/// `from`, or the property's value when the tween first advanced.
+	start: Option<T>,
+	duration: f32,
+	easing: Easing,
+	elapsed: f32,
+}
+
+impl<S, T: Tweenable> Tween<S, T> {
+	/// Starts from the property's value when the tween first advances.
+	pub fn new(
+		target: Arc<S>,
+		property: Property<S, T>,
+		to: T,
+		duration: f32,
+	) -> Self {
+		Self {
+			target,
+			property,
+			from: None,
+			to,
+			start: None,
+			duration,
+			easing: Easing::Linear,
+			elapsed: 0.0,
+		}
+	}
+
+	pub fn with_from(
+		mut self,
+		from: T,
+	) -> Self {
+		self.from = Some(from);
+		self
+	}
+
+	pub fn with_easing(
+		mut self,
+		easing: Easing,
+	) -> Self {
+		self.easing = easing;
+		self
+	}
+}
+
+impl<S, T: Tweenable> Track for Tween<S, T> {
+	fn advance(
+		&mut self,
+		delta_time: f32,
+	) -> Option<f32> {
+		let start = match self.start {
+			Some(start) => start,
+			None => *self.start.insert(self.from.unwrap_or_else(|| (self.property.get)(&self.target))),
+		};
+		self.elapsed += delta_time;
+		let t = match self.duration > 0.0 {
+			true => (self.elapsed / self.duration).min(1.0),
+			false => 1.0,
+		};
+		(self.property.set)(vpb::gmuc!(self.target), start.tween(&self.to, self.easing.apply(t)));
+		match self.elapsed >= self.duration {
+			true => Some(self.elapsed - self.duration),
+			false => None,
+		}
+	}
+
+	/// Keeps the start value read on the first play, so repeats replay the
+	/// same motion.
+	fn reset(
+		&mut self,
+	) {
+		self.elapsed = 0.0;
+	}
+}
+
+/// Waits without touching anything, for gaps in a `Sequence`.
+pub struct Delay {
+	duration: f32,
+	elapsed: f32,
+}
+
+impl Delay {
+	pub fn new(
+		duration: f32,
+	) -> Self {
+		Self {
+			duration,
+			elapsed: 0.0,
+		}
+	}
+}
+
+impl Track for Delay {
+	fn advance(
+		&mut self,
+		delta_time: f32,
+	) -> Option<f32> {
+		self.elapsed += delta_time;
+		match self.elapsed >= self.duration {
+			true => Some(self.elapsed - self.duration),
+			false => None,
+		}
+	}
+
+	fn reset(
+		&mut self,
+	) {
+		self.elapsed = 0.0;
+	}
+}
+
+/// Runs a closure and finishes immediately, for callbacks partway
+/// through a `Sequence`.
+pub struct Call {
+	callback: Box<dyn FnMut()>,
+}
+
+impl Call {
+	pub fn new<F: FnMut() + 'static>(
+		callback: F,
+	) -> Self {
+		Self {
+			callback: Box::new(callback),
+		}
+	}
+}
+
+impl Track for Call {
+	fn advance(
+		&mut self,
+		delta_time: f32,
+	) -> Option<f32> {
+		(self.callback)();
+		Some(delta_time)
+	}
+
+	fn reset(
+		&mut self,
+	) {}
+}
+
+/// Plays its tracks one after another, time left over by one track
+/// carries into the next.
+#[derive(Default)]
+pub struct Sequence {
+	tracks: Vec<Box<dyn Track>>,
+	current: usize,
+}
+
+impl Sequence {
+	pub fn new(
+	) -> Self {
+		Self::default()
+	}
+
+	pub fn then<T: Track + 'static>(
+		mut self,
+		track: T,
+	) -> Self {
+		self.tracks.push(Box::new(track));
+		self
+	}
+}
+
+impl Track for Sequence {
+	fn advance(
+		&mut self,
+		delta_time: f32,
+	) -> Option<f32> {
+		let mut delta_time = delta_time;
+		while let Some(track) = self.tracks.get_mut(self.current) {
+			delta_time = track.advance(delta_time)?;
+			self.current += 1;
+		}
+		Some(delta_time)
+	}
+
+	fn reset(
+		&mut self,
+	) {
+		for track in self.tracks.iter_mut() {
+			track.reset();
+		}
+		self.current = 0;
+	}
+}
+
+/// Plays its tracks together, finishing with the longest.
+#[derive(Default)]
+pub struct Parallel {
+	tracks: Vec<Box<dyn Track>>,
+	/// Time left over by each finished track.
+	finished: Vec<Option<f32>>,
+}
+
+impl Parallel {
+	pub fn new(
+	) -> Self {
+		Self::default()
+	}
+
+	pub fn with<T: Track + 'static>(
+		mut self,
+		track: T,
+	) -> Self {
+		self.tracks.push(Box::new(track));
+		self.finished.push(None);
+		self
+	}
+}
+
+impl Track for Parallel {
+	fn advance(
+		&mut self,
+		delta_time: f32,
+	) -> Option<f32> {
+		for (track, finished) in self.tracks.iter_mut().zip(self.finished.iter_mut()) {
+			match finished {
+				Some(left) => *left += delta_time,
+				None => *finished = track.advance(delta_time),
+			}
+		}
+		// The group ends when the last track did, which left the least over.
+		self.finished.iter().try_fold(delta_time, |left, x| x.map(|x| left.min(x)))
+	}
+
+	fn reset(
+		&mut self,
+	) {
+		for track in self.tracks.iter_mut() {
+			track.reset();
+		}
+		self.finished.iter_mut().for_each(|x| *x = None);
+	}
+}
+
+/// Plays a track `count` times, or forever without a count.
+pub struct Repeat {
+	track: Box<dyn Track>,
+	count: Option<u32>,
+	played: u32,
+}
+
+impl Repeat {
+	pub fn new<T: Track + 'static>(
+		track: T,
+		count: u32,
+	) -> Self {
+		Self {
+			track: Box::new(track),
+			count: Some(count),
+			played: 0,
+		}
+	}
+
+	/// Never finishes, stop it through its `Timeline`.
+	pub fn forever<T: Track + 'static>(
+		track: T,
+	) -> Self {
+		Self {
+			track: Box::new(track),
+			count: None,
+			played: 0,
+		}
+	}
+}
+
+impl Track for Repeat {
+	fn advance(
+		&mut self,
+		delta_time: f32,
+	) -> Option<f32> {
+		let mut delta_time = delta_time;
+		loop {
+			if self.count.map_or(false, |x| self.played >= x) {
+				return Some(delta_time);
+			}
+			let left = self.track.advance(delta_time)?;
+			self.played += 1;
+			self.track.reset();
+			// A track that takes no time would loop forever within a frame.
+			if left >= delta_time && self.count.is_none() {
+				return None;
+			}
+			delta_time = left;
+		}
+	}
+
+	fn reset(
+		&mut self,
+	) {
+		self.track.reset();
+		self.played = 0;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	struct Dot {
+		x: f32,
+	}
+
+	fn dot(
+	) -> Arc<Dot> {
+		Arc::new(Dot { x: 0.0 })
+	}
+
+	fn x(
+	) -> Property<Dot, f32> {
+		Property::new(
+			|x| x.x,
+			|x, value| x.x = value,
+		)
+	}
+
+	#[test]
+	fn tween_returns_the_time_past_its_end() {
+		let target = dot();
+		let mut tween = Tween::new(target.clone(), x(), 4.0, 1.0);
+		assert_eq!(tween.advance(0.5), None);
+		assert_eq!(target.x, 2.0);
+		assert_eq!(tween.advance(0.75), Some(0.25));
+		assert_eq!(target.x, 4.0);
+	}
+
+	#[test]
+	fn sequence_carries_time_into_the_next_track() {
+		let target = dot();
+		let mut sequence = Sequence::new()
+			.then(Delay::new(0.5))
+			.then(Tween::new(target.clone(), x(), 2.0, 1.0));
+		assert_eq!(sequence.advance(0.75), None);
+		assert_eq!(target.x, 0.5);
+		assert_eq!(sequence.advance(1.0), Some(0.25));
+		assert_eq!(target.x, 2.0);
+	}
+
+	#[test]
+	fn parallel_finishes_with_its_longest_track() {
+		let short = dot();
+		let long = dot();
+		let mut parallel = Parallel::new()
+			.with(Tween::new(short.clone(), x(), 1.0, 0.5))
+			.with(Sequence::new()
+				.then(Delay::new(0.5))
+				.then(Tween::new(long.clone(), x(), 1.0, 0.5)));
+		assert_eq!(parallel.advance(0.75), None);
+		assert_eq!(short.x, 1.0);
+		assert_eq!(long.x, 0.5);
+		assert_eq!(parallel.advance(0.5), Some(0.25));
+		assert_eq!(long.x, 1.0);
+	}
+
+	#[test]
+	fn repeat_stops_after_its_count() {
+		let target = dot();
+		let mut repeat = Repeat::new(
+			Sequence::new()
+				.then(Tween::new(target.clone(), x(), 1.0, 0.5).with_from(0.0))
+				.then(Delay::new(0.5)),
+			3,
+		);
+		assert_eq!(repeat.advance(1.25), None);
+		assert_eq!(target.x, 0.5);
+		assert_eq!(repeat.advance(1.75), Some(0.0));
+		assert_eq!(target.x, 1.0);
+		assert_eq!(repeat.advance(0.5), Some(0.5));
+		repeat.reset();
+		assert_eq!(repeat.advance(0.25), None);
+		assert_eq!(target.x, 0.5);
+	}
+}
@@ -1,2 +1,11 @@
 mod ui;
-pub use ui::*;
\ No newline at end of file
+pub use ui::*;
+mod ui_textured;
+pub use ui_textured::*;
+mod vertex3d;
+pub use vertex3d::*;
+mod vertex_skinned;
+pub use vertex_skinned::*;
+pub mod primitives;
+mod format;
+pub use format::*;
\ No newline at end of file
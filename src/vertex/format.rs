@@ -0,0 +1,33 @@
+use ash::vk;
+use nalgebra::{Vector2, Vector3, Vector4};
+
+/// Vertex attribute format of a field type, used by `#[derive(Vertex)]`.
+pub trait VertexFormat {
+	const FORMAT: vk::Format;
+}
+
+macro_rules! vertex_format {
+	($ty:ty, $format:ident) => {
+		impl VertexFormat for $ty {
+			const FORMAT: vk::Format = vk::Format::$format;
+		}
+	};
+}
+
+vertex_format!(f32, R32_SFLOAT);
+vertex_format!([f32; 1], R32_SFLOAT);
+vertex_format!([f32; 2], R32G32_SFLOAT);
+vertex_format!([f32; 3], R32G32B32_SFLOAT);
+vertex_format!([f32; 4], R32G32B32A32_SFLOAT);
+vertex_format!(u32, R32_UINT);
+vertex_format!([u32; 2], R32G32_UINT);
+vertex_format!([u32; 3], R32G32B32_UINT);
+vertex_format!([u32; 4], R32G32B32A32_UINT);
+vertex_format!(i32, R32_SINT);
+vertex_format!([i32; 2], R32G32_SINT);
+vertex_format!([i32; 3], R32G32B32_SINT);
+vertex_format!([i32; 4], R32G32B32A32_SINT);
+vertex_format!([u8; 4], R8G8B8A8_UNORM);
+vertex_format!(Vector2<f32>, R32G32_SFLOAT);
+vertex_format!(Vector3<f32>, R32G32B32_SFLOAT);
+vertex_format!(Vector4<f32>, R32G32B32A32_SFLOAT);
\ No newline at end of file
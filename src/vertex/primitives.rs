@@ -0,0 +1,181 @@
+//! Meshes generated on the cpu, counter clockwise when seen from outside.
+
+use std::f32::consts::PI;
+
+use nalgebra::Vector3;
+
+use crate::Vertex3d;
+
+/// Smooth normals for meshes that don't come with any, every face adds its
+/// area weighted normal to its vertices.
+pub fn compute_normals(
+	vertices: &mut [Vertex3d],
+	indices: &[u32],
+) {
+	let mut normals = vec![Vector3::zeros(); vertices.len()];
+	for triangle in indices.chunks_exact(3) {
+		let [a, b, c] = [triangle[0] as usize, triangle[1] as usize, triangle[2] as usize];
+		let p0 = Vector3::from(vertices[a].position);
+		let normal = (Vector3::from(vertices[b].position) - p0).cross(&(Vector3::from(vertices[c].position) - p0));
+		for i in [a, b, c] {
+			normals[i] += normal;
+		}
+	}
+	for (vertex, normal) in vertices.iter_mut().zip(normals.iter()) {
+		vertex.normal = normal.try_normalize(f32::EPSILON).unwrap_or(Vector3::y()).into();
+	}
+}
+
+/// Fills in the tangents of `vertices` from their positions and uvs,
+/// averaged over every triangle that shares a vertex.
+pub fn compute_tangents(
+	vertices: &mut [Vertex3d],
+	indices: &[u32],
+) {
+	let mut tangents = vec![Vector3::zeros(); vertices.len()];
+	let mut bitangents = vec![Vector3::zeros(); vertices.len()];
+	for triangle in indices.chunks_exact(3) {
+		let [a, b, c] = [triangle[0] as usize, triangle[1] as usize, triangle[2] as usize];
+		let p0 = Vector3::from(vertices[a].position);
+		let edge1 = Vector3::from(vertices[b].position) - p0;
+		let edge2 = Vector3::from(vertices[c].position) - p0;
+		let du1 = vertices[b].uv[0] - vertices[a].uv[0];
+		let dv1 = vertices[b].uv[1] - vertices[a].uv[1];
+		let du2 = vertices[c].uv[0] - vertices[a].uv[0];
+		let dv2 = vertices[c].uv[1] - vertices[a].uv[1];
+		let determinant = du1 * dv2 - du2 * dv1;
+		if determinant.abs() < f32::EPSILON {
+			continue;
+		}
+		let r = 1.0 / determinant;
+		let tangent = (edge1 * dv2 - edge2 * dv1) * r;
+		let bitangent = (edge2 * du1 - edge1 * du2) * r;
+		for i in [a, b, c] {
+			tangents[i] += tangent;
+			bitangents[i] += bitangent;
+		}
+	}
+	for (i, vertex) in vertices.iter_mut().enumerate() {
+		let normal = Vector3::from(vertex.normal);
+		// Gram-Schmidt against the normal.
+		let tangent = tangents[i] - normal * normal.dot(&tangents[i]);
+		let tangent = match tangent.try_normalize(f32::EPSILON) {
+			Some(tangent) => tangent,
+			None => normal.cross(&Vector3::x()).try_normalize(f32::EPSILON).unwrap_or(Vector3::z()),
+		};
+		let handedness = match normal.cross(&tangent).dot(&bitangents[i]) < 0.0 {
+			true => -1.0,
+			false => 1.0,
+		};
+		vertex.tangent = [tangent.x, tangent.y, tangent.z, handedness];
+	}
+}
+
+/// Axis aligned cube centered on the origin with a separate face per side.
+pub fn cube(
+	size: f32,
+) -> (Vec<Vertex3d>, Vec<u32>) {
+	let h = size * 0.5;
+	// (normal, u axis, v axis)
+	let faces = [
+		([1.0, 0.0, 0.0], [0.0, 0.0, -1.0], [0.0, -1.0, 0.0]),
+		([-1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, -1.0, 0.0]),
+		([0.0, 1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]),
+		([0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, -1.0]),
+		([0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [0.0, -1.0, 0.0]),
+		([0.0, 0.0, -1.0], [-1.0, 0.0, 0.0], [0.0, -1.0, 0.0]),
+	];
+	let mut vertices = Vec::with_capacity(24);
+	let mut indices = Vec::with_capacity(36);
+	for (normal, u, v) in faces.iter() {
+		let normal = Vector3::from(*normal);
+		let u = Vector3::from(*u);
+		let v = Vector3::from(*v);
+		let base = vertices.len() as u32;
+		let handedness = match normal.cross(&u).dot(&v) < 0.0 {
+			true => -1.0,
+			false => 1.0,
+		};
+		for (su, sv) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)] {
+			let position = (normal + u * su + v * sv) * h;
+			vertices.push(Vertex3d {
+				position: position.into(),
+				normal: normal.into(),
+				tangent: [u.x, u.y, u.z, handedness],
+				uv: [(su + 1.0) * 0.5, (sv + 1.0) * 0.5],
+				color: [1.0; 4],
+			});
+		}
+		// u cross v points inward for some faces, wind to face the normal.
+		match u.cross(&v).dot(&normal) > 0.0 {
+			true => indices.extend_from_slice(&[base, base + 1, base + 2, base + 2, base + 3, base]),
+			false => indices.extend_from_slice(&[base, base + 2, base + 1, base + 2, base, base + 3]),
+		}
+	}
+	(vertices, indices)
+}
+
+/// Sphere centered on the origin, `sectors` around the y axis and `stacks`
+/// from pole to pole.
+pub fn uv_sphere(
+	radius: f32,
+	sectors: u32,
+	stacks: u32,
+) -> (Vec<Vertex3d>, Vec<u32>) {
+	let sectors = sectors.max(3);
+	let stacks = stacks.max(2);
+	let mut vertices = Vec::with_capacity(((sectors + 1) * (stacks + 1)) as usize);
+	let mut indices = Vec::with_capacity((sectors * stacks * 6) as usize);
+	for stack in 0..=stacks {
+		let v = stack as f32 / stacks as f32;
+		let phi = v * PI;
+		for sector in 0..=sectors {
+			let u = sector as f32 / sectors as f32;
+			let theta = u * 2.0 * PI;
+			let normal = Vector3::new(
+				phi.sin() * theta.cos(),
+				phi.cos(),
+				-phi.sin() * theta.sin(),
+			);
+			vertices.push(Vertex3d {
+				position: (normal * radius).into(),
+				normal: normal.into(),
+				tangent: [-theta.sin(), 0.0, -theta.cos(), -1.0],
+				uv: [u, v],
+				color: [1.0; 4],
+			});
+		}
+	}
+	let row = sectors + 1;
+	for stack in 0..stacks {
+		for sector in 0..sectors {
+			let a = stack * row + sector;
+			let b = a + row;
+			if stack != 0 {
+				indices.extend_from_slice(&[a, b, a + 1]);
+			}
+			if stack != stacks - 1 {
+				indices.extend_from_slice(&[a + 1, b, b + 1]);
+			}
+		}
+	}
+	(vertices, indices)
+}
+
+/// Square in the xz plane facing up.
+pub fn plane(
+	size: f32,
+) -> (Vec<Vertex3d>, Vec<u32>) {
+	let h = size * 0.5;
+	let vertices = [(-h, -h, 0.0, 0.0), (h, -h, 1.0, 0.0), (h, h, 1.0, 1.0), (-h, h, 0.0, 1.0)].iter().map(
+		|(x, z, u, v)|
+		Vertex3d {
+			position: [*x, 0.0, *z],
+			normal: [0.0, 1.0, 0.0],
+			tangent: [1.0, 0.0, 0.0, -1.0],
+			uv: [*u, *v],
+			color: [1.0; 4],
+		}
+	).collect();
+	(vertices, vec![0, 2, 1, 2, 0, 3])
+}
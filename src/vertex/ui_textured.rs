@@ -0,0 +1,9 @@
+use crate::Vertex;
+
+#[repr(C)]
+#[derive(Clone, Copy, Vertex)]
+pub struct VertexUITextured {
+	pub position: [f32; 2],
+	pub uv: [f32; 2],
+	pub color: [f32; 4],
+}
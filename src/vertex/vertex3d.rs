@@ -0,0 +1,14 @@
+use bytemuck::{Zeroable, Pod};
+
+use crate::Vertex;
+
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, Pod, Zeroable, Vertex)]
+pub struct Vertex3d {
+	pub position: [f32; 3],
+	pub normal: [f32; 3],
+	/// xyz along increasing u, w is the handedness of the bitangent.
+	pub tangent: [f32; 4],
+	pub uv: [f32; 2],
+	pub color: [f32; 4],
+}
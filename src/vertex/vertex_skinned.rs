@@ -0,0 +1,37 @@
+use bytemuck::{Zeroable, Pod};
+
+use crate::{Vertex, Vertex3d};
+
+/// `Vertex3d` bound to up to four joints of a `Skeleton`.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, Pod, Zeroable, Vertex)]
+pub struct VertexSkinned {
+	pub position: [f32; 3],
+	pub normal: [f32; 3],
+	/// xyz along increasing u, w is the handedness of the bitangent.
+	pub tangent: [f32; 4],
+	pub uv: [f32; 2],
+	pub color: [f32; 4],
+	/// Indices into the skeleton's joints.
+	pub joints: [u32; 4],
+	/// Influence of each joint, summing to 1.
+	pub weights: [f32; 4],
+}
+
+impl VertexSkinned {
+	pub fn from_vertex(
+		vertex: Vertex3d,
+		joints: [u32; 4],
+		weights: [f32; 4],
+	) -> Self {
+		Self {
+			position: vertex.position,
+			normal: vertex.normal,
+			tangent: vertex.tangent,
+			uv: vertex.uv,
+			color: vertex.color,
+			joints,
+			weights,
+		}
+	}
+}
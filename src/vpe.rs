@@ -3,6 +3,10 @@
 #![feature(return_position_impl_trait_in_trait)]
 #![feature(yeet_expr)]
 
+// Lets `vpe-derive` output refer to `::vpe` from inside this crate.
+extern crate self as vpe;
+pub use vpe_derive::{Block, Vertex, PushConstant};
+
 mod program;
 pub use program::*;
 mod scene;
@@ -21,4 +25,26 @@ pub use input_state::*;
 mod render_state;
 pub use render_state::*;
 mod system;
-pub use system::*;
\ No newline at end of file
+pub use system::*;
+mod layout;
+pub use layout::*;
+mod memory;
+pub use memory::*;
+mod texture;
+pub use texture::*;
+mod material;
+pub use material::*;
+mod environment;
+pub use environment::*;
+mod shadow;
+pub use shadow::*;
+mod animation;
+pub use animation::*;
+mod tween;
+pub use tween::*;
+mod render_graph;
+pub use render_graph::*;
+mod text;
+pub use text::*;
+mod import;
+pub use import::*;
\ No newline at end of file
@@ -0,0 +1,406 @@
+use proc_macro::TokenStream;
+use proc_macro2::{TokenStream as TokenStream2, Span};
+use quote::quote;
+use syn::{parse_macro_input, DeriveInput, Data, Fields, Ident, Error, LitInt};
+
+fn named_fields(
+	input: &DeriveInput,
+) -> Result<Vec<&syn::Field>, Error> {
+	match &input.data {
+		Data::Struct(data) => match &data.fields {
+			Fields::Named(fields) => Ok(fields.named.iter().collect()),
+			_ => Err(Error::new_spanned(&input.ident, "expected a struct with named fields")),
+		},
+		_ => Err(Error::new_spanned(&input.ident, "expected a struct")),
+	}
+}
+
+fn has_repr_c(
+	input: &DeriveInput,
+) -> bool {
+	input.attrs.iter().any(|attr| {
+		if !attr.path().is_ident("repr") {
+			return false;
+		}
+		let mut repr_c = false;
+		let _ = attr.parse_nested_meta(|meta| {
+			if meta.path.is_ident("C") {
+				repr_c = true;
+			}
+			Ok(())
+		});
+		repr_c
+	})
+}
+
+/// Compile time checks that every field sits where glsl places it under
+/// `layout` (`Std140` or `Std430`), plus the `LayoutChecked` impl.
+fn layout_checks(
+	name: &Ident,
+	fields: &[&syn::Field],
+	layout: TokenStream2,
+) -> TokenStream2 {
+	let checks = fields.iter().map(|field| {
+		let field_name = field.ident.as_ref().unwrap();
+		let ty = &field.ty;
+		let message = format!(
+			"field `{}` of `{}` is not at its glsl offset, add padding or use a padded `Std140*` type",
+			field_name, name,
+		);
+		quote! {
+			offset = ::vpe::round_up(offset, <#ty as #layout>::ALIGN);
+			assert!(
+				::core::mem::offset_of!(#name, #field_name) == offset,
+				#message,
+			);
+			offset += <#ty as #layout>::SIZE;
+		}
+	});
+	quote! {
+		const _: () = {
+			#[allow(unused_mut, unused_assignments)]
+			let mut offset = 0usize;
+			#(#checks)*
+		};
+
+		unsafe impl ::vpe::LayoutChecked for #name {}
+	}
+}
+
+fn parse_stages(
+	meta: &syn::meta::ParseNestedMeta,
+	stages: &mut Vec<Ident>,
+) -> Result<(), Error> {
+	meta.parse_nested_meta(|stage| {
+		match stage.path.get_ident() {
+			Some(ident) => {
+				stages.push(ident.clone());
+				Ok(())
+			},
+			None => Err(stage.error("expected a shader stage")),
+		}
+	})
+}
+
+fn stage_flags(
+	stages: &[Ident],
+) -> Result<TokenStream2, Error> {
+	let mut stage_flags = quote!(::ash::vk::ShaderStageFlags::empty());
+	for stage in stages.iter() {
+		let flag = stage_flag(stage)?;
+		stage_flags = quote!(#stage_flags | #flag);
+	}
+	Ok(stage_flags)
+}
+
+struct BlockAttributes {
+	descriptor: Ident,
+	stages: Vec<Ident>,
+}
+
+fn parse_block_attributes(
+	input: &DeriveInput,
+) -> Result<BlockAttributes, Error> {
+	let mut attributes = BlockAttributes {
+		descriptor: Ident::new("uniform", Span::call_site()),
+		stages: Vec::new(),
+	};
+	for attr in input.attrs.iter().filter(|x| x.path().is_ident("block")) {
+		attr.parse_nested_meta(|meta| {
+			if meta.path.is_ident("descriptor") {
+				attributes.descriptor = meta.value()?.parse()?;
+				Ok(())
+			} else if meta.path.is_ident("stages") {
+				meta.parse_nested_meta(|stage| {
+					match stage.path.get_ident() {
+						Some(ident) => {
+							attributes.stages.push(ident.clone());
+							Ok(())
+						},
+						None => Err(stage.error("expected a shader stage")),
+					}
+				})
+			} else {
+				Err(meta.error("expected `descriptor` or `stages`"))
+			}
+		})?;
+	}
+	if attributes.stages.is_empty() {
+		attributes.stages.push(Ident::new("vertex", Span::call_site()));
+	}
+	Ok(attributes)
+}
+
+fn stage_flag(
+	stage: &Ident,
+) -> Result<TokenStream2, Error> {
+	Ok(match stage.to_string().as_str() {
+		"vertex" => quote!(::ash::vk::ShaderStageFlags::VERTEX),
+		"fragment" => quote!(::ash::vk::ShaderStageFlags::FRAGMENT),
+		"geometry" => quote!(::ash::vk::ShaderStageFlags::GEOMETRY),
+		"compute" => quote!(::ash::vk::ShaderStageFlags::COMPUTE),
+		"all_graphics" => quote!(::ash::vk::ShaderStageFlags::ALL_GRAPHICS),
+		_ => return Err(Error::new_spanned(stage, "unknown shader stage, expected vertex, fragment, geometry, compute or all_graphics")),
+	})
+}
+
+/// Implements `vpb::Block` and `vpe::BlockReflect` for a `repr(C)` struct.
+/// Every field must implement `vpe::Std140`, field offsets are checked
+/// against the std140 rules at compile time and the struct gets
+/// `vpe::LayoutChecked` so it can be passed to `update_checked`.
+/// With `descriptor = storage` only `vpe::BlockReflect` is implemented and
+/// the layout is checked against std430 instead.
+///
+/// ```ignore
+/// #[repr(C)]
+/// #[derive(Copy, Clone, Block)]
+/// #[block(descriptor = uniform, stages(vertex, fragment))]
+/// pub struct BlockLight { .. }
+/// ```
+#[proc_macro_derive(Block, attributes(block))]
+pub fn derive_block(
+	input: TokenStream,
+) -> TokenStream {
+	let input = parse_macro_input!(input as DeriveInput);
+	match derive_block_impl(&input) {
+		Ok(tokens) => tokens.into(),
+		Err(e) => e.to_compile_error().into(),
+	}
+}
+
+fn derive_block_impl(
+	input: &DeriveInput,
+) -> Result<TokenStream2, Error> {
+	let name = &input.ident;
+	let name_string = name.to_string();
+	if !has_repr_c(input) {
+		return Err(Error::new_spanned(name, "blocks must be #[repr(C)]"));
+	}
+	let fields = named_fields(input)?;
+	let attributes = parse_block_attributes(input)?;
+	let (storage, descriptor_type) = match attributes.descriptor.to_string().as_str() {
+		"uniform" => (false, quote!(::ash::vk::DescriptorType::UNIFORM_BUFFER)),
+		"storage" => (true, quote!(::ash::vk::DescriptorType::STORAGE_BUFFER)),
+		_ => return Err(Error::new_spanned(&attributes.descriptor, "unsupported descriptor type, expected uniform or storage")),
+	};
+	let stage_flags = stage_flags(&attributes.stages)?;
+	let layout_checks = layout_checks(
+		name,
+		&fields,
+		if storage { quote!(::vpe::Std430) } else { quote!(::vpe::Std140) },
+	);
+	let reflect = quote! {
+		#layout_checks
+
+		impl ::vpe::BlockReflect for #name {
+			fn block_name(
+			) -> &'static str {
+				#name_string
+			}
+
+			fn block_size(
+			) -> usize {
+				::core::mem::size_of::<#name>()
+			}
+
+			fn descriptor_type(
+			) -> ::ash::vk::DescriptorType {
+				#descriptor_type
+			}
+
+			fn stage_flags(
+			) -> ::ash::vk::ShaderStageFlags {
+				#stage_flags
+			}
+		}
+	};
+	if storage {
+		// Storage blocks are element types for `ObjectBlockStructure::push_storage_block`,
+		// vpb only creates uniform block states.
+		return Ok(reflect);
+	}
+	Ok(quote! {
+		#reflect
+
+		impl ::vpb::Block for #name {
+			fn create_block_state(
+				program_data: &::vpb::ProgramData,
+				descriptor_set_layout: &::ash::vk::DescriptorSetLayout,
+				frame_count: usize,
+				binding: ::vpb::BindingId,
+				set: ::vpb::SetId,
+			) -> ::std::sync::Arc<::vpb::BlockState> {
+				::std::sync::Arc::new(::vpb::BlockState::new(
+					program_data,
+					descriptor_set_layout,
+					frame_count,
+					set,
+					::vpb::DescriptorDescription::new(vec![
+						::vpb::DDType::Uniform(::vpb::DDTypeUniform {
+							binding,
+							size: ::core::mem::size_of::<#name>(),
+						}),
+					]),
+				))
+			}
+
+			fn create_descriptor_set_layout(
+				device: &::std::sync::Arc<::vpb::Device>,
+				binding: ::vpb::BindingId,
+			) -> ::ash::vk::DescriptorSetLayout { unsafe {
+				let descriptor_set_layout_binding = ::ash::vk::DescriptorSetLayoutBinding::builder()
+					.binding(binding.0)
+					.descriptor_type(<#name as ::vpe::BlockReflect>::descriptor_type())
+					.stage_flags(<#name as ::vpe::BlockReflect>::stage_flags())
+					.descriptor_count(1)
+					.build();
+				let descriptor_set_layout_info = ::ash::vk::DescriptorSetLayoutCreateInfo::builder()
+					.bindings(&[
+						descriptor_set_layout_binding,
+					]).build();
+				device.device.create_descriptor_set_layout(
+					&descriptor_set_layout_info,
+					None,
+				).unwrap()
+			}}
+		}
+	})
+}
+
+/// Implements `vpe::PushConstant` for a `repr(C)`, `Pod` struct. Field
+/// offsets are checked against std430, which push constants use.
+///
+/// ```ignore
+/// #[repr(C)]
+/// #[derive(Copy, Clone, Pod, Zeroable, PushConstant)]
+/// #[push_constant(stages(vertex))]
+/// pub struct PushModel { .. }
+/// ```
+#[proc_macro_derive(PushConstant, attributes(push_constant))]
+pub fn derive_push_constant(
+	input: TokenStream,
+) -> TokenStream {
+	let input = parse_macro_input!(input as DeriveInput);
+	match derive_push_constant_impl(&input) {
+		Ok(tokens) => tokens.into(),
+		Err(e) => e.to_compile_error().into(),
+	}
+}
+
+fn derive_push_constant_impl(
+	input: &DeriveInput,
+) -> Result<TokenStream2, Error> {
+	let name = &input.ident;
+	if !has_repr_c(input) {
+		return Err(Error::new_spanned(name, "push constants must be #[repr(C)]"));
+	}
+	let fields = named_fields(input)?;
+	let mut stages = Vec::new();
+	for attr in input.attrs.iter().filter(|x| x.path().is_ident("push_constant")) {
+		attr.parse_nested_meta(|meta| {
+			if meta.path.is_ident("stages") {
+				parse_stages(&meta, &mut stages)
+			} else {
+				Err(meta.error("expected `stages`"))
+			}
+		})?;
+	}
+	if stages.is_empty() {
+		stages.push(Ident::new("vertex", Span::call_site()));
+	}
+	let stage_flags = stage_flags(&stages)?;
+	let layout_checks = layout_checks(name, &fields, quote!(::vpe::Std430));
+	let size_message = format!(
+		"`{}` is larger than the push constant space vulkan guarantees",
+		name,
+	);
+	Ok(quote! {
+		#layout_checks
+
+		const _: () = assert!(
+			::core::mem::size_of::<#name>() <= ::vpe::MAX_PUSH_CONSTANT_SIZE,
+			#size_message,
+		);
+
+		impl ::vpe::PushConstant for #name {
+			fn stage_flags(
+			) -> ::ash::vk::ShaderStageFlags {
+				#stage_flags
+			}
+		}
+	})
+}
+
+/// Implements `vpb::Vertex` for a `repr(C)` struct. Each field becomes an
+/// attribute of binding 0, its format comes from `vpe::VertexFormat` and
+/// its location is the field index unless `#[vertex(location = N)]` is set.
+#[proc_macro_derive(Vertex, attributes(vertex))]
+pub fn derive_vertex(
+	input: TokenStream,
+) -> TokenStream {
+	let input = parse_macro_input!(input as DeriveInput);
+	match derive_vertex_impl(&input) {
+		Ok(tokens) => tokens.into(),
+		Err(e) => e.to_compile_error().into(),
+	}
+}
+
+fn derive_vertex_impl(
+	input: &DeriveInput,
+) -> Result<TokenStream2, Error> {
+	let name = &input.ident;
+	if !has_repr_c(input) {
+		return Err(Error::new_spanned(name, "vertices must be #[repr(C)]"));
+	}
+	let fields = named_fields(input)?;
+	let mut attributes = Vec::with_capacity(fields.len());
+	for (i, field) in fields.iter().enumerate() {
+		let field_name = field.ident.as_ref().unwrap();
+		let ty = &field.ty;
+		let mut location = i as u32;
+		for attr in field.attrs.iter().filter(|x| x.path().is_ident("vertex")) {
+			attr.parse_nested_meta(|meta| {
+				if meta.path.is_ident("location") {
+					let value: LitInt = meta.value()?.parse()?;
+					location = value.base10_parse()?;
+					Ok(())
+				} else {
+					Err(meta.error("expected `location`"))
+				}
+			})?;
+		}
+		attributes.push(quote! {
+			::ash::vk::VertexInputAttributeDescription::builder()
+				.location(#location)
+				.binding(0)
+				.format(<#ty as ::vpe::VertexFormat>::FORMAT)
+				.offset(::core::mem::offset_of!(#name, #field_name) as u32)
+				.build()
+		});
+	}
+	Ok(quote! {
+		impl ::vpb::Vertex for #name {
+			fn stride() -> u32 {
+				::core::mem::size_of::<#name>() as u32
+			}
+
+			fn binding_descriptions(
+			) -> Vec<::ash::vk::VertexInputBindingDescription> {
+				vec![
+					::ash::vk::VertexInputBindingDescription::builder()
+						.binding(0)
+						.stride(<#name as ::vpb::Vertex>::stride())
+						.input_rate(::ash::vk::VertexInputRate::VERTEX)
+						.build(),
+				]
+			}
+
+			fn attribute_descriptions(
+			) -> Vec<::ash::vk::VertexInputAttributeDescription> {
+				vec![
+					#(#attributes,)*
+				]
+			}
+		}
+	})
+}
\ No newline at end of file